@@ -0,0 +1,202 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM configuration tool implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_pool_command {
+	use clap::{ArgMatches};
+	use std::net::{TcpStream};
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::parse_size;
+	use fm_library::cxl_fm_lib::format_size;
+	use fm_library::cxl_fm_lib::CXL_FM_HOST_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LENGTH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_POLICY_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LOCALITY_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_TAG_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_POOL_GET_INFO_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_POOL_ALLOCATE_COMMAND;
+	use fm_library::cxl_dcd_lib::format_tag;
+	use fm_library::cxl_dcd_lib::parse_tag;
+	use fm_library::cxl_capacity_pool_lib::CxlPoolInfo;
+	use fm_library::cxl_capacity_pool_lib::CxlPoolAllocation;
+	use fm_library::cxl_capacity_pool_lib::placement_policy_name;
+	use fm_library::cxl_capacity_pool_lib::placement_policy_by_name;
+	use crate::cxl_apply_command::execute_plan;
+
+	/*
+	 * Show DC regions of the pool
+	 */
+	fn show_pool_info(data: &str) -> Result<(), CxlFmError> {
+		let info = CxlPoolInfo::from_bytes(&from_hex(data)?)?;
+		let capacity: u64 = info.regions.iter().map(|region| region.capacity).sum();
+		let free: u64 = info.regions.iter().map(|region| region.free).sum();
+
+		println!("Total capacity: {}", format_size(capacity));
+		println!("Free capacity: {}", format_size(free));
+
+		for region in &info.regions {
+			println!("Device {} region {}:", region.device, region.region);
+			println!("    Locality: {}", region.locality);
+			println!("    Block size: {}", format_size(region.block_size));
+			println!("    Capacity (free/total): {}/{}",
+				 format_size(region.free), format_size(region.capacity));
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Get free capacity of the pool
+	 */
+	pub fn get_info(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_POOL_GET_INFO_COMMAND_DESCRIPTOR);
+		}
+
+		if options.args_present() {
+			/*
+			 * Ignore currently.
+			 * Add code later.
+			 */
+		}
+
+		let request = CxlFmRequest::new(CXL_FM_POOL_GET_INFO_COMMAND);
+
+		match TcpStream::connect(&env.ip_port) {
+			Ok(stream) => {
+				if env.is_debug {
+					println!("Successfully connected to server: {}",
+						 env.ip_port);
+				}
+
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_pool_info(&data));
+
+				if let Err(e) = result {
+					println!("Failed to get pool info: {}", e);
+				}
+			},
+			Err(e) => {
+				println!("Failed to connect: {}", e);
+			}
+		}
+	}
+
+	/*
+	 * Show capacity granted by the pool
+	 */
+	fn show_allocation(data: &str) -> Result<(), CxlFmError> {
+		let allocation = CxlPoolAllocation::from_bytes(&from_hex(data)?)?;
+
+		println!("Host ID: {}", allocation.host_id);
+		println!("Placement policy: {}", placement_policy_name(allocation.policy));
+		println!("Granted capacity: {}", format_size(allocation.length()));
+
+		if allocation.pending_length() != 0 {
+			println!("Pending capacity: {}", format_size(allocation.pending_length()));
+		}
+
+		for grant in &allocation.grants {
+			println!("Device {} region {}:", grant.device, grant.region);
+
+			if grant.pending != 0 {
+				println!("    Not accepted by the host: {}", format_size(grant.pending));
+			}
+
+			for extent in &grant.extents {
+				println!("    DPA: {:#x}, length: {}, tag: {}",
+					 extent.start_dpa, format_size(extent.length),
+					 format_tag(&extent.tag));
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Build pool allocation request
+	 */
+	fn allocate_request(options: &ArgMatches) -> Result<CxlFmRequest, CxlFmError> {
+		let host = options.get_one::<u16>(crate::CXL_FM_POOL_HOST_OPTION).unwrap();
+		let length = options.get_one::<String>(crate::CXL_FM_POOL_LENGTH_OPTION).unwrap();
+		let placement = options.get_one::<String>(crate::CXL_FM_POOL_PLACEMENT_OPTION).unwrap();
+
+		let mut request = CxlFmRequest::new(CXL_FM_POOL_ALLOCATE_COMMAND)
+					.arg(CXL_FM_HOST_ARG, &host.to_string())
+					.arg(CXL_FM_LENGTH_ARG, &parse_size(length)?.to_string())
+					.arg(CXL_FM_POLICY_ARG,
+					     &placement_policy_by_name(placement).unwrap().to_string());
+
+		if let Some(locality) = options.get_one::<u16>(crate::CXL_FM_POOL_LOCALITY_OPTION) {
+			request = request.arg(CXL_FM_LOCALITY_ARG, &locality.to_string());
+		}
+
+		if let Some(tag) = options.get_one::<String>(crate::CXL_FM_POOL_TAG_OPTION) {
+			request = request.arg(CXL_FM_TAG_ARG, &format_tag(&parse_tag(tag)?));
+		}
+
+		Ok(request)
+	}
+
+	/*
+	 * Grant capacity from the pool to a host
+	 */
+	pub fn allocate(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_POOL_ALLOCATE_COMMAND_DESCRIPTOR);
+		}
+
+		let request = match allocate_request(options) {
+			Ok(request) => request,
+			Err(e) => {
+				println!("Invalid request: {}", e);
+				return;
+			}
+		};
+
+		if env.is_dry_run {
+			execute_plan(request, "allocate capacity", env);
+			return;
+		}
+
+		match TcpStream::connect(&env.ip_port) {
+			Ok(stream) => {
+				if env.is_debug {
+					println!("Successfully connected to server: {}",
+						 env.ip_port);
+				}
+
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_allocation(&data));
+
+				if let Err(e) = result {
+					println!("Failed to allocate capacity: {}", e);
+				}
+			},
+			Err(e) => {
+				println!("Failed to connect: {}", e);
+			}
+		}
+	}
+}
@@ -0,0 +1,144 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM configuration tool implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_apply_command {
+	use clap::{ArgMatches};
+	use std::fs;
+	use std::net::{TcpStream};
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::to_hex;
+	use fm_library::cxl_fm_lib::CXL_FM_PAYLOAD_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_APPLY_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_RECONCILE_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_REMEDIATE_ARG;
+	use fm_library::cxl_desired_state::CxlFabricSpec;
+	use fm_library::cxl_desired_state::CxlFabricPlan;
+	use fm_library::cxl_desired_state::plan_step_status_name;
+
+	/*
+	 * Show executed plan or, in dry-run mode, planned steps
+	 */
+	pub fn show_plan(data: &str, env: &CxlFmOptions) -> Result<(), CxlFmError> {
+		let plan = CxlFabricPlan::from_bytes(&from_hex(data)?)?;
+
+		if env.is_dry_run {
+			println!("Dry run: no changes were made");
+		}
+
+		if plan.steps.is_empty() {
+			println!("No changes: fabric is in desired state");
+			return Ok(());
+		}
+
+		println!("Plan: {} step(s)", plan.steps.len());
+
+		for (index, step) in plan.steps.iter().enumerate() {
+			println!("{:>3}. [{}] {} (opcode {:#06x}): {}",
+				 index + 1, plan_step_status_name(step.status),
+				 step.target, step.opcode, step.description);
+
+			if !step.error.is_empty() {
+				println!("     Error: {}", step.error);
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Send mutating request and show its plan
+	 */
+	pub fn execute_plan(request: CxlFmRequest, action: &str, env: &CxlFmOptions) {
+		match TcpStream::connect(&env.ip_port) {
+			Ok(stream) => {
+				if env.is_debug {
+					println!("Successfully connected to server: {}",
+						 env.ip_port);
+				}
+
+				let result = send_request(&stream, &request.dry_run(env.is_dry_run), env)
+						.and_then(|data| show_plan(&data, env));
+
+				if let Err(e) = result {
+					println!("Failed to {}: {}", action, e);
+				}
+			},
+			Err(e) => {
+				println!("Failed to connect: {}", e);
+			}
+		}
+	}
+
+	/*
+	 * Read and validate desired state file
+	 */
+	fn read_spec(path: &str) -> Result<String, CxlFmError> {
+		let content = fs::read_to_string(path)
+			.map_err(|e| CxlFmError::Io(format!("{}: {}", path, e)))?;
+
+		CxlFabricSpec::parse(&content)?;
+		Ok(content)
+	}
+
+	/*
+	 * Bring the fabric to the state declared in TOML file
+	 */
+	pub fn apply(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_APPLY_COMMAND_DESCRIPTOR);
+		}
+
+		let path = options.get_one::<String>(crate::CXL_FM_APPLY_FILE_OPTION).unwrap();
+		let content = match read_spec(path) {
+			Ok(content) => content,
+			Err(e) => {
+				println!("Failed to read desired state: {}", e);
+				return;
+			}
+		};
+
+		let request = CxlFmRequest::new(CXL_FM_APPLY_COMMAND)
+				.arg(CXL_FM_PAYLOAD_ARG, &to_hex(content.as_bytes()));
+
+		execute_plan(request, "apply desired state", env);
+	}
+
+	/*
+	 * Compare the fabric with applied desired state and report drift
+	 */
+	pub fn reconcile(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_RECONCILE_COMMAND_DESCRIPTOR);
+		}
+
+		let mut request = CxlFmRequest::new(CXL_FM_RECONCILE_COMMAND);
+
+		if options.get_flag(crate::CXL_FM_RECONCILE_REMEDIATE_OPTION) {
+			request = request.arg(CXL_FM_REMEDIATE_ARG, "1");
+		}
+
+		execute_plan(request, "reconcile desired state", env);
+	}
+}
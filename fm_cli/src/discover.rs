@@ -22,70 +22,205 @@
 pub mod cxl_fm_discover_command {
 	use clap::{ArgMatches};
 	use std::net::{TcpStream};
+	use std::collections::BTreeMap;
+	use std::time::{Duration, Instant};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
-	use fm_library::cxl_fm_lib::send_command;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::CXL_FM_RESCAN_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ID_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ADDRESS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_VERSION_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_SWITCHES_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICES_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_CCIS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_UPTIME_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ROLE_ARG;
 	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_FM_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_CXL_DEVICE_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_CXL_SWITCH_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_LD_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_TOPOLOGY_COMMAND;
+	use fm_library::cxl_topology::FabricTopology;
+	use fm_library::cxl_topology::CxlTopologyNode;
+	use fm_library::cxl_topology::node_kind_name;
+	use fm_library::cxl_topology::is_device_kind;
+	use fm_library::cxl_topology::CXL_NODE_SWITCH;
+	use fm_library::cxl_topology::CXL_NODE_VPPB;
+	use fm_library::cxl_topology::CXL_NODE_LD;
+	use fm_library::cxl_topology::CXL_EDGE_CONTAINS;
+	use fm_library::cxl_topology::CXL_EDGE_LINK;
+	use fm_library::cxl_topology::CXL_EDGE_BINDING;
+	use fm_library::cxl_fm_orchestrator::CxlFmInfo;
+	use fm_library::cxl_fm_orchestrator::CxlFmInfoList;
+	use fm_library::cxl_fm_orchestrator::fm_role_by_name;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_ACTIVE;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_HEALTH_HEALTHY;
+	use fm_library::cxl_fm_announce;
+	use fm_library::cxl_fm_orchestrator::fm_health_name;
+	use fm_library::cxl_fm_orchestrator::fm_role_name;
 
 	/*
-	 * Discover available FM instances
+	 * Show FMs registered in orchestrator
 	 */
-	pub fn discover_fms(options: &ArgMatches, env: &CxlFmOptions) {
+	fn show_fms(list: &CxlFmInfoList) {
+		println!("FMs: {}", list.fms.len());
+
+		for fm in &list.fms {
+			println!("fm {}", fm.id);
+			println!("    address: {}", fm.address);
+			println!("    version: {}", fm.version);
+			println!("    switches: {}", match fm.switches.is_empty() {
+				true => String::from("none"),
+				false => fm.switches.join(", "),
+			});
+			println!("    devices: {}", match fm.devices.is_empty() {
+				true => String::from("none"),
+				false => fm.devices.join(", "),
+			});
+			println!("    CCIs: {}", match fm.ccis.is_empty() {
+				true => String::from("none"),
+				false => fm.ccis.join(", "),
+			});
+			println!("    health: {} (last heartbeat {}s ago)",
+				 fm_health_name(fm.health), fm.heartbeat_age);
+			println!("    role: {}", fm_role_name(fm.role));
+			println!("    uptime: {}s", fm.uptime);
+		}
+	}
+
+	/*
+	 * Get list of component names of the announcement
+	 */
+	fn names(request: &CxlFmRequest, key: &str) -> Vec<String> {
+		request.get(key)
+			.map(|names| names.split(',')
+				.filter(|name| !name.is_empty())
+				.map(String::from)
+				.collect())
+			.unwrap_or_default()
+	}
+
+	/*
+	 * Get FM by its announcement
+	 */
+	fn announced_fm(request: &CxlFmRequest) -> Result<CxlFmInfo, CxlFmError> {
+		let id = request.get(CXL_FM_ID_ARG)
+			.filter(|id| !id.is_empty())
+			.ok_or(CxlFmError::Protocol(String::from("announcement has no id")))?;
+
+		Ok(CxlFmInfo {
+			id: String::from(id),
+			address: String::from(request.get(CXL_FM_ADDRESS_ARG).unwrap_or_default()),
+			version: String::from(request.get(CXL_FM_VERSION_ARG).unwrap_or_default()),
+			switches: names(request, CXL_FM_SWITCHES_ARG),
+			devices: names(request, CXL_FM_DEVICES_ARG),
+			ccis: names(request, CXL_FM_CCIS_ARG),
+			health: CXL_FM_HEALTH_HEALTHY,
+			role: request.get(CXL_FM_ROLE_ARG)
+				.and_then(fm_role_by_name)
+				.unwrap_or(CXL_FM_ROLE_ACTIVE),
+			uptime: request.get_number_or::<u64>(CXL_FM_UPTIME_ARG, 0)?,
+			heartbeat_age: 0,
+		})
+	}
+
+	/*
+	 * Listen to announcements of FMs during timeout,
+	 * the last announcement of every FM is kept
+	 */
+	fn listen_fms(options: &ArgMatches, env: &CxlFmOptions) -> Result<CxlFmInfoList, CxlFmError> {
+		let group = options.get_one::<String>(crate::CXL_FM_DISCOVER_ANNOUNCE_GROUP_OPTION).unwrap();
+		let interface = options.get_one::<String>(crate::CXL_FM_DISCOVER_ANNOUNCE_INTERFACE_OPTION).unwrap();
+		let timeout = *options.get_one::<u64>(crate::CXL_FM_DISCOVER_LISTEN_TIMEOUT_OPTION).unwrap();
+		let group = cxl_fm_announce::parse_group(group)?;
+		let interface = cxl_fm_announce::parse_interface(interface)?;
+		let socket = cxl_fm_announce::listen(&group, &interface)?;
+		let deadline = Instant::now() + Duration::from_secs(timeout);
+		let mut fms: BTreeMap<String, (CxlFmInfo, Instant)> = BTreeMap::new();
+
 		if env.is_debug {
-			println!("{}", crate::CXL_FM_DISCOVER_FM_COMMAND_DESCRIPTOR);
+			println!("Listening to announcements of {} for {}s", group, timeout);
 		}
 
-		if options.args_present() {
+		loop {
+			let left = deadline.saturating_duration_since(Instant::now());
+
+			if left.is_zero() {
+				break;
+			}
+
+			socket.set_read_timeout(Some(left))
+				.map_err(|e| CxlFmError::Io(e.to_string()))?;
+
 			/*
-			 * Ignore currently.
-			 * Add code later.
+			 * Read timeout ends listening, broken announcement
+			 * of other sender is skipped
 			 */
-		}
+			let fm = match cxl_fm_announce::receive(&socket) {
+				Ok(request) => announced_fm(&request),
+				Err(CxlFmError::Io(_)) => break,
+				Err(e) => Err(e),
+			};
 
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
+			match fm {
+				Ok(fm) => {
+					fms.insert(fm.id.clone(), (fm, Instant::now()));
+				},
+				Err(e) => {
+					if env.is_debug {
+						println!("Announcement is ignored: {}", e);
+					}
 				}
-
-				send_command(&mut stream,
-					     CXL_FM_DISCOVER_FM_COMMAND,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
 			}
 		}
+
+		Ok(CxlFmInfoList {
+			fms: fms.into_values()
+				.map(|(mut fm, seen)| {
+					fm.heartbeat_age = seen.elapsed().as_secs();
+					fm
+				})
+				.collect(),
+		})
 	}
 
 	/*
-	 * Discover available CXL devices
+	 * Discover FM instances registered in orchestrator
+	 * or listen to announcements of FM instances
 	 */
-	pub fn discover_cxl_devices(options: &ArgMatches, env: &CxlFmOptions) {
+	pub fn discover_fms(options: &ArgMatches, env: &CxlFmOptions) {
 		if env.is_debug {
-			println!("{}", crate::CXL_FM_DISCOVER_DEVICES_COMMAND_DESCRIPTOR);
+			println!("{}", crate::CXL_FM_DISCOVER_FM_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		if options.get_flag(crate::CXL_FM_DISCOVER_LISTEN_OPTION) {
+			match listen_fms(options, env) {
+				Ok(list) => show_fms(&list),
+				Err(e) => println!("Failed to discover FMs: {}", e),
+			}
+
+			return;
 		}
 
+		let request = CxlFmRequest::new(CXL_FM_DISCOVER_FM_COMMAND);
+
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DISCOVER_CXL_DEVICE_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| CxlFmInfoList::from_bytes(&from_hex(&data)?))
+						.map(|list| show_fms(&list));
+
+				if let Err(e) = result {
+					println!("Failed to discover FMs: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -94,30 +229,128 @@ pub mod cxl_fm_discover_command {
 	}
 
 	/*
-	 * Discover available CXL switches
+	 * Show component with its attributes
 	 */
-	pub fn discover_cxl_switches(options: &ArgMatches, env: &CxlFmOptions) {
-		if env.is_debug {
-			println!("{}", crate::CXL_FM_DISCOVER_SWITCHES_COMMAND_DESCRIPTOR);
+	fn show_node(node: &CxlTopologyNode, indent: &str) {
+		println!("{}{} {}", indent, node_kind_name(node.kind), node.name);
+
+		for (key, value) in &node.attributes {
+			println!("{}    {}: {}", indent, key, value);
 		}
+	}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+	/*
+	 * Show discovered CXL devices
+	 */
+	fn show_devices(data: &str) -> Result<(), CxlFmError> {
+		let topology = FabricTopology::from_bytes(&from_hex(data)?)?;
+		let devices: Vec<&CxlTopologyNode> = topology.nodes.iter()
+			.filter(|node| is_device_kind(node.kind))
+			.collect();
+
+		println!("Devices: {}", devices.len());
+
+		for device in devices {
+			show_node(device, "");
+
+			for port in topology.edges.iter()
+				.filter(|edge| edge.to == device.id && edge.kind == CXL_EDGE_LINK)
+				.filter_map(|edge| topology.node(edge.from)) {
+				println!("    connected to: {}", port.name);
+			}
 		}
 
+		Ok(())
+	}
+
+	/*
+	 * Show discovered CXL switches with ports and VCSs
+	 */
+	fn show_switches(data: &str) -> Result<(), CxlFmError> {
+		let topology = FabricTopology::from_bytes(&from_hex(data)?)?;
+		let switches = topology.nodes_of_kind(CXL_NODE_SWITCH);
+
+		println!("Switches: {}", switches.len());
+
+		for switch in switches {
+			show_node(switch, "");
+
+			for child in topology.children(switch.id, CXL_EDGE_CONTAINS) {
+				let node = match topology.node(child) {
+					Some(node) => node,
+					None => continue,
+				};
+
+				show_node(node, "    ");
+
+				for device in topology.children(child, CXL_EDGE_LINK)
+					.iter()
+					.filter_map(|id| topology.node(*id))
+					.filter(|device| device.kind != CXL_NODE_SWITCH) {
+					println!("        link: {}", device.name);
+				}
+
+				for vppb in topology.children(child, CXL_EDGE_CONTAINS)
+					.iter()
+					.filter_map(|id| topology.node(*id))
+					.filter(|vppb| vppb.kind == CXL_NODE_VPPB) {
+					match topology.children(vppb.id, CXL_EDGE_BINDING)
+						.first()
+						.and_then(|target| topology.node(*target)) {
+						Some(target) => println!("        {}: bound to {}",
+									 vppb.name, target.name),
+						None => println!("        {}: unbound", vppb.name),
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Show discovered logical devices and their bindings
+	 */
+	fn show_logical_devices(data: &str) -> Result<(), CxlFmError> {
+		let topology = FabricTopology::from_bytes(&from_hex(data)?)?;
+		let lds = topology.nodes_of_kind(CXL_NODE_LD);
+
+		println!("Logical devices: {}", lds.len());
+
+		for ld in lds {
+			show_node(ld, "");
+
+			if let Some(vppb) = topology.parent(ld.id, CXL_EDGE_BINDING)
+						.and_then(|id| topology.node(id)) {
+				println!("    bound to: {}", vppb.name);
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Request fabric topology and show it
+	 */
+	fn discover(options: &ArgMatches, env: &CxlFmOptions, command: &str,
+		    show: fn(&str) -> Result<(), CxlFmError>) {
+		let rescan = options.get_flag(crate::CXL_FM_DISCOVER_RESCAN_OPTION);
+		let request = CxlFmRequest::new(command)
+					.arg(CXL_FM_RESCAN_ARG, if rescan { "1" } else { "0" });
+
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DISCOVER_CXL_SWITCH_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show(&data));
+
+				if let Err(e) = result {
+					println!("Failed to discover fabric: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -125,6 +358,28 @@ pub mod cxl_fm_discover_command {
 		}
 	}
 
+	/*
+	 * Discover available CXL devices
+	 */
+	pub fn discover_cxl_devices(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_DISCOVER_DEVICES_COMMAND_DESCRIPTOR);
+		}
+
+		discover(options, env, CXL_FM_DISCOVER_CXL_DEVICE_COMMAND, show_devices);
+	}
+
+	/*
+	 * Discover available CXL switches
+	 */
+	pub fn discover_cxl_switches(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_DISCOVER_SWITCHES_COMMAND_DESCRIPTOR);
+		}
+
+		discover(options, env, CXL_FM_DISCOVER_CXL_SWITCH_COMMAND, show_switches);
+	}
+
 	/*
 	 * Discover available logical devices
 	 */
@@ -133,23 +388,51 @@ pub mod cxl_fm_discover_command {
 			println!("{}", crate::CXL_FM_DISCOVER_LOGICAL_DEVICES_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		discover(options, env, CXL_FM_DISCOVER_LD_COMMAND, show_logical_devices);
+	}
+
+	/*
+	 * Show fabric topology in the requested format
+	 */
+	fn show_topology(data: &str, format: &str) -> Result<(), CxlFmError> {
+		let topology = FabricTopology::from_bytes(&from_hex(data)?)?;
+
+		match format {
+			crate::CXL_FM_DISCOVER_FORMAT_DOT => print!("{}", topology.to_dot()),
+			crate::CXL_FM_DISCOVER_FORMAT_JSON => print!("{}", topology.to_json()),
+			_ => print!("{}", topology.to_tree()),
 		}
 
+		Ok(())
+	}
+
+	/*
+	 * Discover fabric topology
+	 */
+	pub fn discover_topology(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_DISCOVER_TOPOLOGY_COMMAND_DESCRIPTOR);
+		}
+
+		let format = options.get_one::<String>(crate::CXL_FM_DISCOVER_FORMAT_OPTION)
+				.map_or(crate::CXL_FM_DISCOVER_FORMAT_TREE, |format| format.as_str());
+		let rescan = options.get_flag(crate::CXL_FM_DISCOVER_RESCAN_OPTION);
+		let request = CxlFmRequest::new(CXL_FM_DISCOVER_TOPOLOGY_COMMAND)
+					.arg(CXL_FM_RESCAN_ARG, if rescan { "1" } else { "0" });
+
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DISCOVER_LD_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_topology(&data, format));
+
+				if let Err(e) = result {
+					println!("Failed to get topology: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -21,15 +21,77 @@
 
 pub mod cxl_dcd_command {
 	use clap::{ArgMatches};
+	use std::fs;
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
-	use fm_library::cxl_fm_lib::send_command;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::parse_size;
+	use fm_library::cxl_fm_lib::format_size;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_HOST_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_START_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_COUNT_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_REGION_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_BLOCK_SIZE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_SANITIZE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_POLICY_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LENGTH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_TAG_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_EXTENTS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_FORCED_ARG;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_INFO_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_SET_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_EXTENT_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_ADD_CAPACITY_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_RELEASE_CAPACITY_COMMAND;
+	use fm_library::cxl_dcd_lib::CxlDcdInfo;
+	use fm_library::cxl_dcd_lib::CxlHostDcRegionConfig;
+	use fm_library::cxl_dcd_lib::CxlDcExtentList;
+	use fm_library::cxl_dcd_lib::CxlDcAddResult;
+	use fm_library::cxl_dcd_lib::CxlDcExtent;
+	use fm_library::cxl_dcd_lib::format_tag;
+	use fm_library::cxl_dcd_lib::parse_tag;
+	use fm_library::cxl_dcd_lib::format_extent_ranges;
+	use fm_library::cxl_dcd_lib::selection_policy_by_name;
+	use fm_library::cxl_dcd_lib::CXL_DCD_TAG_SIZE;
+	use fm_library::cxl_dcd_lib::policy_names;
+	use fm_library::cxl_dcd_lib::selection_policy_name;
+	use fm_library::cxl_dcd_lib::removal_policy_name;
+	use fm_library::cxl_dcd_lib::block_sizes;
+	use crate::cxl_apply_command::execute_plan;
+
+	/*
+	 * Show Dynamic Capacity Device (DCD) info
+	 */
+	fn show_dcd_info(data: &str) -> Result<(), CxlFmError> {
+		let info = CxlDcdInfo::from_bytes(&from_hex(data)?)?;
+
+		println!("Number of hosts: {}", info.num_hosts);
+		println!("Number of supported DC regions: {}", info.num_regions);
+		println!("Supported selection policies: {}",
+			 policy_names(info.selection_policies, selection_policy_name).join(", "));
+		println!("Supported removal policies: {}",
+			 policy_names(info.removal_policies, removal_policy_name).join(", "));
+		println!("Total dynamic capacity: {}", format_size(info.total_capacity));
+
+		for region in 0..info.num_regions as usize {
+			let sizes: Vec<String> = block_sizes(info.block_size_masks[region])
+							.into_iter()
+							.map(format_size)
+							.collect();
+
+			println!("Region {}:", region);
+			println!("    Supported block sizes: {}", sizes.join(", "));
+			println!("    Sanitize on release configurable: {}",
+				 info.sanitize_on_release_mask & (1 << region) != 0);
+		}
+
+		Ok(())
+	}
 
 	/*
 	 * Get Dynamic Capacity Device (DCD) info
@@ -39,23 +101,23 @@ pub mod cxl_dcd_command {
 			println!("{}", crate::CXL_FM_DCD_GET_INFO_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let request = CxlFmRequest::new(CXL_FM_DCD_GET_INFO_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, device);
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DCD_GET_INFO_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_dcd_info(&data));
+
+				if let Err(e) = result {
+					println!("Failed to get DCD info: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -63,6 +125,33 @@ pub mod cxl_dcd_command {
 		}
 	}
 
+	/*
+	 * Show dynamic capacity region configuration
+	 */
+	fn show_capacity_config(start_region: u8, data: &str) -> Result<(), CxlFmError> {
+		let config = CxlHostDcRegionConfig::from_bytes(start_region, &from_hex(data)?)?;
+
+		println!("Host ID: {}", config.host_id);
+		println!("Number of available regions: {}", config.num_regions);
+
+		for (index, region) in config.regions.iter().enumerate() {
+			println!("Region {}:", config.start_region as usize + index);
+			println!("    Base: {:#x}", region.base);
+			println!("    Decode length: {}", format_size(region.decode_length));
+			println!("    Length: {}", format_size(region.length));
+			println!("    Block size: {}", format_size(region.block_size));
+			println!("    Sanitize on release: {}", region.is_sanitize_on_release());
+			println!("    DSMAD handle: {:#x}", region.dsmad_handle);
+		}
+
+		println!("Extents (available/total): {}/{}",
+			 config.available_extents, config.total_extents);
+		println!("Tags (available/total): {}/{}",
+			 config.available_tags, config.total_tags);
+
+		Ok(())
+	}
+
 	/*
 	 * Get dynamic capacity region configuration
 	 */
@@ -71,23 +160,30 @@ pub mod cxl_dcd_command {
 			println!("{}", crate::CXL_FM_DCD_GET_CONFIG_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let host = options.get_one::<u16>(crate::CXL_FM_DCD_HOST_OPTION).unwrap();
+		let start = options.get_one::<u8>(crate::CXL_FM_DCD_START_OPTION).unwrap();
+		let count = options.get_one::<u8>(crate::CXL_FM_DCD_COUNT_OPTION).unwrap();
+
+		let request = CxlFmRequest::new(CXL_FM_DCD_GET_CONFIG_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, device)
+					.arg(CXL_FM_HOST_ARG, &host.to_string())
+					.arg(CXL_FM_START_ARG, &start.to_string())
+					.arg(CXL_FM_COUNT_ARG, &count.to_string());
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DCD_GET_CONFIG_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_capacity_config(*start, &data));
+
+				if let Err(e) = result {
+					println!("Failed to get DC region configuration: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -103,23 +199,53 @@ pub mod cxl_dcd_command {
 			println!("{}", crate::CXL_FM_DCD_SET_CONFIG_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let host = options.get_one::<u16>(crate::CXL_FM_DCD_HOST_OPTION).unwrap();
+		let region = options.get_one::<u8>(crate::CXL_FM_DCD_REGION_OPTION).unwrap();
+		let block_size = options.get_one::<String>(crate::CXL_FM_DCD_BLOCK_SIZE_OPTION).unwrap();
+		let sanitize = options.get_one::<bool>(crate::CXL_FM_DCD_SANITIZE_OPTION);
+
+		let block_size = match parse_size(block_size) {
+			Ok(block_size) => block_size,
+			Err(e) => {
+				println!("Invalid block size: {}", e);
+				return;
+			}
+		};
+
+		let mut request = CxlFmRequest::new(CXL_FM_DCD_SET_CONFIG_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, device)
+					.arg(CXL_FM_HOST_ARG, &host.to_string())
+					.arg(CXL_FM_REGION_ARG, &region.to_string())
+					.arg(CXL_FM_BLOCK_SIZE_ARG, &block_size.to_string());
+
+		/*
+		 * Sanitize on release is kept unless it is given
+		 */
+		if let Some(sanitize) = sanitize {
+			request = request.arg(CXL_FM_SANITIZE_ARG, if *sanitize { "1" } else { "0" });
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DCD_SET_CONFIG_COMMAND,
-					     &env);
+				match send_request(&stream, &request, env) {
+					Ok(_) => {
+						match sanitize {
+							Some(sanitize) => println!("Region {}: block size {}, sanitize on release {}",
+										   region, format_size(block_size), sanitize),
+							None => println!("Region {}: block size {}",
+									 region, format_size(block_size)),
+						}
+					},
+					Err(e) => {
+						println!("Failed to set DC region configuration: {}", e);
+					}
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -127,6 +253,89 @@ pub mod cxl_dcd_command {
 		}
 	}
 
+	/*
+	 * Show DPA, length and tag of extents
+	 */
+	fn show_extents(start_index: u32, extents: &[CxlDcExtent]) {
+		for (index, extent) in extents.iter().enumerate() {
+			println!("Extent {}:", start_index as usize + index);
+			println!("    DPA: {:#x}", extent.start_dpa);
+			println!("    Length: {}", format_size(extent.length));
+			println!("    Tag: {}", format_tag(&extent.tag));
+
+			if extent.shared_seq != 0 {
+				println!("    Shared extent sequence: {}", extent.shared_seq);
+			}
+		}
+	}
+
+	/*
+	 * Show Dynamic Capacity Device (DCD) extent list
+	 */
+	fn show_extent_list(data: &str) -> Result<(), CxlFmError> {
+		let list = CxlDcExtentList::from_bytes(&from_hex(data)?)?;
+
+		println!("Host ID: {}", list.host_id);
+		println!("Start index: {}", list.start_index);
+		println!("Returned extents: {}", list.extents.len());
+		println!("Total extents: {}", list.total_extents);
+		println!("Generation number: {}", list.generation);
+
+		show_extents(list.start_index, &list.extents);
+
+		Ok(())
+	}
+
+	/*
+	 * Read extent list file. Every line contains DPA and length
+	 * of an extent separated by whitespace, '#' starts a comment.
+	 */
+	fn read_extent_file(path: &str) -> Result<Vec<CxlDcExtent>, CxlFmError> {
+		let content = fs::read_to_string(path)
+			.map_err(|e| CxlFmError::Io(format!("{}: {}", path, e)))?;
+		let mut extents = Vec::new();
+
+		for line in content.lines() {
+			let line = line.split('#').next().unwrap().trim();
+
+			if line.is_empty() {
+				continue;
+			}
+
+			let fields: Vec<&str> = line.split_whitespace().collect();
+			if fields.len() != 2 {
+				return Err(CxlFmError::InvalidInput(format!("invalid extent: {}",
+									    line)));
+			}
+
+			extents.push(CxlDcExtent {
+				start_dpa: parse_size(fields[0])?,
+				length: parse_size(fields[1])?,
+				tag: [0; CXL_DCD_TAG_SIZE],
+				shared_seq: 0,
+			});
+		}
+
+		Ok(extents)
+	}
+
+	/*
+	 * Build request with optional tag and extent list file
+	 */
+	fn add_extent_args(mut request: CxlFmRequest,
+			   options: &ArgMatches) -> Result<CxlFmRequest, CxlFmError> {
+		if let Some(tag) = options.get_one::<String>(crate::CXL_FM_DCD_TAG_OPTION) {
+			request = request.arg(CXL_FM_TAG_ARG, &format_tag(&parse_tag(tag)?));
+		}
+
+		if let Some(path) = options.get_one::<String>(crate::CXL_FM_DCD_EXTENTS_OPTION) {
+			let extents = read_extent_file(path)?;
+			request = request.arg(CXL_FM_EXTENTS_ARG, &format_extent_ranges(&extents));
+		}
+
+		Ok(request)
+	}
+
 	/*
 	 * Get Dynamic Capacity Device (DCD) extent list
 	 */
@@ -135,23 +344,26 @@ pub mod cxl_dcd_command {
 			println!("{}", crate::CXL_FM_DCD_GET_EXTENT_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let host = options.get_one::<u16>(crate::CXL_FM_DCD_HOST_OPTION).unwrap();
+
+		let request = CxlFmRequest::new(CXL_FM_DCD_GET_EXTENT_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, device)
+					.arg(CXL_FM_HOST_ARG, &host.to_string());
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DCD_GET_EXTENT_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_extent_list(&data));
+
+				if let Err(e) = result {
+					println!("Failed to get DC extent list: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -159,6 +371,54 @@ pub mod cxl_dcd_command {
 		}
 	}
 
+	/*
+	 * Show extents accepted by the host and capacity
+	 * that is not accepted yet
+	 */
+	fn show_added_capacity(data: &str) -> Result<(), CxlFmError> {
+		let added = CxlDcAddResult::from_bytes(&from_hex(data)?)?;
+
+		println!("Host ID: {}", added.host_id);
+		println!("Accepted capacity: {}", format_size(added.accepted_length()));
+		println!("Accepted extents: {}", added.accepted.len());
+		println!("Pending capacity: {}", format_size(added.pending_length));
+		println!("Generation number: {}", added.generation);
+
+		show_extents(0, &added.accepted);
+
+		if !added.pending.is_empty() {
+			println!("Pending extents:");
+			show_extents(0, &added.pending);
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Build Initiate dynamic capacity add request
+	 */
+	fn add_capacity_request(options: &ArgMatches) -> Result<CxlFmRequest, CxlFmError> {
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let host = options.get_one::<u16>(crate::CXL_FM_DCD_HOST_OPTION).unwrap();
+		let region = options.get_one::<u8>(crate::CXL_FM_DCD_REGION_OPTION).unwrap();
+		let policy = options.get_one::<String>(crate::CXL_FM_DCD_POLICY_OPTION).unwrap();
+
+		let length = match options.get_one::<String>(crate::CXL_FM_DCD_LENGTH_OPTION) {
+			Some(length) => parse_size(length)?,
+			None => 0,
+		};
+
+		let request = CxlFmRequest::new(CXL_FM_DCD_ADD_CAPACITY_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, device)
+					.arg(CXL_FM_HOST_ARG, &host.to_string())
+					.arg(CXL_FM_REGION_ARG, &region.to_string())
+					.arg(CXL_FM_POLICY_ARG,
+					     &selection_policy_by_name(policy).unwrap().to_string())
+					.arg(CXL_FM_LENGTH_ARG, &length.to_string());
+
+		add_extent_args(request, options)
+	}
+
 	/*
 	 * Initiate dynamic capacity add
 	 */
@@ -167,23 +427,32 @@ pub mod cxl_dcd_command {
 			println!("{}", crate::CXL_FM_DCD_ADD_CAPACITY_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		let request = match add_capacity_request(options) {
+			Ok(request) => request,
+			Err(e) => {
+				println!("Invalid request: {}", e);
+				return;
+			}
+		};
+
+		if env.is_dry_run {
+			execute_plan(request, "add dynamic capacity", env);
+			return;
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DCD_ADD_CAPACITY_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_added_capacity(&data));
+
+				if let Err(e) = result {
+					println!("Failed to add dynamic capacity: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -191,6 +460,48 @@ pub mod cxl_dcd_command {
 		}
 	}
 
+	/*
+	 * Show extents released by the host
+	 */
+	fn show_released_capacity(data: &str) -> Result<(), CxlFmError> {
+		let released = CxlDcExtentList::from_bytes(&from_hex(data)?)?;
+		let length: u64 = released.extents.iter().map(|extent| extent.length).sum();
+
+		println!("Host ID: {}", released.host_id);
+		println!("Released capacity: {}", format_size(length));
+		println!("Released extents: {}", released.extents.len());
+		println!("Remaining extents: {}", released.total_extents);
+		println!("Generation number: {}", released.generation);
+
+		show_extents(0, &released.extents);
+
+		Ok(())
+	}
+
+	/*
+	 * Build Initiate dynamic capacity release request
+	 */
+	fn release_capacity_request(options: &ArgMatches) -> Result<CxlFmRequest, CxlFmError> {
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let host = options.get_one::<u16>(crate::CXL_FM_DCD_HOST_OPTION).unwrap();
+		let forced = options.get_flag(crate::CXL_FM_DCD_FORCED_OPTION);
+		let sanitize = options.get_flag(crate::CXL_FM_DCD_SANITIZE_OPTION);
+
+		let length = match options.get_one::<String>(crate::CXL_FM_DCD_LENGTH_OPTION) {
+			Some(length) => parse_size(length)?,
+			None => 0,
+		};
+
+		let request = CxlFmRequest::new(CXL_FM_DCD_RELEASE_CAPACITY_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, device)
+					.arg(CXL_FM_HOST_ARG, &host.to_string())
+					.arg(CXL_FM_LENGTH_ARG, &length.to_string())
+					.arg(CXL_FM_FORCED_ARG, if forced { "1" } else { "0" })
+					.arg(CXL_FM_SANITIZE_ARG, if sanitize { "1" } else { "0" });
+
+		add_extent_args(request, options)
+	}
+
 	/*
 	 * Initiate dynamic capacity release
 	 */
@@ -199,23 +510,32 @@ pub mod cxl_dcd_command {
 			println!("{}", crate::CXL_FM_DCD_RELEASE_CAPACITY_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		let request = match release_capacity_request(options) {
+			Ok(request) => request,
+			Err(e) => {
+				println!("Invalid request: {}", e);
+				return;
+			}
+		};
+
+		if env.is_dry_run {
+			execute_plan(request, "release dynamic capacity", env);
+			return;
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_DCD_RELEASE_CAPACITY_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_released_capacity(&data));
+
+				if let Err(e) = result {
+					println!("Failed to release dynamic capacity: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -21,16 +21,53 @@
 
 pub mod cxl_fm_command {
 	use clap::{ArgMatches};
+	use std::io::{BufRead, BufReader, Write};
+	use std::fs;
 	use std::net::{TcpStream};
+	use std::thread;
+	use std::time::Duration;
 	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
 	use fm_library::cxl_fm_lib::send_command;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::to_hex;
+	use fm_library::cxl_fm_lib::parse_responce;
+	use fm_library::cxl_fm_lib::CXL_FM_SUBSCRIBE_EVENTS_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_FROM_ARG;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_INFO_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_START_FM_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_RESTART_FM_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_STOP_FM_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_STATUS_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_NAME_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ADDRESS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_STATE_DIR_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_RECONCILE_INTERVAL_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_EVENT_POLL_INTERVAL_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_REMEDIATE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_TIMEOUT_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_CONFIG_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_PAYLOAD_ARG;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_SET_FM_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_EVENTS_COMMAND;
+	use fm_library::cxl_config::CxlConfig;
+	use fm_library::cxl_fm_event::CxlFmEventFilter;
+	use fm_library::cxl_fm_event::CxlFmEventList;
+	use fm_library::cxl_fm_event::CxlFmEvent;
+	use fm_library::cxl_fm_event::event_severity_by_name;
+	use fm_library::cxl_fm_event::event_severity_name;
+	use fm_library::cxl_fm_event::event_type_by_name;
+	use fm_library::cxl_fm_event::event_type_name;
+	use fm_library::cxl_fm_event::event_timestamp;
+	use fm_library::cxl_fm_event::format_timestamp;
+	use fm_library::cxl_fm_event::parse_event_time;
+	use fm_library::cxl_fm_orchestrator::CxlFmInstanceList;
+	use fm_library::cxl_fm_orchestrator::instance_state_name;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_INSTANCE_RUNNING;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_INSTANCE_CRASHED;
 
 	/*
 	 * Get Fabric Manager (FM) status/info
@@ -48,15 +85,15 @@ pub mod cxl_fm_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_GET_FM_INFO_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -65,30 +102,63 @@ pub mod cxl_fm_command {
 	}
 
 	/*
-	 * Start Fabric Manager (FM) instance
+	 * Show status of FM instances
 	 */
-	pub fn start(options: &ArgMatches, env: &CxlFmOptions) {
-		if env.is_debug {
-			println!("{}", crate::CXL_FM_START_COMMAND_DESCRIPTOR);
+	fn show_instances(data: &str) -> Result<(), CxlFmError> {
+		let list = CxlFmInstanceList::from_bytes(&from_hex(data)?)?;
+
+		if list.instances.is_empty() {
+			println!("No FM instances");
+			return Ok(());
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		for instance in &list.instances {
+			let mut line = format!("{}: {}", instance.name, instance_state_name(instance.state));
+
+			if instance.state != CXL_FM_INSTANCE_RUNNING && !instance.last_exit.is_empty() {
+				line += &format!(" ({})", instance.last_exit);
+			}
+
+			line += &format!(", address {}", instance.address);
+
+			match instance.state {
+				CXL_FM_INSTANCE_RUNNING => {
+					line += &format!(", pid {}, uptime {}s", instance.pid, instance.uptime);
+				},
+				CXL_FM_INSTANCE_CRASHED => {
+					line += &format!(", restart in {}s", instance.restart_delay);
+				},
+				_ => {},
+			}
+
+			if instance.restarts != 0 {
+				line += &format!(", restarts {}", instance.restarts);
+			}
+
+			println!("{}", line);
 		}
 
+		Ok(())
+	}
+
+	/*
+	 * Send FM instance request to orchestrator
+	 * and show status of the instance
+	 */
+	fn manage_instance(request: &CxlFmRequest, action: &str, env: &CxlFmOptions) {
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_START_FM_COMMAND,
-					     &env);
+				let result = send_request(&stream, request, env)
+						.and_then(|data| show_instances(&data));
+
+				if let Err(e) = result {
+					println!("Failed to {}: {}", action, e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -97,35 +167,65 @@ pub mod cxl_fm_command {
 	}
 
 	/*
-	 * Restart Fabric Manager (FM) instance
+	 * Add name and optional timeout of the instance to request
 	 */
-	pub fn restart(options: &ArgMatches, env: &CxlFmOptions) {
+	fn instance_request(command: &str, options: &ArgMatches) -> CxlFmRequest {
+		let name = options.get_one::<String>(crate::CXL_FM_INSTANCE_NAME_OPTION).unwrap();
+		let mut request = CxlFmRequest::new(command).arg(CXL_FM_NAME_ARG, name);
+
+		if let Some(timeout) = options.try_get_one::<u64>(crate::CXL_FM_INSTANCE_TIMEOUT_OPTION).ok().flatten() {
+			request = request.arg(CXL_FM_TIMEOUT_ARG, &timeout.to_string());
+		}
+
+		request
+	}
+
+	/*
+	 * Start Fabric Manager (FM) instance
+	 */
+	pub fn start(options: &ArgMatches, env: &CxlFmOptions) {
 		if env.is_debug {
-			println!("{}", crate::CXL_FM_RESTART_COMMAND_DESCRIPTOR);
+			println!("{}", crate::CXL_FM_START_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		let address = options.get_one::<String>(crate::CXL_FM_INSTANCE_ADDRESS_OPTION).unwrap();
+		let mut request = instance_request(CXL_FM_START_FM_COMMAND, options)
+			.arg(CXL_FM_ADDRESS_ARG, address);
+
+		if let Some(state_dir) = options.get_one::<String>(crate::CXL_FM_INSTANCE_STATE_DIR_OPTION) {
+			request = request.arg(CXL_FM_STATE_DIR_ARG, state_dir);
 		}
 
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
+		if let Some(interval) = options.get_one::<u64>(crate::CXL_FM_INSTANCE_RECONCILE_INTERVAL_OPTION) {
+			request = request.arg(CXL_FM_RECONCILE_INTERVAL_ARG, &interval.to_string());
+		}
 
-				send_command(&mut stream,
-					     CXL_FM_RESTART_FM_COMMAND,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
-			}
+		if let Some(interval) = options.get_one::<u64>(crate::CXL_FM_INSTANCE_EVENT_POLL_INTERVAL_OPTION) {
+			request = request.arg(CXL_FM_EVENT_POLL_INTERVAL_ARG, &interval.to_string());
+		}
+
+		if options.get_flag(crate::CXL_FM_INSTANCE_REMEDIATE_OPTION) {
+			request = request.arg(CXL_FM_REMEDIATE_ARG, "1");
 		}
+
+		if let Some(config) = options.get_one::<String>(crate::CXL_FM_INSTANCE_CONFIG_OPTION) {
+			request = request.arg(CXL_FM_CONFIG_ARG, config);
+		}
+
+		manage_instance(&request, "start FM instance", env);
+	}
+
+	/*
+	 * Restart Fabric Manager (FM) instance
+	 */
+	pub fn restart(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_RESTART_COMMAND_DESCRIPTOR);
+		}
+
+		let request = instance_request(CXL_FM_RESTART_FM_COMMAND, options);
+
+		manage_instance(&request, "restart FM instance", env);
 	}
 
 	/*
@@ -136,23 +236,49 @@ pub mod cxl_fm_command {
 			println!("{}", crate::CXL_FM_STOP_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		let request = instance_request(CXL_FM_STOP_FM_COMMAND, options);
+
+		manage_instance(&request, "stop FM instance", env);
+	}
+
+	/*
+	 * Show status of Fabric Manager (FM) instances
+	 */
+	pub fn status(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_STATUS_COMMAND_DESCRIPTOR);
 		}
 
+		let mut request = CxlFmRequest::new(CXL_FM_GET_FM_STATUS_COMMAND);
+
+		if let Some(name) = options.get_one::<String>(crate::CXL_FM_INSTANCE_NAME_OPTION) {
+			request = request.arg(CXL_FM_NAME_ARG, name);
+		}
+
+		manage_instance(&request, "get status of FM instances", env);
+	}
+
+	/*
+	 * Send configuration request and show
+	 * the configuration FM answers with
+	 */
+	fn configure(request: &CxlFmRequest, action: &str, env: &CxlFmOptions) {
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_STOP_FM_COMMAND,
-					     &env);
+				let result = send_request(&stream, request, env)
+						.and_then(|data| from_hex(&data))
+						.and_then(|data| String::from_utf8(data)
+							.map_err(|_| CxlFmError::Protocol(String::from("configuration is not UTF-8 text"))));
+
+				match result {
+					Ok(content) => print!("{}", content),
+					Err(e) => println!("Failed to {}: {}", action, e),
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -163,65 +289,122 @@ pub mod cxl_fm_command {
 	/*
 	 * Get Fabric Manager (FM) configuration
 	 */
-	pub fn get_config(options: &ArgMatches, env: &CxlFmOptions) {
+	pub fn get_config(_options: &ArgMatches, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", crate::CXL_FM_GET_CONFIG_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
+		let request = CxlFmRequest::new(CXL_FM_GET_FM_CONFIG_COMMAND);
 
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
+		configure(&request, "get configuration", env);
+	}
 
-				send_command(&mut stream,
-					     CXL_FM_GET_FM_CONFIG_COMMAND,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
+	/*
+	 * Build configuration changes from "table.key=value"
+	 * options, the value is in TOML syntax
+	 */
+	fn config_changes(values: Vec<&String>) -> Result<String, CxlFmError> {
+		let mut tables: Vec<(&str, Vec<String>)> = Vec::new();
+
+		for value in values {
+			let (table, key, value) = value.split_once('=')
+				.and_then(|(name, value)| name.split_once('.')
+					.map(|(table, key)| (table.trim(), key.trim(), value.trim())))
+				.ok_or(CxlFmError::InvalidInput(format!("{} is not table.key=value", value)))?;
+
+			match tables.iter_mut().find(|(name, _)| *name == table) {
+				Some((_, lines)) => lines.push(format!("{} = {}", key, value)),
+				None => tables.push((table, vec![format!("{} = {}", key, value)])),
 			}
 		}
+
+		Ok(tables.iter()
+			.map(|(table, lines)| format!("[{}]\n{}\n", table, lines.join("\n")))
+			.collect())
 	}
 
 	/*
 	 * Set Fabric Manager (FM) configuration
+	 *
+	 * Tables of configuration file or single keys
+	 * change the live configuration of FM.
 	 */
 	pub fn set_config(options: &ArgMatches, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", crate::CXL_FM_SET_CONFIG_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
-
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
+		let content = match options.get_one::<String>(crate::CXL_FM_SET_CONFIG_FILE_OPTION) {
+			Some(path) => fs::read_to_string(path)
+				.map_err(|e| CxlFmError::Io(format!("{}: {}", path, e))),
+			None => config_changes(options.get_many::<String>(crate::CXL_FM_SET_CONFIG_SET_OPTION)
+				.map(|values| values.collect())
+				.unwrap_or_default()),
+		};
 
-				send_command(&mut stream,
-					     CXL_FM_SET_FM_CONFIG_COMMAND,
-					     &env);
-			},
+		let content = match content.and_then(|content| CxlConfig::parse(&content).map(|_| content)) {
+			Ok(content) => content,
 			Err(e) => {
-				println!("Failed to connect: {}", e);
+				println!("Failed to set configuration: {}", e);
+				return;
 			}
+		};
+
+		let request = CxlFmRequest::new(CXL_FM_SET_FM_CONFIG_COMMAND)
+				.arg(CXL_FM_PAYLOAD_ARG, &to_hex(content.as_bytes()));
+
+		configure(&request, "set configuration", env);
+	}
+
+	/*
+	 * Build event filter from command options
+	 */
+	fn event_filter(options: &ArgMatches) -> Result<CxlFmEventFilter, CxlFmError> {
+		let now = event_timestamp();
+		let time = |option: &str| -> Result<Option<u64>, CxlFmError> {
+			match options.try_get_one::<String>(option).ok().flatten() {
+				Some(value) => Ok(Some(parse_event_time(value, now)?)),
+				None => Ok(None),
+			}
+		};
+
+		Ok(CxlFmEventFilter {
+			severity: options.get_one::<String>(crate::CXL_FM_GET_EVENTS_SEVERITY_OPTION)
+					.and_then(|name| event_severity_by_name(name))
+					.unwrap_or_default(),
+			component: options.get_one::<String>(crate::CXL_FM_GET_EVENTS_COMPONENT_OPTION)
+					.cloned(),
+			event_type: options.get_one::<String>(crate::CXL_FM_GET_EVENTS_TYPE_OPTION)
+					.and_then(|name| event_type_by_name(name)),
+			since: time(crate::CXL_FM_GET_EVENTS_SINCE_OPTION)?.unwrap_or(0),
+			until: time(crate::CXL_FM_GET_EVENTS_UNTIL_OPTION)?,
+		})
+	}
+
+	/*
+	 * Show FM events from the oldest to the newest
+	 */
+	fn show_events(data: &str) -> Result<(), CxlFmError> {
+		let list = CxlFmEventList::from_bytes(&from_hex(data)?)?;
+
+		if list.events.is_empty() {
+			println!("No events");
+			return Ok(());
+		}
+
+		for event in &list.events {
+			show_event(event);
 		}
+
+		Ok(())
+	}
+
+	fn show_event(event: &CxlFmEvent) {
+		println!("{:>6} {} [{}] {} {}: {}",
+			 event.sequence, format_timestamp(event.timestamp),
+			 event_severity_name(event.severity),
+			 event_type_name(event.event_type),
+			 event.component, event.message);
 	}
 
 	/*
@@ -232,27 +415,131 @@ pub mod cxl_fm_command {
 			println!("{}", crate::CXL_FM_GET_EVENTS_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
+		let filter = match event_filter(options) {
+			Ok(filter) => filter,
+			Err(e) => {
+				println!("Failed to get events: {}", e);
+				return;
+			}
+		};
+
+		let request = filter.apply(CxlFmRequest::new(CXL_FM_GET_FM_EVENTS_COMMAND));
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_GET_FM_EVENTS_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_events(&data));
+
+				if let Err(e) = result {
+					println!("Failed to get events: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
 			}
 		}
 	}
+
+	/*
+	 * Delay before subscriber reconnects (seconds)
+	 */
+	const CXL_FM_WATCH_RECONNECT_DELAY: u64 = 1;
+
+	/*
+	 * Subscribe to events starting from the sequence number
+	 * and show them until the connection is closed. The sequence
+	 * number of the next expected event is kept.
+	 */
+	fn subscribe(request: &CxlFmRequest, next_sequence: &mut u64,
+		     is_connected: &mut bool, env: &CxlFmOptions) -> CxlFmError {
+		let request = match *next_sequence {
+			0 => request.clone(),
+			next => request.clone().arg(CXL_FM_FROM_ARG, &next.to_string()),
+		};
+		let mut stream = match TcpStream::connect(&env.ip_port) {
+			Ok(stream) => stream,
+			Err(e) => return CxlFmError::Io(e.to_string()),
+		};
+
+		if env.is_debug {
+			println!("Successfully connected to server: {}", env.ip_port);
+		}
+
+		*is_connected = true;
+
+		if let Err(e) = writeln!(stream, "{}", request.clone().fm(env.fm.as_deref()).to_line()) {
+			return CxlFmError::Io(e.to_string());
+		}
+
+		for line in BufReader::new(&stream).lines() {
+			let event = line.map_err(|e| CxlFmError::Io(e.to_string()))
+				.and_then(|line| parse_responce(line.trim_end()))
+				.and_then(|data| match data.is_empty() {
+					/*
+					 * Keepalive
+					 */
+					true => Ok(None),
+					false => Ok(Some(CxlFmEvent::from_bytes(&from_hex(&data)?)?)),
+				});
+
+			match event {
+				Ok(Some(event)) => {
+					show_event(&event);
+					*next_sequence = event.sequence + 1;
+				},
+				Ok(None) => {},
+				Err(e) => return e,
+			}
+		}
+
+		CxlFmError::Io(String::from("connection closed"))
+	}
+
+	/*
+	 * Show Fabric Manager (FM) events as they occur.
+	 * Lost connection is re-established, events that
+	 * happened meanwhile are shown after reconnection.
+	 */
+	pub fn watch_events(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_WATCH_EVENTS_COMMAND_DESCRIPTOR);
+		}
+
+		let filter = match event_filter(options) {
+			Ok(filter) => filter,
+			Err(e) => {
+				println!("Failed to watch events: {}", e);
+				return;
+			}
+		};
+
+		let request = filter.apply(CxlFmRequest::new(CXL_FM_SUBSCRIBE_EVENTS_COMMAND));
+		let mut next_sequence = options.get_one::<u64>(crate::CXL_FM_WATCH_EVENTS_FROM_OPTION)
+						.copied()
+						.unwrap_or(0);
+		let mut is_connected = false;
+
+		loop {
+			match subscribe(&request, &mut next_sequence, &mut is_connected, env) {
+				CxlFmError::Io(e) if is_connected => {
+					println!("Connection lost: {}, reconnecting...", e);
+				},
+				CxlFmError::Io(e) => {
+					println!("Failed to connect: {}", e);
+					return;
+				},
+				e => {
+					println!("Failed to watch events: {}", e);
+					return;
+				},
+			}
+
+			thread::sleep(Duration::from_secs(CXL_FM_WATCH_RECONNECT_DELAY));
+		}
+	}
 }
@@ -23,7 +23,14 @@ pub mod cxl_logical_device_command {
 	use clap::{ArgMatches};
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
 	use fm_library::cxl_fm_lib::send_command;
+	use fm_library::cxl_fm_lib::parse_size;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LD_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_RANGE1_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_RANGE2_ARG;
 	use fm_library::cxl_fm_lib::CXL_FM_BIND_LD_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_UNBIND_LD_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_CONNECT_MLD_COMMAND;
@@ -38,6 +45,9 @@ pub mod cxl_logical_device_command {
 	use fm_library::cxl_fm_lib::CXL_FM_GET_QOS_BANDWIDTH_LIMIT_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_SET_QOS_BANDWIDTH_LIMIT_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_LD_ERASE;
+	use crate::cxl_ppb_command::bind_request;
+	use crate::cxl_ppb_command::unbind_request;
+	use crate::cxl_apply_command::execute_plan;
 
 	/*
 	 * Bind Logical Device (LD)
@@ -47,28 +57,8 @@ pub mod cxl_logical_device_command {
 			println!("{}", crate::CXL_FM_LOGICAL_DEVICE_BIND_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
-
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
-
-				send_command(&mut stream,
-					     CXL_FM_BIND_LD_COMMAND,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
-			}
-		}
+		execute_plan(bind_request(CXL_FM_BIND_LD_COMMAND, options),
+			     "bind logical device", env);
 	}
 
 	/*
@@ -79,28 +69,8 @@ pub mod cxl_logical_device_command {
 			println!("{}", crate::CXL_FM_LOGICAL_DEVICE_UNBIND_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
-
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
-
-				send_command(&mut stream,
-					     CXL_FM_UNBIND_LD_COMMAND,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
-			}
-		}
+		execute_plan(unbind_request(CXL_FM_UNBIND_LD_COMMAND, options),
+			     "unbind logical device", env);
 	}
 
 	/*
@@ -119,15 +89,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_CONNECT_MLD_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -151,15 +121,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_DISCONNECT_MLD_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -183,15 +153,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_GET_LD_ALLOCATION_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -199,6 +169,22 @@ pub mod cxl_logical_device_command {
 		}
 	}
 
+	/*
+	 * Build Set LD Allocations request of one LD
+	 */
+	fn set_allocation_request(options: &ArgMatches) -> Result<CxlFmRequest, CxlFmError> {
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let ld = options.get_one::<u8>(crate::CXL_FM_LOGICAL_DEVICE_LD_OPTION).unwrap();
+		let range1 = options.get_one::<String>(crate::CXL_FM_LOGICAL_DEVICE_RANGE1_OPTION).unwrap();
+		let range2 = options.get_one::<String>(crate::CXL_FM_LOGICAL_DEVICE_RANGE2_OPTION).unwrap();
+
+		Ok(CxlFmRequest::new(CXL_FM_SET_LD_ALLOCATION_COMMAND)
+			.arg(CXL_FM_DEVICE_ARG, device)
+			.arg(CXL_FM_LD_ARG, &ld.to_string())
+			.arg(CXL_FM_RANGE1_ARG, &parse_size(range1)?.to_string())
+			.arg(CXL_FM_RANGE2_ARG, &parse_size(range2)?.to_string()))
+	}
+
 	/*
 	 * Set Logical Device (LD) allocations
 	 */
@@ -207,28 +193,15 @@ pub mod cxl_logical_device_command {
 			println!("{}", crate::CXL_FM_LOGICAL_DEVICE_SET_ALLOCATION_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
-
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
-
-				send_command(&mut stream,
-					     CXL_FM_SET_LD_ALLOCATION_COMMAND,
-					     &env);
-			},
+		let request = match set_allocation_request(options) {
+			Ok(request) => request,
 			Err(e) => {
-				println!("Failed to connect: {}", e);
+				println!("Invalid request: {}", e);
+				return;
 			}
-		}
+		};
+
+		execute_plan(request, "set LD allocation", env);
 	}
 
 	/*
@@ -247,15 +220,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_GET_QOS_CONTROL_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -279,15 +252,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_SET_QOS_CONTROL_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -311,15 +284,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_GET_QOS_STATUS_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -343,15 +316,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_GET_QOS_BANDWIDTH_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -375,15 +348,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_SET_QOS_BANDWIDTH_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -407,15 +380,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_GET_QOS_BANDWIDTH_LIMIT_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -439,15 +412,15 @@ pub mod cxl_logical_device_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_SET_QOS_BANDWIDTH_LIMIT_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -463,27 +436,13 @@ pub mod cxl_logical_device_command {
 			println!("{}", crate::CXL_FM_LOGICAL_DEVICE_ERASE_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let ld = options.get_one::<u8>(crate::CXL_FM_LOGICAL_DEVICE_LD_OPTION).unwrap();
 
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
+		let request = CxlFmRequest::new(CXL_FM_LD_ERASE)
+				.arg(CXL_FM_DEVICE_ARG, device)
+				.arg(CXL_FM_LD_ARG, &ld.to_string());
 
-				send_command(&mut stream,
-					     CXL_FM_LD_ERASE,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
-			}
-		}
+		execute_plan(request, "erase logical device", env);
 	}
 }
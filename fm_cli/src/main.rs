@@ -28,8 +28,10 @@ mod pci2pci_bridge;
 mod physical_port;
 mod mld_port;
 mod dynamic_capacity_device;
+mod capacity_pool;
+mod desired_state;
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgGroup, Command};
 pub use crate::discover::cxl_fm_discover_command;
 pub use crate::fm::cxl_fm_command;
 pub use crate::switch::cxl_switch_command;
@@ -39,6 +41,8 @@ pub use crate::pci2pci_bridge::cxl_ppb_command;
 pub use crate::physical_port::cxl_physical_port_command;
 pub use crate::mld_port::cxl_mld_port_command;
 pub use crate::dynamic_capacity_device::cxl_dcd_command;
+pub use crate::capacity_pool::cxl_pool_command;
+pub use crate::desired_state::cxl_apply_command;
 pub use fm_library::cxl_fm_lib::CxlFmOptions;
 
 /*
@@ -57,6 +61,14 @@ const CXL_FM_TOOL_IP_ADDRESS_OPTION: &str = "ip";
 const CXL_FM_TOOL_IP_ADDRESS_OPTION_SHORT: char = 'i';
 const CXL_FM_TOOL_PORT_OPTION: &str = "port";
 const CXL_FM_TOOL_PORT_OPTION_SHORT: char = 'p';
+const CXL_FM_TOOL_DRY_RUN_OPTION: &str = "dry-run";
+const CXL_FM_TOOL_FM_OPTION: &str = "fm";
+
+/*
+ * Common options of commands
+ */
+const CXL_FM_DEVICE_OPTION: &str = "device";
+const CXL_FM_DEVICE_OPTION_DEFAULT: &str = "0";
 
 /*
  * Discover command strings
@@ -71,6 +83,18 @@ const CXL_FM_DISCOVER_SWITCHES_COMMAND: &str = "cxl_switch";
 const CXL_FM_DISCOVER_SWITCHES_COMMAND_DESCRIPTOR: &str = "Discover CXL switches";
 const CXL_FM_DISCOVER_LOGICAL_DEVICES_COMMAND: &str = "logical_devices";
 const CXL_FM_DISCOVER_LOGICAL_DEVICES_COMMAND_DESCRIPTOR: &str = "Discover logical devices";
+const CXL_FM_DISCOVER_TOPOLOGY_COMMAND: &str = "topology";
+const CXL_FM_DISCOVER_TOPOLOGY_COMMAND_DESCRIPTOR: &str = "Show fabric topology";
+const CXL_FM_DISCOVER_RESCAN_OPTION: &str = "rescan";
+const CXL_FM_DISCOVER_FORMAT_OPTION: &str = "format";
+const CXL_FM_DISCOVER_LISTEN_OPTION: &str = "listen";
+const CXL_FM_DISCOVER_ANNOUNCE_GROUP_OPTION: &str = "announce-group";
+const CXL_FM_DISCOVER_ANNOUNCE_INTERFACE_OPTION: &str = "announce-interface";
+const CXL_FM_DISCOVER_LISTEN_TIMEOUT_OPTION: &str = "timeout";
+const CXL_FM_DISCOVER_LISTEN_TIMEOUT_OPTION_DEFAULT: &str = "6";
+const CXL_FM_DISCOVER_FORMAT_TREE: &str = "tree";
+const CXL_FM_DISCOVER_FORMAT_DOT: &str = "dot";
+const CXL_FM_DISCOVER_FORMAT_JSON: &str = "json";
 
 /*
  * FM command strings
@@ -85,12 +109,32 @@ const CXL_FM_RESTART_COMMAND: &str = "restart";
 const CXL_FM_RESTART_COMMAND_DESCRIPTOR: &str = "Restart Fabric Manager (FM) instance";
 const CXL_FM_STOP_COMMAND: &str = "stop";
 const CXL_FM_STOP_COMMAND_DESCRIPTOR: &str = "Stop Fabric Manager (FM) instance";
+const CXL_FM_STATUS_COMMAND: &str = "status";
+const CXL_FM_STATUS_COMMAND_DESCRIPTOR: &str = "Show status of Fabric Manager (FM) instances";
+const CXL_FM_INSTANCE_NAME_OPTION: &str = "name";
+const CXL_FM_INSTANCE_ADDRESS_OPTION: &str = "address";
+const CXL_FM_INSTANCE_STATE_DIR_OPTION: &str = "state-dir";
+const CXL_FM_INSTANCE_RECONCILE_INTERVAL_OPTION: &str = "reconcile-interval";
+const CXL_FM_INSTANCE_EVENT_POLL_INTERVAL_OPTION: &str = "event-poll-interval";
+const CXL_FM_INSTANCE_REMEDIATE_OPTION: &str = "remediate";
+const CXL_FM_INSTANCE_TIMEOUT_OPTION: &str = "timeout";
+const CXL_FM_INSTANCE_CONFIG_OPTION: &str = "config";
+const CXL_FM_SET_CONFIG_SET_OPTION: &str = "set";
+const CXL_FM_SET_CONFIG_FILE_OPTION: &str = "file";
 const CXL_FM_GET_CONFIG_COMMAND: &str = "get_config";
 const CXL_FM_GET_CONFIG_COMMAND_DESCRIPTOR: &str = "Get Fabric Manager (FM) configuration";
 const CXL_FM_SET_CONFIG_COMMAND: &str = "set_config";
 const CXL_FM_SET_CONFIG_COMMAND_DESCRIPTOR: &str = "Set Fabric Manager (FM) configuration";
 const CXL_FM_GET_EVENTS_COMMAND: &str = "get_events";
 const CXL_FM_GET_EVENTS_COMMAND_DESCRIPTOR: &str = "Get Fabric Manager (FM) event records";
+const CXL_FM_GET_EVENTS_SEVERITY_OPTION: &str = "severity";
+const CXL_FM_GET_EVENTS_COMPONENT_OPTION: &str = "component";
+const CXL_FM_GET_EVENTS_TYPE_OPTION: &str = "type";
+const CXL_FM_GET_EVENTS_SINCE_OPTION: &str = "since";
+const CXL_FM_GET_EVENTS_UNTIL_OPTION: &str = "until";
+const CXL_FM_WATCH_EVENTS_COMMAND: &str = "watch_events";
+const CXL_FM_WATCH_EVENTS_COMMAND_DESCRIPTOR: &str = "Show Fabric Manager (FM) events as they occur";
+const CXL_FM_WATCH_EVENTS_FROM_OPTION: &str = "from";
 
 /*
  * Switch command strings
@@ -111,6 +155,12 @@ const CXL_FM_MH_DEVICE_COMMAND: &str = "mh_device";
 const CXL_FM_MH_DEVICE_COMMAND_DESCRIPTOR: &str = "Manage Multi Headed Device (MHD)";
 const CXL_FM_MH_DEVICE_GET_INFO_COMMAND: &str = "get_info";
 const CXL_FM_MH_DEVICE_GET_INFO_COMMAND_DESCRIPTOR: &str = "Get Multi Headed Device (MHD) status/info";
+const CXL_FM_MH_DEVICE_GET_HEAD_INFO_COMMAND: &str = "get_head_info";
+const CXL_FM_MH_DEVICE_GET_HEAD_INFO_COMMAND_DESCRIPTOR: &str = "Get link state of Multi Headed Device (MHD) heads";
+const CXL_FM_MH_DEVICE_START_OPTION: &str = "start";
+const CXL_FM_MH_DEVICE_START_OPTION_DEFAULT: &str = "0";
+const CXL_FM_MH_DEVICE_COUNT_OPTION: &str = "count";
+const CXL_FM_MH_DEVICE_COUNT_OPTION_DEFAULT: &str = "255";
 
 /*
  * Logical Device (LD) command strings
@@ -145,6 +195,10 @@ const CXL_FM_LOGICAL_DEVICE_SET_QOS_BANDWIDTH_LIMIT_COMMAND: &str = "set_qos_ban
 const CXL_FM_LOGICAL_DEVICE_SET_QOS_BANDWIDTH_LIMIT_COMMAND_DESCRIPTOR: &str = "Set QoS bandwidth limit";
 const CXL_FM_LOGICAL_DEVICE_ERASE_COMMAND: &str = "erase";
 const CXL_FM_LOGICAL_DEVICE_ERASE_COMMAND_DESCRIPTOR: &str = "Secure erase after unbinding";
+const CXL_FM_LOGICAL_DEVICE_LD_OPTION: &str = "ld";
+const CXL_FM_LOGICAL_DEVICE_RANGE1_OPTION: &str = "range1";
+const CXL_FM_LOGICAL_DEVICE_RANGE2_OPTION: &str = "range2";
+const CXL_FM_LOGICAL_DEVICE_RANGE2_OPTION_DEFAULT: &str = "0";
 
 /*
  * PCI-to-PCI Bridge (PPB) command strings
@@ -157,6 +211,20 @@ const CXL_FM_PPB_BIND_COMMAND: &str = "bind";
 const CXL_FM_PPB_BIND_COMMAND_DESCRIPTOR: &str = "Bind Virtual PCI-to-PCI Bridge (vPPB) inside a CXL switch";
 const CXL_FM_PPB_UNBIND_COMMAND: &str = "unbind";
 const CXL_FM_PPB_UNBIND_COMMAND_DESCRIPTOR: &str = "Unbind Virtual PCI-to-PCI Bridge (vPPB) inside a CXL switch";
+const CXL_FM_PPB_GENERATE_AER_COMMAND: &str = "generate_aer";
+const CXL_FM_PPB_GENERATE_AER_COMMAND_DESCRIPTOR: &str = "Generate AER event on Virtual PCI-to-PCI Bridge (vPPB)";
+const CXL_FM_PPB_SWITCH_OPTION: &str = "switch";
+const CXL_FM_PPB_VCS_OPTION: &str = "vcs";
+const CXL_FM_PPB_VPPB_OPTION: &str = "vppb";
+const CXL_FM_PPB_PORT_OPTION: &str = "port";
+const CXL_FM_PPB_LD_OPTION: &str = "ld";
+const CXL_FM_PPB_MODE_OPTION: &str = "mode";
+const CXL_FM_PPB_MODE_OPTION_DEFAULT: &str = "wait";
+const CXL_FM_PPB_MODE_WAIT: &str = "wait";
+const CXL_FM_PPB_MODE_MANAGED: &str = "managed";
+const CXL_FM_PPB_MODE_SURPRISE: &str = "surprise";
+const CXL_FM_PPB_ERROR_TYPE_OPTION: &str = "error-type";
+const CXL_FM_PPB_HEADER_OPTION: &str = "header";
 
 /*
  * Physical port command strings
@@ -171,6 +239,25 @@ const CXL_FM_PHYSICAL_PORT_BIND_COMMAND: &str = "bind";
 const CXL_FM_PHYSICAL_PORT_BIND_COMMAND_DESCRIPTOR: &str = "Bind physical port to Virtual PCI-to-PCI Bridge (vPPB)";
 const CXL_FM_PHYSICAL_PORT_UNBIND_COMMAND: &str = "unbind";
 const CXL_FM_PHYSICAL_PORT_UNBIND_COMMAND_DESCRIPTOR: &str = "Unbind physical port from Virtual PCI-to-PCI Bridge (vPPB)";
+const CXL_FM_PHYSICAL_PORT_REMOVE_COMMAND: &str = "remove";
+const CXL_FM_PHYSICAL_PORT_REMOVE_COMMAND_DESCRIPTOR: &str = "Prepare device attached to physical port for managed removal";
+#[cfg(feature = "emulation")]
+const CXL_FM_PHYSICAL_PORT_HOT_PLUG_COMMAND: &str = "hot_plug";
+#[cfg(feature = "emulation")]
+const CXL_FM_PHYSICAL_PORT_HOT_PLUG_COMMAND_DESCRIPTOR: &str = "Insert device into slot of emulated switch, pull it out or fail its link";
+const CXL_FM_PHYSICAL_PORT_OPERATION_OPTION: &str = "operation";
+const CXL_FM_PHYSICAL_PORT_OPERATION_OPTION_DEFAULT: &str = "reset-ppb";
+const CXL_FM_PHYSICAL_PORT_ASSERT_PERST: &str = "assert-perst";
+const CXL_FM_PHYSICAL_PORT_DEASSERT_PERST: &str = "deassert-perst";
+const CXL_FM_PHYSICAL_PORT_RESET_PPB: &str = "reset-ppb";
+#[cfg(feature = "emulation")]
+const CXL_FM_PHYSICAL_PORT_SLOT_INSERT: &str = "insert";
+#[cfg(feature = "emulation")]
+const CXL_FM_PHYSICAL_PORT_SLOT_REMOVE: &str = "remove";
+#[cfg(feature = "emulation")]
+const CXL_FM_PHYSICAL_PORT_LINK_DOWN: &str = "link-down";
+#[cfg(feature = "emulation")]
+const CXL_FM_PHYSICAL_PORT_LINK_UP: &str = "link-up";
 
 /*
  * Multi-Logical Device (MLD) ports command strings
@@ -201,6 +288,87 @@ const CXL_FM_DCD_ADD_CAPACITY_COMMAND: &str = "add_capacity";
 const CXL_FM_DCD_ADD_CAPACITY_COMMAND_DESCRIPTOR: &str = "Initiate dynamic capacity add";
 const CXL_FM_DCD_RELEASE_CAPACITY_COMMAND: &str = "release_capacity";
 const CXL_FM_DCD_RELEASE_CAPACITY_COMMAND_DESCRIPTOR: &str = "Initiate dynamic capacity release";
+const CXL_FM_DCD_HOST_OPTION: &str = "host";
+const CXL_FM_DCD_START_OPTION: &str = "start";
+const CXL_FM_DCD_START_OPTION_DEFAULT: &str = "0";
+const CXL_FM_DCD_COUNT_OPTION: &str = "count";
+const CXL_FM_DCD_COUNT_OPTION_DEFAULT: &str = "8";
+const CXL_FM_DCD_REGION_OPTION: &str = "region";
+const CXL_FM_DCD_BLOCK_SIZE_OPTION: &str = "block-size";
+const CXL_FM_DCD_SANITIZE_OPTION: &str = "sanitize-on-release";
+const CXL_FM_DCD_POLICY_OPTION: &str = "policy";
+const CXL_FM_DCD_POLICY_OPTION_DEFAULT: &str = "free";
+const CXL_FM_DCD_POLICY_FREE: &str = "free";
+const CXL_FM_DCD_POLICY_CONTIGUOUS: &str = "contiguous";
+const CXL_FM_DCD_POLICY_PRESCRIPTIVE: &str = "prescriptive";
+const CXL_FM_DCD_POLICY_SHARED_ACCESS: &str = "shared-access";
+const CXL_FM_DCD_LENGTH_OPTION: &str = "length";
+const CXL_FM_DCD_TAG_OPTION: &str = "tag";
+const CXL_FM_DCD_EXTENTS_OPTION: &str = "extents";
+const CXL_FM_DCD_FORCED_OPTION: &str = "forced";
+const CXL_FM_DCD_RELEASE_GROUP: &str = "release";
+
+/*
+ * Dynamic capacity pool command strings
+ */
+const CXL_FM_POOL_COMMAND: &str = "pool";
+const CXL_FM_POOL_COMMAND_DESCRIPTOR: &str = "Manage fabric-wide dynamic capacity pool";
+const CXL_FM_POOL_GET_INFO_COMMAND: &str = "get_info";
+const CXL_FM_POOL_GET_INFO_COMMAND_DESCRIPTOR: &str = "Get free capacity of DCD regions in the pool";
+const CXL_FM_POOL_ALLOCATE_COMMAND: &str = "allocate";
+const CXL_FM_POOL_ALLOCATE_COMMAND_DESCRIPTOR: &str = "Grant capacity from the pool to a host";
+const CXL_FM_POOL_HOST_OPTION: &str = "host";
+const CXL_FM_POOL_LENGTH_OPTION: &str = "length";
+const CXL_FM_POOL_PLACEMENT_OPTION: &str = "placement";
+const CXL_FM_POOL_PLACEMENT_PACK: &str = "pack";
+const CXL_FM_POOL_PLACEMENT_SPREAD: &str = "spread";
+const CXL_FM_POOL_PLACEMENT_LOCALITY: &str = "locality";
+const CXL_FM_POOL_LOCALITY_OPTION: &str = "locality";
+const CXL_FM_POOL_TAG_OPTION: &str = "tag";
+
+/*
+ * Desired state command strings
+ */
+const CXL_FM_APPLY_COMMAND: &str = "apply";
+const CXL_FM_APPLY_COMMAND_DESCRIPTOR: &str = "Bring the fabric to the state declared in TOML file";
+const CXL_FM_APPLY_FILE_OPTION: &str = "file";
+const CXL_FM_APPLY_FILE_OPTION_SHORT: char = 'f';
+const CXL_FM_RECONCILE_COMMAND: &str = "reconcile";
+const CXL_FM_RECONCILE_COMMAND_DESCRIPTOR: &str = "Compare the fabric with applied desired state and report drift";
+const CXL_FM_RECONCILE_REMEDIATE_OPTION: &str = "remediate";
+
+/*
+ * Commands of emulated components (emulation feature)
+ */
+#[cfg(feature = "emulation")]
+fn physical_port_emulation_commands() -> Vec<Command> {
+	vec![
+		Command::new(CXL_FM_PHYSICAL_PORT_HOT_PLUG_COMMAND)
+			.about(CXL_FM_PHYSICAL_PORT_HOT_PLUG_COMMAND_DESCRIPTOR)
+			.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+				.long(CXL_FM_PPB_SWITCH_OPTION)
+				.action(clap::ArgAction::Set)
+				.required(true))
+			.arg(Arg::new(CXL_FM_PPB_PORT_OPTION)
+				.long(CXL_FM_PPB_PORT_OPTION)
+				.action(clap::ArgAction::Set)
+				.value_parser(clap::value_parser!(u8))
+				.required(true))
+			.arg(Arg::new(CXL_FM_PHYSICAL_PORT_OPERATION_OPTION)
+				.long(CXL_FM_PHYSICAL_PORT_OPERATION_OPTION)
+				.action(clap::ArgAction::Set)
+				.value_parser([CXL_FM_PHYSICAL_PORT_SLOT_INSERT,
+					       CXL_FM_PHYSICAL_PORT_SLOT_REMOVE,
+					       CXL_FM_PHYSICAL_PORT_LINK_DOWN,
+					       CXL_FM_PHYSICAL_PORT_LINK_UP])
+				.required(true))
+	]
+}
+
+#[cfg(not(feature = "emulation"))]
+fn physical_port_emulation_commands() -> Vec<Command> {
+	Vec::new()
+}
 
 /*
  * Command line interface definition
@@ -226,6 +394,14 @@ fn cli() -> Command {
 			.long(CXL_FM_TOOL_PORT_OPTION)
 			.action(clap::ArgAction::Set)
 			.required(true))
+		.arg(Arg::new(CXL_FM_TOOL_DRY_RUN_OPTION)
+			.long(CXL_FM_TOOL_DRY_RUN_OPTION)
+			.action(clap::ArgAction::SetTrue)
+			.global(true))
+		.arg(Arg::new(CXL_FM_TOOL_FM_OPTION)
+			.long(CXL_FM_TOOL_FM_OPTION)
+			.action(clap::ArgAction::Set)
+			.global(true))
 		.subcommand(
 			Command::new(CXL_FM_DISCOVER_COMMAND)
 				.about(CXL_FM_DISCOVER_COMMAND_DESCRIPTOR)
@@ -235,18 +411,57 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_DISCOVER_FM_COMMAND)
 						.about(CXL_FM_DISCOVER_FM_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DISCOVER_LISTEN_OPTION)
+							.long(CXL_FM_DISCOVER_LISTEN_OPTION)
+							.action(clap::ArgAction::SetTrue))
+						.arg(Arg::new(CXL_FM_DISCOVER_ANNOUNCE_GROUP_OPTION)
+							.long(CXL_FM_DISCOVER_ANNOUNCE_GROUP_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(fm_library::cxl_fm_announce::CXL_FM_ANNOUNCE_GROUP_DEFAULT))
+						.arg(Arg::new(CXL_FM_DISCOVER_ANNOUNCE_INTERFACE_OPTION)
+							.long(CXL_FM_DISCOVER_ANNOUNCE_INTERFACE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(fm_library::cxl_fm_announce::CXL_FM_ANNOUNCE_INTERFACE_DEFAULT))
+						.arg(Arg::new(CXL_FM_DISCOVER_LISTEN_TIMEOUT_OPTION)
+							.long(CXL_FM_DISCOVER_LISTEN_TIMEOUT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u64))
+							.default_value(CXL_FM_DISCOVER_LISTEN_TIMEOUT_OPTION_DEFAULT))
 				)
 				.subcommand(
 					Command::new(CXL_FM_DISCOVER_DEVICES_COMMAND)
 						.about(CXL_FM_DISCOVER_DEVICES_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DISCOVER_RESCAN_OPTION)
+							.long(CXL_FM_DISCOVER_RESCAN_OPTION)
+							.action(clap::ArgAction::SetTrue))
 				)
 				.subcommand(
 					Command::new(CXL_FM_DISCOVER_SWITCHES_COMMAND)
 						.about(CXL_FM_DISCOVER_SWITCHES_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DISCOVER_RESCAN_OPTION)
+							.long(CXL_FM_DISCOVER_RESCAN_OPTION)
+							.action(clap::ArgAction::SetTrue))
 				)
 				.subcommand(
 					Command::new(CXL_FM_DISCOVER_LOGICAL_DEVICES_COMMAND)
 						.about(CXL_FM_DISCOVER_LOGICAL_DEVICES_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DISCOVER_RESCAN_OPTION)
+							.long(CXL_FM_DISCOVER_RESCAN_OPTION)
+							.action(clap::ArgAction::SetTrue))
+				)
+				.subcommand(
+					Command::new(CXL_FM_DISCOVER_TOPOLOGY_COMMAND)
+						.about(CXL_FM_DISCOVER_TOPOLOGY_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DISCOVER_FORMAT_OPTION)
+							.long(CXL_FM_DISCOVER_FORMAT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser([CXL_FM_DISCOVER_FORMAT_TREE,
+								       CXL_FM_DISCOVER_FORMAT_DOT,
+								       CXL_FM_DISCOVER_FORMAT_JSON])
+							.default_value(CXL_FM_DISCOVER_FORMAT_TREE))
+						.arg(Arg::new(CXL_FM_DISCOVER_RESCAN_OPTION)
+							.long(CXL_FM_DISCOVER_RESCAN_OPTION)
+							.action(clap::ArgAction::SetTrue))
 				)
 		)
 		.subcommand(
@@ -262,14 +477,62 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_START_COMMAND)
 						.about(CXL_FM_START_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_INSTANCE_NAME_OPTION)
+							.long(CXL_FM_INSTANCE_NAME_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_INSTANCE_ADDRESS_OPTION)
+							.long(CXL_FM_INSTANCE_ADDRESS_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_INSTANCE_STATE_DIR_OPTION)
+							.long(CXL_FM_INSTANCE_STATE_DIR_OPTION)
+							.action(clap::ArgAction::Set))
+						.arg(Arg::new(CXL_FM_INSTANCE_RECONCILE_INTERVAL_OPTION)
+							.long(CXL_FM_INSTANCE_RECONCILE_INTERVAL_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u64)))
+						.arg(Arg::new(CXL_FM_INSTANCE_EVENT_POLL_INTERVAL_OPTION)
+							.long(CXL_FM_INSTANCE_EVENT_POLL_INTERVAL_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u64)))
+						.arg(Arg::new(CXL_FM_INSTANCE_REMEDIATE_OPTION)
+							.long(CXL_FM_INSTANCE_REMEDIATE_OPTION)
+							.action(clap::ArgAction::SetTrue))
+						.arg(Arg::new(CXL_FM_INSTANCE_CONFIG_OPTION)
+							.long(CXL_FM_INSTANCE_CONFIG_OPTION)
+							.action(clap::ArgAction::Set))
 				)
 				.subcommand(
 					Command::new(CXL_FM_RESTART_COMMAND)
 						.about(CXL_FM_RESTART_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_INSTANCE_NAME_OPTION)
+							.long(CXL_FM_INSTANCE_NAME_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_INSTANCE_TIMEOUT_OPTION)
+							.long(CXL_FM_INSTANCE_TIMEOUT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u64)))
 				)
 				.subcommand(
 					Command::new(CXL_FM_STOP_COMMAND)
 						.about(CXL_FM_STOP_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_INSTANCE_NAME_OPTION)
+							.long(CXL_FM_INSTANCE_NAME_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_INSTANCE_TIMEOUT_OPTION)
+							.long(CXL_FM_INSTANCE_TIMEOUT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u64)))
+				)
+				.subcommand(
+					Command::new(CXL_FM_STATUS_COMMAND)
+						.about(CXL_FM_STATUS_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_INSTANCE_NAME_OPTION)
+							.long(CXL_FM_INSTANCE_NAME_OPTION)
+							.action(clap::ArgAction::Set))
 				)
 				.subcommand(
 					Command::new(CXL_FM_GET_CONFIG_COMMAND)
@@ -278,10 +541,60 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_SET_CONFIG_COMMAND)
 						.about(CXL_FM_SET_CONFIG_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_SET_CONFIG_SET_OPTION)
+							.long(CXL_FM_SET_CONFIG_SET_OPTION)
+							.action(clap::ArgAction::Append))
+						.arg(Arg::new(CXL_FM_SET_CONFIG_FILE_OPTION)
+							.long(CXL_FM_SET_CONFIG_FILE_OPTION)
+							.action(clap::ArgAction::Set)
+							.conflicts_with(CXL_FM_SET_CONFIG_SET_OPTION))
+						.arg_required_else_help(true)
 				)
 				.subcommand(
 					Command::new(CXL_FM_GET_EVENTS_COMMAND)
 						.about(CXL_FM_GET_EVENTS_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_GET_EVENTS_SEVERITY_OPTION)
+							.long(CXL_FM_GET_EVENTS_SEVERITY_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(["info", "warning", "failure", "fatal"]))
+						.arg(Arg::new(CXL_FM_GET_EVENTS_COMPONENT_OPTION)
+							.long(CXL_FM_GET_EVENTS_COMPONENT_OPTION)
+							.action(clap::ArgAction::Set))
+						.arg(Arg::new(CXL_FM_GET_EVENTS_TYPE_OPTION)
+							.long(CXL_FM_GET_EVENTS_TYPE_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(["drift", "remediation", "recovery",
+								       "device", "link", "hot-plug",
+								       "capacity", "operation", "policy",
+								       "failover"]))
+						.arg(Arg::new(CXL_FM_GET_EVENTS_SINCE_OPTION)
+							.long(CXL_FM_GET_EVENTS_SINCE_OPTION)
+							.action(clap::ArgAction::Set))
+						.arg(Arg::new(CXL_FM_GET_EVENTS_UNTIL_OPTION)
+							.long(CXL_FM_GET_EVENTS_UNTIL_OPTION)
+							.action(clap::ArgAction::Set))
+				)
+				.subcommand(
+					Command::new(CXL_FM_WATCH_EVENTS_COMMAND)
+						.about(CXL_FM_WATCH_EVENTS_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_GET_EVENTS_SEVERITY_OPTION)
+							.long(CXL_FM_GET_EVENTS_SEVERITY_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(["info", "warning", "failure", "fatal"]))
+						.arg(Arg::new(CXL_FM_GET_EVENTS_COMPONENT_OPTION)
+							.long(CXL_FM_GET_EVENTS_COMPONENT_OPTION)
+							.action(clap::ArgAction::Set))
+						.arg(Arg::new(CXL_FM_GET_EVENTS_TYPE_OPTION)
+							.long(CXL_FM_GET_EVENTS_TYPE_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(["drift", "remediation", "recovery",
+								       "device", "link", "hot-plug",
+								       "capacity", "operation", "policy",
+								       "failover"]))
+						.arg(Arg::new(CXL_FM_WATCH_EVENTS_FROM_OPTION)
+							.long(CXL_FM_WATCH_EVENTS_FROM_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u64)))
 				)
 		)
 		.subcommand(
@@ -312,6 +625,28 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_MH_DEVICE_GET_INFO_COMMAND)
 						.about(CXL_FM_MH_DEVICE_GET_INFO_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+				)
+				.subcommand(
+					Command::new(CXL_FM_MH_DEVICE_GET_HEAD_INFO_COMMAND)
+						.about(CXL_FM_MH_DEVICE_GET_HEAD_INFO_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_MH_DEVICE_START_OPTION)
+							.long(CXL_FM_MH_DEVICE_START_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.default_value(CXL_FM_MH_DEVICE_START_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_MH_DEVICE_COUNT_OPTION)
+							.long(CXL_FM_MH_DEVICE_COUNT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8).range(1..))
+							.default_value(CXL_FM_MH_DEVICE_COUNT_OPTION_DEFAULT))
 				)
 		)
 		.subcommand(
@@ -323,10 +658,55 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_LOGICAL_DEVICE_BIND_COMMAND)
 						.about(CXL_FM_LOGICAL_DEVICE_BIND_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VCS_OPTION)
+							.long(CXL_FM_PPB_VCS_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VPPB_OPTION)
+							.long(CXL_FM_PPB_VPPB_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_PORT_OPTION)
+							.long(CXL_FM_PPB_PORT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_LD_OPTION)
+							.long(CXL_FM_PPB_LD_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16))
+							.required(true))
 				)
 				.subcommand(
 					Command::new(CXL_FM_LOGICAL_DEVICE_UNBIND_COMMAND)
 						.about(CXL_FM_LOGICAL_DEVICE_UNBIND_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VCS_OPTION)
+							.long(CXL_FM_PPB_VCS_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VPPB_OPTION)
+							.long(CXL_FM_PPB_VPPB_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_MODE_OPTION)
+							.long(CXL_FM_PPB_MODE_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser([CXL_FM_PPB_MODE_WAIT,
+								       CXL_FM_PPB_MODE_MANAGED,
+								       CXL_FM_PPB_MODE_SURPRISE])
+							.default_value(CXL_FM_PPB_MODE_OPTION_DEFAULT))
 				)
 				.subcommand(
 					Command::new(CXL_FM_LOGICAL_DEVICE_CONNECT_COMMAND)
@@ -343,6 +723,23 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_LOGICAL_DEVICE_SET_ALLOCATION_COMMAND)
 						.about(CXL_FM_LOGICAL_DEVICE_SET_ALLOCATION_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_LOGICAL_DEVICE_LD_OPTION)
+							.long(CXL_FM_LOGICAL_DEVICE_LD_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_LOGICAL_DEVICE_RANGE1_OPTION)
+							.long(CXL_FM_LOGICAL_DEVICE_RANGE1_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_LOGICAL_DEVICE_RANGE2_OPTION)
+							.long(CXL_FM_LOGICAL_DEVICE_RANGE2_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_LOGICAL_DEVICE_RANGE2_OPTION_DEFAULT))
 				)
 				.subcommand(
 					Command::new(CXL_FM_LOGICAL_DEVICE_GET_QOS_CONTROL_COMMAND)
@@ -375,6 +772,15 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_LOGICAL_DEVICE_ERASE_COMMAND)
 						.about(CXL_FM_LOGICAL_DEVICE_ERASE_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_LOGICAL_DEVICE_LD_OPTION)
+							.long(CXL_FM_LOGICAL_DEVICE_LD_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
 				)
 		)
 		.subcommand(
@@ -390,10 +796,80 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_PPB_BIND_COMMAND)
 						.about(CXL_FM_PPB_BIND_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VCS_OPTION)
+							.long(CXL_FM_PPB_VCS_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VPPB_OPTION)
+							.long(CXL_FM_PPB_VPPB_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_PORT_OPTION)
+							.long(CXL_FM_PPB_PORT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_LD_OPTION)
+							.long(CXL_FM_PPB_LD_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16)))
 				)
 				.subcommand(
 					Command::new(CXL_FM_PPB_UNBIND_COMMAND)
 						.about(CXL_FM_PPB_UNBIND_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VCS_OPTION)
+							.long(CXL_FM_PPB_VCS_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VPPB_OPTION)
+							.long(CXL_FM_PPB_VPPB_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_MODE_OPTION)
+							.long(CXL_FM_PPB_MODE_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser([CXL_FM_PPB_MODE_WAIT,
+								       CXL_FM_PPB_MODE_MANAGED,
+								       CXL_FM_PPB_MODE_SURPRISE])
+							.default_value(CXL_FM_PPB_MODE_OPTION_DEFAULT))
+				)
+				.subcommand(
+					Command::new(CXL_FM_PPB_GENERATE_AER_COMMAND)
+						.about(CXL_FM_PPB_GENERATE_AER_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VCS_OPTION)
+							.long(CXL_FM_PPB_VCS_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VPPB_OPTION)
+							.long(CXL_FM_PPB_VPPB_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_ERROR_TYPE_OPTION)
+							.long(CXL_FM_PPB_ERROR_TYPE_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_HEADER_OPTION)
+							.long(CXL_FM_PPB_HEADER_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
 				)
 		)
 		.subcommand(
@@ -409,15 +885,85 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND)
 						.about(CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_PORT_OPTION)
+							.long(CXL_FM_PPB_PORT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PHYSICAL_PORT_OPERATION_OPTION)
+							.long(CXL_FM_PHYSICAL_PORT_OPERATION_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser([CXL_FM_PHYSICAL_PORT_ASSERT_PERST,
+								       CXL_FM_PHYSICAL_PORT_DEASSERT_PERST,
+								       CXL_FM_PHYSICAL_PORT_RESET_PPB])
+							.default_value(CXL_FM_PHYSICAL_PORT_OPERATION_OPTION_DEFAULT))
 				)
 				.subcommand(
 					Command::new(CXL_FM_PHYSICAL_PORT_BIND_COMMAND)
 						.about(CXL_FM_PHYSICAL_PORT_BIND_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VCS_OPTION)
+							.long(CXL_FM_PPB_VCS_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VPPB_OPTION)
+							.long(CXL_FM_PPB_VPPB_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_PORT_OPTION)
+							.long(CXL_FM_PPB_PORT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
 				)
 				.subcommand(
 					Command::new(CXL_FM_PHYSICAL_PORT_UNBIND_COMMAND)
 						.about(CXL_FM_PHYSICAL_PORT_UNBIND_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VCS_OPTION)
+							.long(CXL_FM_PPB_VCS_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_VPPB_OPTION)
+							.long(CXL_FM_PPB_VPPB_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_MODE_OPTION)
+							.long(CXL_FM_PPB_MODE_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser([CXL_FM_PPB_MODE_WAIT,
+								       CXL_FM_PPB_MODE_MANAGED,
+								       CXL_FM_PPB_MODE_SURPRISE])
+							.default_value(CXL_FM_PPB_MODE_OPTION_DEFAULT))
+				)
+				.subcommand(
+					Command::new(CXL_FM_PHYSICAL_PORT_REMOVE_COMMAND)
+						.about(CXL_FM_PHYSICAL_PORT_REMOVE_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_PPB_SWITCH_OPTION)
+							.long(CXL_FM_PPB_SWITCH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_PPB_PORT_OPTION)
+							.long(CXL_FM_PPB_PORT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
 				)
+				.subcommands(physical_port_emulation_commands())
 		)
 		.subcommand(
 			Command::new(CXL_FM_MLD_PORT_COMMAND)
@@ -447,28 +993,208 @@ fn cli() -> Command {
 				.subcommand(
 					Command::new(CXL_FM_DCD_GET_INFO_COMMAND)
 						.about(CXL_FM_DCD_GET_INFO_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
 				)
 				.subcommand(
 					Command::new(CXL_FM_DCD_GET_CONFIG_COMMAND)
 						.about(CXL_FM_DCD_GET_CONFIG_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_DCD_HOST_OPTION)
+							.long(CXL_FM_DCD_HOST_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16))
+							.required(true))
+						.arg(Arg::new(CXL_FM_DCD_START_OPTION)
+							.long(CXL_FM_DCD_START_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.default_value(CXL_FM_DCD_START_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_DCD_COUNT_OPTION)
+							.long(CXL_FM_DCD_COUNT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8).range(1..))
+							.default_value(CXL_FM_DCD_COUNT_OPTION_DEFAULT))
 				)
 				.subcommand(
 					Command::new(CXL_FM_DCD_SET_CONFIG_COMMAND)
 						.about(CXL_FM_DCD_SET_CONFIG_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_DCD_HOST_OPTION)
+							.long(CXL_FM_DCD_HOST_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16))
+							.required(true))
+						.arg(Arg::new(CXL_FM_DCD_REGION_OPTION)
+							.long(CXL_FM_DCD_REGION_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_DCD_BLOCK_SIZE_OPTION)
+							.long(CXL_FM_DCD_BLOCK_SIZE_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_DCD_SANITIZE_OPTION)
+							.long(CXL_FM_DCD_SANITIZE_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(bool)))
 				)
 				.subcommand(
 					Command::new(CXL_FM_DCD_GET_EXTENT_COMMAND)
 						.about(CXL_FM_DCD_GET_EXTENT_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_DCD_HOST_OPTION)
+							.long(CXL_FM_DCD_HOST_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16))
+							.required(true))
 				)
 				.subcommand(
 					Command::new(CXL_FM_DCD_ADD_CAPACITY_COMMAND)
 						.about(CXL_FM_DCD_ADD_CAPACITY_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_DCD_HOST_OPTION)
+							.long(CXL_FM_DCD_HOST_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16))
+							.required(true))
+						.arg(Arg::new(CXL_FM_DCD_REGION_OPTION)
+							.long(CXL_FM_DCD_REGION_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u8))
+							.required(true))
+						.arg(Arg::new(CXL_FM_DCD_POLICY_OPTION)
+							.long(CXL_FM_DCD_POLICY_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser([CXL_FM_DCD_POLICY_FREE,
+								       CXL_FM_DCD_POLICY_CONTIGUOUS,
+								       CXL_FM_DCD_POLICY_PRESCRIPTIVE,
+								       CXL_FM_DCD_POLICY_SHARED_ACCESS])
+							.default_value(CXL_FM_DCD_POLICY_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_DCD_LENGTH_OPTION)
+							.long(CXL_FM_DCD_LENGTH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required_if_eq_any([(CXL_FM_DCD_POLICY_OPTION,
+									      CXL_FM_DCD_POLICY_FREE),
+									     (CXL_FM_DCD_POLICY_OPTION,
+									      CXL_FM_DCD_POLICY_CONTIGUOUS)]))
+						.arg(Arg::new(CXL_FM_DCD_TAG_OPTION)
+							.long(CXL_FM_DCD_TAG_OPTION)
+							.action(clap::ArgAction::Set)
+							.required_if_eq(CXL_FM_DCD_POLICY_OPTION,
+									CXL_FM_DCD_POLICY_SHARED_ACCESS))
+						.arg(Arg::new(CXL_FM_DCD_EXTENTS_OPTION)
+							.long(CXL_FM_DCD_EXTENTS_OPTION)
+							.action(clap::ArgAction::Set)
+							.required_if_eq(CXL_FM_DCD_POLICY_OPTION,
+									CXL_FM_DCD_POLICY_PRESCRIPTIVE))
 				)
 				.subcommand(
 					Command::new(CXL_FM_DCD_RELEASE_CAPACITY_COMMAND)
 						.about(CXL_FM_DCD_RELEASE_CAPACITY_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_DEVICE_OPTION)
+							.long(CXL_FM_DEVICE_OPTION)
+							.action(clap::ArgAction::Set)
+							.default_value(CXL_FM_DEVICE_OPTION_DEFAULT))
+						.arg(Arg::new(CXL_FM_DCD_HOST_OPTION)
+							.long(CXL_FM_DCD_HOST_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16))
+							.required(true))
+						.arg(Arg::new(CXL_FM_DCD_TAG_OPTION)
+							.long(CXL_FM_DCD_TAG_OPTION)
+							.action(clap::ArgAction::Set))
+						.arg(Arg::new(CXL_FM_DCD_EXTENTS_OPTION)
+							.long(CXL_FM_DCD_EXTENTS_OPTION)
+							.action(clap::ArgAction::Set)
+							.conflicts_with(CXL_FM_DCD_LENGTH_OPTION))
+						.arg(Arg::new(CXL_FM_DCD_LENGTH_OPTION)
+							.long(CXL_FM_DCD_LENGTH_OPTION)
+							.action(clap::ArgAction::Set))
+						.arg(Arg::new(CXL_FM_DCD_FORCED_OPTION)
+							.long(CXL_FM_DCD_FORCED_OPTION)
+							.action(clap::ArgAction::SetTrue))
+						.arg(Arg::new(CXL_FM_DCD_SANITIZE_OPTION)
+							.long(CXL_FM_DCD_SANITIZE_OPTION)
+							.action(clap::ArgAction::SetTrue))
+						.group(ArgGroup::new(CXL_FM_DCD_RELEASE_GROUP)
+							.args([CXL_FM_DCD_TAG_OPTION,
+							       CXL_FM_DCD_EXTENTS_OPTION,
+							       CXL_FM_DCD_LENGTH_OPTION])
+							.required(true)
+							.multiple(true))
 				)
 		)
+		.subcommand(
+			Command::new(CXL_FM_POOL_COMMAND)
+				.about(CXL_FM_POOL_COMMAND_DESCRIPTOR)
+				.subcommand_required(true)
+				.arg_required_else_help(true)
+				.allow_external_subcommands(true)
+				.subcommand(
+					Command::new(CXL_FM_POOL_GET_INFO_COMMAND)
+						.about(CXL_FM_POOL_GET_INFO_COMMAND_DESCRIPTOR)
+				)
+				.subcommand(
+					Command::new(CXL_FM_POOL_ALLOCATE_COMMAND)
+						.about(CXL_FM_POOL_ALLOCATE_COMMAND_DESCRIPTOR)
+						.arg(Arg::new(CXL_FM_POOL_HOST_OPTION)
+							.long(CXL_FM_POOL_HOST_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16))
+							.required(true))
+						.arg(Arg::new(CXL_FM_POOL_LENGTH_OPTION)
+							.long(CXL_FM_POOL_LENGTH_OPTION)
+							.action(clap::ArgAction::Set)
+							.required(true))
+						.arg(Arg::new(CXL_FM_POOL_PLACEMENT_OPTION)
+							.long(CXL_FM_POOL_PLACEMENT_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser([CXL_FM_POOL_PLACEMENT_PACK,
+								       CXL_FM_POOL_PLACEMENT_SPREAD,
+								       CXL_FM_POOL_PLACEMENT_LOCALITY])
+							.default_value(CXL_FM_POOL_PLACEMENT_PACK))
+						.arg(Arg::new(CXL_FM_POOL_LOCALITY_OPTION)
+							.long(CXL_FM_POOL_LOCALITY_OPTION)
+							.action(clap::ArgAction::Set)
+							.value_parser(clap::value_parser!(u16))
+							.required_if_eq(CXL_FM_POOL_PLACEMENT_OPTION,
+									CXL_FM_POOL_PLACEMENT_LOCALITY))
+						.arg(Arg::new(CXL_FM_POOL_TAG_OPTION)
+							.long(CXL_FM_POOL_TAG_OPTION)
+							.action(clap::ArgAction::Set))
+				)
+		)
+		.subcommand(
+			Command::new(CXL_FM_APPLY_COMMAND)
+				.about(CXL_FM_APPLY_COMMAND_DESCRIPTOR)
+				.arg(Arg::new(CXL_FM_APPLY_FILE_OPTION)
+					.short(CXL_FM_APPLY_FILE_OPTION_SHORT)
+					.long(CXL_FM_APPLY_FILE_OPTION)
+					.action(clap::ArgAction::Set)
+					.required(true))
+		)
+		.subcommand(
+			Command::new(CXL_FM_RECONCILE_COMMAND)
+				.about(CXL_FM_RECONCILE_COMMAND_DESCRIPTOR)
+				.arg(Arg::new(CXL_FM_RECONCILE_REMEDIATE_OPTION)
+					.long(CXL_FM_RECONCILE_REMEDIATE_OPTION)
+					.action(clap::ArgAction::SetTrue))
+		)
 }
 
 /*
@@ -482,8 +1208,10 @@ fn main() {
 	let ip_port = format!("{ip}:{port}");
 
 	let options = CxlFmOptions {
-		ip_port: String::from(ip_port),
-		is_debug: matches.get_flag(CXL_FM_TOOL_DEBUG_OPTION) == true,
+		ip_port,
+		is_debug: matches.get_flag(CXL_FM_TOOL_DEBUG_OPTION),
+		is_dry_run: matches.get_flag(CXL_FM_TOOL_DRY_RUN_OPTION),
+		fm: matches.get_one::<String>(CXL_FM_TOOL_FM_OPTION).cloned(),
 	};
 
 	if options.is_debug {
@@ -494,69 +1222,81 @@ fn main() {
 		Some((CXL_FM_DISCOVER_COMMAND, discover)) => {
 			match discover.subcommand() {
 				Some((CXL_FM_DISCOVER_FM_COMMAND, fm)) => {
-					cxl_fm_discover_command::discover_fms(&fm,
+					cxl_fm_discover_command::discover_fms(fm,
 									      &options);
 				},
 				Some((CXL_FM_DISCOVER_DEVICES_COMMAND, devices)) => {
-					cxl_fm_discover_command::discover_cxl_devices(&devices,
+					cxl_fm_discover_command::discover_cxl_devices(devices,
 										      &options);
 				},
 				Some((CXL_FM_DISCOVER_SWITCHES_COMMAND, switch)) => {
-					cxl_fm_discover_command::discover_cxl_switches(&switch,
+					cxl_fm_discover_command::discover_cxl_switches(switch,
 											&options);
 				},
 				Some((CXL_FM_DISCOVER_LOGICAL_DEVICES_COMMAND, logical_devices)) => {
-					cxl_fm_discover_command::discover_logical_devices(&logical_devices,
+					cxl_fm_discover_command::discover_logical_devices(logical_devices,
 											  &options);
 				},
+				Some((CXL_FM_DISCOVER_TOPOLOGY_COMMAND, topology)) => {
+					cxl_fm_discover_command::discover_topology(topology,
+										   &options);
+				},
 				_ => unreachable!(),
 			}
 		},
 		Some((CXL_FM_COMMAND, fm)) => {
 			match fm.subcommand() {
 				Some((CXL_FM_GET_INFO_COMMAND, get_info)) => {
-					cxl_fm_command::get_info(&get_info,
+					cxl_fm_command::get_info(get_info,
 								 &options);
 				},
 				Some((CXL_FM_START_COMMAND, start)) => {
-					cxl_fm_command::start(&start,
+					cxl_fm_command::start(start,
 							      &options);
 				},
 				Some((CXL_FM_RESTART_COMMAND, restart)) => {
-					cxl_fm_command::restart(&restart,
+					cxl_fm_command::restart(restart,
 								&options);
 				},
 				Some((CXL_FM_STOP_COMMAND, stop)) => {
-					cxl_fm_command::stop(&stop,
+					cxl_fm_command::stop(stop,
 							     &options);
 				},
+				Some((CXL_FM_STATUS_COMMAND, status)) => {
+					cxl_fm_command::status(status,
+							       &options);
+				},
 				Some((CXL_FM_GET_CONFIG_COMMAND, get_config)) => {
-					cxl_fm_command::get_config(&get_config,
+					cxl_fm_command::get_config(get_config,
 								   &options);
 				},
 				Some((CXL_FM_SET_CONFIG_COMMAND, set_config)) => {
-					cxl_fm_command::set_config(&set_config,
+					cxl_fm_command::set_config(set_config,
 								   &options);
 				},
 				Some((CXL_FM_GET_EVENTS_COMMAND, get_events)) => {
-					cxl_fm_command::get_events(&get_events,
+					cxl_fm_command::get_events(get_events,
 								   &options);
 				},
+				Some((CXL_FM_WATCH_EVENTS_COMMAND, watch_events)) => {
+					cxl_fm_command::watch_events(watch_events,
+								     &options);
+				},
 				_ => unreachable!(),
 			}
 		},
 		Some((CXL_FM_SWITCH_COMMAND, switch)) => {
 			match switch.subcommand() {
 				Some((CXL_FM_SWITCH_GET_INFO_COMMAND, get_info)) => {
-					cxl_switch_command::get_info(&get_info,
+					cxl_switch_command::get_info(get_info,
 								     &options);
 				},
 				Some((CXL_FM_SWITCH_GET_CONFIG_COMMAND, get_config)) => {
-					cxl_switch_command::get_config(&get_config,
+					cxl_switch_command::get_config(get_config,
 								       &options);
 				},
 				Some((CXL_FM_SWITCH_SET_CONFIG_COMMAND, set_config)) => {
-					cxl_switch_command::set_config(&set_config,
+					cxl_switch_command::set_config(set_config,
 									&options);
 				},
 				_ => unreachable!(),
@@ -565,68 +1305,72 @@ fn main() {
 		Some((CXL_FM_MH_DEVICE_COMMAND, mh_device)) => {
 			match mh_device.subcommand() {
 				Some((CXL_FM_MH_DEVICE_GET_INFO_COMMAND, get_info)) => {
-					cxl_mh_device_command::get_info(&get_info,
+					cxl_mh_device_command::get_info(get_info,
 									&options);
 				},
+				Some((CXL_FM_MH_DEVICE_GET_HEAD_INFO_COMMAND, get_head_info)) => {
+					cxl_mh_device_command::get_head_info(get_head_info,
+									     &options);
+				},
 				_ => unreachable!(),
 			}
 		},
 		Some((CXL_FM_LOGICAL_DEVICE_COMMAND, logical_device)) => {
 			match logical_device.subcommand() {
 				Some((CXL_FM_LOGICAL_DEVICE_BIND_COMMAND, bind)) => {
-					cxl_logical_device_command::bind(&bind,
+					cxl_logical_device_command::bind(bind,
 									 &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_UNBIND_COMMAND, unbind)) => {
-					cxl_logical_device_command::unbind(&unbind,
+					cxl_logical_device_command::unbind(unbind,
 									   &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_CONNECT_COMMAND, connect)) => {
-					cxl_logical_device_command::connect(&connect,
+					cxl_logical_device_command::connect(connect,
 									    &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_DISCONNECT_COMMAND, disconnect)) => {
-					cxl_logical_device_command::disconnect(&disconnect,
+					cxl_logical_device_command::disconnect(disconnect,
 										&options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_GET_ALLOCATION_COMMAND, get_allocation)) => {
-					cxl_logical_device_command::get_allocation(&get_allocation,
+					cxl_logical_device_command::get_allocation(get_allocation,
 										   &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_SET_ALLOCATION_COMMAND, set_allocation)) => {
-					cxl_logical_device_command::set_allocation(&set_allocation,
+					cxl_logical_device_command::set_allocation(set_allocation,
 										   &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_GET_QOS_CONTROL_COMMAND, get_qos_control)) => {
-					cxl_logical_device_command::get_qos_control(&get_qos_control,
+					cxl_logical_device_command::get_qos_control(get_qos_control,
 										    &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_SET_QOS_CONTROL_COMMAND, set_qos_control)) => {
-					cxl_logical_device_command::set_qos_control(&set_qos_control,
+					cxl_logical_device_command::set_qos_control(set_qos_control,
 										    &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_GET_QOS_STATUS_COMMAND, get_qos_status)) => {
-					cxl_logical_device_command::get_qos_status(&get_qos_status,
+					cxl_logical_device_command::get_qos_status(get_qos_status,
 										   &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_GET_QOS_BANDWIDTH_COMMAND, get_qos_bandwidth)) => {
-					cxl_logical_device_command::get_qos_bandwidth(&get_qos_bandwidth,
+					cxl_logical_device_command::get_qos_bandwidth(get_qos_bandwidth,
 										      &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_SET_QOS_BANDWIDTH_COMMAND, set_qos_bandwidth)) => {
-					cxl_logical_device_command::set_qos_bandwidth(&set_qos_bandwidth,
+					cxl_logical_device_command::set_qos_bandwidth(set_qos_bandwidth,
 										      &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_GET_QOS_BANDWIDTH_LIMIT_COMMAND, get_qos_bandwidth_limit)) => {
-					cxl_logical_device_command::get_qos_bandwidth_limit(&get_qos_bandwidth_limit,
+					cxl_logical_device_command::get_qos_bandwidth_limit(get_qos_bandwidth_limit,
 											    &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_SET_QOS_BANDWIDTH_LIMIT_COMMAND, set_qos_bandwidth_limit)) => {
-					cxl_logical_device_command::set_qos_bandwidth_limit(&set_qos_bandwidth_limit,
+					cxl_logical_device_command::set_qos_bandwidth_limit(set_qos_bandwidth_limit,
 											    &options);
 				},
 				Some((CXL_FM_LOGICAL_DEVICE_ERASE_COMMAND, erase)) => {
-					cxl_logical_device_command::erase(&erase,
+					cxl_logical_device_command::erase(erase,
 									  &options);
 				},
 				_ => unreachable!(),
@@ -635,53 +1379,66 @@ fn main() {
 		Some((CXL_FM_PPB_COMMAND, ppb)) => {
 			match ppb.subcommand() {
 				Some((CXL_FM_PPB_CONFIG_COMMAND, config)) => {
-					cxl_ppb_command::config(&config,
+					cxl_ppb_command::config(config,
 								&options);
 				},
 				Some((CXL_FM_PPB_BIND_COMMAND, bind)) => {
-					cxl_ppb_command::bind(&bind,
+					cxl_ppb_command::bind(bind,
 							      &options);
 				},
 				Some((CXL_FM_PPB_UNBIND_COMMAND, unbind)) => {
-					cxl_ppb_command::unbind(&unbind,
+					cxl_ppb_command::unbind(unbind,
 								&options);
 				},
+				Some((CXL_FM_PPB_GENERATE_AER_COMMAND, generate_aer)) => {
+					cxl_ppb_command::generate_aer(generate_aer,
+								      &options);
+				},
 				_ => unreachable!(),
 			}
 		},
 		Some((CXL_FM_PHYSICAL_PORT_COMMAND, physical_port)) => {
 			match physical_port.subcommand() {
 				Some((CXL_FM_PHYSICAL_PORT_GET_INFO_COMMAND, get_info)) => {
-					cxl_physical_port_command::get_info(&get_info,
+					cxl_physical_port_command::get_info(get_info,
 									    &options);
 				},
 				Some((CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND, control)) => {
-					cxl_physical_port_command::control(&control,
+					cxl_physical_port_command::control(control,
 									   &options);
 				},
 				Some((CXL_FM_PHYSICAL_PORT_BIND_COMMAND, bind)) => {
-					cxl_physical_port_command::bind(&bind,
+					cxl_physical_port_command::bind(bind,
 									&options);
 				},
 				Some((CXL_FM_PHYSICAL_PORT_UNBIND_COMMAND, unbind)) => {
-					cxl_physical_port_command::unbind(&unbind,
+					cxl_physical_port_command::unbind(unbind,
+									  &options);
+				},
+				Some((CXL_FM_PHYSICAL_PORT_REMOVE_COMMAND, remove)) => {
+					cxl_physical_port_command::remove(remove,
 									  &options);
 				},
+				#[cfg(feature = "emulation")]
+				Some((CXL_FM_PHYSICAL_PORT_HOT_PLUG_COMMAND, hot_plug)) => {
+					cxl_physical_port_command::hot_plug(hot_plug,
+									    &options);
+				},
 				_ => unreachable!(),
 			}
 		},
 		Some((CXL_FM_MLD_PORT_COMMAND, mld_port)) => {
 			match mld_port.subcommand() {
 				Some((CXL_FM_MLD_PORT_TUNNEL_COMMAND, tunnel)) => {
-					cxl_mld_port_command::tunnel(&tunnel,
+					cxl_mld_port_command::tunnel(tunnel,
 								     &options);
 				},
 				Some((CXL_FM_MLD_PORT_SEND_CONFIG_COMMAND, send_config)) => {
-					cxl_mld_port_command::send_config(&send_config,
+					cxl_mld_port_command::send_config(send_config,
 									  &options);
 				},
 				Some((CXL_FM_MLD_PORT_SEND_MEM_REQUEST_COMMAND, send_memory_request)) => {
-					cxl_mld_port_command::send_memory_request(&send_memory_request,
+					cxl_mld_port_command::send_memory_request(send_memory_request,
 										  &options);
 				},
 				_ => unreachable!(),
@@ -690,32 +1447,51 @@ fn main() {
 		Some((CXL_FM_DCD_COMMAND, dcd)) => {
 			match dcd.subcommand() {
 				Some((CXL_FM_DCD_GET_INFO_COMMAND, get_info)) => {
-					cxl_dcd_command::get_info(&get_info,
+					cxl_dcd_command::get_info(get_info,
 								  &options);
 				},
 				Some((CXL_FM_DCD_GET_CONFIG_COMMAND, get_capacity_config)) => {
-					cxl_dcd_command::get_capacity_config(&get_capacity_config,
+					cxl_dcd_command::get_capacity_config(get_capacity_config,
 									     &options);
 				},
 				Some((CXL_FM_DCD_SET_CONFIG_COMMAND, set_capacity_config)) => {
-					cxl_dcd_command::set_capacity_config(&set_capacity_config,
+					cxl_dcd_command::set_capacity_config(set_capacity_config,
 									     &options);
 				},
 				Some((CXL_FM_DCD_GET_EXTENT_COMMAND, get_extent_list)) => {
-					cxl_dcd_command::get_extent_list(&get_extent_list,
+					cxl_dcd_command::get_extent_list(get_extent_list,
 									 &options);
 				},
 				Some((CXL_FM_DCD_ADD_CAPACITY_COMMAND, add_capacity)) => {
-					cxl_dcd_command::add_capacity(&add_capacity,
+					cxl_dcd_command::add_capacity(add_capacity,
 								      &options);
 				},
 				Some((CXL_FM_DCD_RELEASE_CAPACITY_COMMAND, release_capacity)) => {
-					cxl_dcd_command::release_capacity(&release_capacity,
+					cxl_dcd_command::release_capacity(release_capacity,
 									  &options);
 				},
 				_ => unreachable!(),
 			}
 		},
+		Some((CXL_FM_POOL_COMMAND, pool)) => {
+			match pool.subcommand() {
+				Some((CXL_FM_POOL_GET_INFO_COMMAND, get_info)) => {
+					cxl_pool_command::get_info(get_info,
+								   &options);
+				},
+				Some((CXL_FM_POOL_ALLOCATE_COMMAND, allocate)) => {
+					cxl_pool_command::allocate(allocate,
+								   &options);
+				},
+				_ => unreachable!(),
+			}
+		},
+		Some((CXL_FM_APPLY_COMMAND, apply)) => {
+			cxl_apply_command::apply(apply, &options);
+		},
+		Some((CXL_FM_RECONCILE_COMMAND, reconcile)) => {
+			cxl_apply_command::reconcile(reconcile, &options);
+		},
 		_ => unreachable!(),
 	}
 }
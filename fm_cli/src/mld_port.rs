@@ -44,15 +44,15 @@ pub mod cxl_mld_port_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_MLD_PORT_TUNNEL_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -76,15 +76,15 @@ pub mod cxl_mld_port_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_MLD_PORT_SEND_CONFIG_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -108,15 +108,15 @@ pub mod cxl_mld_port_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_MLD_PORT_SEND_MEM_REQ_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
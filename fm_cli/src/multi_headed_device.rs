@@ -23,8 +23,51 @@ pub mod cxl_mh_device_command {
 	use clap::{ArgMatches};
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
-	use fm_library::cxl_fm_lib::send_command;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::from_hex;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_MHD_INFO_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_GET_MHD_HEAD_INFO_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_START_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_COUNT_ARG;
+	use fm_library::cxl_fm_api::ltssm_state_name;
+	use fm_library::cxl_fm_api::link_speed_name;
+	use fm_library::cxl_fm_api::link_speeds_vector;
+	use fm_library::cxl_mh_device_lib::CxlGetMhdInfoResponse;
+	use fm_library::cxl_mh_device_lib::CxlGetHeadInfoResponse;
+
+	/*
+	 * Show Multi Headed Device (MHD) info and head-to-LD mapping
+	 */
+	fn show_mhd_info(data: &str) -> Result<(), CxlFmError> {
+		let info = CxlGetMhdInfoResponse::from_bytes(&from_hex(data)?)?;
+
+		println!("Number of heads: {}", info.num_heads);
+		println!("Number of supported LDs: {}", info.num_lds);
+		println!("Start LD: {}", info.start_ld);
+		println!("Head-to-LD mapping:");
+
+		let mut header = format!("{:>8}", "");
+		for ld in 0..info.ld_map.len() {
+			header.push_str(&format!("{:>6}",
+						 format!("LD{}", info.start_ld as usize + ld)));
+		}
+		println!("{}", header);
+
+		for (head, row) in info.head_to_ld_matrix().iter().enumerate() {
+			let mut line = format!("{:>8}", format!("Head{}", head));
+
+			for is_mapped in row {
+				line.push_str(&format!("{:>6}", if *is_mapped { "X" } else { "." }));
+			}
+
+			println!("{}", line);
+		}
+
+		Ok(())
+	}
 
 	/*
 	 * Get Multi Headed Device (MHD) status/info
@@ -34,23 +77,90 @@ pub mod cxl_mh_device_command {
 			println!("{}", crate::CXL_FM_MH_DEVICE_GET_INFO_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let request = CxlFmRequest::new(CXL_FM_GET_MHD_INFO_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, device);
+
+		match TcpStream::connect(&env.ip_port) {
+			Ok(stream) => {
+				if env.is_debug {
+					println!("Successfully connected to server: {}",
+						 env.ip_port);
+				}
+
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_mhd_info(&data));
+
+				if let Err(e) = result {
+					println!("Failed to get MHD info: {}", e);
+				}
+			},
+			Err(e) => {
+				println!("Failed to connect: {}", e);
+			}
+		}
+	}
+
+	/*
+	 * Show link state of Multi Headed Device (MHD) heads
+	 */
+	fn show_head_info(start_head: u8, data: &str) -> Result<(), CxlFmError> {
+		let info = CxlGetHeadInfoResponse::from_bytes(start_head, &from_hex(data)?)?;
+
+		println!("Number of heads: {}", info.heads.len());
+
+		for (index, head) in info.heads.iter().enumerate() {
+			println!("Head {}:", info.start_head as usize + index);
+			println!("    Port number: {}", head.port_number);
+			println!("    Max link width: x{}", head.max_link_width);
+			println!("    Negotiated link width: x{}", head.negotiated_link_width);
+			println!("    Supported link speeds: {}",
+				 link_speeds_vector(head.supported_link_speeds).join(", "));
+			println!("    Max link speed: {}", link_speed_name(head.max_link_speed));
+			println!("    Current link speed: {}", link_speed_name(head.current_link_speed));
+			println!("    LTSSM state: {}", ltssm_state_name(head.ltssm_state));
+			println!("    First negotiated lane: {}", head.first_negotiated_lane);
+
+			if head.is_degraded() {
+				println!("    Link status: DEGRADED");
+			} else {
+				println!("    Link status: OK");
+			}
 		}
 
+		Ok(())
+	}
+
+	/*
+	 * Get link state of Multi Headed Device (MHD) heads
+	 */
+	pub fn get_head_info(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_MH_DEVICE_GET_HEAD_INFO_COMMAND_DESCRIPTOR);
+		}
+
+		let device = options.get_one::<String>(crate::CXL_FM_DEVICE_OPTION).unwrap();
+		let start = options.get_one::<u8>(crate::CXL_FM_MH_DEVICE_START_OPTION).unwrap();
+		let count = options.get_one::<u8>(crate::CXL_FM_MH_DEVICE_COUNT_OPTION).unwrap();
+
+		let request = CxlFmRequest::new(CXL_FM_GET_MHD_HEAD_INFO_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, device)
+					.arg(CXL_FM_START_ARG, &start.to_string())
+					.arg(CXL_FM_COUNT_ARG, &count.to_string());
+
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_GET_MHD_INFO_COMMAND,
-					     &env);
+				let result = send_request(&stream, &request, env)
+						.and_then(|data| show_head_info(*start, &data));
+
+				if let Err(e) = result {
+					println!("Failed to get MHD head info: {}", e);
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -23,10 +23,26 @@ pub mod cxl_ppb_command {
 	use clap::{ArgMatches};
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
 	use fm_library::cxl_fm_lib::send_command;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::CXL_FM_SWITCH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_VCS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_VPPB_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_PORT_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LD_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_MODE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ERROR_TYPE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_HEADER_ARG;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_PPB_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_PPB_BIND_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_PPB_UNBIND_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_PPB_GENERATE_AER_COMMAND;
+	use fm_library::cxl_switch_lib::unbind_option_by_name;
+	use fm_library::cxl_switch_lib::aer_error_name;
+	use fm_library::cxl_switch_lib::parse_aer_error;
+	use fm_library::cxl_switch_lib::parse_aer_header;
+	use crate::cxl_apply_command::execute_plan;
 
 	/*
 	 * Send PCI-to-PCI Bridge (PPB) configuration request
@@ -44,15 +60,15 @@ pub mod cxl_ppb_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_GET_PPB_CONFIG_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -60,6 +76,46 @@ pub mod cxl_ppb_command {
 		}
 	}
 
+	/*
+	 * Build bind request of vPPB to physical port or LD
+	 */
+	pub fn bind_request(command: &str, options: &ArgMatches) -> CxlFmRequest {
+		let switch = options.get_one::<String>(crate::CXL_FM_PPB_SWITCH_OPTION).unwrap();
+		let vcs = options.get_one::<u8>(crate::CXL_FM_PPB_VCS_OPTION).unwrap();
+		let vppb = options.get_one::<u8>(crate::CXL_FM_PPB_VPPB_OPTION).unwrap();
+		let port = options.get_one::<u8>(crate::CXL_FM_PPB_PORT_OPTION).unwrap();
+
+		let request = CxlFmRequest::new(command)
+				.arg(CXL_FM_SWITCH_ARG, switch)
+				.arg(CXL_FM_VCS_ARG, &vcs.to_string())
+				.arg(CXL_FM_VPPB_ARG, &vppb.to_string())
+				.arg(CXL_FM_PORT_ARG, &port.to_string());
+
+		/*
+		 * Physical port bind has no LD option
+		 */
+		match options.try_get_one::<u16>(crate::CXL_FM_PPB_LD_OPTION).ok().flatten() {
+			Some(ld) => request.arg(CXL_FM_LD_ARG, &ld.to_string()),
+			None => request,
+		}
+	}
+
+	/*
+	 * Build unbind request of vPPB
+	 */
+	pub fn unbind_request(command: &str, options: &ArgMatches) -> CxlFmRequest {
+		let switch = options.get_one::<String>(crate::CXL_FM_PPB_SWITCH_OPTION).unwrap();
+		let vcs = options.get_one::<u8>(crate::CXL_FM_PPB_VCS_OPTION).unwrap();
+		let vppb = options.get_one::<u8>(crate::CXL_FM_PPB_VPPB_OPTION).unwrap();
+		let mode = options.get_one::<String>(crate::CXL_FM_PPB_MODE_OPTION).unwrap();
+
+		CxlFmRequest::new(command)
+			.arg(CXL_FM_SWITCH_ARG, switch)
+			.arg(CXL_FM_VCS_ARG, &vcs.to_string())
+			.arg(CXL_FM_VPPB_ARG, &vppb.to_string())
+			.arg(CXL_FM_MODE_ARG, &unbind_option_by_name(mode).unwrap().to_string())
+	}
+
 	/*
 	 * Bind Virtual PCI-to-PCI Bridge (vPPB) inside a CXL switch
 	 */
@@ -68,28 +124,8 @@ pub mod cxl_ppb_command {
 			println!("{}", crate::CXL_FM_PPB_BIND_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
-
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
-
-				send_command(&mut stream,
-					     CXL_FM_PPB_BIND_COMMAND,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
-			}
-		}
+		execute_plan(bind_request(CXL_FM_PPB_BIND_COMMAND, options),
+			     "bind vPPB", env);
 	}
 
 	/*
@@ -100,23 +136,52 @@ pub mod cxl_ppb_command {
 			println!("{}", crate::CXL_FM_PPB_UNBIND_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		execute_plan(unbind_request(CXL_FM_PPB_UNBIND_COMMAND, options),
+			     "unbind vPPB", env);
+	}
+
+	/*
+	 * Generate AER event on Virtual PCI-to-PCI Bridge (vPPB)
+	 */
+	pub fn generate_aer(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_PPB_GENERATE_AER_COMMAND_DESCRIPTOR);
 		}
 
+		let switch = options.get_one::<String>(crate::CXL_FM_PPB_SWITCH_OPTION).unwrap();
+		let vcs = options.get_one::<u8>(crate::CXL_FM_PPB_VCS_OPTION).unwrap();
+		let vppb = options.get_one::<u8>(crate::CXL_FM_PPB_VPPB_OPTION).unwrap();
+		let error_type = options.get_one::<String>(crate::CXL_FM_PPB_ERROR_TYPE_OPTION).unwrap();
+		let header = options.get_one::<String>(crate::CXL_FM_PPB_HEADER_OPTION).unwrap();
+
+		let aer_error = match parse_aer_error(error_type)
+			.and_then(|aer_error| parse_aer_header(header).map(|_| aer_error)) {
+			Ok(aer_error) => aer_error,
+			Err(e) => {
+				println!("Failed to generate AER: {}", e);
+				return;
+			},
+		};
+
+		let request = CxlFmRequest::new(CXL_FM_PPB_GENERATE_AER_COMMAND)
+				.arg(CXL_FM_SWITCH_ARG, switch)
+				.arg(CXL_FM_VCS_ARG, &vcs.to_string())
+				.arg(CXL_FM_VPPB_ARG, &vppb.to_string())
+				.arg(CXL_FM_ERROR_TYPE_ARG, error_type)
+				.arg(CXL_FM_HEADER_ARG, header);
+
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_PPB_UNBIND_COMMAND,
-					     &env);
+				match send_request(&stream, &request, env) {
+					Ok(_) => println!("{}/vcs{}/vppb{}: AER {} is generated",
+							  switch, vcs, vppb, aer_error_name(aer_error)),
+					Err(e) => println!("Failed to generate AER: {}", e),
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
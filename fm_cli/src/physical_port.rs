@@ -23,11 +23,26 @@ pub mod cxl_physical_port_command {
 	use clap::{ArgMatches};
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
 	use fm_library::cxl_fm_lib::send_command;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::CXL_FM_SWITCH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_PORT_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_OPERATION_ARG;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_PHYSICAL_PORT_INFO_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_BIND_PHYSICAL_PORT_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_fm_lib::CXL_FM_EMULATE_HOT_PLUG_COMMAND;
+	use fm_library::cxl_switch_lib::port_control_by_name;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_switch_lib::slot_operation_by_name;
+	use crate::cxl_ppb_command::bind_request;
+	use crate::cxl_ppb_command::unbind_request;
+	use crate::cxl_apply_command::execute_plan;
 
 	/*
 	 * Get state of physical port
@@ -45,15 +60,15 @@ pub mod cxl_physical_port_command {
 		}
 
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
+				send_command(&stream,
 					     CXL_FM_GET_PHYSICAL_PORT_INFO_COMMAND,
-					     &env);
+					     env);
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
@@ -69,28 +84,17 @@ pub mod cxl_physical_port_command {
 			println!("{}", crate::CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
+		let switch = options.get_one::<String>(crate::CXL_FM_PPB_SWITCH_OPTION).unwrap();
+		let port = options.get_one::<u8>(crate::CXL_FM_PPB_PORT_OPTION).unwrap();
+		let operation = options.get_one::<String>(crate::CXL_FM_PHYSICAL_PORT_OPERATION_OPTION).unwrap();
 
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
+		let request = CxlFmRequest::new(CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND)
+				.arg(CXL_FM_SWITCH_ARG, switch)
+				.arg(CXL_FM_PORT_ARG, &port.to_string())
+				.arg(CXL_FM_OPERATION_ARG,
+				     &port_control_by_name(operation).unwrap().to_string());
 
-				send_command(&mut stream,
-					     CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
-			}
-		}
+		execute_plan(request, "control physical port", env);
 	}
 
 	/*
@@ -101,28 +105,8 @@ pub mod cxl_physical_port_command {
 			println!("{}", crate::CXL_FM_PHYSICAL_PORT_BIND_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
-		}
-
-		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
-				if env.is_debug {
-					println!("Successfully connected to server: {}",
-						 env.ip_port);
-				}
-
-				send_command(&mut stream,
-					     CXL_FM_BIND_PHYSICAL_PORT_COMMAND,
-					     &env);
-			},
-			Err(e) => {
-				println!("Failed to connect: {}", e);
-			}
-		}
+		execute_plan(bind_request(CXL_FM_BIND_PHYSICAL_PORT_COMMAND, options),
+			     "bind physical port", env);
 	}
 
 	/*
@@ -133,23 +117,67 @@ pub mod cxl_physical_port_command {
 			println!("{}", crate::CXL_FM_PHYSICAL_PORT_UNBIND_COMMAND_DESCRIPTOR);
 		}
 
-		if options.args_present() {
-			/*
-			 * Ignore currently.
-			 * Add code later.
-			 */
+		execute_plan(unbind_request(CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND, options),
+			     "unbind physical port", env);
+	}
+
+	/*
+	 * Prepare device attached to physical port for managed removal
+	 */
+	pub fn remove(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_PHYSICAL_PORT_REMOVE_COMMAND_DESCRIPTOR);
+		}
+
+		let switch = options.get_one::<String>(crate::CXL_FM_PPB_SWITCH_OPTION).unwrap();
+		let port = options.get_one::<u8>(crate::CXL_FM_PPB_PORT_OPTION).unwrap();
+
+		let request = CxlFmRequest::new(CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND)
+				.arg(CXL_FM_SWITCH_ARG, switch)
+				.arg(CXL_FM_PORT_ARG, &port.to_string());
+
+		execute_plan(request, "prepare managed removal", env);
+	}
+
+	/*
+	 * Insert device into slot of emulated switch or pull it out
+	 */
+	#[cfg(feature = "emulation")]
+	pub fn hot_plug(options: &ArgMatches, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", crate::CXL_FM_PHYSICAL_PORT_HOT_PLUG_COMMAND_DESCRIPTOR);
 		}
 
+		let switch = options.get_one::<String>(crate::CXL_FM_PPB_SWITCH_OPTION).unwrap();
+		let port = options.get_one::<u8>(crate::CXL_FM_PPB_PORT_OPTION).unwrap();
+		let operation = options.get_one::<String>(crate::CXL_FM_PHYSICAL_PORT_OPERATION_OPTION).unwrap();
+
+		let request = CxlFmRequest::new(CXL_FM_EMULATE_HOT_PLUG_COMMAND)
+				.arg(CXL_FM_SWITCH_ARG, switch)
+				.arg(CXL_FM_PORT_ARG, &port.to_string())
+				.arg(CXL_FM_OPERATION_ARG,
+				     &slot_operation_by_name(operation).unwrap().to_string());
+
 		match TcpStream::connect(&env.ip_port) {
-			Ok(mut stream) => {
+			Ok(stream) => {
 				if env.is_debug {
 					println!("Successfully connected to server: {}",
 						 env.ip_port);
 				}
 
-				send_command(&mut stream,
-					     CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND,
-					     &env);
+				match send_request(&stream, &request, env) {
+					Ok(_) => {
+						let status = match operation.as_str() {
+							crate::CXL_FM_PHYSICAL_PORT_SLOT_INSERT => "device is inserted",
+							crate::CXL_FM_PHYSICAL_PORT_SLOT_REMOVE => "device is pulled out",
+							crate::CXL_FM_PHYSICAL_PORT_LINK_DOWN => "link is down",
+							_ => "link is up",
+						};
+
+						println!("{}/port{}: {}", switch, port, status);
+					},
+					Err(e) => println!("Failed to {} device: {}", operation, e),
+				}
 			},
 			Err(e) => {
 				println!("Failed to connect: {}", e);
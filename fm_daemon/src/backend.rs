@@ -0,0 +1,61 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_backend {
+	use fm_library::cxl_fm_lib::CxlFmError;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_fm_api::CXL_FM_API_UNSUPPORTED;
+
+	/*
+	 * trait CxlCciBackend - Component Command Interface (CCI) transport
+	 *
+	 * Backend delivers FM API command (opcode + payload) to CXL component
+	 * and returns responce payload or FM API return code as error.
+	 */
+	pub trait CxlCciBackend: Send {
+		/*
+		 * Get name of the CCI
+		 */
+		fn name(&self) -> String;
+
+		/*
+		 * Get locality domain (NUMA node or switch) of the component
+		 */
+		fn locality(&self) -> u16 {
+			0
+		}
+
+		/*
+		 * Execute FM API command
+		 */
+		fn execute(&mut self, opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError>;
+
+		/*
+		 * Insert device into the slot of downstream port or pull
+		 * it out. Only slots of emulated components are operated
+		 * by software.
+		 */
+		#[cfg(feature = "emulation")]
+		fn hot_plug(&mut self, _port: u8, _operation: u8) -> Result<(), CxlFmError> {
+			Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED))
+		}
+	}
+}
@@ -0,0 +1,598 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_capacity_pool_command {
+	use std::net::{TcpStream};
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_error;
+	use fm_library::cxl_fm_lib::format_size;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_HOST_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_REGION_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_POLICY_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LENGTH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_TAG_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_EXTENTS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_FORCED_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LOCALITY_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_EXTENT_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DCD_ADD_CAPACITY_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DCD_RELEASE_CAPACITY_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_POOL_GET_INFO_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_POOL_ALLOCATE_COMMAND;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_DCD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_HOST_DC_REGION_CONFIG;
+	use fm_library::cxl_fm_api::CXL_FM_API_UNSUPPORTED;
+	use fm_library::cxl_fm_api::CXL_FM_API_INITIATE_DC_ADD;
+	use fm_library::cxl_dcd_lib::CxlDcdInfo;
+	use fm_library::cxl_dcd_lib::CxlDcExtent;
+	use fm_library::cxl_dcd_lib::CxlGetHostDcRegionConfigRequest;
+	use fm_library::cxl_dcd_lib::CxlHostDcRegionConfig;
+	use fm_library::cxl_dcd_lib::format_extent_ranges;
+	use fm_library::cxl_dcd_lib::CXL_DCD_SELECTION_POLICY_FREE;
+	use fm_library::cxl_capacity_pool_lib::CxlPoolInfo;
+	use fm_library::cxl_capacity_pool_lib::CxlPoolRegion;
+	use fm_library::cxl_capacity_pool_lib::CxlPoolGrant;
+	use fm_library::cxl_capacity_pool_lib::CxlPoolAllocation;
+	use fm_library::cxl_capacity_pool_lib::CXL_POOL_PLACEMENT_PACK;
+	use fm_library::cxl_capacity_pool_lib::CXL_POOL_PLACEMENT_SPREAD;
+	use fm_library::cxl_capacity_pool_lib::CXL_POOL_PLACEMENT_LOCALITY;
+	use fm_library::cxl_capacity_pool_lib::placement_policy_name;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_WARNING;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_POLICY;
+	use crate::cxl_dcd_command::read_extent_list;
+	use crate::cxl_dcd_command::add_extents;
+	use crate::cxl_dcd_command::release_extents;
+	use crate::cxl_fm_context::CxlFmContext;
+	use crate::cxl_fm_plan::CxlPlanEntry;
+	use crate::cxl_fm_plan::CxlPlanAction;
+	use crate::cxl_fm_plan::plan_step;
+	use crate::cxl_fm_plan::send_plan;
+
+	/*
+	 * struct CxlPoolDevice - DCD of the capacity pool
+	 * @num_hosts: number of hosts supported by the device
+	 * @regions: DC regions of the device
+	 */
+	pub struct CxlPoolDevice {
+		pub num_hosts: u8,
+		pub regions: Vec<CxlPoolRegion>,
+	}
+
+	/*
+	 * Read DC regions and free capacity of the device.
+	 * Free capacity is the region length minus extents
+	 * of all hosts (shared extents are counted once).
+	 */
+	pub fn read_pool_device(ctx: &CxlFmContext, device: usize,
+				locality: u16) -> Result<Option<CxlPoolDevice>, CxlFmError> {
+		let info = match ctx.execute(device, CXL_FM_API_GET_DCD_INFO, &[]) {
+			Ok(payload) => CxlDcdInfo::from_bytes(&payload)?,
+			Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED)) => return Ok(None),
+			Err(e) => return Err(e),
+		};
+
+		let fm_api_request = CxlGetHostDcRegionConfigRequest {
+			host_id: 0,
+			region_count: info.num_regions,
+			start_region: 0,
+		};
+		let payload = ctx.execute(device,
+					  CXL_FM_API_GET_HOST_DC_REGION_CONFIG,
+					  &fm_api_request.to_bytes())?;
+		let config = CxlHostDcRegionConfig::from_bytes(0, &payload)?;
+
+		let request = CxlFmRequest::new(CXL_FM_DCD_GET_EXTENT_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, &device.to_string());
+		let mut allocated: Vec<CxlDcExtent> = Vec::new();
+
+		for host_id in 0..info.num_hosts as u16 {
+			allocated.extend(read_extent_list(&request, ctx, host_id)?.extents);
+		}
+
+		allocated.sort_by_key(|extent| extent.start_dpa);
+		allocated.dedup_by_key(|extent| extent.start_dpa);
+
+		let regions = config.regions.iter()
+			.enumerate()
+			.map(|(index, region)| {
+				let used: u64 = allocated.iter()
+					.filter(|extent| extent.start_dpa >= region.base &&
+							 extent.start_dpa < region.base + region.length)
+					.map(|extent| extent.length)
+					.sum();
+
+				CxlPoolRegion {
+					device: device as u16,
+					region: index as u8,
+					locality,
+					block_size: region.block_size,
+					capacity: region.length,
+					free: region.length.saturating_sub(used),
+				}
+			})
+			.collect();
+
+		Ok(Some(CxlPoolDevice {
+			num_hosts: info.num_hosts,
+			regions,
+		}))
+	}
+
+	/*
+	 * Read DC regions of all devices serving the host
+	 */
+	fn read_pool(ctx: &CxlFmContext,
+		     host_id: Option<u16>) -> Result<Vec<CxlPoolRegion>, CxlFmError> {
+		let mut regions = Vec::new();
+
+		for (device, locality) in ctx.localities().into_iter().enumerate() {
+			if let Some(pool_device) = read_pool_device(ctx, device, locality)? {
+				if host_id.is_some_and(|host_id| host_id >= pool_device.num_hosts as u16) {
+					continue;
+				}
+
+				regions.extend(pool_device.regions);
+			}
+		}
+
+		Ok(regions)
+	}
+
+	/*
+	 * Take capacity from regions one by one in the given order
+	 */
+	fn pack(regions: &[&CxlPoolRegion], plan: &mut Vec<(CxlPoolRegion, u64)>,
+		length: u64) -> u64 {
+		let mut remaining = length;
+
+		for region in regions {
+			if remaining == 0 {
+				break;
+			}
+
+			let taken = plan.iter()
+				.filter(|(planned, _)| planned == *region)
+				.map(|(_, length)| length)
+				.sum::<u64>();
+			let free = region.free - taken;
+			let share = std::cmp::min(remaining, free);
+			let share = share - share % region.block_size;
+
+			if share == 0 {
+				continue;
+			}
+
+			match plan.iter_mut().find(|(planned, _)| planned == *region) {
+				Some((_, length)) => *length += share,
+				None => plan.push(((*region).clone(), share)),
+			}
+
+			remaining -= share;
+		}
+
+		remaining
+	}
+
+	/*
+	 * Split capacity evenly between devices, every device
+	 * takes its share from its regions one by one
+	 */
+	fn spread(regions: &[&CxlPoolRegion], plan: &mut Vec<(CxlPoolRegion, u64)>,
+		  length: u64) -> u64 {
+		let mut devices: Vec<u16> = regions.iter().map(|region| region.device).collect();
+		let mut remaining = length;
+
+		devices.sort();
+		devices.dedup();
+
+		while remaining != 0 {
+			let share = std::cmp::max(remaining / devices.len() as u64, 1);
+			let mut progress = false;
+
+			for device in &devices {
+				let device_regions: Vec<&CxlPoolRegion> = regions.iter()
+					.filter(|region| region.device == *device)
+					.copied()
+					.collect();
+				let granule = device_regions.iter()
+					.map(|region| region.block_size)
+					.min()
+					.unwrap();
+				let wanted = std::cmp::min(remaining, std::cmp::max(share, granule));
+				let left = pack(&device_regions, plan, wanted);
+
+				progress |= left != wanted;
+				remaining -= wanted - left;
+
+				if remaining == 0 {
+					break;
+				}
+			}
+
+			if !progress {
+				break;
+			}
+		}
+
+		remaining
+	}
+
+	/*
+	 * Select DC regions and capacity of every region
+	 * according to placement policy
+	 */
+	fn place(regions: &[CxlPoolRegion], policy: u8, locality: u16,
+		 length: u64) -> Result<Vec<(CxlPoolRegion, u64)>, CxlFmError> {
+		let mut candidates: Vec<&CxlPoolRegion> = regions.iter()
+			.filter(|region| region.free >= region.block_size)
+			.collect();
+		let mut plan = Vec::new();
+
+		if candidates.is_empty() {
+			return Err(CxlFmError::InvalidInput(format!("pool cannot provide {}: no free capacity",
+								    format_size(length))));
+		}
+
+		let remaining = match policy {
+			CXL_POOL_PLACEMENT_PACK => pack(&candidates, &mut plan, length),
+			CXL_POOL_PLACEMENT_SPREAD => spread(&candidates, &mut plan, length),
+			CXL_POOL_PLACEMENT_LOCALITY => {
+				candidates.sort_by_key(|region| region.locality != locality);
+				pack(&candidates, &mut plan, length)
+			},
+			_ => {
+				return Err(CxlFmError::InvalidInput(format!("unknown placement policy: {}",
+									    policy)));
+			},
+		};
+
+		if remaining != 0 {
+			return Err(CxlFmError::InvalidInput(format!("pool cannot provide {}: {} is not available",
+								    format_size(length),
+								    format_size(remaining))));
+		}
+
+		Ok(plan)
+	}
+
+	/*
+	 * Release capacity granted by the pool
+	 */
+	fn rollback(ctx: &CxlFmContext, host_id: u16,
+		    grants: &[CxlPoolGrant]) -> Result<(), CxlFmError> {
+		for grant in grants {
+			let request = CxlFmRequest::new(CXL_FM_DCD_RELEASE_CAPACITY_COMMAND)
+						.arg(CXL_FM_DEVICE_ARG, &grant.device.to_string())
+						.arg(CXL_FM_HOST_ARG, &host_id.to_string())
+						.arg(CXL_FM_EXTENTS_ARG, &format_extent_ranges(&grant.extents))
+						.arg(CXL_FM_FORCED_ARG, "1");
+
+			release_extents(&request, ctx)?;
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Place requested capacity in the pool and build
+	 * DC add request for every selected region
+	 */
+	fn grant_requests(request: &CxlFmRequest,
+			  ctx: &CxlFmContext) -> Result<Vec<(CxlPoolRegion, CxlFmRequest)>, CxlFmError> {
+		let host_id = request.get_number::<u16>(CXL_FM_HOST_ARG)?;
+		let length = request.get_number::<u64>(CXL_FM_LENGTH_ARG)?;
+		let policy = request.get_number::<u8>(CXL_FM_POLICY_ARG)?;
+		let locality = request.get_number_or::<u16>(CXL_FM_LOCALITY_ARG, 0)?;
+
+		if length == 0 {
+			return Err(CxlFmError::InvalidInput(String::from("zero length")));
+		}
+
+		let regions = read_pool(ctx, Some(host_id))?;
+		let plan = place(&regions, policy, locality, length)?;
+
+		Ok(plan.into_iter().map(|(region, length)| {
+			let mut add_request = CxlFmRequest::new(CXL_FM_DCD_ADD_CAPACITY_COMMAND)
+						.arg(CXL_FM_DEVICE_ARG, &region.device.to_string())
+						.arg(CXL_FM_HOST_ARG, &host_id.to_string())
+						.arg(CXL_FM_REGION_ARG, &region.region.to_string())
+						.arg(CXL_FM_POLICY_ARG,
+						     &CXL_DCD_SELECTION_POLICY_FREE.to_string())
+						.arg(CXL_FM_LENGTH_ARG, &length.to_string());
+
+			if let Some(tag) = request.get(CXL_FM_TAG_ARG) {
+				add_request = add_request.arg(CXL_FM_TAG_ARG, tag);
+			}
+
+			(region, add_request)
+		}).collect())
+	}
+
+	/*
+	 * Grant capacity to the host from the pool. If any device fails,
+	 * capacity already granted by other devices is released.
+	 */
+	fn allocate_capacity(request: &CxlFmRequest,
+			     ctx: &CxlFmContext) -> Result<CxlPoolAllocation, CxlFmError> {
+		let host_id = request.get_number::<u16>(CXL_FM_HOST_ARG)?;
+		let policy = request.get_number::<u8>(CXL_FM_POLICY_ARG)?;
+
+		let mut allocation = CxlPoolAllocation {
+			host_id,
+			policy,
+			grants: Vec::new(),
+		};
+
+		for (region, add_request) in grant_requests(request, ctx)? {
+			match add_extents(&add_request, ctx) {
+				Ok(added) => {
+					let mut message = format!("{} policy granted {} of region {} to host {}",
+								  placement_policy_name(policy),
+								  format_size(added.accepted_length()),
+								  region.region, host_id);
+					let mut severity = CXL_EVENT_SEVERITY_INFO;
+
+					if added.pending_length != 0 {
+						message.push_str(&format!(", {} is not accepted yet",
+									  format_size(added.pending_length)));
+						severity = CXL_EVENT_SEVERITY_WARNING;
+					}
+
+					ctx.report_event(severity, CXL_FM_EVENT_POLICY,
+							 &ctx.device_name(region.device as usize).unwrap_or_default(),
+							 &message);
+
+					allocation.grants.push(CxlPoolGrant {
+						device: region.device,
+						region: region.region,
+						extents: added.accepted,
+						pending: added.pending_length,
+					});
+				},
+				Err(e) => {
+					let status = match rollback(ctx, host_id, &allocation.grants) {
+						Ok(_) => String::from("rolled back"),
+						Err(rollback_error) => format!("rollback failed: {}",
+									       rollback_error),
+					};
+
+					ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_POLICY,
+							 &ctx.device_name(region.device as usize).unwrap_or_default(),
+							 &format!("allocation for host {} failed, {} grant(s) {}",
+								  host_id, allocation.grants.len(), status));
+
+					return Err(CxlFmError::Remote(format!("device {} region {}: {} ({})",
+									      region.device, region.region,
+									      e, status)));
+				},
+			}
+		}
+
+		Ok(allocation)
+	}
+
+	/*
+	 * Plan pool allocation as DC add of every selected region
+	 */
+	fn allocation_plan(request: &CxlFmRequest,
+			   ctx: &CxlFmContext) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		Ok(grant_requests(request, ctx)?.into_iter().map(|(region, add_request)| {
+			let length = add_request.get_number_or::<u64>(CXL_FM_LENGTH_ARG, 0)
+						.unwrap_or(0);
+			let device = region.device as usize;
+			let description = format!("add {} to host {} from region {} ({} of {} free)",
+						  format_size(length),
+						  add_request.get(CXL_FM_HOST_ARG).unwrap_or("0"),
+						  region.region, format_size(region.free),
+						  format_size(region.capacity));
+
+			(plan_step(CXL_FM_API_INITIATE_DC_ADD,
+				   &ctx.device_name(device).unwrap_or_else(|| device.to_string()),
+				   description),
+			 CxlPlanAction::AddCapacity(add_request))
+		}).collect())
+	}
+
+	/*
+	 * Get DC regions and free capacity of the pool
+	 */
+	pub fn get_info(stream: &TcpStream, ctx: &CxlFmContext, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", CXL_FM_POOL_GET_INFO_COMMAND);
+		}
+
+		match read_pool(ctx, None) {
+			Ok(regions) => send_payload(stream, &CxlPoolInfo { regions }.to_bytes(), env),
+			Err(e) => send_error(stream, &e, env),
+		}
+	}
+
+	/*
+	 * Grant capacity to the host from the pool
+	 */
+	pub fn allocate(stream: &TcpStream, request: &CxlFmRequest,
+			ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
+		if env.is_debug {
+			println!("{}", CXL_FM_POOL_ALLOCATE_COMMAND);
+		}
+
+		if request.is_dry_run() {
+			return send_plan(stream, request, ctx, allocation_plan(request, ctx), env);
+		}
+
+		let result = allocate_capacity(request, ctx);
+
+		match &result {
+			Ok(allocation) => send_payload(stream, &allocation.to_bytes(), env),
+			Err(e) => send_error(stream, e, env),
+		}
+
+		result.is_ok()
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use fm_library::cxl_fm_api::CXL_FM_API_RESOURCES_EXHAUSTED;
+		use crate::cxl_backend::CxlCciBackend;
+		use crate::cxl_emulator::CxlEmulatedDevice;
+		use crate::cxl_emulator::CXL_EMULATED_DCD_REGION_LENGTH;
+
+		const MB: u64 = 1 << 20;
+
+		fn region(device: u16, index: u8, locality: u16, free: u64) -> CxlPoolRegion {
+			CxlPoolRegion {
+				device,
+				region: index,
+				locality,
+				block_size: 2 * MB,
+				capacity: 64 * MB,
+				free,
+			}
+		}
+
+		fn lengths(plan: &[(CxlPoolRegion, u64)]) -> Vec<(u16, u8, u64)> {
+			plan.iter()
+				.map(|(region, length)| (region.device, region.region, *length))
+				.collect()
+		}
+
+		#[test]
+		fn pack_fills_regions_one_by_one() {
+			let regions = vec![region(0, 0, 0, 8 * MB), region(0, 1, 0, 64 * MB),
+					   region(1, 0, 1, 64 * MB)];
+			let plan = place(&regions, CXL_POOL_PLACEMENT_PACK, 0, 20 * MB).unwrap();
+
+			assert_eq!(lengths(&plan), vec![(0, 0, 8 * MB), (0, 1, 12 * MB)]);
+		}
+
+		#[test]
+		fn spread_splits_capacity_between_devices() {
+			let regions = vec![region(0, 0, 0, 64 * MB), region(1, 0, 1, 64 * MB),
+					   region(2, 0, 0, 64 * MB)];
+			let plan = place(&regions, CXL_POOL_PLACEMENT_SPREAD, 0, 12 * MB).unwrap();
+
+			assert_eq!(lengths(&plan), vec![(0, 0, 4 * MB), (1, 0, 4 * MB), (2, 0, 4 * MB)]);
+		}
+
+		#[test]
+		fn spread_moves_rest_to_devices_with_free_capacity() {
+			let regions = vec![region(0, 0, 0, 2 * MB), region(1, 0, 1, 64 * MB)];
+			let plan = place(&regions, CXL_POOL_PLACEMENT_SPREAD, 0, 16 * MB).unwrap();
+
+			assert_eq!(lengths(&plan), vec![(0, 0, 2 * MB), (1, 0, 14 * MB)]);
+		}
+
+		#[test]
+		fn locality_prefers_devices_of_the_domain() {
+			let regions = vec![region(0, 0, 0, 64 * MB), region(1, 0, 1, 8 * MB),
+					   region(2, 0, 1, 64 * MB)];
+			let plan = place(&regions, CXL_POOL_PLACEMENT_LOCALITY, 1, 16 * MB).unwrap();
+
+			assert_eq!(lengths(&plan), vec![(1, 0, 8 * MB), (2, 0, 8 * MB)]);
+		}
+
+		#[test]
+		fn pool_without_capacity_is_rejected() {
+			let full = vec![region(0, 0, 0, MB), region(1, 0, 1, 0)];
+
+			for policy in [CXL_POOL_PLACEMENT_PACK, CXL_POOL_PLACEMENT_SPREAD,
+				       CXL_POOL_PLACEMENT_LOCALITY] {
+				assert!(matches!(place(&[], policy, 0, 2 * MB),
+						 Err(CxlFmError::InvalidInput(_))));
+				assert!(matches!(place(&full, policy, 0, 2 * MB),
+						 Err(CxlFmError::InvalidInput(_))));
+			}
+		}
+
+		#[test]
+		fn pool_cannot_provide_more_than_free() {
+			let regions = vec![region(0, 0, 0, 8 * MB), region(1, 0, 1, 8 * MB)];
+
+			for policy in [CXL_POOL_PLACEMENT_PACK, CXL_POOL_PLACEMENT_SPREAD] {
+				assert!(place(&regions, policy, 0, 18 * MB).is_err());
+			}
+		}
+
+		/*
+		 * struct CxlFailingAddDevice - emulated device
+		 *                              that refuses to add capacity
+		 */
+		struct CxlFailingAddDevice(CxlEmulatedDevice);
+
+		impl CxlCciBackend for CxlFailingAddDevice {
+			fn name(&self) -> String {
+				self.0.name()
+			}
+
+			fn execute(&mut self, opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+				match opcode {
+					CXL_FM_API_INITIATE_DC_ADD => Err(CxlFmError::Device(CXL_FM_API_RESOURCES_EXHAUSTED)),
+					_ => self.0.execute(opcode, payload),
+				}
+			}
+		}
+
+		fn allocate_request(length: u64) -> CxlFmRequest {
+			CxlFmRequest::new(CXL_FM_POOL_ALLOCATE_COMMAND)
+				.arg(CXL_FM_HOST_ARG, "0")
+				.arg(CXL_FM_LENGTH_ARG, &length.to_string())
+				.arg(CXL_FM_POLICY_ARG, &CXL_POOL_PLACEMENT_SPREAD.to_string())
+		}
+
+		fn free_capacity(ctx: &CxlFmContext) -> u64 {
+			read_pool(ctx, None).unwrap().iter().map(|region| region.free).sum()
+		}
+
+		#[test]
+		fn allocation_is_granted_by_devices() {
+			let ctx = CxlFmContext::with_devices(vec![Box::new(CxlEmulatedDevice::new("mhd0", 2, 1)),
+								  Box::new(CxlEmulatedDevice::new("mhd1", 2, 1))]);
+			let free = free_capacity(&ctx);
+			let allocation = allocate_capacity(&allocate_request(8 * MB), &ctx).unwrap();
+
+			assert_eq!(allocation.length(), 8 * MB);
+			assert_eq!(allocation.pending_length(), 0);
+			assert_eq!(allocation.grants.len(), 2);
+			assert_eq!(free_capacity(&ctx), free - 8 * MB);
+
+			rollback(&ctx, 0, &allocation.grants).unwrap();
+			assert_eq!(free_capacity(&ctx), free);
+		}
+
+		#[test]
+		fn failed_allocation_is_rolled_back() {
+			let ctx = CxlFmContext::with_devices(vec![Box::new(CxlEmulatedDevice::new("mhd0", 2, 1)),
+								  Box::new(CxlFailingAddDevice(CxlEmulatedDevice::new("mhd1", 2, 1)))]);
+			let free = free_capacity(&ctx);
+
+			assert_eq!(free, 4 * CXL_EMULATED_DCD_REGION_LENGTH);
+			assert!(matches!(allocate_capacity(&allocate_request(8 * MB), &ctx),
+					 Err(CxlFmError::Remote(_))));
+			assert_eq!(free_capacity(&ctx), free);
+		}
+	}
+}
@@ -0,0 +1,664 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_context {
+	use std::collections::HashMap;
+	use std::collections::HashSet;
+	use std::sync::Mutex;
+	use std::sync::MutexGuard;
+	use std::sync::PoisonError;
+	use std::thread;
+	use std::time::{Duration, Instant};
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_dcd_lib::CxlDcExtent;
+	use fm_library::cxl_dcd_lib::CxlDcExtentList;
+	use fm_library::cxl_switch_lib::CxlPhysicalPortInfo;
+	use fm_library::cxl_fm_api::CxlCciMessage;
+	use fm_library::cxl_fm_api::CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND;
+	use fm_library::cxl_fm_api::CXL_FM_API_SUCCESS;
+	use fm_library::cxl_fm_api::CXL_CCI_MESSAGE_REQUEST;
+	use fm_library::cxl_switch_lib::CxlTunnelRequest;
+	use fm_library::cxl_switch_lib::CxlTunnelResponse;
+	use fm_library::cxl_topology::FabricTopology;
+	use fm_library::cxl_desired_state::CxlFabricSpec;
+	use fm_library::cxl_topology::is_device_kind;
+	use fm_library::cxl_topology::CXL_NODE_PHYSICAL_PORT;
+	use fm_library::cxl_topology::CXL_EDGE_CONTAINS;
+	use fm_library::cxl_topology::CXL_EDGE_LINK;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_FAILED;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_FAILURE;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_OPERATION;
+	use fm_library::cxl_fm_event::event_timestamp;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_ACTIVE;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_STANDBY;
+	use fm_library::cxl_fm_config::CxlFmDaemonConfig;
+	use fm_library::cxl_fm_config::CxlFmPoliciesConfig;
+	use fm_library::cxl_fm_config::CxlFmSecurityConfig;
+	use fm_library::cxl_fm_config::CXL_FM_BACKEND_EMULATED_SWITCH;
+	use fm_library::cxl_fm_config::CXL_FM_BACKEND_MAILBOX;
+	use crate::cxl_backend::CxlCciBackend;
+	use crate::cxl_emulator::CxlEmulatedDevice;
+	use crate::cxl_emulator::CXL_EMULATED_DEVICE_HEADS;
+	use crate::cxl_emulator::CXL_EMULATED_DEVICE_LDS_PER_HEAD;
+	use crate::cxl_emulated_switch::CxlEmulatedSwitch;
+	use crate::cxl_mailbox::CxlMailboxBackend;
+	use crate::cxl_fm_event_log::CxlFmEventLog;
+	use crate::cxl_fm_state_store::CxlFmStateStore;
+	use crate::cxl_fm_state_store::CxlFmStateRecord;
+	use crate::cxl_fm_state_store::CxlFmLostPort;
+	use crate::cxl_fm_state_store::inventory_of;
+
+	/*
+	 * Serial numbers of emulated components
+	 * whose configuration has no serial number
+	 */
+	const CXL_EMULATED_DEVICE_SERIAL_BASE: u64 = 0x1000;
+	const CXL_EMULATED_SWITCH_SERIAL_BASE: u64 = 0x2000;
+
+	/*
+	 * Period of checking the live policies (seconds)
+	 */
+	const CXL_FM_POLICY_CHECK_PERIOD: u64 = 1;
+
+	/*
+	 * enum CxlCciPath - the way to reach component CCI
+	 * @Direct: CCI of the daemon with given index
+	 * @Tunnel: switch CCI index and downstream port ID
+	 */
+	#[derive(Debug, Clone, Copy, PartialEq)]
+	pub enum CxlCciPath {
+		Direct(usize),
+		Tunnel(usize, u8),
+	}
+
+	/*
+	 * Take lock of shared state. The state left by request
+	 * that panicked is used as is, so the panic doesn't stop
+	 * other requests.
+	 */
+	pub fn lock_shared<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+		mutex.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/*
+	 * struct CxlFmContext - state of FM daemon
+	 * @devices: CCIs of managed CXL components
+	 * @dc_extents: DC extents confirmed by device for (device, host)
+	 * @topology: discovered fabric topology
+	 * @port_states: last seen state of (switch, physical port)
+	 * @desired_state: the last applied desired state of the fabric
+	 * @drift: drift reported by the last reconciliation pass
+	 * @events: FM event log
+	 * @unavailable: downstream ports whose device and LDs are
+	 *               unavailable after removal, by name
+	 * @lost: resources of downstream ports lost by surprise
+	 *        removal or link failure, by port name
+	 * @operation: serializes fabric changes of requests
+	 *             and reconciliation passes
+	 * @store: durable state store (absent if state is not kept)
+	 * @role: active FM manages the fabric, standby FM only
+	 *        replicates state of the active one
+	 * @lease: time when the active role expires unless orchestrator
+	 *         confirms it (no limit for FM without orchestrator)
+	 * @config: the live configuration of FM daemon
+	 */
+	pub struct CxlFmContext {
+		pub devices: Mutex<Vec<Box<dyn CxlCciBackend>>>,
+		pub dc_extents: Mutex<HashMap<(usize, u16), Vec<CxlDcExtent>>>,
+		pub topology: Mutex<FabricTopology>,
+		pub port_states: Mutex<HashMap<(usize, u8), CxlPhysicalPortInfo>>,
+		pub desired_state: Mutex<Option<CxlFabricSpec>>,
+		pub drift: Mutex<Vec<String>>,
+		pub events: Mutex<CxlFmEventLog>,
+		pub unavailable: Mutex<HashSet<String>>,
+		pub lost: Mutex<HashMap<String, CxlFmLostPort>>,
+		pub operation: Mutex<()>,
+		pub store: Mutex<Option<CxlFmStateStore>>,
+		pub role: Mutex<u8>,
+		pub lease: Mutex<Option<Instant>>,
+		pub config: Mutex<CxlFmDaemonConfig>,
+	}
+
+	impl CxlFmContext {
+		/*
+		 * Create context with components of configured backends
+		 */
+		pub fn new(config: CxlFmDaemonConfig) -> CxlFmContext {
+			let mut devices: Vec<Box<dyn CxlCciBackend>> = Vec::new();
+			let mut device_index = 0;
+			let mut switch_index = 0;
+
+			for backend in &config.backends {
+				if backend.backend_type == CXL_FM_BACKEND_MAILBOX {
+					devices.push(Box::new(CxlMailboxBackend::new(&backend.name,
+										     backend.path.as_deref().unwrap_or_default(),
+										     backend.locality.unwrap_or(0))));
+					continue;
+				}
+
+				if backend.backend_type == CXL_FM_BACKEND_EMULATED_SWITCH {
+					let serial_number = backend.serial_number
+						.unwrap_or(CXL_EMULATED_SWITCH_SERIAL_BASE + switch_index);
+
+					devices.push(Box::new(CxlEmulatedSwitch::new(&backend.name, serial_number)));
+					switch_index += 1;
+					continue;
+				}
+
+				let mut device = CxlEmulatedDevice::new(&backend.name,
+									backend.heads.unwrap_or(CXL_EMULATED_DEVICE_HEADS),
+									backend.lds_per_head.unwrap_or(CXL_EMULATED_DEVICE_LDS_PER_HEAD));
+
+				device.serial_number = backend.serial_number
+					.unwrap_or(CXL_EMULATED_DEVICE_SERIAL_BASE + device_index);
+				device.locality = backend.locality.unwrap_or(0);
+				devices.push(Box::new(device));
+				device_index += 1;
+			}
+
+			CxlFmContext {
+				devices: Mutex::new(devices),
+				dc_extents: Mutex::new(HashMap::new()),
+				topology: Mutex::new(FabricTopology::new()),
+				port_states: Mutex::new(HashMap::new()),
+				desired_state: Mutex::new(None),
+				drift: Mutex::new(Vec::new()),
+				events: Mutex::new(CxlFmEventLog::new()),
+				unavailable: Mutex::new(HashSet::new()),
+				lost: Mutex::new(HashMap::new()),
+				operation: Mutex::new(()),
+				store: Mutex::new(None),
+				role: Mutex::new(CXL_FM_ROLE_ACTIVE),
+				lease: Mutex::new(None),
+				config: Mutex::new(config),
+			}
+		}
+
+		/*
+		 * Create context with the given components only
+		 */
+		#[cfg(test)]
+		pub fn with_devices(devices: Vec<Box<dyn CxlCciBackend>>) -> CxlFmContext {
+			let mut config = CxlFmDaemonConfig::new("fm_daemon");
+
+			config.backends.clear();
+
+			let ctx = CxlFmContext::new(config);
+			*lock_shared(&ctx.devices) = devices;
+			ctx
+		}
+
+		/*
+		 * Get the live policies of fabric management
+		 */
+		pub fn policies(&self) -> CxlFmPoliciesConfig {
+			lock_shared(&self.config).policies.clone()
+		}
+
+		/*
+		 * Wait until the interval of the live policies passes
+		 * since the last time. Zero interval suspends the work
+		 * until the policies are changed.
+		 */
+		pub fn wait_interval(&self, last: &mut Instant, interval: fn(&CxlFmPoliciesConfig) -> u64) {
+			loop {
+				thread::sleep(Duration::from_secs(CXL_FM_POLICY_CHECK_PERIOD));
+
+				let interval = interval(&self.policies());
+
+				if interval != 0 && last.elapsed() >= Duration::from_secs(interval) {
+					*last = Instant::now();
+					return;
+				}
+			}
+		}
+
+		/*
+		 * Get the live access rules of FM API
+		 */
+		pub fn security(&self) -> CxlFmSecurityConfig {
+			lock_shared(&self.config).security.clone()
+		}
+
+		/*
+		 * Is FM standby? Standby FM doesn't touch the fabric.
+		 * Active FM whose lease has expired is standby too.
+		 */
+		pub fn is_standby(&self) -> bool {
+			*lock_shared(&self.role) == CXL_FM_ROLE_STANDBY ||
+			lock_shared(&self.lease).is_some_and(|lease| Instant::now() >= lease)
+		}
+
+		/*
+		 * Extend the active role for the duration
+		 */
+		pub fn renew_lease(&self, duration: Duration) {
+			*lock_shared(&self.lease) = Some(Instant::now() + duration);
+		}
+
+		/*
+		 * Add event to FM event log
+		 */
+		pub fn report_event(&self, severity: u8, event_type: u8,
+				    component: &str, message: &str) -> u64 {
+			self.report_event_at(event_timestamp(), severity, event_type, component, message)
+		}
+
+		/*
+		 * Add event that happened at given time to FM event log
+		 */
+		pub fn report_event_at(&self, timestamp: u64, severity: u8, event_type: u8,
+				       component: &str, message: &str) -> u64 {
+			let mut events = lock_shared(&self.events);
+			let sequence = events.push_at(timestamp, severity, event_type, component, message);
+
+			if let Some(event) = events.events.back().cloned() {
+				drop(events);
+				self.journal(CxlFmStateRecord::Event(event));
+			}
+
+			sequence
+		}
+
+		/*
+		 * Write record into the state store
+		 */
+		pub fn journal(&self, record: CxlFmStateRecord) {
+			if let Some(store) = lock_shared(&self.store).as_mut() {
+				if let Err(e) = store.append(record) {
+					println!("Failed to journal state: {}", e);
+				}
+			}
+		}
+
+		/*
+		 * Take the operation lock. The lock left by request that
+		 * panicked is taken over and its operation is finished
+		 * as failed, so the panic doesn't stop FM from changing
+		 * the fabric.
+		 */
+		pub fn lock_operation(&self) -> MutexGuard<'_, ()> {
+			self.operation.lock().unwrap_or_else(|e| {
+				println!("Operation lock is recovered after failed request");
+				self.operation.clear_poison();
+
+				let operation = e.into_inner();
+
+				self.end_operation(true, false);
+				operation
+			})
+		}
+
+		/*
+		 * Record start of the fabric change. The change must not
+		 * be executed if it cannot be recorded.
+		 */
+		pub fn begin_operation(&self, request: &CxlFmRequest) -> Result<(), CxlFmError> {
+			match lock_shared(&self.store).as_mut() {
+				Some(store) => store.begin_operation(&request.to_line()).map(|_| ()),
+				None => Ok(()),
+			}
+		}
+
+		/*
+		 * Record status of plan step of the current operation
+		 */
+		pub fn journal_step(&self, index: usize, status: u8) {
+			if let Some(store) = lock_shared(&self.store).as_mut() {
+				if let Err(e) = store.record_step(index as u16, status) {
+					println!("Failed to journal step: {}", e);
+				}
+			}
+		}
+
+		/*
+		 * Record end of the current operation,
+		 * is_done is false if the operation could not be executed
+		 */
+		pub fn end_operation(&self, is_requested: bool, is_done: bool) {
+			let mut operation = None;
+
+			if let Some(store) = lock_shared(&self.store).as_mut() {
+				operation = store.current
+					.and_then(|id| store.state.operations.get(&id))
+					.cloned();
+
+				if let Err(e) = store.end_operation(is_requested, is_done) {
+					println!("Failed to journal operation end: {}", e);
+				}
+			}
+
+			/*
+			 * Operation that changed the fabric is completed
+			 */
+			if let Some(operation) = operation.filter(|operation| !operation.steps.is_empty()) {
+				let is_failed = !is_done ||
+					operation.steps.values().any(|step| *step == CXL_PLAN_STEP_FAILED);
+				let severity = match is_failed {
+					true => CXL_EVENT_SEVERITY_FAILURE,
+					false => CXL_EVENT_SEVERITY_INFO,
+				};
+
+				self.report_event(severity, CXL_FM_EVENT_OPERATION, "fm",
+						  &format!("{}: {}", operation.request, operation.result(is_done)));
+			}
+		}
+
+		/*
+		 * Store switches and devices of discovered fabric
+		 * if they differ from the known ones
+		 */
+		pub fn update_inventory(&self, topology: &FabricTopology) {
+			let inventory = inventory_of(topology);
+			let is_changed = lock_shared(&self.store)
+				.as_ref()
+				.is_some_and(|store| store.state.inventory != inventory);
+
+			if is_changed {
+				self.journal(CxlFmStateRecord::Inventory(inventory));
+			}
+		}
+
+		/*
+		 * Mark resources of downstream port unavailable or
+		 * available again. Discovered port shows its status.
+		 * Returns true if the status is changed.
+		 */
+		pub fn set_unavailable(&self, port: &str, is_unavailable: bool) -> bool {
+			let is_changed = match is_unavailable {
+				true => lock_shared(&self.unavailable).insert(String::from(port)),
+				false => lock_shared(&self.unavailable).remove(port),
+			};
+
+			if !is_changed {
+				return false;
+			}
+
+			self.journal(CxlFmStateRecord::Unavailable(String::from(port), is_unavailable));
+			self.show_status(port);
+			true
+		}
+
+		/*
+		 * Are resources of downstream port unavailable?
+		 */
+		pub fn is_unavailable(&self, port: &str) -> bool {
+			lock_shared(&self.unavailable).contains(port)
+		}
+
+		/*
+		 * Record resources of downstream port as lost.
+		 * Resources lost earlier are replaced.
+		 */
+		pub fn set_lost(&self, lost: CxlFmLostPort) {
+			let port = lost.port.clone();
+
+			self.journal(CxlFmStateRecord::Lost(lost.clone()));
+			lock_shared(&self.lost).insert(port.clone(), lost);
+			self.show_status(&port);
+		}
+
+		/*
+		 * Forget lost resources of downstream port
+		 * whose device is back and return them
+		 */
+		pub fn take_lost(&self, port: &str) -> Option<CxlFmLostPort> {
+			let lost = lock_shared(&self.lost).remove(port)?;
+
+			self.journal(CxlFmStateRecord::Recovered(String::from(port)));
+			self.show_status(port);
+			Some(lost)
+		}
+
+		/*
+		 * Are resources of downstream port lost?
+		 */
+		pub fn is_lost(&self, port: &str) -> bool {
+			lock_shared(&self.lost).contains_key(port)
+		}
+
+		/*
+		 * Get status of downstream port shown by topology
+		 */
+		pub fn port_status(&self, port: &str) -> &'static str {
+			if self.is_lost(port) {
+				"lost"
+			} else if self.is_unavailable(port) {
+				"unavailable"
+			} else {
+				"available"
+			}
+		}
+
+		fn show_status(&self, port: &str) {
+			let status = self.port_status(port);
+			let mut topology = lock_shared(&self.topology);
+
+			if let Some(id) = topology.find(CXL_NODE_PHYSICAL_PORT, port).map(|node| node.id) {
+				topology.set_attr(id, "status", status);
+			}
+		}
+
+		/*
+		 * Insert device into the slot of switch port or pull it out
+		 */
+		#[cfg(feature = "emulation")]
+		pub fn hot_plug(&self, device: usize, port: u8, operation: u8) -> Result<(), CxlFmError> {
+			let mut devices = lock_shared(&self.devices);
+
+			match devices.get_mut(device) {
+				Some(cci) => cci.hot_plug(port, operation),
+				None => Err(CxlFmError::NotFound(format!("device {}", device))),
+			}
+		}
+
+		/*
+		 * Get locality domain of every device
+		 */
+		pub fn localities(&self) -> Vec<u16> {
+			lock_shared(&self.devices)
+				.iter()
+				.map(|device| device.locality())
+				.collect()
+		}
+
+		/*
+		 * Get number of managed CCIs
+		 */
+		pub fn device_count(&self) -> usize {
+			lock_shared(&self.devices).len()
+		}
+
+		/*
+		 * Get name of the device CCI
+		 */
+		pub fn device_name(&self, device: usize) -> Option<String> {
+			lock_shared(&self.devices)
+				.get(device)
+				.map(|cci| cci.name())
+		}
+
+		/*
+		 * Execute FM API command on the device
+		 */
+		pub fn execute(&self, device: usize,
+				opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let mut devices = lock_shared(&self.devices);
+
+			match devices.get_mut(device) {
+				Some(cci) => cci.execute(opcode, payload),
+				None => Err(CxlFmError::NotFound(format!("device {}", device))),
+			}
+		}
+
+		/*
+		 * Get index of the CCI by its name
+		 */
+		pub fn device_index(&self, name: &str) -> Option<usize> {
+			lock_shared(&self.devices)
+				.iter()
+				.position(|cci| cci.name() == name)
+		}
+
+		/*
+		 * Find the way to reach discovered device by its name:
+		 * device has its own CCI or it is linked to switch port
+		 */
+		pub fn path_of(&self, topology: &FabricTopology,
+			       name: &str) -> Result<CxlCciPath, CxlFmError> {
+			let not_found = || CxlFmError::NotFound(format!("device {}", name));
+			let node = topology.nodes.iter()
+				.find(|node| is_device_kind(node.kind) && node.name == name)
+				.ok_or_else(not_found)?;
+
+			if let Some(index) = node.get("cci").and_then(|cci| self.device_index(cci)) {
+				return Ok(CxlCciPath::Direct(index));
+			}
+
+			let port = topology.parent(node.id, CXL_EDGE_LINK)
+					.and_then(|port| topology.node(port))
+					.ok_or_else(not_found)?;
+			let port_id = port.get("id")
+					.and_then(|id| id.parse::<u8>().ok())
+					.ok_or_else(not_found)?;
+			let switch = topology.parent(port.id, CXL_EDGE_CONTAINS)
+					.and_then(|switch| topology.node(switch))
+					.and_then(|switch| switch.get("cci"))
+					.and_then(|cci| self.device_index(cci))
+					.ok_or_else(not_found)?;
+
+			Ok(CxlCciPath::Tunnel(switch, port_id))
+		}
+
+		/*
+		 * Execute FM API command on the component reachable by the path
+		 */
+		pub fn execute_path(&self, path: CxlCciPath,
+				    opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			match path {
+				CxlCciPath::Direct(device) => self.execute(device, opcode, payload),
+				CxlCciPath::Tunnel(switch, port) => {
+					let message = CxlCciMessage {
+						category: CXL_CCI_MESSAGE_REQUEST,
+						tag: 0,
+						opcode,
+						return_code: 0,
+						payload: payload.to_vec(),
+					};
+					let fm_api_request = CxlTunnelRequest {
+						port_or_ld: port,
+						message: message.to_bytes(),
+					};
+
+					let data = self.execute(switch, CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND,
+								&fm_api_request.to_bytes())?;
+					let responce = CxlTunnelResponse::from_bytes(&data)?;
+					let message = CxlCciMessage::from_bytes(&responce.message)?;
+
+					if message.return_code != CXL_FM_API_SUCCESS {
+						return Err(CxlFmError::Device(message.return_code));
+					}
+
+					Ok(message.payload)
+				},
+			}
+		}
+
+		/*
+		 * Execute FM API command on the device defined by request
+		 */
+		pub fn execute_for(&self, request: &CxlFmRequest,
+				    opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			self.execute(Self::device_for(request)?, opcode, payload)
+		}
+
+		/*
+		 * Get device index defined by request
+		 */
+		pub fn device_for(request: &CxlFmRequest) -> Result<usize, CxlFmError> {
+			request.get_number_or::<usize>(CXL_FM_DEVICE_ARG, 0)
+		}
+
+		/*
+		 * Store DC extents of the host confirmed by the device
+		 */
+		pub fn update_dc_extents(&self, device: usize, host_id: u16,
+					 extents: Vec<CxlDcExtent>) {
+			self.journal(CxlFmStateRecord::Extents(device, CxlDcExtentList {
+				host_id,
+				start_index: 0,
+				total_extents: extents.len() as u32,
+				generation: 0,
+				extents: extents.clone(),
+			}));
+			lock_shared(&self.dc_extents).insert((device, host_id), extents);
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use std::fs;
+		use std::sync::Arc;
+		use fm_library::cxl_fm_lib::CXL_FM_BIND_LD_COMMAND;
+
+		#[test]
+		fn operation_lock_is_recovered_after_panic() {
+			let directory = std::env::temp_dir()
+				.join(format!("fm_context_{}", std::process::id()));
+			let ctx = Arc::new(CxlFmContext::with_devices(Vec::new()));
+
+			*lock_shared(&ctx.store) = Some(CxlFmStateStore::open(directory.to_str().unwrap()).unwrap());
+
+			let handler = Arc::clone(&ctx);
+			let result = thread::spawn(move || {
+				let _operation = handler.lock_operation();
+
+				handler.begin_operation(&CxlFmRequest::new(CXL_FM_BIND_LD_COMMAND)).unwrap();
+
+				let _store = lock_shared(&handler.store);
+				panic!("request handler failed");
+			}).join();
+
+			assert!(result.is_err());
+			assert!(ctx.operation.is_poisoned());
+			assert!(ctx.store.is_poisoned());
+
+			drop(ctx.lock_operation());
+			assert!(!ctx.operation.is_poisoned());
+
+			{
+				let store = lock_shared(&ctx.store);
+				let store = store.as_ref().unwrap();
+
+				assert_eq!(store.current, None);
+				assert!(store.state.operations.is_empty());
+				assert_eq!(store.state.audit.len(), 1);
+				assert_eq!(store.state.audit[0].result, "failed, no steps executed");
+			}
+
+			fs::remove_dir_all(directory).unwrap();
+		}
+	}
+}
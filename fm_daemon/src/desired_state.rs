@@ -0,0 +1,402 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_desired_state_command {
+	use std::net::{TcpStream};
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_error;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::format_size;
+	use fm_library::cxl_fm_lib::CXL_FM_APPLY_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_EXTENT_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DCD_ADD_CAPACITY_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DCD_RELEASE_CAPACITY_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_PAYLOAD_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_HOST_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_REGION_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_POLICY_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LENGTH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_TAG_ARG;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_LD_ALLOCATIONS;
+	use fm_library::cxl_fm_api::CXL_FM_API_SET_LD_ALLOCATIONS;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_QOS_CONTROL;
+	use fm_library::cxl_fm_api::CXL_FM_API_SET_QOS_CONTROL;
+	use fm_library::cxl_fm_api::CXL_FM_API_INITIATE_DC_ADD;
+	use fm_library::cxl_fm_api::CXL_FM_API_INITIATE_DC_RELEASE;
+	use fm_library::cxl_switch_lib::CXL_UNBIND_WAIT;
+	use fm_library::cxl_ld_lib::CxlGetLdAllocationsRequest;
+	use fm_library::cxl_ld_lib::CxlGetLdAllocationsResponse;
+	use fm_library::cxl_ld_lib::CxlSetLdAllocationsRequest;
+	use fm_library::cxl_ld_lib::CxlLdAllocation;
+	use fm_library::cxl_ld_lib::CxlQosControl;
+	use fm_library::cxl_ld_lib::granularity_size;
+	use fm_library::cxl_ld_lib::CXL_QOS_EGRESS_PORT_CONGESTION;
+	use fm_library::cxl_ld_lib::CXL_QOS_TEMPORARY_THROUGHPUT_REDUCTION;
+	use fm_library::cxl_dcd_lib::format_tag;
+	use fm_library::cxl_dcd_lib::CXL_DCD_SELECTION_POLICY_FREE;
+	use fm_library::cxl_dcd_lib::CXL_DCD_TAG_SIZE;
+	use fm_library::cxl_desired_state::CxlFabricSpec;
+	use fm_library::cxl_desired_state::CxlFabricPlan;
+	use fm_library::cxl_desired_state::CxlBindingSpec;
+	use fm_library::cxl_desired_state::CxlAllocationSpec;
+	use fm_library::cxl_desired_state::CxlQosSpec;
+	use fm_library::cxl_desired_state::CxlCapacitySpec;
+	use fm_library::cxl_topology::FabricTopology;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_context::CxlCciPath;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_discover_command::discover_fabric;
+	use crate::cxl_dcd_command::read_extent_list;
+	use crate::cxl_fm_plan::CxlPlanAction;
+	use crate::cxl_fm_plan::CxlPlanEntry;
+	use crate::cxl_fm_plan::plan_step;
+	use crate::cxl_fm_plan::find_vppb;
+	use crate::cxl_fm_plan::bound_target;
+	use crate::cxl_fm_plan::binding_target;
+	use crate::cxl_fm_plan::bind_entry;
+	use crate::cxl_fm_plan::unbind_entry;
+	use crate::cxl_fm_plan::run_plan;
+	use crate::cxl_fm_state_store::CxlFmStateRecord;
+
+	/*
+	 * Maximal number of LDs requested by Get LD Allocations
+	 */
+	const CXL_APPLY_LD_LIST_LIMIT: u8 = u8::MAX;
+
+	/*
+	 * Plan vPPB bindings: rebinding is unbind followed by bind
+	 */
+	fn plan_bindings(ctx: &CxlFmContext, topology: &FabricTopology, spec: &CxlFabricSpec,
+			 unbinds: &mut Vec<CxlPlanEntry>,
+			 binds: &mut Vec<CxlPlanEntry>) -> Result<(), CxlFmError> {
+		for binding in &spec.bindings {
+			let CxlBindingSpec { switch, vcs, vppb, port, ld, .. } = binding;
+			let vppb_node = find_vppb(topology, switch, *vcs, *vppb)?;
+			let current = bound_target(topology, vppb_node);
+			let desired = binding_target(topology, switch, *port, *ld)?;
+
+			if current == desired {
+				continue;
+			}
+
+			if current.is_some() {
+				unbinds.push(unbind_entry(ctx, topology, switch, *vcs, *vppb,
+							  CXL_UNBIND_WAIT)?);
+			}
+
+			if let Some(port) = port {
+				binds.push(bind_entry(ctx, topology, switch, *vcs, *vppb, *port, *ld)?);
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Plan LD allocations: one Set LD Allocations command
+	 * covering changed LDs of every device
+	 */
+	pub fn plan_allocations(ctx: &CxlFmContext, topology: &FabricTopology,
+			    spec: &CxlFabricSpec) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let mut entries = Vec::new();
+		let mut devices: Vec<&str> = spec.allocations.iter()
+			.map(|allocation| allocation.device.as_str())
+			.collect();
+
+		devices.dedup();
+
+		for device in devices {
+			let path = ctx.path_of(topology, device)?;
+			let fm_api_request = CxlGetLdAllocationsRequest {
+				start_ld: 0,
+				ld_list_limit: CXL_APPLY_LD_LIST_LIMIT,
+			};
+			let data = ctx.execute_path(path, CXL_FM_API_GET_LD_ALLOCATIONS,
+						    &fm_api_request.to_bytes())?;
+			let current = CxlGetLdAllocationsResponse::from_bytes(&data)?;
+			let granularity = granularity_size(current.granularity);
+			let mut desired = current.allocations.clone();
+
+			for allocation in spec.allocations.iter().filter(|a| a.device == device) {
+				let CxlAllocationSpec { ld, range1, range2, .. } = allocation;
+
+				if *ld as usize >= desired.len() ||
+				   !range1.is_multiple_of(granularity) ||
+				   !range2.is_multiple_of(granularity) {
+					return Err(CxlFmError::InvalidInput(format!("{} LD {}: invalid allocation (granularity {})",
+										    device, ld,
+										    format_size(granularity))));
+				}
+
+				desired[*ld as usize] = CxlLdAllocation {
+					range1: range1 / granularity,
+					range2: range2 / granularity,
+				};
+			}
+
+			let changed: Vec<usize> = (0..desired.len())
+				.filter(|ld| desired[*ld] != current.allocations[*ld])
+				.collect();
+			let (first, last) = match (changed.first(), changed.last()) {
+				(Some(first), Some(last)) => (*first, *last),
+				_ => continue,
+			};
+
+			let description: Vec<String> = changed.iter()
+				.map(|ld| {
+					let show = |a: &CxlLdAllocation| format!("{}+{}",
+										   format_size(a.range1 * granularity),
+										   format_size(a.range2 * granularity));
+
+					format!("LD {}: {} -> {}", ld,
+						show(&current.allocations[*ld]), show(&desired[*ld]))
+				})
+				.collect();
+			let fm_api_request = CxlSetLdAllocationsRequest {
+				start_ld: first as u8,
+				allocations: desired[first..=last].to_vec(),
+			};
+
+			entries.push((plan_step(CXL_FM_API_SET_LD_ALLOCATIONS, device,
+						format!("set allocations {}", description.join(", "))),
+				       CxlPlanAction::Command(path, CXL_FM_API_SET_LD_ALLOCATIONS,
+							      fm_api_request.to_bytes())));
+		}
+
+		Ok(entries)
+	}
+
+	/*
+	 * Merge QoS settings defined by spec into current ones
+	 */
+	fn desired_qos(current: &CxlQosControl, qos: &CxlQosSpec) -> CxlQosControl {
+		let mut desired = current.clone();
+		let mut set_flag = |flag: u8, value: Option<bool>| match value {
+			Some(true) => desired.telemetry_control |= flag,
+			Some(false) => desired.telemetry_control &= !flag,
+			None => {},
+		};
+
+		set_flag(CXL_QOS_EGRESS_PORT_CONGESTION, qos.egress_port_congestion);
+		set_flag(CXL_QOS_TEMPORARY_THROUGHPUT_REDUCTION, qos.temporary_throughput_reduction);
+
+		desired.egress_moderate_percentage = qos.egress_moderate_percentage
+			.unwrap_or(current.egress_moderate_percentage);
+		desired.egress_severe_percentage = qos.egress_severe_percentage
+			.unwrap_or(current.egress_severe_percentage);
+		desired.backpressure_sample_interval = qos.backpressure_sample_interval
+			.unwrap_or(current.backpressure_sample_interval);
+		desired.req_cmp_basis = qos.req_cmp_basis
+			.unwrap_or(current.req_cmp_basis);
+		desired.completion_collection_interval = qos.completion_collection_interval
+			.unwrap_or(current.completion_collection_interval);
+		desired
+	}
+
+	/*
+	 * Describe changed QoS control fields
+	 */
+	fn describe_qos(current: &CxlQosControl, desired: &CxlQosControl) -> String {
+		let fields = [
+			("telemetry_control", current.telemetry_control as u16,
+			 desired.telemetry_control as u16),
+			("egress_moderate_percentage", current.egress_moderate_percentage as u16,
+			 desired.egress_moderate_percentage as u16),
+			("egress_severe_percentage", current.egress_severe_percentage as u16,
+			 desired.egress_severe_percentage as u16),
+			("backpressure_sample_interval", current.backpressure_sample_interval as u16,
+			 desired.backpressure_sample_interval as u16),
+			("req_cmp_basis", current.req_cmp_basis, desired.req_cmp_basis),
+			("completion_collection_interval", current.completion_collection_interval as u16,
+			 desired.completion_collection_interval as u16),
+		];
+
+		fields.iter()
+			.filter(|(_, current, desired)| current != desired)
+			.map(|(name, current, desired)| format!("{}: {} -> {}", name, current, desired))
+			.collect::<Vec<String>>()
+			.join(", ")
+	}
+
+	/*
+	 * Plan QoS control settings
+	 */
+	fn plan_qos(ctx: &CxlFmContext, topology: &FabricTopology,
+		    spec: &CxlFabricSpec) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let mut entries = Vec::new();
+
+		for qos in &spec.qos {
+			let path = ctx.path_of(topology, &qos.device)?;
+			let data = ctx.execute_path(path, CXL_FM_API_GET_QOS_CONTROL, &[])?;
+			let current = CxlQosControl::from_bytes(&data)?;
+			let desired = desired_qos(&current, qos);
+
+			if desired == current {
+				continue;
+			}
+
+			entries.push((plan_step(CXL_FM_API_SET_QOS_CONTROL, &qos.device,
+						format!("set QoS control {}",
+						       describe_qos(&current, &desired))),
+				       CxlPlanAction::Command(path, CXL_FM_API_SET_QOS_CONTROL,
+							      desired.to_bytes())));
+		}
+
+		Ok(entries)
+	}
+
+	/*
+	 * Plan Dynamic Capacity grants. Capacity of the host is the total
+	 * length of its extents with the tag (any extents for zero tag).
+	 */
+	fn plan_capacity(ctx: &CxlFmContext, topology: &FabricTopology, spec: &CxlFabricSpec,
+			 releases: &mut Vec<CxlPlanEntry>,
+			 adds: &mut Vec<CxlPlanEntry>) -> Result<(), CxlFmError> {
+		for capacity in &spec.capacity {
+			let CxlCapacitySpec { device, host, region, length, tag, .. } = capacity;
+			let index = match ctx.path_of(topology, device)? {
+				CxlCciPath::Direct(index) => index,
+				CxlCciPath::Tunnel(..) => {
+					return Err(CxlFmError::InvalidInput(format!("{}: Dynamic Capacity is managed by direct CCI only",
+										    device)));
+				},
+			};
+
+			let request = CxlFmRequest::new(CXL_FM_DCD_GET_EXTENT_COMMAND)
+						.arg(CXL_FM_DEVICE_ARG, &index.to_string());
+			let current: u64 = read_extent_list(&request, ctx, *host)?.extents.iter()
+				.filter(|extent| *tag == [0; CXL_DCD_TAG_SIZE] || extent.tag == *tag)
+				.map(|extent| extent.length)
+				.sum();
+
+			let (command, opcode, difference) = match current.cmp(length) {
+				std::cmp::Ordering::Less => (CXL_FM_DCD_ADD_CAPACITY_COMMAND,
+							     CXL_FM_API_INITIATE_DC_ADD,
+							     length - current),
+				std::cmp::Ordering::Greater => (CXL_FM_DCD_RELEASE_CAPACITY_COMMAND,
+								CXL_FM_API_INITIATE_DC_RELEASE,
+								current - length),
+				std::cmp::Ordering::Equal => continue,
+			};
+
+			let mut request = CxlFmRequest::new(command)
+						.arg(CXL_FM_DEVICE_ARG, &index.to_string())
+						.arg(CXL_FM_HOST_ARG, &host.to_string())
+						.arg(CXL_FM_LENGTH_ARG, &difference.to_string());
+
+			if *tag != [0; CXL_DCD_TAG_SIZE] {
+				request = request.arg(CXL_FM_TAG_ARG, &format_tag(tag));
+			}
+
+			let description = format!("host {} capacity {} -> {} (tag {})",
+						  host, format_size(current), format_size(*length),
+						  format_tag(tag));
+
+			if opcode == CXL_FM_API_INITIATE_DC_ADD {
+				request = request.arg(CXL_FM_REGION_ARG, &region.to_string())
+						 .arg(CXL_FM_POLICY_ARG,
+						      &CXL_DCD_SELECTION_POLICY_FREE.to_string());
+				adds.push((plan_step(opcode, device, format!("add {}: {}",
+									     format_size(difference),
+									     description)),
+					   CxlPlanAction::AddCapacity(request)));
+			} else {
+				releases.push((plan_step(opcode, device, format!("release {}: {}",
+										 format_size(difference),
+										 description)),
+					       CxlPlanAction::ReleaseCapacity(request)));
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Compute ordered operations to reach desired state:
+	 * unbind vPPBs, change LD allocations and QoS, bind vPPBs,
+	 * release and then add Dynamic Capacity
+	 */
+	pub fn build_plan(ctx: &CxlFmContext, topology: &FabricTopology,
+		      spec: &CxlFabricSpec) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let mut unbinds = Vec::new();
+		let mut binds = Vec::new();
+		let mut releases = Vec::new();
+		let mut adds = Vec::new();
+
+		plan_bindings(ctx, topology, spec, &mut unbinds, &mut binds)?;
+		plan_capacity(ctx, topology, spec, &mut releases, &mut adds)?;
+
+		let mut plan = unbinds;
+		plan.extend(plan_allocations(ctx, topology, spec)?);
+		plan.extend(plan_qos(ctx, topology, spec)?);
+		plan.extend(binds);
+		plan.extend(releases);
+		plan.extend(adds);
+		Ok(plan)
+	}
+
+	/*
+	 * Bring the fabric to the desired state
+	 */
+	pub fn apply_spec(request: &CxlFmRequest, ctx: &CxlFmContext) -> Result<CxlFabricPlan, CxlFmError> {
+		let payload = request.get(CXL_FM_PAYLOAD_ARG)
+			.ok_or_else(|| CxlFmError::InvalidInput(String::from("no desired state")))?;
+		let content = String::from_utf8(from_hex(payload)?)
+			.map_err(|_| CxlFmError::InvalidInput(String::from("desired state is not UTF-8")))?;
+		let spec = CxlFabricSpec::parse(&content)?;
+
+		let topology = discover_fabric(ctx, false)?;
+		let entries = build_plan(ctx, &topology, &spec)?;
+
+		/*
+		 * Reconciliation follows the last applied state
+		 */
+		if !request.is_dry_run() {
+			ctx.journal(CxlFmStateRecord::DesiredState(content));
+			*lock_shared(&ctx.desired_state) = Some(spec);
+		}
+
+		run_plan(request, ctx, entries)
+	}
+
+	/*
+	 * Apply declarative fabric configuration
+	 */
+	pub fn apply(stream: &TcpStream, request: &CxlFmRequest,
+		     ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
+		if env.is_debug {
+			println!("{}", CXL_FM_APPLY_COMMAND);
+		}
+
+		let result = apply_spec(request, ctx);
+
+		match &result {
+			Ok(plan) => send_payload(stream, &plan.to_bytes(), env),
+			Err(e) => send_error(stream, e, env),
+		}
+
+		result.is_ok()
+	}
+}
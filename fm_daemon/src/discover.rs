@@ -22,42 +22,533 @@
 pub mod cxl_fm_discover_command {
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
-	use fm_library::cxl_fm_lib::send_responce;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_error;
 	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_CXL_DEVICE_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_CXL_SWITCH_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_LD_COMMAND;
-	use fm_library::cxl_fm_lib::CXL_FM_NO_DATA;
+	use fm_library::cxl_fm_lib::CXL_FM_DISCOVER_TOPOLOGY_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_RESCAN_ARG;
+	use fm_library::cxl_fm_api::CxlIdentifyResponse;
+	use fm_library::cxl_fm_api::link_speed_name;
+	use fm_library::cxl_fm_api::ltssm_state_name;
+	use fm_library::cxl_fm_api::CXL_FM_API_IDENTIFY;
+	use fm_library::cxl_fm_api::CXL_FM_API_IDENTIFY_SWITCH_DEVICE;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_PHYSICAL_PORT_STATE;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_VCS_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_LD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_MHD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_DCD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_UNSUPPORTED;
+	use fm_library::cxl_fm_api::CXL_COMPONENT_SWITCH;
+	use fm_library::cxl_fm_api::CXL_LTSSM_L0;
+	use fm_library::cxl_switch_lib::CxlIdentifySwitchResponse;
+	use fm_library::cxl_switch_lib::CxlGetPhysicalPortStateRequest;
+	use fm_library::cxl_switch_lib::CxlGetPhysicalPortStateResponse;
+	use fm_library::cxl_switch_lib::CxlPhysicalPortInfo;
+	use fm_library::cxl_switch_lib::CxlGetVcsInfoRequest;
+	use fm_library::cxl_switch_lib::CxlGetVcsInfoResponse;
+	use fm_library::cxl_switch_lib::bitmask_bits;
+	use fm_library::cxl_switch_lib::port_state_name;
+	use fm_library::cxl_switch_lib::port_device_name;
+	use fm_library::cxl_switch_lib::CXL_PORT_STATE_DSP;
+	use fm_library::cxl_switch_lib::CXL_PORT_DEVICE_NONE;
+	use fm_library::cxl_switch_lib::CXL_VPPB_BOUND_PORT;
+	use fm_library::cxl_switch_lib::CXL_VPPB_BOUND_LD;
+	use fm_library::cxl_ld_lib::CxlGetLdInfoResponse;
+	use fm_library::cxl_mh_device_lib::CxlGetMhdInfoRequest;
+	use fm_library::cxl_mh_device_lib::CxlGetMhdInfoResponse;
+	use fm_library::cxl_dcd_lib::CxlDcdInfo;
+	use fm_library::cxl_topology::FabricTopology;
+	use fm_library::cxl_topology::is_device_kind;
+	use fm_library::cxl_topology::CXL_NODE_HOST;
+	use fm_library::cxl_topology::CXL_NODE_SWITCH;
+	use fm_library::cxl_topology::CXL_NODE_PHYSICAL_PORT;
+	use fm_library::cxl_topology::CXL_NODE_VCS;
+	use fm_library::cxl_topology::CXL_NODE_VPPB;
+	use fm_library::cxl_topology::CXL_NODE_SLD;
+	use fm_library::cxl_topology::CXL_NODE_MLD;
+	use fm_library::cxl_topology::CXL_NODE_LD;
+	use fm_library::cxl_topology::CXL_NODE_MHD;
+	use fm_library::cxl_topology::CXL_NODE_DCD;
+	use fm_library::cxl_topology::CXL_CAPABILITY_MLD;
+	use fm_library::cxl_topology::CXL_CAPABILITY_MHD;
+	use fm_library::cxl_topology::CXL_CAPABILITY_DCD;
+	use fm_library::cxl_topology::CXL_EDGE_CONTAINS;
+	use fm_library::cxl_topology::CXL_EDGE_LINK;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_context::CxlCciPath;
+	use crate::cxl_fm_context::lock_shared;
+
+	/*
+	 * Maximal number of entries requested by list commands
+	 */
+	const CXL_DISCOVER_LIST_LIMIT: u8 = u8::MAX;
+
+	/*
+	 * Execute command that component may not support
+	 */
+	fn execute_optional(ctx: &CxlFmContext, path: CxlCciPath,
+			    opcode: u16, payload: &[u8]) -> Result<Option<Vec<u8>>, CxlFmError> {
+		match ctx.execute_path(path, opcode, payload) {
+			Ok(data) => Ok(Some(data)),
+			Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED)) => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+
+	/*
+	 * Find or create component contained in the parent
+	 */
+	fn child_node(topology: &mut FabricTopology, parent: u32,
+		      kind: u8, name: &str) -> Result<u32, CxlFmError> {
+		if let Some(node) = topology.find(kind, name) {
+			return Ok(node.id);
+		}
+
+		let id = topology.add_node(kind, name);
+		topology.add_edge(parent, id, CXL_EDGE_CONTAINS)?;
+		Ok(id)
+	}
+
+	/*
+	 * Find or create host of the fabric
+	 */
+	fn host_node(topology: &mut FabricTopology, name: &str) -> u32 {
+		match topology.find(CXL_NODE_HOST, name) {
+			Some(node) => node.id,
+			None => topology.add_node(CXL_NODE_HOST, name),
+		}
+	}
+
+	/*
+	 * Identify device, its type and capabilities and add it
+	 * to the topology. Device seen through several paths
+	 * (heads of MHD) is added once.
+	 */
+	fn probe_device(ctx: &CxlFmContext, topology: &mut FabricTopology,
+			path: CxlCciPath, name: &str) -> Result<u32, CxlFmError> {
+		let identity = CxlIdentifyResponse::from_bytes(&ctx.execute_path(path, CXL_FM_API_IDENTIFY, &[])?)?;
+		let serial = format!("{:#x}", identity.serial_number);
+
+		let known = topology.nodes.iter()
+			.find(|node| is_device_kind(node.kind) &&
+				     node.get("serial") == Some(serial.as_str()))
+			.map(|node| node.id);
+		if let Some(id) = known {
+			return Ok(id);
+		}
+
+		let mhd_request = CxlGetMhdInfoRequest {
+			start_ld: 0,
+			ld_map_list_limit: CXL_DISCOVER_LIST_LIMIT,
+		};
+		let mhd_info = match execute_optional(ctx, path, CXL_FM_API_GET_MHD_INFO,
+						      &mhd_request.to_bytes())? {
+			Some(data) => Some(CxlGetMhdInfoResponse::from_bytes(&data)?),
+			None => None,
+		};
+		let ld_info = match execute_optional(ctx, path, CXL_FM_API_GET_LD_INFO, &[])? {
+			Some(data) => Some(CxlGetLdInfoResponse::from_bytes(&data)?),
+			None => None,
+		};
+		let dcd_info = match execute_optional(ctx, path, CXL_FM_API_GET_DCD_INFO, &[])? {
+			Some(data) => Some(CxlDcdInfo::from_bytes(&data)?),
+			None => None,
+		};
+
+		let num_heads = mhd_info.as_ref().map_or(1, |info| info.num_heads);
+		let ld_map = mhd_info.as_ref().map_or(Vec::new(), |info| info.ld_map.clone());
+		let num_lds = match (&mhd_info, &ld_info) {
+			(Some(info), _) => info.num_lds as u16,
+			(None, Some(info)) => info.ld_count,
+			(None, None) => 1,
+		};
+
+		let kind = if num_heads > 1 {
+			CXL_NODE_MHD
+		} else if dcd_info.is_some() {
+			CXL_NODE_DCD
+		} else if num_lds > 1 {
+			CXL_NODE_MLD
+		} else {
+			CXL_NODE_SLD
+		};
+
+		let mut capabilities = Vec::new();
+		if num_lds > 1 {
+			capabilities.push(CXL_CAPABILITY_MLD);
+		}
+		if num_heads > 1 {
+			capabilities.push(CXL_CAPABILITY_MHD);
+		}
+		if dcd_info.is_some() {
+			capabilities.push(CXL_CAPABILITY_DCD);
+		}
+
+		let id = topology.add_node(kind, name);
+		topology.set_attr(id, "serial", &serial);
+		topology.set_attr(id, "vendor_id", &format!("{:#06x}", identity.vendor_id));
+		topology.set_attr(id, "device_id", &format!("{:#06x}", identity.device_id));
+		if let CxlCciPath::Direct(device) = path {
+			topology.set_attr(id, "cci", &ctx.device_name(device).unwrap_or_default());
+		}
+		if let Some(info) = &ld_info {
+			topology.set_attr(id, "memory_size", &info.memory_size.to_string());
+		}
+		topology.set_attr(id, "heads", &num_heads.to_string());
+		topology.set_attr(id, "lds", &num_lds.to_string());
+		if !capabilities.is_empty() {
+			topology.set_attr(id, "capabilities", &capabilities.join(","));
+		}
+		if let Some(info) = &dcd_info {
+			topology.set_attr(id, "dc_regions", &info.num_regions.to_string());
+			topology.set_attr(id, "dc_capacity", &info.total_capacity.to_string());
+		}
+
+		let mut head_lds = vec![Vec::new(); num_heads as usize];
+
+		if num_lds > 1 {
+			for ld in 0..num_lds {
+				let ld_id = child_node(topology, id, CXL_NODE_LD,
+						       &format!("{}/ld{}", name, ld))?;
+
+				if let Some(head) = ld_map.get(ld as usize) {
+					topology.set_attr(ld_id, "head", &head.to_string());
+
+					if let Some(lds) = head_lds.get_mut(*head as usize) {
+						lds.push(ld_id);
+					}
+				}
+			}
+		}
+
+		/*
+		 * Every head of MHD managed by its own CCI is linked
+		 * to a host. A head of MHD behind switch port is linked
+		 * to the port, the hosts of other heads are not known.
+		 */
+		if num_heads > 1 && matches!(path, CxlCciPath::Direct(_)) {
+			for (head, lds) in head_lds.iter().enumerate() {
+				let host = host_node(topology, &format!("{}/host{}", name, head));
+
+				topology.set_attr(host, "device", name);
+				topology.set_attr(host, "head", &head.to_string());
+
+				for ld in lds {
+					topology.add_edge(host, *ld, CXL_EDGE_LINK)?;
+				}
+			}
+		}
+
+		Ok(id)
+	}
+
+	/*
+	 * Remove device attached to the port unless
+	 * it is still linked to another port
+	 */
+	fn detach_device(topology: &mut FabricTopology, port: u32) {
+		for device in topology.children(port, CXL_EDGE_LINK) {
+			topology.remove_edge(port, device, CXL_EDGE_LINK);
+
+			if topology.parent(device, CXL_EDGE_LINK).is_none() {
+				topology.remove_node(device);
+			}
+		}
+	}
+
+	/*
+	 * Update physical port and re-probe attached device
+	 */
+	fn update_port(ctx: &CxlFmContext, topology: &mut FabricTopology,
+		       device: usize, switch: u32, switch_name: &str,
+		       info: &CxlPhysicalPortInfo) -> Result<(), CxlFmError> {
+		let port_name = format!("{}/port{}", switch_name, info.port_id);
+		let port = child_node(topology, switch, CXL_NODE_PHYSICAL_PORT, &port_name)?;
+
+		topology.set_attr(port, "id", &info.port_id.to_string());
+		topology.set_attr(port, "state", port_state_name(info.config_state));
+		topology.set_attr(port, "ltssm", ltssm_state_name(info.ltssm_state));
+		topology.set_attr(port, "width", &format!("x{}", info.negotiated_link_width));
+		topology.set_attr(port, "speed", link_speed_name(info.current_link_speed));
+		topology.set_attr(port, "device", port_device_name(info.connected_device_type));
+		if info.config_state == CXL_PORT_STATE_DSP {
+			topology.set_attr(port, "status", ctx.port_status(&port_name));
+		}
+
+		detach_device(topology, port);
+
+		if info.config_state == CXL_PORT_STATE_DSP &&
+		   info.ltssm_state == CXL_LTSSM_L0 &&
+		   info.connected_device_type != CXL_PORT_DEVICE_NONE {
+			let path = CxlCciPath::Tunnel(device, info.port_id);
+			let name = format!("{}/port{}/dev", switch_name, info.port_id);
+			let attached = probe_device(ctx, topology, path, &name)?;
+
+			topology.add_edge(port, attached, CXL_EDGE_LINK)?;
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Read VCSs of the switch and bindings of their vPPBs
+	 */
+	fn update_vcs(ctx: &CxlFmContext, topology: &mut FabricTopology,
+		      device: usize, switch: u32, switch_name: &str,
+		      vcs_ids: Vec<u8>) -> Result<(), CxlFmError> {
+		let fm_api_request = CxlGetVcsInfoRequest {
+			start_vppb: 0,
+			vppb_list_limit: CXL_DISCOVER_LIST_LIMIT,
+			vcs_ids,
+		};
+		let data = ctx.execute(device, CXL_FM_API_GET_VCS_INFO, &fm_api_request.to_bytes())?;
+		let responce = CxlGetVcsInfoResponse::from_bytes(&fm_api_request, &data)?;
+
+		for vcs in responce.vcs {
+			let vcs_name = format!("{}/vcs{}", switch_name, vcs.vcs_id);
+			let vcs_id = child_node(topology, switch, CXL_NODE_VCS, &vcs_name)?;
+			let usp_name = format!("{}/port{}", switch_name, vcs.usp_id);
+			let host = host_node(topology, &format!("{}/host{}", switch_name, vcs.vcs_id));
+
+			topology.set_attr(vcs_id, "state",
+					  if vcs.state != 0 { "enabled" } else { "disabled" });
+			topology.set_attr(vcs_id, "usp", &vcs.usp_id.to_string());
+			topology.set_attr(host, "vcs", &vcs_name);
+			if let Some(usp) = topology.find(CXL_NODE_PHYSICAL_PORT, &usp_name).map(|n| n.id) {
+				topology.add_edge(vcs_id, usp, CXL_EDGE_LINK)?;
+				topology.add_edge(host, usp, CXL_EDGE_LINK)?;
+			}
+
+			for (index, vppb) in vcs.vppbs.iter().enumerate() {
+				let vppb_id = child_node(topology, vcs_id, CXL_NODE_VPPB,
+							 &format!("{}/vppb{}", vcs_name, index))?;
+
+				topology.set_attr(vppb_id, "vcs", &vcs.vcs_id.to_string());
+				topology.set_attr(vppb_id, "id", &index.to_string());
+				topology.add_edge(host, vppb_id, CXL_EDGE_LINK)?;
+				let port_name = format!("{}/port{}", switch_name, vppb.bound_port);
+				let port = topology.find(CXL_NODE_PHYSICAL_PORT, &port_name)
+						.map(|node| node.id);
+
+				topology.unbind(vppb_id);
+
+				let target = match (vppb.binding_status, port) {
+					(CXL_VPPB_BOUND_PORT, Some(port)) => Some(port),
+					(CXL_VPPB_BOUND_LD, Some(port)) => {
+						topology.children(port, CXL_EDGE_LINK)
+							.first()
+							.and_then(|dev| topology.node(*dev))
+							.map(|dev| format!("{}/ld{}", dev.name, vppb.bound_ld))
+							.and_then(|ld| topology.find(CXL_NODE_LD, &ld))
+							.map(|ld| ld.id)
+					},
+					_ => None,
+				};
+
+				if let Some(target) = target {
+					topology.bind(vppb_id, target)?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Discover switch: identify it, enumerate physical ports,
+	 * follow changed ports to attached devices and read VCSs.
+	 * Ports with unchanged state since the previous pass are skipped.
+	 */
+	fn discover_switch(ctx: &CxlFmContext, topology: &mut FabricTopology,
+			   device: usize, name: &str) -> Result<(), CxlFmError> {
+		let data = ctx.execute(device, CXL_FM_API_IDENTIFY_SWITCH_DEVICE, &[])?;
+		let info = CxlIdentifySwitchResponse::from_bytes(&data)?;
+
+		let switch = match topology.find(CXL_NODE_SWITCH, name) {
+			Some(node) => node.id,
+			None => topology.add_node(CXL_NODE_SWITCH, name),
+		};
+
+		topology.set_attr(switch, "cci", name);
+		topology.set_attr(switch, "ports", &info.num_ports.to_string());
+		topology.set_attr(switch, "vcs", &info.num_vcs.to_string());
+		topology.set_attr(switch, "vppbs", &info.total_vppbs.to_string());
+		topology.set_attr(switch, "bound_vppbs", &info.bound_vppbs.to_string());
+		topology.set_attr(switch, "hdm_decoders", &info.num_hdm_decoders.to_string());
+
+		let fm_api_request = CxlGetPhysicalPortStateRequest {
+			ports: (0..info.num_ports).collect(),
+		};
+		let data = ctx.execute(device, CXL_FM_API_GET_PHYSICAL_PORT_STATE,
+				       &fm_api_request.to_bytes())?;
+		let responce = CxlGetPhysicalPortStateResponse::from_bytes(&data)?;
+
+		for port in &responce.ports {
+			let key = (device, port.port_id);
+
+			if lock_shared(&ctx.port_states).get(&key) == Some(port) {
+				continue;
+			}
+
+			update_port(ctx, topology, device, switch, name, port)?;
+			lock_shared(&ctx.port_states).insert(key, port.clone());
+		}
+
+		update_vcs(ctx, topology, device, switch, name, bitmask_bits(&info.active_vcs))
+	}
+
+	/*
+	 * Discover the fabric starting from the daemon's CCIs.
+	 * The first pass (or rescan) builds the whole topology,
+	 * next passes re-probe only ports that changed state.
+	 * Discovered switches and devices are kept as inventory.
+	 */
+	pub fn discover_fabric(ctx: &CxlFmContext,
+			       rescan: bool) -> Result<FabricTopology, CxlFmError> {
+		let mut topology = lock_shared(&ctx.topology);
+
+		if rescan || topology.nodes.is_empty() {
+			*topology = FabricTopology::new();
+			lock_shared(&ctx.port_states).clear();
+		}
+
+		for device in 0..ctx.device_count() {
+			let name = ctx.device_name(device).unwrap_or_default();
+
+			if topology.nodes.iter().any(|node| node.kind != CXL_NODE_SWITCH &&
+							     node.get("cci") == Some(name.as_str())) {
+				continue;
+			}
+
+			let result = ctx.execute(device, CXL_FM_API_IDENTIFY, &[])
+				.and_then(|data| CxlIdentifyResponse::from_bytes(&data))
+				.and_then(|identity| {
+					if identity.component_type == CXL_COMPONENT_SWITCH {
+						discover_switch(ctx, &mut topology, device, &name)
+					} else {
+						probe_device(ctx, &mut topology,
+							     CxlCciPath::Direct(device), &name).map(|_| ())
+					}
+				});
+
+			if let Err(e) = result {
+				println!("Failed to discover {}: {}", name, e);
+			}
+		}
+
+		ctx.update_inventory(&topology);
+		Ok(topology.clone())
+	}
+
+	/*
+	 * Discover fabric and send the topology
+	 */
+	fn send_topology(stream: &TcpStream, request: &CxlFmRequest,
+			 ctx: &CxlFmContext, env: &CxlFmOptions) {
+		let topology = request.get_number_or::<u8>(CXL_FM_RESCAN_ARG, 0)
+			.and_then(|rescan| discover_fabric(ctx, rescan != 0));
+
+		match topology {
+			Ok(topology) => send_payload(stream, &topology.to_bytes(), env),
+			Err(e) => send_error(stream, &e, env),
+		}
+	}
 
 	/*
 	 * Discover available CXL devices
 	 */
-	pub fn discover_cxl_devices(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn discover_cxl_devices(stream: &TcpStream, request: &CxlFmRequest,
+				    ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_DISCOVER_CXL_DEVICE_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_topology(stream, request, ctx, env);
 	}
 
 	/*
 	 * Discover available CXL switches
 	 */
-	pub fn discover_cxl_switches(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn discover_cxl_switches(stream: &TcpStream, request: &CxlFmRequest,
+				     ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_DISCOVER_CXL_SWITCH_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_topology(stream, request, ctx, env);
 	}
 
 	/*
 	 * Discover available logical devices
 	 */
-	pub fn discover_logical_devices(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn discover_logical_devices(stream: &TcpStream, request: &CxlFmRequest,
+					ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_DISCOVER_LD_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_topology(stream, request, ctx, env);
+	}
+
+	/*
+	 * Get the whole fabric topology
+	 */
+	pub fn discover_topology(stream: &TcpStream, request: &CxlFmRequest,
+				 ctx: &CxlFmContext, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", CXL_FM_DISCOVER_TOPOLOGY_COMMAND);
+		}
+
+		send_topology(stream, request, ctx, env);
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use fm_library::cxl_fm_config::CxlFmDaemonConfig;
+
+		fn linked(topology: &FabricTopology, host: &str) -> Vec<String> {
+			let host = topology.find(CXL_NODE_HOST, host).unwrap();
+
+			topology.children(host.id, CXL_EDGE_LINK)
+				.iter()
+				.filter_map(|id| topology.node(*id))
+				.map(|node| node.name.clone())
+				.collect()
+		}
+
+		#[test]
+		fn multi_headed_dcd_has_both_capabilities() {
+			let ctx = CxlFmContext::new(CxlFmDaemonConfig::new("fm_daemon"));
+			let topology = discover_fabric(&ctx, false).unwrap();
+			let mhd = topology.find(CXL_NODE_MHD, "mhd0").unwrap();
+
+			assert!(mhd.has_capability(CXL_CAPABILITY_MHD));
+			assert!(mhd.has_capability(CXL_CAPABILITY_DCD));
+			assert!(mhd.has_capability(CXL_CAPABILITY_MLD));
+
+			let sld = topology.find(CXL_NODE_SLD, "sw0/port2/dev").unwrap();
+			assert!(!sld.has_capability(CXL_CAPABILITY_DCD));
+		}
+
+		#[test]
+		fn hosts_are_discovered() {
+			let ctx = CxlFmContext::new(CxlFmDaemonConfig::new("fm_daemon"));
+			let topology = discover_fabric(&ctx, false).unwrap();
+
+			assert_eq!(linked(&topology, "sw0/host1"),
+				   vec!["sw0/port1", "sw0/vcs1/vppb0", "sw0/vcs1/vppb1",
+					"sw0/vcs1/vppb2", "sw0/vcs1/vppb3"]);
+			assert_eq!(linked(&topology, "mhd0/host1"), vec!["mhd0/ld2", "mhd0/ld3"]);
+
+			/*
+			 * Hosts of MHD behind switch port are not known
+			 */
+			assert!(topology.find(CXL_NODE_HOST, "sw0/port5/dev/host0").is_none());
+		}
 	}
 }
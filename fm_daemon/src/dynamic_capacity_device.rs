@@ -22,78 +22,875 @@
 pub mod cxl_dcd_command {
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
-	use fm_library::cxl_fm_lib::send_responce;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_ok;
+	use fm_library::cxl_fm_lib::send_error;
+	use fm_library::cxl_fm_lib::CXL_FM_HOST_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_START_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_COUNT_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_REGION_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_BLOCK_SIZE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_SANITIZE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_POLICY_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LENGTH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_TAG_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_EXTENTS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_FORCED_ARG;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_INFO_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_SET_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_EXTENT_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_ADD_CAPACITY_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_DCD_RELEASE_CAPACITY_COMMAND;
-	use fm_library::cxl_fm_lib::CXL_FM_NO_DATA;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_DCD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_HOST_DC_REGION_CONFIG;
+	use fm_library::cxl_fm_api::CXL_FM_API_SET_DC_REGION_CONFIG;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_DC_REGION_EXTENT_LIST;
+	use fm_library::cxl_fm_api::CXL_FM_API_INITIATE_DC_ADD;
+	use fm_library::cxl_fm_api::CXL_FM_API_INITIATE_DC_RELEASE;
+	use fm_library::cxl_fm_api::CXL_FM_API_INVALID_INPUT;
+	use fm_library::cxl_fm_api::CXL_FM_API_RETRY_REQUIRED;
+	use fm_library::cxl_dcd_lib::CxlGetHostDcRegionConfigRequest;
+	use fm_library::cxl_dcd_lib::CxlSetDcRegionConfigRequest;
+	use fm_library::cxl_dcd_lib::CxlHostDcRegionConfig;
+	use fm_library::cxl_dcd_lib::CxlGetDcExtentListRequest;
+	use fm_library::cxl_dcd_lib::CxlDcExtentList;
+	use fm_library::cxl_dcd_lib::CxlInitiateDcAddRequest;
+	use fm_library::cxl_dcd_lib::CxlDcAddResult;
+	use fm_library::cxl_dcd_lib::CxlInitiateDcReleaseRequest;
+	use fm_library::cxl_dcd_lib::CxlDcExtent;
+	use fm_library::cxl_dcd_lib::CXL_DCD_REMOVAL_POLICY_TAG_BASED;
+	use fm_library::cxl_dcd_lib::CXL_DCD_REMOVAL_POLICY_PRESCRIPTIVE;
+	use fm_library::cxl_dcd_lib::parse_tag;
+	use fm_library::cxl_dcd_lib::parse_extent_ranges;
+	use fm_library::cxl_dcd_lib::CXL_DCD_TAG_SIZE;
+	use fm_library::cxl_dcd_lib::CXL_DCD_REGION_SANITIZE_ON_RELEASE;
+	use fm_library::cxl_fm_lib::format_size;
+	use fm_library::cxl_fm_api::CXL_FM_API_UNSUPPORTED;
+	use fm_library::cxl_dcd_lib::selection_policy_name;
+	use fm_library::cxl_dcd_lib::format_tag;
+	use fm_library::cxl_dcd_lib::CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE;
+	use crate::cxl_fm_context::CxlFmContext;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_plan::CxlPlanEntry;
+	use crate::cxl_fm_plan::CxlPlanAction;
+	use crate::cxl_fm_plan::plan_step;
+	use crate::cxl_fm_plan::send_plan;
+	use crate::cxl_capacity_pool_command::read_pool_device;
+
+	/*
+	 * Number of extents requested by one Get DC Region Extent Lists
+	 */
+	const CXL_DCD_EXTENT_LIST_PAGE_SIZE: u32 = 16;
+
+	/*
+	 * Maximal number of restarts of extent list reading
+	 * because of extent list generation change
+	 */
+	const CXL_DCD_EXTENT_LIST_MAX_RETRIES: u32 = 8;
 
 	/*
 	 * Get Dynamic Capacity Device (DCD) info
 	 */
-	pub fn get_info(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn get_info(stream: &TcpStream, request: &CxlFmRequest,
+			ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_DCD_GET_INFO_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		match ctx.execute_for(request, CXL_FM_API_GET_DCD_INFO, &[]) {
+			Ok(payload) => send_payload(stream, &payload, env),
+			Err(e) => send_error(stream, &e, env),
+		}
 	}
 
 	/*
 	 * Get dynamic capacity region configuration
 	 */
-	pub fn get_capacity_config(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn get_capacity_config(stream: &TcpStream, request: &CxlFmRequest,
+				   ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_DCD_GET_CONFIG_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		let result = request.get_number::<u16>(CXL_FM_HOST_ARG)
+			.and_then(|host_id| {
+				let fm_api_request = CxlGetHostDcRegionConfigRequest {
+					host_id,
+					region_count: request.get_number::<u8>(CXL_FM_COUNT_ARG)?,
+					start_region: request.get_number::<u8>(CXL_FM_START_ARG)?,
+				};
+
+				ctx.execute_for(request,
+						CXL_FM_API_GET_HOST_DC_REGION_CONFIG,
+						&fm_api_request.to_bytes())
+			});
+
+		match result {
+			Ok(payload) => send_payload(stream, &payload, env),
+			Err(e) => send_error(stream, &e, env),
+		}
+	}
+
+	/*
+	 * Get the current flags of DC region from the region
+	 * configuration of the host. They are kept if the request
+	 * doesn't change them.
+	 */
+	fn region_flags(request: &CxlFmRequest, ctx: &CxlFmContext,
+			region_id: u8) -> Result<u8, CxlFmError> {
+		let fm_api_request = CxlGetHostDcRegionConfigRequest {
+			host_id: request.get_number::<u16>(CXL_FM_HOST_ARG)?,
+			region_count: 1,
+			start_region: region_id,
+		};
+		let payload = ctx.execute_for(request,
+					      CXL_FM_API_GET_HOST_DC_REGION_CONFIG,
+					      &fm_api_request.to_bytes())?;
+		let config = CxlHostDcRegionConfig::from_bytes(region_id, &payload)?;
+
+		match config.regions.first() {
+			Some(region) => Ok(region.flags),
+			None => Err(CxlFmError::NotFound(format!("region {}", region_id))),
+		}
 	}
 
 	/*
 	 * Set dynamic capacity region configuration
 	 */
-	pub fn set_capacity_config(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn set_capacity_config(stream: &TcpStream, request: &CxlFmRequest,
+				   ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_DCD_SET_CONFIG_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		let result = request.get_number::<u8>(CXL_FM_REGION_ARG)
+			.and_then(|region_id| {
+				let flags = match request.get(CXL_FM_SANITIZE_ARG) {
+					Some(_) => match request.get_number::<u8>(CXL_FM_SANITIZE_ARG)? {
+						0 => 0,
+						_ => CXL_DCD_REGION_SANITIZE_ON_RELEASE,
+					},
+					None => region_flags(request, ctx, region_id)?,
+				};
+
+				let fm_api_request = CxlSetDcRegionConfigRequest {
+					region_id,
+					block_size: request.get_number::<u64>(CXL_FM_BLOCK_SIZE_ARG)?,
+					flags,
+				};
+
+				ctx.execute_for(request,
+						CXL_FM_API_SET_DC_REGION_CONFIG,
+						&fm_api_request.to_bytes())
+			});
+
+		match &result {
+			Ok(_) => send_ok(stream, "", env),
+			Err(e) => send_error(stream, e, env),
+		}
+
+		result.is_ok()
+	}
+
+	/*
+	 * Read the whole extent list of the host. The list can be changed
+	 * by the device between pages. If generation number has changed,
+	 * then the list is torn and the reading is restarted.
+	 */
+	pub fn read_extent_list(request: &CxlFmRequest, ctx: &CxlFmContext,
+			    host_id: u16) -> Result<CxlDcExtentList, CxlFmError> {
+		'restart: for _ in 0..CXL_DCD_EXTENT_LIST_MAX_RETRIES {
+			let mut list = CxlDcExtentList {
+				host_id,
+				start_index: 0,
+				total_extents: 0,
+				generation: 0,
+				extents: Vec::new(),
+			};
+
+			loop {
+				let fm_api_request = CxlGetDcExtentListRequest {
+					host_id,
+					extent_count: CXL_DCD_EXTENT_LIST_PAGE_SIZE,
+					start_index: list.extents.len() as u32,
+				};
+
+				let result = ctx.execute_for(request,
+							     CXL_FM_API_GET_DC_REGION_EXTENT_LIST,
+							     &fm_api_request.to_bytes());
+
+				/*
+				 * The list could shrink below the start index
+				 */
+				let payload = match result {
+					Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT))
+						if !list.extents.is_empty() => continue 'restart,
+					result => result?,
+				};
+				let part = CxlDcExtentList::from_bytes(&payload)?;
+
+				if part.start_index != fm_api_request.start_index {
+					return Err(CxlFmError::Protocol(format!("unexpected start index: {}",
+										part.start_index)));
+				}
+
+				if !list.extents.is_empty() && part.generation != list.generation {
+					continue 'restart;
+				}
+
+				list.total_extents = part.total_extents;
+				list.generation = part.generation;
+				list.extents.extend_from_slice(&part.extents);
+
+				if part.extents.is_empty() ||
+				   list.extents.len() >= list.total_extents as usize {
+					break;
+				}
+			}
+
+			return Ok(list);
+		}
+
+		Err(CxlFmError::Device(CXL_FM_API_RETRY_REQUIRED))
+	}
+
+	/*
+	 * Read the extent list of the host and record it if it has
+	 * changed, e.g. the host has accepted pending capacity
+	 */
+	pub fn observe_extent_list(request: &CxlFmRequest, ctx: &CxlFmContext,
+				   host_id: u16) -> Result<CxlDcExtentList, CxlFmError> {
+		let list = read_extent_list(request, ctx, host_id)?;
+		let device = CxlFmContext::device_for(request)?;
+		let is_changed = lock_shared(&ctx.dc_extents)
+			.get(&(device, host_id)) != Some(&list.extents);
+
+		if is_changed {
+			ctx.update_dc_extents(device, host_id, list.extents.clone());
+		}
+
+		Ok(list)
 	}
 
 	/*
 	 * Get Dynamic Capacity Device (DCD) extent list
 	 */
-	pub fn get_extent_list(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn get_extent_list(stream: &TcpStream, request: &CxlFmRequest,
+			       ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_DCD_GET_EXTENT_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		let result = request.get_number::<u16>(CXL_FM_HOST_ARG)
+			.and_then(|host_id| observe_extent_list(request, ctx, host_id));
+
+		match result {
+			Ok(list) => send_payload(stream, &list.to_bytes(), env),
+			Err(e) => send_error(stream, &e, env),
+		}
+	}
+
+	/*
+	 * Build Initiate Dynamic Capacity Add request
+	 */
+	fn add_capacity_request(request: &CxlFmRequest) -> Result<CxlInitiateDcAddRequest, CxlFmError> {
+		let tag = match request.get(CXL_FM_TAG_ARG) {
+			Some(tag) => parse_tag(tag)?,
+			None => [0; CXL_DCD_TAG_SIZE],
+		};
+
+		let extents = match request.get(CXL_FM_EXTENTS_ARG) {
+			Some(extents) => parse_extent_ranges(extents, tag)?,
+			None => Vec::new(),
+		};
+
+		Ok(CxlInitiateDcAddRequest {
+			host_id: request.get_number::<u16>(CXL_FM_HOST_ARG)?,
+			selection_policy: request.get_number::<u8>(CXL_FM_POLICY_ARG)?,
+			region: request.get_number::<u8>(CXL_FM_REGION_ARG)?,
+			length: request.get_number_or::<u64>(CXL_FM_LENGTH_ARG, 0)?,
+			tag,
+			extents,
+		})
+	}
+
+	/*
+	 * Add capacity and find extents accepted by the host. Accepted
+	 * extents are the difference between the host extent lists
+	 * before and after the command. FM doesn't wait for the host,
+	 * capacity that is not accepted yet is reported as pending,
+	 * it is recorded when the extent list shows it accepted.
+	 */
+	pub fn add_extents(request: &CxlFmRequest,
+		       ctx: &CxlFmContext) -> Result<CxlDcAddResult, CxlFmError> {
+		let fm_api_request = add_capacity_request(request)?;
+		let host_id = fm_api_request.host_id;
+
+		let before = read_extent_list(request, ctx, host_id)?;
+
+		ctx.execute_for(request,
+				CXL_FM_API_INITIATE_DC_ADD,
+				&fm_api_request.to_bytes())?;
+
+		let after = observe_extent_list(request, ctx, host_id)?;
+		let accepted = after.extents.iter()
+			.filter(|extent| !before.extents.contains(extent))
+			.cloned()
+			.collect();
+
+		Ok(CxlDcAddResult::new(&fm_api_request, after.generation, accepted))
+	}
+
+	/*
+	 * Get name of the device defined by request
+	 */
+	fn device_target(request: &CxlFmRequest,
+			 ctx: &CxlFmContext) -> Result<String, CxlFmError> {
+		let device = CxlFmContext::device_for(request)?;
+
+		Ok(ctx.device_name(device).unwrap_or_else(|| device.to_string()))
+	}
+
+	/*
+	 * Plan dynamic capacity add. The request is checked
+	 * against DC regions and free capacity of the device.
+	 */
+	pub fn add_plan(request: &CxlFmRequest,
+			ctx: &CxlFmContext) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let fm_api_request = add_capacity_request(request)?;
+		let pool_device = read_pool_device(ctx, CxlFmContext::device_for(request)?, 0)?
+					.ok_or(CxlFmError::Device(CXL_FM_API_UNSUPPORTED))?;
+
+		if fm_api_request.host_id >= pool_device.num_hosts as u16 {
+			return Err(CxlFmError::InvalidInput(format!("device has no host {}",
+								    fm_api_request.host_id)));
+		}
+
+		let region = pool_device.regions.get(fm_api_request.region as usize)
+			.ok_or_else(|| CxlFmError::InvalidInput(format!("device has no DC region {}",
+									fm_api_request.region)))?;
+
+		let is_prescriptive = fm_api_request.selection_policy ==
+					CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE;
+		let length = if is_prescriptive {
+			fm_api_request.extents.iter().map(|extent| extent.length).sum()
+		} else {
+			fm_api_request.length
+		};
+
+		if length == 0 || !length.is_multiple_of(region.block_size) {
+			return Err(CxlFmError::InvalidInput(format!("length {} is not a multiple of block size {}",
+								    format_size(length),
+								    format_size(region.block_size))));
+		}
+
+		if !is_prescriptive && length > region.free {
+			return Err(CxlFmError::InvalidInput(format!("region {} has only {} free",
+								    region.region,
+								    format_size(region.free))));
+		}
+
+		let description = format!("add {} to host {} from region {} ({} policy, {} of {} free)",
+					  format_size(length), fm_api_request.host_id, region.region,
+					  selection_policy_name(fm_api_request.selection_policy),
+					  format_size(region.free), format_size(region.capacity));
+
+		Ok(vec![(plan_step(CXL_FM_API_INITIATE_DC_ADD,
+				   &device_target(request, ctx)?, description),
+			 CxlPlanAction::AddCapacity(request.clone()))])
 	}
 
 	/*
 	 * Initiate dynamic capacity add
 	 */
-	pub fn add_capacity(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn add_capacity(stream: &TcpStream, request: &CxlFmRequest,
+			    ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_DCD_ADD_CAPACITY_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		if request.is_dry_run() {
+			return send_plan(stream, request, ctx, add_plan(request, ctx), env);
+		}
+
+		let result = add_extents(request, ctx);
+
+		match &result {
+			Ok(added) => send_payload(stream, &added.to_bytes(), env),
+			Err(e) => send_error(stream, e, env),
+		}
+
+		result.is_ok()
+	}
+
+	/*
+	 * Build Initiate Dynamic Capacity Release request.
+	 * Explicit extent list selects prescriptive removal policy,
+	 * otherwise capacity is released by tag and/or length.
+	 */
+	fn release_capacity_request(request: &CxlFmRequest) -> Result<CxlInitiateDcReleaseRequest, CxlFmError> {
+		let tag = match request.get(CXL_FM_TAG_ARG) {
+			Some(tag) => parse_tag(tag)?,
+			None => [0; CXL_DCD_TAG_SIZE],
+		};
+
+		let extents = match request.get(CXL_FM_EXTENTS_ARG) {
+			Some(extents) => parse_extent_ranges(extents, tag)?,
+			None => Vec::new(),
+		};
+
+		let removal_policy = if extents.is_empty() {
+			CXL_DCD_REMOVAL_POLICY_TAG_BASED
+		} else {
+			CXL_DCD_REMOVAL_POLICY_PRESCRIPTIVE
+		};
+
+		Ok(CxlInitiateDcReleaseRequest {
+			host_id: request.get_number::<u16>(CXL_FM_HOST_ARG)?,
+			removal_policy,
+			forced: request.get_number_or::<u8>(CXL_FM_FORCED_ARG, 0)? != 0,
+			sanitize: request.get_number_or::<u8>(CXL_FM_SANITIZE_ARG, 0)? != 0,
+			length: request.get_number_or::<u64>(CXL_FM_LENGTH_ARG, 0)?,
+			tag,
+			extents,
+		})
+	}
+
+	/*
+	 * Get parts of extents that are not covered by remaining extents
+	 */
+	fn removed_parts(before: &[CxlDcExtent], after: &[CxlDcExtent]) -> Vec<CxlDcExtent> {
+		let mut removed = Vec::new();
+
+		for extent in before {
+			let mut next = extent.start_dpa;
+			let mut kept: Vec<&CxlDcExtent> = after.iter()
+				.filter(|part| part.start_dpa >= extent.start_dpa &&
+					       part.end_dpa() <= extent.end_dpa())
+				.collect();
+
+			kept.sort_by_key(|part| part.start_dpa);
+
+			for part in kept.iter().map(|part| (part.start_dpa, part.end_dpa()))
+					.chain(std::iter::once((extent.end_dpa(), extent.end_dpa()))) {
+				if part.0 > next {
+					removed.push(CxlDcExtent {
+						start_dpa: next,
+						length: part.0 - next,
+						..extent.clone()
+					});
+				}
+
+				next = std::cmp::max(next, part.1);
+			}
+		}
+
+		removed
+	}
+
+	/*
+	 * Release capacity and find extents removed from the host.
+	 * The extent bookkeeping is updated only after the device
+	 * has confirmed the release.
+	 */
+	pub fn release_extents(request: &CxlFmRequest,
+			   ctx: &CxlFmContext) -> Result<CxlDcExtentList, CxlFmError> {
+		let fm_api_request = release_capacity_request(request)?;
+		let host_id = fm_api_request.host_id;
+
+		let before = read_extent_list(request, ctx, host_id)?;
+
+		ctx.execute_for(request,
+				CXL_FM_API_INITIATE_DC_RELEASE,
+				&fm_api_request.to_bytes())?;
+
+		let mut after = read_extent_list(request, ctx, host_id)?;
+
+		ctx.update_dc_extents(CxlFmContext::device_for(request)?,
+				      host_id, after.extents.clone());
+
+		after.extents = removed_parts(&before.extents, &after.extents);
+		Ok(after)
+	}
+
+	/*
+	 * Plan dynamic capacity release. The request is checked
+	 * against the extents allocated to the host.
+	 */
+	fn release_plan(request: &CxlFmRequest,
+			ctx: &CxlFmContext) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let fm_api_request = release_capacity_request(request)?;
+		let host_id = fm_api_request.host_id;
+		let list = read_extent_list(request, ctx, host_id)?;
+
+		let length = if fm_api_request.removal_policy == CXL_DCD_REMOVAL_POLICY_PRESCRIPTIVE {
+			for extent in &fm_api_request.extents {
+				if !list.extents.iter().any(|allocated| extent.start_dpa >= allocated.start_dpa &&
+								       extent.end_dpa() <= allocated.end_dpa()) {
+					return Err(CxlFmError::InvalidInput(format!("extent {:#x}-{:#x} is not allocated to host {}",
+										    extent.start_dpa, extent.end_dpa(),
+										    host_id)));
+				}
+			}
+
+			fm_api_request.extents.iter().map(|extent| extent.length).sum()
+		} else {
+			let any_tag = fm_api_request.tag == [0; CXL_DCD_TAG_SIZE];
+			let allocated: u64 = list.extents.iter()
+				.filter(|extent| any_tag || extent.tag == fm_api_request.tag)
+				.map(|extent| extent.length)
+				.sum();
+
+			if allocated == 0 || fm_api_request.length > allocated {
+				return Err(CxlFmError::InvalidInput(format!("host {} has only {} to release",
+									    host_id, format_size(allocated))));
+			}
+
+			if fm_api_request.length == 0 {
+				allocated
+			} else {
+				fm_api_request.length
+			}
+		};
+
+		let mut description = format!("release {} of host {}", format_size(length), host_id);
+
+		if fm_api_request.tag != [0; CXL_DCD_TAG_SIZE] {
+			description.push_str(&format!(" tagged {}", format_tag(&fm_api_request.tag)));
+		}
+		if fm_api_request.forced {
+			description.push_str(", forced");
+		}
+		if fm_api_request.sanitize {
+			description.push_str(", sanitize");
+		}
+
+		Ok(vec![(plan_step(CXL_FM_API_INITIATE_DC_RELEASE,
+				   &device_target(request, ctx)?, description),
+			 CxlPlanAction::ReleaseCapacity(request.clone()))])
 	}
 
 	/*
 	 * Initiate dynamic capacity release
 	 */
-	pub fn release_capacity(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn release_capacity(stream: &TcpStream, request: &CxlFmRequest,
+				ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_DCD_RELEASE_CAPACITY_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		if request.is_dry_run() {
+			return send_plan(stream, request, ctx, release_plan(request, ctx), env);
+		}
+
+		let result = release_extents(request, ctx);
+
+		match &result {
+			Ok(released) => send_payload(stream, &released.to_bytes(), env),
+			Err(e) => send_error(stream, e, env),
+		}
+
+		result.is_ok()
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use fm_library::cxl_dcd_lib::CxlDcRegionConfig;
+		use crate::cxl_backend::CxlCciBackend;
+
+		/*
+		 * struct CxlExtentListDevice - device whose extent list
+		 *                              changes while it is read
+		 * @extents: extents of host 0
+		 * @generation: extent list generation number
+		 * @changes: number of pages followed by the list change
+		 * @shrink: the list change removes extents instead of adding
+		 */
+		struct CxlExtentListDevice {
+			extents: Vec<CxlDcExtent>,
+			generation: u32,
+			changes: u32,
+			shrink: bool,
+		}
+
+		fn extent(index: u64) -> CxlDcExtent {
+			CxlDcExtent {
+				start_dpa: index * 0x200000,
+				length: 0x200000,
+				tag: [0; CXL_DCD_TAG_SIZE],
+				shared_seq: 0,
+			}
+		}
+
+		impl CxlExtentListDevice {
+			fn new(count: u64, changes: u32, shrink: bool) -> CxlExtentListDevice {
+				CxlExtentListDevice {
+					extents: (0..count).map(extent).collect(),
+					generation: 1,
+					changes,
+					shrink,
+				}
+			}
+		}
+
+		impl CxlCciBackend for CxlExtentListDevice {
+			fn name(&self) -> String {
+				String::from("dcd0")
+			}
+
+			fn execute(&mut self, opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+				assert_eq!(opcode, CXL_FM_API_GET_DC_REGION_EXTENT_LIST);
+
+				let request = CxlGetDcExtentListRequest::from_bytes(payload)?;
+				let start = request.start_index as usize;
+
+				if start > self.extents.len() {
+					return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+				}
+
+				let end = std::cmp::min(self.extents.len(),
+							start + request.extent_count as usize);
+				let page = CxlDcExtentList {
+					host_id: request.host_id,
+					start_index: request.start_index,
+					total_extents: self.extents.len() as u32,
+					generation: self.generation,
+					extents: self.extents[start..end].to_vec(),
+				};
+
+				if self.changes != 0 {
+					match self.shrink {
+						true => self.extents.truncate(1),
+						false => self.extents.push(extent(self.extents.len() as u64)),
+					}
+					self.generation += 1;
+					self.changes -= 1;
+				}
+
+				Ok(page.to_bytes())
+			}
+		}
+
+		fn read(device: CxlExtentListDevice) -> Result<CxlDcExtentList, CxlFmError> {
+			let ctx = CxlFmContext::with_devices(vec![Box::new(device)]);
+			let request = CxlFmRequest::new(CXL_FM_DCD_GET_EXTENT_COMMAND);
+
+			read_extent_list(&request, &ctx, 0)
+		}
+
+		#[test]
+		fn extent_list_is_read_page_by_page() {
+			let count = 2 * CXL_DCD_EXTENT_LIST_PAGE_SIZE as u64 + 3;
+			let list = read(CxlExtentListDevice::new(count, 0, false)).unwrap();
+
+			assert_eq!(list.total_extents as u64, count);
+			assert_eq!(list.generation, 1);
+			assert_eq!(list.extents, (0..count).map(extent).collect::<Vec<_>>());
+		}
+
+		#[test]
+		fn empty_extent_list() {
+			let list = read(CxlExtentListDevice::new(0, 0, false)).unwrap();
+
+			assert_eq!(list.total_extents, 0);
+			assert!(list.extents.is_empty());
+		}
+
+		#[test]
+		fn generation_change_restarts_reading() {
+			let count = CXL_DCD_EXTENT_LIST_PAGE_SIZE as u64 + 1;
+			let list = read(CxlExtentListDevice::new(count, 2, false)).unwrap();
+
+			assert_eq!(list.generation, 3);
+			assert_eq!(list.total_extents as u64, count + 2);
+			assert_eq!(list.extents, (0..count + 2).map(extent).collect::<Vec<_>>());
+		}
+
+		#[test]
+		fn shrunk_list_restarts_reading() {
+			let count = 2 * CXL_DCD_EXTENT_LIST_PAGE_SIZE as u64;
+			let list = read(CxlExtentListDevice::new(count, 1, true)).unwrap();
+
+			assert_eq!(list.generation, 2);
+			assert_eq!(list.extents, vec![extent(0)]);
+		}
+
+		#[test]
+		fn endless_changes_require_retry() {
+			let count = CXL_DCD_EXTENT_LIST_PAGE_SIZE as u64 + 1;
+			let result = read(CxlExtentListDevice::new(count, u32::MAX, false));
+
+			assert!(matches!(result, Err(CxlFmError::Device(CXL_FM_API_RETRY_REQUIRED))));
+		}
+
+		/*
+		 * struct CxlLazyHostDevice - device whose host accepts
+		 *                            offered capacity after a delay
+		 * @accepted: extents accepted by host 0
+		 * @offered: extents offered to host 0
+		 * @delay: number of extent list reads before acceptance
+		 */
+		struct CxlLazyHostDevice {
+			accepted: Vec<CxlDcExtent>,
+			offered: Vec<CxlDcExtent>,
+			delay: u32,
+		}
+
+		impl CxlCciBackend for CxlLazyHostDevice {
+			fn name(&self) -> String {
+				String::from("dcd0")
+			}
+
+			fn execute(&mut self, opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+				match opcode {
+					CXL_FM_API_INITIATE_DC_ADD => {
+						let request = CxlInitiateDcAddRequest::from_bytes(payload)?;
+
+						self.offered = match request.extents.is_empty() {
+							true => vec![extent(8)],
+							false => request.extents,
+						};
+						Ok(Vec::new())
+					},
+					_ => {
+						if !self.offered.is_empty() {
+							match self.delay {
+								0 => self.accepted.append(&mut self.offered),
+								_ => self.delay -= 1,
+							}
+						}
+
+						Ok(CxlDcExtentList {
+							host_id: 0,
+							start_index: 0,
+							total_extents: self.accepted.len() as u32,
+							generation: self.accepted.len() as u32,
+							extents: self.accepted.clone(),
+						}.to_bytes())
+					},
+				}
+			}
+		}
+
+		fn lazy_host(delay: u32) -> CxlFmContext {
+			CxlFmContext::with_devices(vec![Box::new(CxlLazyHostDevice {
+				accepted: vec![extent(0)],
+				offered: Vec::new(),
+				delay,
+			})])
+		}
+
+		fn recorded(ctx: &CxlFmContext) -> Vec<CxlDcExtent> {
+			lock_shared(&ctx.dc_extents)[&(0, 0)].clone()
+		}
+
+		fn add_request(policy: u8) -> CxlFmRequest {
+			CxlFmRequest::new(CXL_FM_DCD_ADD_CAPACITY_COMMAND)
+				.arg(CXL_FM_HOST_ARG, "0")
+				.arg(CXL_FM_REGION_ARG, "0")
+				.arg(CXL_FM_POLICY_ARG, &policy.to_string())
+				.arg(CXL_FM_LENGTH_ARG, &extent(8).length.to_string())
+		}
+
+		#[test]
+		fn accepted_capacity_is_recorded() {
+			let ctx = lazy_host(0);
+			let added = add_extents(&add_request(0), &ctx).unwrap();
+
+			assert!(added.is_complete());
+			assert_eq!(added.accepted, vec![extent(8)]);
+			assert_eq!(added.pending_length, 0);
+			assert_eq!(recorded(&ctx), vec![extent(0), extent(8)]);
+		}
+
+		#[test]
+		fn pending_capacity_is_recorded_when_accepted() {
+			let ctx = lazy_host(1);
+			let request = add_request(0);
+			let added = add_extents(&request, &ctx).unwrap();
+
+			assert!(!added.is_complete());
+			assert!(added.accepted.is_empty());
+			assert_eq!(added.pending_length, extent(8).length);
+			assert_eq!(recorded(&ctx), vec![extent(0)]);
+
+			let list = observe_extent_list(&request, &ctx, 0).unwrap();
+
+			assert_eq!(list.extents, vec![extent(0), extent(8)]);
+			assert_eq!(recorded(&ctx), vec![extent(0), extent(8)]);
+		}
+
+		#[test]
+		fn prescribed_extents_not_accepted_are_pending() {
+			let request = add_request(CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE)
+				.arg(CXL_FM_EXTENTS_ARG, "0x1000000:0x200000,0x1200000:0x200000");
+			let added = add_extents(&request, &lazy_host(u32::MAX)).unwrap();
+
+			assert_eq!(added.pending.iter().map(|extent| extent.start_dpa).collect::<Vec<_>>(),
+				   vec![0x1000000, 0x1200000]);
+			assert_eq!(added.pending_length, 2 * extent(8).length);
+		}
+
+		/*
+		 * struct CxlRegionFlagsDevice - device whose region
+		 *                               flags differ between hosts
+		 * @sanitizing_host: host that has sanitize on release flag
+		 */
+		struct CxlRegionFlagsDevice {
+			sanitizing_host: u16,
+		}
+
+		impl CxlCciBackend for CxlRegionFlagsDevice {
+			fn name(&self) -> String {
+				String::from("dcd0")
+			}
+
+			fn execute(&mut self, opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+				assert_eq!(opcode, CXL_FM_API_GET_HOST_DC_REGION_CONFIG);
+
+				let request = CxlGetHostDcRegionConfigRequest::from_bytes(payload)?;
+				let flags = match request.host_id == self.sanitizing_host {
+					true => CXL_DCD_REGION_SANITIZE_ON_RELEASE,
+					false => 0,
+				};
+
+				Ok(CxlHostDcRegionConfig {
+					host_id: request.host_id,
+					num_regions: 1,
+					start_region: request.start_region,
+					regions: vec![CxlDcRegionConfig {
+						base: 0,
+						decode_length: 0x10000000,
+						length: 0x10000000,
+						block_size: 0x200000,
+						dsmad_handle: 0,
+						flags,
+					}],
+					total_extents: 0,
+					available_extents: 0,
+					total_tags: 0,
+					available_tags: 0,
+				}.to_bytes())
+			}
+		}
+
+		#[test]
+		fn region_flags_are_read_for_the_requested_host() {
+			let ctx = CxlFmContext::with_devices(vec![Box::new(CxlRegionFlagsDevice {
+				sanitizing_host: 1,
+			})]);
+			let request = |host| CxlFmRequest::new(CXL_FM_DCD_SET_CONFIG_COMMAND)
+				.arg(CXL_FM_HOST_ARG, host)
+				.arg(CXL_FM_REGION_ARG, "0");
+
+			assert_eq!(region_flags(&request("1"), &ctx, 0).unwrap(),
+				   CXL_DCD_REGION_SANITIZE_ON_RELEASE);
+			assert_eq!(region_flags(&request("0"), &ctx, 0).unwrap(), 0);
+			assert!(region_flags(&CxlFmRequest::new(CXL_FM_DCD_SET_CONFIG_COMMAND), &ctx, 0).is_err());
+		}
 	}
 }
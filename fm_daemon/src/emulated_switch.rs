@@ -0,0 +1,662 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_emulated_switch {
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_api::CxlIdentifyResponse;
+	use fm_library::cxl_fm_api::CxlCciMessage;
+	use fm_library::cxl_fm_api::CXL_FM_API_IDENTIFY;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_EVENT_RECORDS;
+	use fm_library::cxl_fm_api::CXL_FM_API_CLEAR_EVENT_RECORDS;
+	use fm_library::cxl_fm_api::CXL_FM_API_IDENTIFY_SWITCH_DEVICE;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_PHYSICAL_PORT_STATE;
+	use fm_library::cxl_fm_api::CXL_FM_API_PHYSICAL_PORT_CONTROL;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_VCS_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_BIND_VPPB;
+	use fm_library::cxl_fm_api::CXL_FM_API_UNBIND_VPPB;
+	use fm_library::cxl_fm_api::CXL_FM_API_GENERATE_AER_EVENT;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_LD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND;
+	use fm_library::cxl_fm_api::CXL_FM_API_SUCCESS;
+	use fm_library::cxl_fm_api::CXL_FM_API_INVALID_INPUT;
+	use fm_library::cxl_fm_api::CXL_FM_API_INVALID_PAYLOAD_LENGTH;
+	use fm_library::cxl_fm_api::CXL_FM_API_INTERNAL_ERROR;
+	use fm_library::cxl_fm_api::CXL_FM_API_UNSUPPORTED;
+	use fm_library::cxl_fm_api::CXL_CCI_MESSAGE_REQUEST;
+	use fm_library::cxl_fm_api::CXL_CCI_MESSAGE_RESPONCE;
+	use fm_library::cxl_fm_api::CXL_COMPONENT_SWITCH;
+	use fm_library::cxl_fm_api::CXL_LTSSM_L0;
+	use fm_library::cxl_fm_api::CXL_LTSSM_DETECT;
+	use fm_library::cxl_fm_api::CXL_LINK_SPEED_32GT;
+	use fm_library::cxl_switch_lib::CxlIdentifySwitchResponse;
+	use fm_library::cxl_switch_lib::CxlGetPhysicalPortStateRequest;
+	use fm_library::cxl_switch_lib::CxlGetPhysicalPortStateResponse;
+	use fm_library::cxl_switch_lib::CxlPhysicalPortInfo;
+	use fm_library::cxl_switch_lib::CxlGetVcsInfoRequest;
+	use fm_library::cxl_switch_lib::CxlGetVcsInfoResponse;
+	use fm_library::cxl_switch_lib::CxlVcsInfo;
+	use fm_library::cxl_switch_lib::CxlVppbInfo;
+	use fm_library::cxl_switch_lib::CxlTunnelRequest;
+	use fm_library::cxl_switch_lib::CxlTunnelResponse;
+	use fm_library::cxl_switch_lib::CxlBindVppbRequest;
+	use fm_library::cxl_switch_lib::CxlUnbindVppbRequest;
+	use fm_library::cxl_switch_lib::CxlGenerateAerRequest;
+	use fm_library::cxl_switch_lib::CXL_AER_ERROR_STATUS_BIT_MASK;
+	use fm_library::cxl_switch_lib::CXL_AER_ERROR_UNCORRECTABLE;
+	use fm_library::cxl_switch_lib::CxlPhysicalPortControlRequest;
+	use fm_library::cxl_switch_lib::bits_bitmask;
+	use fm_library::cxl_switch_lib::CXL_BIND_PORT_LD_ID;
+	use fm_library::cxl_switch_lib::CXL_PORT_STATE_DSP;
+	use fm_library::cxl_switch_lib::CXL_PORT_STATE_USP;
+	use fm_library::cxl_switch_lib::CXL_PORT_DEVICE_NONE;
+	use fm_library::cxl_switch_lib::CXL_PORT_DEVICE_TYPE3_SLD;
+	use fm_library::cxl_switch_lib::CXL_PORT_DEVICE_TYPE3_MLD;
+	use fm_library::cxl_switch_lib::CXL_VPPB_UNBOUND;
+	use fm_library::cxl_switch_lib::CXL_VPPB_BOUND_PORT;
+	use fm_library::cxl_switch_lib::CXL_VPPB_BOUND_LD;
+	use fm_library::cxl_switch_lib::CXL_PORT_CONTROL_ASSERT_PERST;
+	use fm_library::cxl_switch_lib::CXL_PORT_CONTROL_DEASSERT_PERST;
+	use fm_library::cxl_switch_lib::CXL_PORT_CONTROL_RESET_PPB;
+	use fm_library::cxl_switch_lib::CXL_PORT_LINK_PERST;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_switch_lib::CXL_SLOT_INSERT;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_switch_lib::CXL_SLOT_REMOVE;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_switch_lib::CXL_SLOT_LINK_DOWN;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_switch_lib::CXL_SLOT_LINK_UP;
+	use fm_library::cxl_ld_lib::CxlGetLdInfoResponse;
+	use fm_library::cxl_fm_event::CXL_EVENT_RECORD_PHYSICAL_SWITCH;
+	use fm_library::cxl_fm_event::CXL_SWITCH_EVENT_LINK_STATE;
+	use fm_library::cxl_fm_event::CXL_SWITCH_EVENT_SLOT_STATUS;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_WARNING;
+	use crate::cxl_backend::CxlCciBackend;
+	use crate::cxl_emulator::CxlEmulatedDevice;
+	use crate::cxl_emulator::CxlEmulatedEventLog;
+	use crate::cxl_emulator::CXL_EMULATED_DEVICE_LINK_WIDTH;
+	use crate::cxl_emulator::CXL_EMULATED_DEVICE_LINK_SPEEDS;
+	use crate::cxl_emulator::CXL_EMULATED_DEVICE_HEADS;
+	use crate::cxl_emulator::CXL_EMULATED_DEVICE_LDS_PER_HEAD;
+	use crate::cxl_emulator::CXL_EMULATED_VENDOR_ID;
+	use crate::cxl_emulator::CXL_EMULATED_MAX_MESSAGE_SIZE;
+
+	/*
+	 * Default geometry of emulated switch
+	 */
+	pub const CXL_EMULATED_SWITCH_PORTS: u8 = 6;
+	pub const CXL_EMULATED_SWITCH_VCS: u8 = 2;
+	pub const CXL_EMULATED_SWITCH_VPPBS_PER_VCS: u8 = 4;
+	pub const CXL_EMULATED_SWITCH_HDM_DECODERS: u8 = 4;
+	pub const CXL_EMULATED_SWITCH_DEVICE_ID: u16 = 0x0D90;
+	pub const CXL_EMULATED_SWITCH_MLD_LDS: u8 = 4;
+
+	/*
+	 * Supported CXL modes: 68B flit, 256B flit, MLD
+	 */
+	pub const CXL_EMULATED_SWITCH_CXL_MODES: u8 = 0x7;
+
+	/*
+	 * struct CxlEmulatedSwitch - emulated CXL switch
+	 * @name: name of the switch CCI
+	 * @serial_number: switch serial number
+	 * @ports: state of physical ports
+	 * @devices: devices attached to physical ports
+	 * @removed: devices pulled out of the slots, they can be inserted back
+	 * @vcs: state of Virtual CXL Switches
+	 * @events: event logs of the switch
+	 *
+	 * Ports 0 and 1 are USPs of the hosts, downstream ports have
+	 * SLD, MLD, nothing and MHD with dynamic capacity attached.
+	 * Attached devices are reachable through tunnelling only.
+	 */
+	pub struct CxlEmulatedSwitch {
+		pub name: String,
+		pub serial_number: u64,
+		pub ports: Vec<CxlPhysicalPortInfo>,
+		pub devices: Vec<Option<Box<dyn CxlCciBackend>>>,
+		pub removed: Vec<Option<Box<dyn CxlCciBackend>>>,
+		pub vcs: Vec<CxlVcsInfo>,
+		pub events: CxlEmulatedEventLog,
+	}
+
+	impl CxlEmulatedSwitch {
+		pub fn new(name: &str, serial_number: u64) -> CxlEmulatedSwitch {
+			let mut switch = CxlEmulatedSwitch {
+				name: String::from(name),
+				serial_number,
+				ports: Vec::new(),
+				devices: Vec::new(),
+				removed: Vec::new(),
+				vcs: Vec::new(),
+				events: CxlEmulatedEventLog::new(),
+			};
+
+			for port in 0..CXL_EMULATED_SWITCH_PORTS {
+				switch.ports.push(CxlPhysicalPortInfo {
+					port_id: port,
+					config_state: CXL_PORT_STATE_DSP,
+					connected_device_mode: 0,
+					connected_device_type: CXL_PORT_DEVICE_NONE,
+					supported_cxl_modes: CXL_EMULATED_SWITCH_CXL_MODES,
+					max_link_width: CXL_EMULATED_DEVICE_LINK_WIDTH,
+					negotiated_link_width: 0,
+					supported_link_speeds: CXL_EMULATED_DEVICE_LINK_SPEEDS,
+					max_link_speed: CXL_LINK_SPEED_32GT,
+					current_link_speed: 0,
+					ltssm_state: CXL_LTSSM_DETECT,
+					first_negotiated_lane: 0,
+					link_state_flags: 0,
+					supported_ld_count: 0,
+				});
+				switch.devices.push(None);
+				switch.removed.push(None);
+			}
+
+			for vcs in 0..CXL_EMULATED_SWITCH_VCS {
+				let port = &mut switch.ports[vcs as usize];
+
+				port.config_state = CXL_PORT_STATE_USP;
+				port.negotiated_link_width = CXL_EMULATED_DEVICE_LINK_WIDTH;
+				port.current_link_speed = CXL_LINK_SPEED_32GT;
+				port.ltssm_state = CXL_LTSSM_L0;
+
+				switch.vcs.push(CxlVcsInfo {
+					vcs_id: vcs,
+					state: 1,
+					usp_id: vcs,
+					num_vppbs: CXL_EMULATED_SWITCH_VPPBS_PER_VCS,
+					vppbs: vec![CxlVppbInfo {
+						binding_status: CXL_VPPB_UNBOUND,
+						bound_port: 0,
+						bound_ld: 0,
+					}; CXL_EMULATED_SWITCH_VPPBS_PER_VCS as usize],
+				});
+			}
+
+			let mut mhd = CxlEmulatedDevice::new(&format!("{}/mhd", name),
+							     CXL_EMULATED_DEVICE_HEADS,
+							     CXL_EMULATED_DEVICE_LDS_PER_HEAD);
+			let mut sld = CxlEmulatedDevice::sld(&format!("{}/sld", name));
+			let mut mld = CxlEmulatedDevice::mld(&format!("{}/mld", name),
+							     CXL_EMULATED_SWITCH_MLD_LDS);
+
+			sld.serial_number = serial_number + 2;
+			mld.serial_number = serial_number + 3;
+			mhd.serial_number = serial_number + 5;
+
+			switch.attach(2, Box::new(sld));
+			switch.attach(3, Box::new(mld));
+			switch.attach(5, Box::new(mhd));
+
+			/*
+			 * Host 0 gets the SLD and first LDs of MLD and MHD,
+			 * host 1 gets second LDs of MLD and MHD.
+			 */
+			switch.vcs[0].vppbs[0] = Self::vppb(CXL_VPPB_BOUND_PORT, 2, 0);
+			switch.vcs[0].vppbs[1] = Self::vppb(CXL_VPPB_BOUND_LD, 3, 0);
+			switch.vcs[0].vppbs[2] = Self::vppb(CXL_VPPB_BOUND_LD, 5, 0);
+			switch.vcs[1].vppbs[0] = Self::vppb(CXL_VPPB_BOUND_LD, 3, 1);
+			switch.vcs[1].vppbs[1] = Self::vppb(CXL_VPPB_BOUND_LD, 5, 1);
+
+			/*
+			 * Devices present at power on are not hot-added
+			 */
+			switch.events.reset();
+			switch
+		}
+
+		fn vppb(binding_status: u8, bound_port: u8, bound_ld: u8) -> CxlVppbInfo {
+			CxlVppbInfo {
+				binding_status,
+				bound_port,
+				bound_ld,
+			}
+		}
+
+		/*
+		 * Attach device to downstream port and train the link
+		 */
+		pub fn attach(&mut self, port: u8, mut device: Box<dyn CxlCciBackend>) {
+			let ld_info = device.execute(CXL_FM_API_GET_LD_INFO, &[])
+				.and_then(|data| CxlGetLdInfoResponse::from_bytes(&data));
+			let info = &mut self.ports[port as usize];
+
+			match ld_info {
+				Ok(ld_info) => {
+					info.connected_device_type = CXL_PORT_DEVICE_TYPE3_MLD;
+					info.supported_ld_count = ld_info.ld_count as u8;
+				},
+				Err(_) => {
+					info.connected_device_type = CXL_PORT_DEVICE_TYPE3_SLD;
+					info.supported_ld_count = 0;
+				},
+			}
+
+			self.devices[port as usize] = Some(device);
+			self.log_port_event(port, CXL_SWITCH_EVENT_SLOT_STATUS, CXL_EVENT_SEVERITY_INFO);
+			self.train_link(port);
+		}
+
+		/*
+		 * Pull device out of downstream port: the link goes down,
+		 * vPPBs bound to the port keep their binding
+		 */
+		pub fn detach(&mut self, port: u8) -> Option<Box<dyn CxlCciBackend>> {
+			let device = self.devices[port as usize].take()?;
+			let info = &mut self.ports[port as usize];
+
+			info.connected_device_type = CXL_PORT_DEVICE_NONE;
+			info.supported_ld_count = 0;
+
+			self.reset_link(port);
+			self.log_port_event(port, CXL_SWITCH_EVENT_SLOT_STATUS, CXL_EVENT_SEVERITY_INFO);
+			Some(device)
+		}
+
+		/*
+		 * Add Physical Switch Event Record of the port.
+		 * Informational and warning events go to the logs
+		 * with the same number as severity.
+		 */
+		fn log_port_event(&mut self, port: u8, event_type: u8, severity: u8) {
+			let info = &self.ports[port as usize];
+			let data = vec![
+				port,
+				event_type,
+				info.config_state,
+				info.connected_device_type,
+				info.ltssm_state,
+			];
+
+			self.events.add(severity, CXL_EVENT_RECORD_PHYSICAL_SWITCH, severity, data);
+		}
+
+		/*
+		 * Bring the link up if device is present and it is not in reset
+		 */
+		fn train_link(&mut self, port: u8) {
+			let info = &mut self.ports[port as usize];
+
+			if self.devices[port as usize].is_none() ||
+			   info.link_state_flags & CXL_PORT_LINK_PERST != 0 ||
+			   info.ltssm_state == CXL_LTSSM_L0 {
+				return;
+			}
+
+			info.negotiated_link_width = CXL_EMULATED_DEVICE_LINK_WIDTH;
+			info.current_link_speed = CXL_LINK_SPEED_32GT;
+			info.ltssm_state = CXL_LTSSM_L0;
+
+			self.log_port_event(port, CXL_SWITCH_EVENT_LINK_STATE, CXL_EVENT_SEVERITY_INFO);
+		}
+
+		/*
+		 * Bring the link down
+		 */
+		fn reset_link(&mut self, port: u8) {
+			let info = &mut self.ports[port as usize];
+
+			if info.ltssm_state != CXL_LTSSM_L0 {
+				return;
+			}
+
+			info.negotiated_link_width = 0;
+			info.current_link_speed = 0;
+			info.ltssm_state = CXL_LTSSM_DETECT;
+
+			self.log_port_event(port, CXL_SWITCH_EVENT_LINK_STATE, CXL_EVENT_SEVERITY_WARNING);
+		}
+
+		fn active_ports(&self) -> Vec<u8> {
+			self.ports.iter()
+				.filter(|port| port.ltssm_state == CXL_LTSSM_L0)
+				.map(|port| port.port_id)
+				.collect()
+		}
+
+		/*
+		 * Identify
+		 */
+		fn identify(&self) -> Result<Vec<u8>, CxlFmError> {
+			let responce = CxlIdentifyResponse {
+				vendor_id: CXL_EMULATED_VENDOR_ID,
+				device_id: CXL_EMULATED_SWITCH_DEVICE_ID,
+				subsystem_vendor_id: CXL_EMULATED_VENDOR_ID,
+				subsystem_id: 0,
+				serial_number: self.serial_number,
+				max_message_size: CXL_EMULATED_MAX_MESSAGE_SIZE,
+				component_type: CXL_COMPONENT_SWITCH,
+			};
+
+			Ok(responce.to_bytes())
+		}
+
+		/*
+		 * Identify Switch Device
+		 */
+		fn identify_switch(&self) -> Result<Vec<u8>, CxlFmError> {
+			let vcs: Vec<u8> = self.vcs.iter().map(|vcs| vcs.vcs_id).collect();
+			let bound_vppbs = self.vcs.iter()
+				.flat_map(|vcs| vcs.vppbs.iter())
+				.filter(|vppb| vppb.binding_status != CXL_VPPB_UNBOUND)
+				.count();
+
+			let responce = CxlIdentifySwitchResponse {
+				ingress_port: 0,
+				num_ports: self.ports.len() as u8,
+				num_vcs: self.vcs.len() as u8,
+				active_ports: bits_bitmask(&self.active_ports()),
+				active_vcs: bits_bitmask(&vcs),
+				total_vppbs: self.vcs.iter().map(|vcs| vcs.num_vppbs as u16).sum(),
+				bound_vppbs: bound_vppbs as u16,
+				num_hdm_decoders: CXL_EMULATED_SWITCH_HDM_DECODERS,
+			};
+
+			Ok(responce.to_bytes())
+		}
+
+		/*
+		 * Get Physical Port State
+		 */
+		fn get_port_state(&self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGetPhysicalPortStateRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let mut ports = Vec::new();
+			for port in request.ports {
+				match self.ports.get(port as usize) {
+					Some(info) => ports.push(info.clone()),
+					None => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+				}
+			}
+
+			Ok(CxlGetPhysicalPortStateResponse { ports }.to_bytes())
+		}
+
+		/*
+		 * Get Virtual CXL Switch Info
+		 */
+		fn get_vcs_info(&self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGetVcsInfoRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let mut vcs = Vec::new();
+			for id in request.vcs_ids {
+				let info = match self.vcs.get(id as usize) {
+					Some(info) => info,
+					None => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+				};
+
+				let start = std::cmp::min(request.start_vppb as usize, info.vppbs.len());
+				let end = std::cmp::min(info.vppbs.len(),
+							start + request.vppb_list_limit as usize);
+
+				vcs.push(CxlVcsInfo {
+					vppbs: info.vppbs[start..end].to_vec(),
+					..info.clone()
+				});
+			}
+
+			Ok(CxlGetVcsInfoResponse { vcs }.to_bytes())
+		}
+
+		/*
+		 * Check that physical port (or its LD) is bound to any vPPB
+		 */
+		fn is_bound(&self, port: u8, ld: Option<u8>) -> bool {
+			self.vcs.iter()
+				.flat_map(|vcs| vcs.vppbs.iter())
+				.any(|vppb| match vppb.binding_status {
+					CXL_VPPB_BOUND_PORT => vppb.bound_port == port,
+					CXL_VPPB_BOUND_LD => vppb.bound_port == port &&
+							     ld.is_none_or(|ld| ld == vppb.bound_ld),
+					_ => false,
+				})
+		}
+
+		/*
+		 * Bind vPPB
+		 *
+		 * Physical port is bound as a whole or LD by LD,
+		 * every target can be bound to one vPPB only.
+		 */
+		fn bind_vppb(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlBindVppbRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+			let invalid = Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+
+			let port = match self.ports.get(request.port_id as usize) {
+				Some(port) if port.config_state == CXL_PORT_STATE_DSP &&
+					      self.devices[port.port_id as usize].is_some() => port.clone(),
+				_ => return invalid,
+			};
+
+			let vppb = match request.ld_id {
+				CXL_BIND_PORT_LD_ID => {
+					if self.is_bound(port.port_id, None) {
+						return invalid;
+					}
+
+					Self::vppb(CXL_VPPB_BOUND_PORT, port.port_id, 0)
+				},
+				ld => {
+					if port.connected_device_type != CXL_PORT_DEVICE_TYPE3_MLD ||
+					   ld >= port.supported_ld_count as u16 ||
+					   self.is_bound(port.port_id, Some(ld as u8)) {
+						return invalid;
+					}
+
+					Self::vppb(CXL_VPPB_BOUND_LD, port.port_id, ld as u8)
+				},
+			};
+
+			match self.vcs.get_mut(request.vcs_id as usize)
+				.and_then(|vcs| vcs.vppbs.get_mut(request.vppb_id as usize)) {
+				Some(entry) if entry.binding_status == CXL_VPPB_UNBOUND => {
+					*entry = vppb;
+					Ok(Vec::new())
+				},
+				_ => invalid,
+			}
+		}
+
+		/*
+		 * Unbind vPPB
+		 */
+		fn unbind_vppb(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlUnbindVppbRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			match self.vcs.get_mut(request.vcs_id as usize)
+				.and_then(|vcs| vcs.vppbs.get_mut(request.vppb_id as usize)) {
+				Some(entry) if entry.binding_status != CXL_VPPB_UNBOUND => {
+					*entry = Self::vppb(CXL_VPPB_UNBOUND, 0, 0);
+					Ok(Vec::new())
+				},
+				_ => Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			}
+		}
+
+		/*
+		 * Generate AER Event
+		 *
+		 * Event is reported by enabled VCS toward its host,
+		 * hosts are not emulated.
+		 */
+		fn generate_aer(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGenerateAerRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+			let reserved = !(CXL_AER_ERROR_STATUS_BIT_MASK | CXL_AER_ERROR_UNCORRECTABLE);
+
+			match self.vcs.get(request.vcs_id as usize) {
+				Some(vcs) if vcs.state != 0 &&
+					     (request.vppb_id as usize) < vcs.vppbs.len() &&
+					     request.aer_error & reserved == 0 => Ok(Vec::new()),
+				_ => Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			}
+		}
+
+		/*
+		 * Physical Port Control
+		 *
+		 * Asserted PERST# keeps the link down,
+		 * PPB reset retrains the link.
+		 */
+		fn port_control(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlPhysicalPortControlRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+			let port = request.ppb_id;
+
+			match self.ports.get(port as usize) {
+				Some(info) if info.config_state == CXL_PORT_STATE_DSP => {},
+				_ => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			}
+
+			match request.operation {
+				CXL_PORT_CONTROL_ASSERT_PERST => {
+					self.ports[port as usize].link_state_flags |= CXL_PORT_LINK_PERST;
+					self.reset_link(port);
+				},
+				CXL_PORT_CONTROL_DEASSERT_PERST => {
+					self.ports[port as usize].link_state_flags &= !CXL_PORT_LINK_PERST;
+					self.train_link(port);
+				},
+				CXL_PORT_CONTROL_RESET_PPB => {
+					self.reset_link(port);
+					self.train_link(port);
+				},
+				_ => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			}
+
+			Ok(Vec::new())
+		}
+
+		/*
+		 * Tunnel Management Command
+		 *
+		 * Device return code is delivered in the responce message,
+		 * the tunnel command itself succeeds.
+		 */
+		fn tunnel(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlTunnelRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+			let message = CxlCciMessage::from_bytes(&request.message)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			if message.category != CXL_CCI_MESSAGE_REQUEST {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let link_up = self.ports.get(request.port_or_ld as usize)
+				.is_some_and(|port| port.ltssm_state == CXL_LTSSM_L0);
+			let device = match self.devices.get_mut(request.port_or_ld as usize) {
+				Some(Some(device)) if link_up => device,
+				_ => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			};
+
+			let (return_code, payload) = match device.execute(message.opcode, &message.payload) {
+				Ok(payload) => (CXL_FM_API_SUCCESS, payload),
+				Err(CxlFmError::Device(code)) => (code, Vec::new()),
+				Err(_) => (CXL_FM_API_INTERNAL_ERROR, Vec::new()),
+			};
+
+			let responce = CxlCciMessage {
+				category: CXL_CCI_MESSAGE_RESPONCE,
+				tag: message.tag,
+				opcode: message.opcode,
+				return_code,
+				payload,
+			};
+
+			Ok(CxlTunnelResponse { message: responce.to_bytes() }.to_bytes())
+		}
+	}
+
+	impl CxlCciBackend for CxlEmulatedSwitch {
+		fn name(&self) -> String {
+			self.name.clone()
+		}
+
+		/*
+		 * Only the device that was pulled out of the slot
+		 * can be inserted back. Link failure leaves the device
+		 * in the slot, the link recovers by retraining.
+		 */
+		#[cfg(feature = "emulation")]
+		fn hot_plug(&mut self, port: u8, operation: u8) -> Result<(), CxlFmError> {
+			let invalid = Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+
+			match self.ports.get(port as usize) {
+				Some(info) if info.config_state == CXL_PORT_STATE_DSP => {},
+				_ => return invalid,
+			}
+
+			match operation {
+				CXL_SLOT_INSERT => match self.removed[port as usize].take() {
+					Some(device) if self.devices[port as usize].is_none() => {
+						self.attach(port, device);
+						Ok(())
+					},
+					device => {
+						self.removed[port as usize] = device;
+						invalid
+					},
+				},
+				CXL_SLOT_REMOVE => match self.detach(port) {
+					Some(device) => {
+						self.removed[port as usize] = Some(device);
+						Ok(())
+					},
+					None => invalid,
+				},
+				CXL_SLOT_LINK_DOWN if self.ports[port as usize].ltssm_state == CXL_LTSSM_L0 => {
+					self.reset_link(port);
+					Ok(())
+				},
+				CXL_SLOT_LINK_UP if self.devices[port as usize].is_some() &&
+						    self.ports[port as usize].ltssm_state != CXL_LTSSM_L0 => {
+					self.train_link(port);
+					Ok(())
+				},
+				_ => invalid,
+			}
+		}
+
+		fn execute(&mut self, opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			match opcode {
+				CXL_FM_API_IDENTIFY => self.identify(),
+				CXL_FM_API_IDENTIFY_SWITCH_DEVICE => self.identify_switch(),
+				CXL_FM_API_GET_EVENT_RECORDS => self.events.get_records(payload),
+				CXL_FM_API_CLEAR_EVENT_RECORDS => self.events.clear_records(payload),
+				CXL_FM_API_GET_PHYSICAL_PORT_STATE => self.get_port_state(payload),
+				CXL_FM_API_PHYSICAL_PORT_CONTROL => self.port_control(payload),
+				CXL_FM_API_GET_VCS_INFO => self.get_vcs_info(payload),
+				CXL_FM_API_BIND_VPPB => self.bind_vppb(payload),
+				CXL_FM_API_UNBIND_VPPB => self.unbind_vppb(payload),
+				CXL_FM_API_GENERATE_AER_EVENT => self.generate_aer(payload),
+				CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND => self.tunnel(payload),
+				_ => Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED)),
+			}
+		}
+	}
+}
@@ -0,0 +1,1193 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_emulator {
+	use std::collections::VecDeque;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_api::CxlIdentifyResponse;
+	use fm_library::cxl_fm_api::CXL_FM_API_IDENTIFY;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_EVENT_RECORDS;
+	use fm_library::cxl_fm_api::CXL_FM_API_CLEAR_EVENT_RECORDS;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_LD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_LD_ALLOCATIONS;
+	use fm_library::cxl_fm_api::CXL_FM_API_SET_LD_ALLOCATIONS;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_QOS_CONTROL;
+	use fm_library::cxl_fm_api::CXL_FM_API_SET_QOS_CONTROL;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_MHD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_MHD_HEAD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_DCD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_HOST_DC_REGION_CONFIG;
+	use fm_library::cxl_fm_api::CXL_FM_API_SET_DC_REGION_CONFIG;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_DC_REGION_EXTENT_LIST;
+	use fm_library::cxl_fm_api::CXL_FM_API_INITIATE_DC_ADD;
+	use fm_library::cxl_fm_api::CXL_FM_API_INITIATE_DC_RELEASE;
+	use fm_library::cxl_fm_api::CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND;
+	use fm_library::cxl_fm_api::CXL_MEMDEV_SECURE_ERASE;
+	use fm_library::cxl_fm_api::CXL_FM_API_SUCCESS;
+	use fm_library::cxl_fm_api::CxlCciMessage;
+	use fm_library::cxl_fm_api::CXL_CCI_MESSAGE_REQUEST;
+	use fm_library::cxl_fm_api::CXL_CCI_MESSAGE_RESPONCE;
+	use fm_library::cxl_fm_api::CXL_FM_API_BUSY;
+	use fm_library::cxl_fm_api::CXL_FM_API_RESOURCES_EXHAUSTED;
+	use fm_library::cxl_fm_api::CXL_FM_API_INVALID_EXTENT_LIST;
+	use fm_library::cxl_fm_api::CXL_LTSSM_L0;
+	use fm_library::cxl_fm_api::CXL_LINK_SPEED_16GT;
+	use fm_library::cxl_fm_api::CXL_LINK_SPEED_32GT;
+	use fm_library::cxl_fm_api::CXL_FM_API_INVALID_INPUT;
+	use fm_library::cxl_fm_api::CXL_FM_API_INVALID_PAYLOAD_LENGTH;
+	use fm_library::cxl_fm_api::CXL_FM_API_UNSUPPORTED;
+	use fm_library::cxl_fm_api::CXL_COMPONENT_TYPE3_DEVICE;
+	use fm_library::cxl_switch_lib::CxlTunnelRequest;
+	use fm_library::cxl_switch_lib::CxlTunnelResponse;
+	use fm_library::cxl_ld_lib::CxlGetLdInfoResponse;
+	use fm_library::cxl_ld_lib::CxlLdAllocation;
+	use fm_library::cxl_ld_lib::CxlGetLdAllocationsRequest;
+	use fm_library::cxl_ld_lib::CxlGetLdAllocationsResponse;
+	use fm_library::cxl_ld_lib::CxlSetLdAllocationsRequest;
+	use fm_library::cxl_ld_lib::CxlQosControl;
+	use fm_library::cxl_ld_lib::granularity_size;
+	use fm_library::cxl_ld_lib::CXL_LD_GRANULARITY_256MB;
+	use fm_library::cxl_mh_device_lib::CxlGetMhdInfoRequest;
+	use fm_library::cxl_mh_device_lib::CxlGetMhdInfoResponse;
+	use fm_library::cxl_mh_device_lib::CxlGetHeadInfoRequest;
+	use fm_library::cxl_mh_device_lib::CxlGetHeadInfoResponse;
+	use fm_library::cxl_mh_device_lib::CxlHeadInfo;
+	use fm_library::cxl_dcd_lib::CxlDcdInfo;
+	use fm_library::cxl_dcd_lib::CxlDcRegionConfig;
+	use fm_library::cxl_dcd_lib::CxlGetHostDcRegionConfigRequest;
+	use fm_library::cxl_dcd_lib::CxlHostDcRegionConfig;
+	use fm_library::cxl_dcd_lib::CxlSetDcRegionConfigRequest;
+	use fm_library::cxl_dcd_lib::CxlGetDcExtentListRequest;
+	use fm_library::cxl_dcd_lib::CxlDcExtentList;
+	use fm_library::cxl_dcd_lib::CxlDcExtent;
+	use fm_library::cxl_dcd_lib::CxlInitiateDcAddRequest;
+	use fm_library::cxl_dcd_lib::CxlInitiateDcReleaseRequest;
+	use fm_library::cxl_dcd_lib::CXL_DCD_REMOVAL_POLICY_TAG_BASED;
+	use fm_library::cxl_dcd_lib::CXL_DCD_REMOVAL_POLICY_PRESCRIPTIVE;
+	use fm_library::cxl_dcd_lib::CXL_DCD_TAG_SIZE;
+	use fm_library::cxl_dcd_lib::CXL_DCD_SELECTION_POLICY_FREE;
+	use fm_library::cxl_dcd_lib::CXL_DCD_SELECTION_POLICY_CONTIGUOUS;
+	use fm_library::cxl_dcd_lib::CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE;
+	use fm_library::cxl_dcd_lib::CXL_DCD_SELECTION_POLICY_SHARED_ACCESS;
+	use fm_library::cxl_dcd_lib::CXL_DCD_MAX_REGIONS;
+	use fm_library::cxl_dcd_lib::CXL_DCD_REGION_SANITIZE_ON_RELEASE;
+	use fm_library::cxl_dcd_lib::parse_tag;
+	use fm_library::cxl_fm_api::CxlPayloadWriter;
+	use fm_library::cxl_fm_event::CxlEventRecord;
+	use fm_library::cxl_fm_event::CxlGetEventRecordsRequest;
+	use fm_library::cxl_fm_event::CxlGetEventRecordsResponse;
+	use fm_library::cxl_fm_event::CxlClearEventRecordsRequest;
+	use fm_library::cxl_fm_event::event_timestamp;
+	use fm_library::cxl_fm_event::CXL_EVENT_LOGS;
+	use fm_library::cxl_fm_event::CXL_EVENT_LOG_DYNAMIC_CAPACITY;
+	use fm_library::cxl_fm_event::CXL_EVENT_RECORDS_OVERFLOW;
+	use fm_library::cxl_fm_event::CXL_EVENT_RECORDS_MORE;
+	use fm_library::cxl_fm_event::CXL_EVENT_CLEAR_ALL;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_EVENT_RECORD_DYNAMIC_CAPACITY;
+	use fm_library::cxl_fm_event::CXL_DC_EVENT_ADD_CAPACITY;
+	use fm_library::cxl_fm_event::CXL_DC_EVENT_RELEASE_CAPACITY;
+	use fm_library::cxl_fm_event::CXL_DC_EVENT_FORCED_RELEASE;
+	use crate::cxl_backend::CxlCciBackend;
+
+	/*
+	 * Default geometry of emulated device
+	 */
+	pub const CXL_EMULATED_DEVICE_HEADS: u8 = 4;
+	pub const CXL_EMULATED_DEVICE_LDS_PER_HEAD: u8 = 2;
+	pub const CXL_EMULATED_DEVICE_LINK_WIDTH: u8 = 16;
+	pub const CXL_EMULATED_DEVICE_CAPACITY: u64 = 16 << 30;
+	pub const CXL_EMULATED_LD_GRANULARITY: u8 = CXL_LD_GRANULARITY_256MB;
+
+	/*
+	 * PCIe identity of emulated components
+	 */
+	pub const CXL_EMULATED_VENDOR_ID: u16 = 0x1AF4;
+	pub const CXL_EMULATED_TYPE3_DEVICE_ID: u16 = 0x0D93;
+	pub const CXL_EMULATED_MAX_MESSAGE_SIZE: u8 = 12;
+
+	/*
+	 * Supported link speeds vector: 2.5GT/s - 32GT/s
+	 */
+	pub const CXL_EMULATED_DEVICE_LINK_SPEEDS: u8 = 0x1F;
+
+	/*
+	 * Dynamic capacity of emulated device
+	 */
+	pub const CXL_EMULATED_DCD_REGIONS: u8 = 2;
+	pub const CXL_EMULATED_DCD_REGION_LENGTH: u64 = 8 << 30;
+	pub const CXL_EMULATED_DCD_DSMAD_HANDLE_BASE: u32 = 0x10;
+	pub const CXL_EMULATED_DCD_MAX_EXTENTS: u32 = 256;
+	pub const CXL_EMULATED_DCD_MAX_TAGS: u32 = 64;
+
+	/*
+	 * Supported block sizes: 2MiB, 64MiB, 256MiB, 1GiB
+	 */
+	pub const CXL_EMULATED_DCD_BLOCK_SIZE_MASK: u64 = (1 << 21) | (1 << 26) | (1 << 28) | (1 << 30);
+	pub const CXL_EMULATED_DCD_DEFAULT_BLOCK_SIZE: u64 = 1 << 21;
+
+	/*
+	 * Supported policies: all selection and removal policies
+	 */
+	pub const CXL_EMULATED_DCD_SELECTION_POLICIES: u16 = 0xF;
+	pub const CXL_EMULATED_DCD_REMOVAL_POLICIES: u16 = 0x3;
+
+	/*
+	 * Event logs of emulated components: records kept by every log
+	 * and records returned by one Get Event Records command
+	 */
+	pub const CXL_EMULATED_EVENT_LOG_CAPACITY: usize = 32;
+	pub const CXL_EMULATED_EVENT_RECORDS_LIMIT: usize = 8;
+
+	/*
+	 * struct CxlEmulatedEventLog - event logs of emulated component
+	 * @logs: records of every event log from the oldest to the newest
+	 * @overflows: lost records count, first and last lost record time
+	 * @next_handle: handle of the next record
+	 *
+	 * The newest record is lost when the log is full,
+	 * as it happens with the device logs.
+	 */
+	pub struct CxlEmulatedEventLog {
+		pub logs: Vec<VecDeque<CxlEventRecord>>,
+		pub overflows: Vec<(u16, u64, u64)>,
+		pub next_handle: u16,
+	}
+
+	impl Default for CxlEmulatedEventLog {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	impl CxlEmulatedEventLog {
+		pub fn new() -> CxlEmulatedEventLog {
+			CxlEmulatedEventLog {
+				logs: vec![VecDeque::new(); CXL_EVENT_LOGS.len()],
+				overflows: vec![(0, 0, 0); CXL_EVENT_LOGS.len()],
+				next_handle: 1,
+			}
+		}
+
+		/*
+		 * Add record into the event log
+		 */
+		pub fn add(&mut self, event_log: u8, uuid: &str, severity: u8, data: Vec<u8>) {
+			let timestamp = event_timestamp();
+			let index = event_log as usize;
+
+			if self.logs[index].len() >= CXL_EMULATED_EVENT_LOG_CAPACITY {
+				let overflow = &mut self.overflows[index];
+
+				if overflow.0 == 0 {
+					overflow.1 = timestamp;
+				}
+
+				overflow.0 = overflow.0.saturating_add(1);
+				overflow.2 = timestamp;
+				return;
+			}
+
+			self.logs[index].push_back(CxlEventRecord {
+				uuid: parse_tag(uuid).unwrap_or_default(),
+				flags: severity,
+				handle: self.next_handle,
+				related_handle: 0,
+				timestamp,
+				maintenance_class: 0,
+				data,
+			});
+
+			/*
+			 * Zero handle is not valid
+			 */
+			self.next_handle = self.next_handle.checked_add(1).unwrap_or(1);
+		}
+
+		/*
+		 * Drop all records
+		 */
+		pub fn reset(&mut self) {
+			*self = Self::new();
+		}
+
+		/*
+		 * Get Event Records
+		 */
+		pub fn get_records(&self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGetEventRecordsRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+			let index = request.event_log as usize;
+
+			let log = match self.logs.get(index) {
+				Some(log) => log,
+				None => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			};
+
+			let (overflow_count, first_overflow, last_overflow) = self.overflows[index];
+			let mut flags = 0;
+
+			if overflow_count != 0 {
+				flags |= CXL_EVENT_RECORDS_OVERFLOW;
+			}
+
+			if log.len() > CXL_EMULATED_EVENT_RECORDS_LIMIT {
+				flags |= CXL_EVENT_RECORDS_MORE;
+			}
+
+			let responce = CxlGetEventRecordsResponse {
+				flags,
+				overflow_count,
+				first_overflow,
+				last_overflow,
+				records: log.iter()
+					.take(CXL_EMULATED_EVENT_RECORDS_LIMIT)
+					.cloned()
+					.collect(),
+			};
+
+			Ok(responce.to_bytes())
+		}
+
+		/*
+		 * Clear Event Records
+		 *
+		 * Records are cleared in the order they were returned,
+		 * so handles must start from the oldest record.
+		 */
+		pub fn clear_records(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlClearEventRecordsRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+			let index = request.event_log as usize;
+
+			let log = match self.logs.get_mut(index) {
+				Some(log) => log,
+				None => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			};
+
+			if request.flags & CXL_EVENT_CLEAR_ALL != 0 {
+				log.clear();
+			} else {
+				let is_ordered = request.handles.iter()
+					.zip(log.iter())
+					.all(|(handle, record)| *handle == record.handle);
+
+				if request.handles.len() > log.len() || !is_ordered {
+					return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+				}
+
+				log.drain(..request.handles.len());
+			}
+
+			self.overflows[index] = (0, 0, 0);
+			Ok(Vec::new())
+		}
+	}
+
+	/*
+	 * struct CxlEmulatedDevice - emulated multi-headed memory device
+	 * @name: name of the device CCI
+	 * @serial_number: device serial number
+	 * @locality: locality domain of the device
+	 * @capacity: static memory capacity
+	 * @num_heads: number of heads
+	 * @ld_map: head ID for every LD
+	 * @heads: link state of every head
+	 * @regions: configuration of DC regions
+	 * @extents: DC extents of every host
+	 * @generations: extent list generation number of every host
+	 * @allocations: memory allocation of every LD
+	 * @qos: QoS control settings
+	 * @events: event logs of the device
+	 *
+	 * The emulated device is used as CCI backend while the daemon
+	 * has no access to real hardware. Device with one head is not
+	 * multi-headed, device with one LD is SLD, device without regions
+	 * has no dynamic capacity.
+	 */
+	pub struct CxlEmulatedDevice {
+		pub name: String,
+		pub serial_number: u64,
+		pub locality: u16,
+		pub capacity: u64,
+		pub num_heads: u8,
+		pub ld_map: Vec<u8>,
+		pub heads: Vec<CxlHeadInfo>,
+		pub regions: Vec<CxlDcRegionConfig>,
+		pub extents: Vec<Vec<CxlDcExtent>>,
+		pub generations: Vec<u32>,
+		pub allocations: Vec<CxlLdAllocation>,
+		pub qos: CxlQosControl,
+		pub events: CxlEmulatedEventLog,
+	}
+
+	impl CxlEmulatedDevice {
+		pub fn new(name: &str, num_heads: u8, lds_per_head: u8) -> CxlEmulatedDevice {
+			let mut ld_map = Vec::new();
+			let mut heads = Vec::new();
+
+			for head in 0..num_heads {
+				for _ in 0..lds_per_head {
+					ld_map.push(head);
+				}
+
+				heads.push(CxlHeadInfo {
+					port_number: head,
+					max_link_width: CXL_EMULATED_DEVICE_LINK_WIDTH,
+					negotiated_link_width: CXL_EMULATED_DEVICE_LINK_WIDTH,
+					supported_link_speeds: CXL_EMULATED_DEVICE_LINK_SPEEDS,
+					max_link_speed: CXL_LINK_SPEED_32GT,
+					current_link_speed: CXL_LINK_SPEED_32GT,
+					ltssm_state: CXL_LTSSM_L0,
+					first_negotiated_lane: 0,
+				});
+			}
+
+			/*
+			 * The last head is trained below its capabilities
+			 * to make degraded links visible.
+			 */
+			if let Some(head) = heads.last_mut() {
+				head.negotiated_link_width = CXL_EMULATED_DEVICE_LINK_WIDTH / 2;
+				head.current_link_speed = CXL_LINK_SPEED_16GT;
+			}
+
+			let regions = (0..CXL_EMULATED_DCD_REGIONS)
+				.map(|index| CxlDcRegionConfig {
+					base: index as u64 * CXL_EMULATED_DCD_REGION_LENGTH,
+					decode_length: CXL_EMULATED_DCD_REGION_LENGTH,
+					length: CXL_EMULATED_DCD_REGION_LENGTH,
+					block_size: CXL_EMULATED_DCD_DEFAULT_BLOCK_SIZE,
+					dsmad_handle: CXL_EMULATED_DCD_DSMAD_HANDLE_BASE + index as u32,
+					flags: 0,
+				})
+				.collect();
+
+			let ld_map_len = ld_map.len();
+
+			CxlEmulatedDevice {
+				name: String::from(name),
+				serial_number: 0,
+				locality: 0,
+				capacity: CXL_EMULATED_DEVICE_CAPACITY,
+				num_heads,
+				ld_map,
+				heads,
+				regions,
+				extents: vec![Vec::new(); num_heads as usize],
+				generations: vec![0; num_heads as usize],
+				allocations: vec![CxlLdAllocation {
+					range1: CXL_EMULATED_DEVICE_CAPACITY /
+						granularity_size(CXL_EMULATED_LD_GRANULARITY) /
+						ld_map_len as u64,
+					range2: 0,
+				}; ld_map_len],
+				qos: CxlQosControl {
+					telemetry_control: 0,
+					egress_moderate_percentage: 10,
+					egress_severe_percentage: 25,
+					backpressure_sample_interval: 8,
+					req_cmp_basis: 0,
+					completion_collection_interval: 64,
+				},
+				events: CxlEmulatedEventLog::new(),
+			}
+		}
+
+		/*
+		 * Create single logical device without dynamic capacity
+		 */
+		pub fn sld(name: &str) -> CxlEmulatedDevice {
+			Self::mld(name, 1)
+		}
+
+		/*
+		 * Create multi-logical device without dynamic capacity
+		 */
+		pub fn mld(name: &str, ld_count: u8) -> CxlEmulatedDevice {
+			let mut device = Self::new(name, 1, ld_count);
+
+			device.regions.clear();
+			device
+		}
+
+		/*
+		 * Identify
+		 */
+		fn identify(&self) -> Result<Vec<u8>, CxlFmError> {
+			let responce = CxlIdentifyResponse {
+				vendor_id: CXL_EMULATED_VENDOR_ID,
+				device_id: CXL_EMULATED_TYPE3_DEVICE_ID,
+				subsystem_vendor_id: CXL_EMULATED_VENDOR_ID,
+				subsystem_id: 0,
+				serial_number: self.serial_number,
+				max_message_size: CXL_EMULATED_MAX_MESSAGE_SIZE,
+				component_type: CXL_COMPONENT_TYPE3_DEVICE,
+			};
+
+			Ok(responce.to_bytes())
+		}
+
+		/*
+		 * Get LD Info
+		 */
+		fn get_ld_info(&self) -> Result<Vec<u8>, CxlFmError> {
+			let responce = CxlGetLdInfoResponse {
+				memory_size: self.capacity,
+				ld_count: self.ld_map.len() as u16,
+				qos_telemetry: 0,
+			};
+
+			Ok(responce.to_bytes())
+		}
+
+		/*
+		 * Get LD Allocations
+		 */
+		fn get_ld_allocations(&self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGetLdAllocationsRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let start = request.start_ld as usize;
+			if start >= self.allocations.len() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let end = std::cmp::min(self.allocations.len(),
+						start + request.ld_list_limit as usize);
+
+			let responce = CxlGetLdAllocationsResponse {
+				num_lds: self.allocations.len() as u8,
+				granularity: CXL_EMULATED_LD_GRANULARITY,
+				start_ld: request.start_ld,
+				allocations: self.allocations[start..end].to_vec(),
+			};
+
+			Ok(responce.to_bytes())
+		}
+
+		/*
+		 * Set LD Allocations
+		 *
+		 * Total allocation of all LDs cannot exceed capacity of the device.
+		 */
+		fn set_ld_allocations(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlSetLdAllocationsRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let start = request.start_ld as usize;
+			if request.allocations.is_empty() ||
+			   start + request.allocations.len() > self.allocations.len() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let mut allocations = self.allocations.clone();
+			allocations[start..start + request.allocations.len()]
+				.copy_from_slice(&request.allocations);
+
+			let total = allocations.iter()
+				.try_fold(0u64, |total, ld| total.checked_add(ld.range1)?.checked_add(ld.range2));
+			let limit = self.capacity / granularity_size(CXL_EMULATED_LD_GRANULARITY);
+
+			if total.is_none_or(|total| total > limit) {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			self.allocations = allocations;
+			Ok(request.to_bytes())
+		}
+
+		/*
+		 * Get QoS Control
+		 */
+		fn get_qos_control(&self) -> Result<Vec<u8>, CxlFmError> {
+			Ok(self.qos.to_bytes())
+		}
+
+		/*
+		 * Set QoS Control
+		 */
+		fn set_qos_control(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let qos = CxlQosControl::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			if qos.egress_moderate_percentage > 100 ||
+			   qos.egress_severe_percentage > 100 ||
+			   qos.egress_moderate_percentage > qos.egress_severe_percentage {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			self.qos = qos;
+			Ok(self.qos.to_bytes())
+		}
+
+		/*
+		 * Tunnel Management Command addressed to LD of MLD
+		 *
+		 * Only Secure Erase can be executed in LD context.
+		 */
+		fn ld_tunnel(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlTunnelRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+			let message = CxlCciMessage::from_bytes(&request.message)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			if message.category != CXL_CCI_MESSAGE_REQUEST ||
+			   request.port_or_ld as usize >= self.ld_map.len() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let return_code = match message.opcode {
+				CXL_MEMDEV_SECURE_ERASE => CXL_FM_API_SUCCESS,
+				_ => CXL_FM_API_UNSUPPORTED,
+			};
+
+			let responce = CxlCciMessage {
+				category: CXL_CCI_MESSAGE_RESPONCE,
+				tag: message.tag,
+				opcode: message.opcode,
+				return_code,
+				payload: Vec::new(),
+			};
+
+			Ok(CxlTunnelResponse { message: responce.to_bytes() }.to_bytes())
+		}
+
+		/*
+		 * Get Multi-Headed Info
+		 */
+		fn get_mhd_info(&self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGetMhdInfoRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let start = request.start_ld as usize;
+			if start >= self.ld_map.len() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let end = std::cmp::min(self.ld_map.len(),
+						start + request.ld_map_list_limit as usize);
+
+			let responce = CxlGetMhdInfoResponse {
+				num_lds: self.ld_map.len() as u8,
+				num_heads: self.num_heads,
+				start_ld: request.start_ld,
+				ld_map: self.ld_map[start..end].to_vec(),
+			};
+
+			Ok(responce.to_bytes())
+		}
+
+		/*
+		 * Get Head Info
+		 */
+		fn get_head_info(&self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGetHeadInfoRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let start = request.start_head as usize;
+			if start >= self.heads.len() || request.num_heads == 0 {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let end = std::cmp::min(self.heads.len(),
+						start + request.num_heads as usize);
+
+			let responce = CxlGetHeadInfoResponse {
+				start_head: request.start_head,
+				heads: self.heads[start..end].to_vec(),
+			};
+
+			Ok(responce.to_bytes())
+		}
+
+		/*
+		 * Get DCD Info
+		 */
+		fn get_dcd_info(&self) -> Result<Vec<u8>, CxlFmError> {
+			let mut block_size_masks = [0; CXL_DCD_MAX_REGIONS];
+
+			for mask in block_size_masks.iter_mut().take(self.regions.len()) {
+				*mask = CXL_EMULATED_DCD_BLOCK_SIZE_MASK;
+			}
+
+			let info = CxlDcdInfo {
+				num_hosts: self.num_heads,
+				num_regions: self.regions.len() as u8,
+				selection_policies: CXL_EMULATED_DCD_SELECTION_POLICIES,
+				removal_policies: CXL_EMULATED_DCD_REMOVAL_POLICIES,
+				sanitize_on_release_mask: (1 << self.regions.len()) - 1,
+				total_capacity: self.regions.iter().map(|r| r.length).sum(),
+				block_size_masks,
+			};
+
+			Ok(info.to_bytes())
+		}
+
+		/*
+		 * Get Host DC Region Configuration
+		 */
+		fn get_host_dc_region_config(&self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGetHostDcRegionConfigRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let start = request.start_region as usize;
+			if request.host_id >= self.num_heads as u16 ||
+			   start >= self.regions.len() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let end = std::cmp::min(self.regions.len(),
+						start + request.region_count as usize);
+			let used_extents = self.extents[request.host_id as usize].len() as u32;
+			let used_tags = self.host_tags(request.host_id as usize).len() as u32;
+
+			let config = CxlHostDcRegionConfig {
+				host_id: request.host_id,
+				num_regions: self.regions.len() as u8,
+				start_region: request.start_region,
+				regions: self.regions[start..end].to_vec(),
+				total_extents: CXL_EMULATED_DCD_MAX_EXTENTS,
+				available_extents: CXL_EMULATED_DCD_MAX_EXTENTS - used_extents,
+				total_tags: CXL_EMULATED_DCD_MAX_TAGS,
+				available_tags: CXL_EMULATED_DCD_MAX_TAGS - used_tags,
+			};
+
+			Ok(config.to_bytes())
+		}
+
+		/*
+		 * Set DC Region Configuration
+		 */
+		fn set_dc_region_config(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlSetDcRegionConfigRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			if request.block_size & CXL_EMULATED_DCD_BLOCK_SIZE_MASK == 0 ||
+			   !request.block_size.is_power_of_two() ||
+			   request.flags & !CXL_DCD_REGION_SANITIZE_ON_RELEASE != 0 {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			match self.regions.get_mut(request.region_id as usize) {
+				Some(region) => {
+					region.block_size = request.block_size;
+					region.flags = request.flags;
+					Ok(Vec::new())
+				},
+				None => Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			}
+		}
+
+		/*
+		 * Get DC Region Extent Lists
+		 */
+		fn get_dc_extent_list(&self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlGetDcExtentListRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let extents = match self.extents.get(request.host_id as usize) {
+				Some(extents) => extents,
+				None => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			};
+
+			let start = request.start_index as usize;
+			if start > extents.len() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let end = std::cmp::min(extents.len(),
+						start + request.extent_count as usize);
+
+			let list = CxlDcExtentList {
+				host_id: request.host_id,
+				start_index: request.start_index,
+				total_extents: extents.len() as u32,
+				generation: self.generations[request.host_id as usize],
+				extents: extents[start..end].to_vec(),
+			};
+
+			Ok(list.to_bytes())
+		}
+
+		/*
+		 * Get distinct non-zero tags of host extents
+		 */
+		fn host_tags(&self, host: usize) -> Vec<[u8; CXL_DCD_TAG_SIZE]> {
+			let mut tags: Vec<[u8; CXL_DCD_TAG_SIZE]> = self.extents[host].iter()
+				.map(|extent| extent.tag)
+				.filter(|tag| *tag != [0; CXL_DCD_TAG_SIZE])
+				.collect();
+
+			tags.sort();
+			tags.dedup();
+			tags
+		}
+
+		/*
+		 * Get free DPA ranges of the region as (start, end) pairs
+		 */
+		fn free_ranges(&self, region: &CxlDcRegionConfig) -> Vec<(u64, u64)> {
+			let region_end = region.base + region.length;
+			let mut allocated: Vec<(u64, u64)> = self.extents.iter()
+				.flatten()
+				.filter(|extent| extent.start_dpa >= region.base &&
+						 extent.start_dpa < region_end)
+				.map(|extent| (extent.start_dpa, extent.end_dpa()))
+				.collect();
+			let mut free = Vec::new();
+			let mut next = region.base;
+
+			allocated.sort();
+
+			for (start, end) in allocated {
+				if start > next {
+					free.push((next, start));
+				}
+
+				next = std::cmp::max(next, end);
+			}
+
+			if next < region_end {
+				free.push((next, region_end));
+			}
+
+			free
+		}
+
+		/*
+		 * Select free capacity of the region for the host
+		 */
+		fn select_capacity(&self, request: &CxlInitiateDcAddRequest,
+				   region: &CxlDcRegionConfig) -> Result<Vec<CxlDcExtent>, CxlFmError> {
+			let new_extent = |start_dpa, length| CxlDcExtent {
+				start_dpa,
+				length,
+				tag: request.tag,
+				shared_seq: 0,
+			};
+
+			if request.length == 0 || !request.length.is_multiple_of(region.block_size) {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let free = self.free_ranges(region);
+
+			match request.selection_policy {
+				CXL_DCD_SELECTION_POLICY_FREE => {
+					let mut extents = Vec::new();
+					let mut remaining = request.length;
+
+					for (start, end) in free {
+						if remaining == 0 {
+							break;
+						}
+
+						let length = std::cmp::min(remaining, end - start);
+						extents.push(new_extent(start, length));
+						remaining -= length;
+					}
+
+					if remaining != 0 {
+						return Err(CxlFmError::Device(CXL_FM_API_RESOURCES_EXHAUSTED));
+					}
+
+					Ok(extents)
+				},
+				CXL_DCD_SELECTION_POLICY_CONTIGUOUS => {
+					match free.iter().find(|(start, end)| end - start >= request.length) {
+						Some((start, _)) => Ok(vec![new_extent(*start, request.length)]),
+						None => Err(CxlFmError::Device(CXL_FM_API_RESOURCES_EXHAUSTED)),
+					}
+				},
+				_ => Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			}
+		}
+
+		/*
+		 * Check extents requested by prescriptive policy
+		 */
+		fn check_prescribed_extents(&self, request: &CxlInitiateDcAddRequest,
+					    region: &CxlDcRegionConfig) -> Result<Vec<CxlDcExtent>, CxlFmError> {
+			let free = self.free_ranges(region);
+			let mut extents = request.extents.clone();
+
+			extents.sort_by_key(|extent| extent.start_dpa);
+
+			for extent in extents.iter_mut() {
+				let is_free = free.iter().any(|(start, end)| {
+					extent.start_dpa >= *start && extent.end_dpa() <= *end
+				});
+				let is_aligned = extent.start_dpa.is_multiple_of(region.block_size) &&
+						 extent.length.is_multiple_of(region.block_size);
+
+				if extent.length == 0 || !is_aligned || !is_free {
+					return Err(CxlFmError::Device(CXL_FM_API_INVALID_EXTENT_LIST));
+				}
+
+				extent.tag = request.tag;
+				extent.shared_seq = 0;
+			}
+
+			if extents.windows(2).any(|pair| pair[0].end_dpa() > pair[1].start_dpa) {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_EXTENT_LIST));
+			}
+
+			Ok(extents)
+		}
+
+		/*
+		 * Find extents of other hosts shared by the tag
+		 */
+		fn shared_extents(&self, request: &CxlInitiateDcAddRequest,
+				  region: &CxlDcRegionConfig) -> Result<Vec<CxlDcExtent>, CxlFmError> {
+			let region_end = region.base + region.length;
+			let host_extents = &self.extents[request.host_id as usize];
+
+			if request.tag == [0; CXL_DCD_TAG_SIZE] {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let mut extents: Vec<CxlDcExtent> = self.extents.iter()
+				.flatten()
+				.filter(|extent| extent.tag == request.tag &&
+						 extent.start_dpa >= region.base &&
+						 extent.start_dpa < region_end)
+				.filter(|extent| !host_extents.iter().any(|owned| {
+					owned.start_dpa == extent.start_dpa
+				}))
+				.cloned()
+				.collect();
+
+			extents.sort_by_key(|extent| extent.start_dpa);
+			extents.dedup_by_key(|extent| extent.start_dpa);
+
+			if extents.is_empty() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			for (index, extent) in extents.iter_mut().enumerate() {
+				extent.shared_seq = index as u16 + 1;
+			}
+
+			Ok(extents)
+		}
+
+		/*
+		 * Initiate Dynamic Capacity Add
+		 *
+		 * The emulated hosts accept all offered extents immediately,
+		 * so accepted extents are visible in the extent list
+		 * after command completion.
+		 */
+		fn initiate_dc_add(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlInitiateDcAddRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let host = request.host_id as usize;
+			let region = match self.regions.get(request.region as usize) {
+				Some(region) if host < self.extents.len() => region,
+				_ => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			};
+
+			let extents = match request.selection_policy {
+				CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE => {
+					self.check_prescribed_extents(&request, region)?
+				},
+				CXL_DCD_SELECTION_POLICY_SHARED_ACCESS => {
+					self.shared_extents(&request, region)?
+				},
+				_ => self.select_capacity(&request, region)?,
+			};
+
+			if extents.is_empty() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_EXTENT_LIST));
+			}
+
+			let used_extents = self.extents[host].len() + extents.len();
+			let tags = self.host_tags(host);
+			let is_new_tag = request.tag != [0; CXL_DCD_TAG_SIZE] &&
+					 !tags.contains(&request.tag);
+
+			if used_extents > CXL_EMULATED_DCD_MAX_EXTENTS as usize ||
+			   (is_new_tag && tags.len() >= CXL_EMULATED_DCD_MAX_TAGS as usize) {
+				return Err(CxlFmError::Device(CXL_FM_API_RESOURCES_EXHAUSTED));
+			}
+
+			for extent in &extents {
+				self.log_dc_event(CXL_DC_EVENT_ADD_CAPACITY, request.host_id,
+						  request.region, extent);
+			}
+
+			self.extents[host].extend(extents);
+			self.generations[host] = self.generations[host].wrapping_add(1);
+
+			Ok(Vec::new())
+		}
+
+		/*
+		 * Add Dynamic Capacity Event Record
+		 */
+		fn log_dc_event(&mut self, event_type: u8, host_id: u16,
+				region: u8, extent: &CxlDcExtent) {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(event_type);
+			writer.reserve(1);
+			writer.write_u16(host_id);
+			writer.write_u8(region);
+			writer.reserve(3);
+			extent.write(&mut writer);
+
+			self.events.add(CXL_EVENT_LOG_DYNAMIC_CAPACITY,
+					CXL_EVENT_RECORD_DYNAMIC_CAPACITY,
+					CXL_EVENT_SEVERITY_INFO, writer.into_bytes());
+		}
+
+		/*
+		 * Find DC region of the DPA
+		 */
+		fn dpa_region(&self, dpa: u64) -> Option<&CxlDcRegionConfig> {
+			self.regions.iter()
+				.find(|region| dpa >= region.base && dpa < region.base + region.length)
+		}
+
+		/*
+		 * Select host DPA ranges released by tag and length.
+		 * Zero tag matches any extent, zero length releases
+		 * all matching extents. The most recently added extents
+		 * are released first.
+		 */
+		fn select_released_ranges(&self,
+					  request: &CxlInitiateDcReleaseRequest) -> Result<Vec<(u64, u64)>, CxlFmError> {
+			let any_tag = request.tag == [0; CXL_DCD_TAG_SIZE];
+			let mut ranges = Vec::new();
+			let mut remaining = request.length;
+
+			for extent in self.extents[request.host_id as usize].iter().rev() {
+				if !any_tag && extent.tag != request.tag {
+					continue;
+				}
+
+				if request.length == 0 {
+					ranges.push((extent.start_dpa, extent.end_dpa()));
+					continue;
+				}
+
+				if remaining == 0 {
+					break;
+				}
+
+				let length = std::cmp::min(remaining, extent.length);
+				let block_size = self.dpa_region(extent.start_dpa)
+							.map_or(1, |region| region.block_size);
+
+				if !length.is_multiple_of(block_size) {
+					return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+				}
+
+				ranges.push((extent.end_dpa() - length, extent.end_dpa()));
+				remaining -= length;
+			}
+
+			if ranges.is_empty() || (remaining != 0 && request.length != 0) {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			Ok(ranges)
+		}
+
+		/*
+		 * Check host DPA ranges requested by prescriptive policy
+		 */
+		fn check_released_extents(&self,
+					  request: &CxlInitiateDcReleaseRequest) -> Result<Vec<(u64, u64)>, CxlFmError> {
+			let host_extents = &self.extents[request.host_id as usize];
+			let mut ranges = Vec::new();
+
+			for extent in &request.extents {
+				let is_owned = host_extents.iter().any(|owned| {
+					extent.start_dpa >= owned.start_dpa &&
+					extent.end_dpa() <= owned.end_dpa()
+				});
+				let is_aligned = match self.dpa_region(extent.start_dpa) {
+					Some(region) => {
+						extent.start_dpa.is_multiple_of(region.block_size) &&
+						extent.length.is_multiple_of(region.block_size)
+					},
+					None => false,
+				};
+
+				if extent.length == 0 || !is_owned || !is_aligned {
+					return Err(CxlFmError::Device(CXL_FM_API_INVALID_EXTENT_LIST));
+				}
+
+				ranges.push((extent.start_dpa, extent.end_dpa()));
+			}
+
+			ranges.sort();
+
+			if ranges.is_empty() || ranges.windows(2).any(|pair| pair[0].1 > pair[1].0) {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_EXTENT_LIST));
+			}
+
+			Ok(ranges)
+		}
+
+		/*
+		 * Cut DPA range out of host extents
+		 */
+		fn remove_range(extents: &mut Vec<CxlDcExtent>, start: u64, end: u64) {
+			let mut remaining = Vec::new();
+
+			for extent in extents.drain(..) {
+				if end <= extent.start_dpa || start >= extent.end_dpa() {
+					remaining.push(extent);
+					continue;
+				}
+
+				if extent.start_dpa < start {
+					remaining.push(CxlDcExtent {
+						length: start - extent.start_dpa,
+						..extent.clone()
+					});
+				}
+
+				if extent.end_dpa() > end {
+					remaining.push(CxlDcExtent {
+						start_dpa: end,
+						length: extent.end_dpa() - end,
+						..extent.clone()
+					});
+				}
+			}
+
+			*extents = remaining;
+		}
+
+		/*
+		 * Initiate Dynamic Capacity Release
+		 *
+		 * The emulated host responds only if its head link is up,
+		 * forced release doesn't wait for the host response.
+		 */
+		fn initiate_dc_release(&mut self, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let request = CxlInitiateDcReleaseRequest::from_bytes(payload)
+				.map_err(|_| CxlFmError::Device(CXL_FM_API_INVALID_PAYLOAD_LENGTH))?;
+
+			let host = request.host_id as usize;
+			if host >= self.extents.len() {
+				return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT));
+			}
+
+			let ranges = match request.removal_policy {
+				CXL_DCD_REMOVAL_POLICY_TAG_BASED => self.select_released_ranges(&request)?,
+				CXL_DCD_REMOVAL_POLICY_PRESCRIPTIVE => self.check_released_extents(&request)?,
+				_ => return Err(CxlFmError::Device(CXL_FM_API_INVALID_INPUT)),
+			};
+
+			let is_responsive = self.heads.get(host)
+						.is_some_and(|head| head.ltssm_state == CXL_LTSSM_L0);
+
+			if !request.forced && !is_responsive {
+				return Err(CxlFmError::Device(CXL_FM_API_BUSY));
+			}
+
+			let event_type = match request.forced {
+				true => CXL_DC_EVENT_FORCED_RELEASE,
+				false => CXL_DC_EVENT_RELEASE_CAPACITY,
+			};
+
+			for (start, end) in ranges {
+				let region = self.regions.iter()
+					.position(|region| start >= region.base &&
+						  start < region.base + region.length)
+					.unwrap_or(0);
+				let extent = CxlDcExtent {
+					start_dpa: start,
+					length: end - start,
+					tag: request.tag,
+					shared_seq: 0,
+				};
+
+				self.log_dc_event(event_type, request.host_id, region as u8, &extent);
+				Self::remove_range(&mut self.extents[host], start, end);
+			}
+
+			self.generations[host] = self.generations[host].wrapping_add(1);
+
+			Ok(Vec::new())
+		}
+	}
+
+	impl CxlCciBackend for CxlEmulatedDevice {
+		fn name(&self) -> String {
+			self.name.clone()
+		}
+
+		fn locality(&self) -> u16 {
+			self.locality
+		}
+
+		fn execute(&mut self, opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			if self.num_heads < 2 &&
+			   matches!(opcode, CXL_FM_API_GET_MHD_INFO | CXL_FM_API_GET_MHD_HEAD_INFO) {
+				return Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED));
+			}
+
+			if self.ld_map.len() < 2 &&
+			   (CXL_FM_API_GET_LD_INFO..=CXL_FM_API_SET_QOS_CONTROL).contains(&opcode) {
+				return Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED));
+			}
+
+			if self.ld_map.len() < 2 && opcode == CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND {
+				return Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED));
+			}
+
+			if self.regions.is_empty() &&
+			   (CXL_FM_API_GET_DCD_INFO..=CXL_FM_API_INITIATE_DC_RELEASE).contains(&opcode) {
+				return Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED));
+			}
+
+			match opcode {
+				CXL_FM_API_IDENTIFY => self.identify(),
+				CXL_FM_API_GET_EVENT_RECORDS => self.events.get_records(payload),
+				CXL_FM_API_CLEAR_EVENT_RECORDS => self.events.clear_records(payload),
+				CXL_FM_API_GET_LD_INFO => self.get_ld_info(),
+				CXL_FM_API_GET_LD_ALLOCATIONS => self.get_ld_allocations(payload),
+				CXL_FM_API_SET_LD_ALLOCATIONS => self.set_ld_allocations(payload),
+				CXL_FM_API_GET_QOS_CONTROL => self.get_qos_control(),
+				CXL_FM_API_SET_QOS_CONTROL => self.set_qos_control(payload),
+				CXL_FM_API_GET_MHD_INFO => self.get_mhd_info(payload),
+				CXL_FM_API_GET_MHD_HEAD_INFO => self.get_head_info(payload),
+				CXL_FM_API_GET_DCD_INFO => self.get_dcd_info(),
+				CXL_FM_API_GET_HOST_DC_REGION_CONFIG => self.get_host_dc_region_config(payload),
+				CXL_FM_API_SET_DC_REGION_CONFIG => self.set_dc_region_config(payload),
+				CXL_FM_API_GET_DC_REGION_EXTENT_LIST => self.get_dc_extent_list(payload),
+				CXL_FM_API_INITIATE_DC_ADD => self.initiate_dc_add(payload),
+				CXL_FM_API_INITIATE_DC_RELEASE => self.initiate_dc_release(payload),
+				CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND => self.ld_tunnel(payload),
+				CXL_MEMDEV_SECURE_ERASE => Ok(Vec::new()),
+				_ => Err(CxlFmError::Device(CXL_FM_API_UNSUPPORTED)),
+			}
+		}
+	}
+}
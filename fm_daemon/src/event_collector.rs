@@ -0,0 +1,196 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+
+pub mod cxl_fm_event_collector {
+	use std::sync::Arc;
+	use std::thread;
+	use std::time::Instant;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_EVENT_RECORDS;
+	use fm_library::cxl_fm_api::CXL_FM_API_CLEAR_EVENT_RECORDS;
+	use fm_library::cxl_fm_event::CxlGetEventRecordsRequest;
+	use fm_library::cxl_fm_event::CxlGetEventRecordsResponse;
+	use fm_library::cxl_fm_event::CxlClearEventRecordsRequest;
+	use fm_library::cxl_fm_event::CxlEventRecord;
+	use fm_library::cxl_fm_event::format_timestamp;
+	use fm_library::cxl_fm_event::CXL_EVENT_LOGS;
+	use fm_library::cxl_fm_event::CXL_EVENT_RECORDS_OVERFLOW;
+	use fm_library::cxl_fm_event::CXL_EVENT_RECORDS_MORE;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_WARNING;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_DEVICE;
+	use fm_library::cxl_topology::is_device_kind;
+	use crate::cxl_fm_context::CxlFmContext;
+	use crate::cxl_fm_context::CxlCciPath;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_hot_plug::handle_port_events;
+
+	/*
+	 * Maximal number of Get Event Records commands
+	 * issued for one event log by one pass
+	 */
+	pub const CXL_FM_EVENT_COLLECTOR_READS: usize = 16;
+
+	/*
+	 * Get CCIs of the daemon and devices reachable
+	 * through switch ports with their component names
+	 */
+	fn event_sources(ctx: &CxlFmContext) -> Vec<(CxlCciPath, String)> {
+		let mut sources: Vec<(CxlCciPath, String)> = (0..ctx.device_count())
+			.map(|device| (CxlCciPath::Direct(device),
+				       ctx.device_name(device).unwrap_or_default()))
+			.collect();
+
+		let topology = lock_shared(&ctx.topology).clone();
+
+		for node in topology.nodes.iter().filter(|node| is_device_kind(node.kind)) {
+			if let Ok(path @ CxlCciPath::Tunnel(..)) = ctx.path_of(&topology, &node.name) {
+				sources.push((path, node.name.clone()));
+			}
+		}
+
+		sources
+	}
+
+	/*
+	 * Read and clear the event log of the component
+	 */
+	fn collect_log(ctx: &CxlFmContext, path: CxlCciPath, component: &str,
+		       event_log: u8, records: &mut Vec<CxlEventRecord>) -> Result<(), CxlFmError> {
+		let request = CxlGetEventRecordsRequest { event_log };
+
+		for _ in 0..CXL_FM_EVENT_COLLECTOR_READS {
+			let data = ctx.execute_path(path, CXL_FM_API_GET_EVENT_RECORDS,
+						    &request.to_bytes())?;
+			let responce = CxlGetEventRecordsResponse::from_bytes(&data)?;
+
+			if responce.flags & CXL_EVENT_RECORDS_OVERFLOW != 0 {
+				ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_DEVICE, component,
+						 &format!("{} event record(s) are lost from {} to {}",
+							  responce.overflow_count,
+							  format_timestamp(responce.first_overflow),
+							  format_timestamp(responce.last_overflow)));
+			}
+
+			if responce.records.is_empty() {
+				break;
+			}
+
+			let clear_request = CxlClearEventRecordsRequest {
+				event_log,
+				flags: 0,
+				handles: responce.records.iter().map(|record| record.handle).collect(),
+			};
+
+			ctx.execute_path(path, CXL_FM_API_CLEAR_EVENT_RECORDS, &clear_request.to_bytes())?;
+			records.extend(responce.records);
+
+			if responce.flags & CXL_EVENT_RECORDS_MORE == 0 {
+				break;
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Report records of the component as FM events
+	 * in the order they happened
+	 */
+	fn report_records(ctx: &CxlFmContext, component: &str, mut records: Vec<CxlEventRecord>) {
+		records.sort_by_key(|record| (record.timestamp, record.handle));
+
+		for record in &records {
+			let (event_type, object, message) = record.describe();
+			let name = match object.is_empty() {
+				true => String::from(component),
+				false => format!("{}/{}", component, object),
+			};
+
+			ctx.report_event_at(record.timestamp, record.severity(), event_type, &name, &message);
+		}
+	}
+
+	/*
+	 * Collect event records of all components.
+	 * Components without event logs and components
+	 * behind the link that is down are skipped.
+	 * Port state changes of switches are handled
+	 * after all records are reported. Standby FM leaves
+	 * the records to the active FM.
+	 */
+	pub fn collect_events(ctx: &CxlFmContext) -> usize {
+		let mut count = 0;
+		let mut port_events = Vec::new();
+
+		if ctx.is_standby() {
+			return 0;
+		}
+
+		for (path, component) in event_sources(ctx) {
+			let mut records = Vec::new();
+
+			for event_log in CXL_EVENT_LOGS {
+				match collect_log(ctx, path, &component, event_log, &mut records) {
+					Ok(_) => {},
+					Err(CxlFmError::Device(_)) => break,
+					Err(e) => {
+						println!("Failed to collect events of {}: {}", component, e);
+						break;
+					},
+				}
+			}
+
+			if let CxlCciPath::Direct(_) = path {
+				port_events.extend(records.iter()
+						   .filter_map(|record| record.port_event())
+						   .map(|(port, event_type)| (component.clone(), port, event_type)));
+			}
+
+			count += records.len();
+			report_records(ctx, &component, records);
+		}
+
+		handle_port_events(ctx, &port_events);
+		count
+	}
+
+	/*
+	 * Start periodic collection of event records
+	 * by the live policies
+	 */
+	pub fn start_collection(ctx: Arc<CxlFmContext>, env: &CxlFmOptions) {
+		let is_debug = env.is_debug;
+		let mut last = Instant::now();
+
+		thread::spawn(move || loop {
+			ctx.wait_interval(&mut last, |policies| policies.event_poll_interval);
+
+			let _operation = ctx.lock_operation();
+			let count = collect_events(&ctx);
+
+			if is_debug && count != 0 {
+				println!("Collected {} event record(s)", count);
+			}
+		});
+	}
+}
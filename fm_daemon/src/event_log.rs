@@ -0,0 +1,217 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_event_log {
+	use std::collections::VecDeque;
+	use std::sync::mpsc::{channel, Receiver, Sender};
+	use fm_library::cxl_fm_event::CxlFmEvent;
+	use fm_library::cxl_fm_event::CxlFmEventFilter;
+	use fm_library::cxl_fm_event::event_timestamp;
+	use fm_library::cxl_fm_event::event_severity_name;
+	use fm_library::cxl_fm_event::event_type_name;
+
+	/*
+	 * Maximal number of events kept by the daemon
+	 */
+	pub const CXL_FM_EVENT_LOG_CAPACITY: usize = 1024;
+
+	/*
+	 * struct CxlFmEventLog - bounded log of FM events
+	 * @events: events from the oldest to the newest
+	 * @next_sequence: sequence number of the next event
+	 * @subscribers: channels of clients that receive new events
+	 *
+	 * The oldest events are dropped when the log is full.
+	 * Subscriber is dropped when its channel is closed.
+	 */
+	pub struct CxlFmEventLog {
+		pub events: VecDeque<CxlFmEvent>,
+		pub next_sequence: u64,
+		pub subscribers: Vec<Sender<CxlFmEvent>>,
+	}
+
+	impl Default for CxlFmEventLog {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	impl CxlFmEventLog {
+		pub fn new() -> CxlFmEventLog {
+			CxlFmEventLog {
+				events: VecDeque::new(),
+				next_sequence: 1,
+				subscribers: Vec::new(),
+			}
+		}
+
+		/*
+		 * Add event to the log and get its sequence number
+		 */
+		pub fn push(&mut self, severity: u8, event_type: u8,
+			    component: &str, message: &str) -> u64 {
+			self.push_at(event_timestamp(), severity, event_type, component, message)
+		}
+
+		/*
+		 * Add event that happened at given time
+		 */
+		pub fn push_at(&mut self, timestamp: u64, severity: u8, event_type: u8,
+			       component: &str, message: &str) -> u64 {
+			let event = CxlFmEvent {
+				sequence: self.next_sequence,
+				timestamp,
+				severity,
+				event_type,
+				component: String::from(component),
+				message: String::from(message),
+			};
+
+			println!("event {} [{}] {} {}: {}",
+				 event.sequence, event_severity_name(severity),
+				 event_type_name(event_type), component, message);
+
+			if self.events.len() >= CXL_FM_EVENT_LOG_CAPACITY {
+				self.events.pop_front();
+			}
+
+			self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+			self.events.push_back(event);
+			self.next_sequence += 1;
+			self.next_sequence - 1
+		}
+
+		/*
+		 * Replace the log by stored events
+		 */
+		pub fn restore(&mut self, events: impl IntoIterator<Item = CxlFmEvent>) {
+			self.events = events.into_iter().collect();
+
+			while self.events.len() > CXL_FM_EVENT_LOG_CAPACITY {
+				self.events.pop_front();
+			}
+
+			self.next_sequence = self.events.back()
+				.map_or(1, |event| event.sequence + 1);
+		}
+
+		/*
+		 * Subscribe to new events. Kept events starting from
+		 * the sequence number are returned, so the subscriber
+		 * gets every event once.
+		 */
+		pub fn subscribe(&mut self, from: u64) -> (Vec<CxlFmEvent>, Receiver<CxlFmEvent>) {
+			let (sender, receiver) = channel();
+			let backlog = self.events.iter()
+				.filter(|event| from != 0 && event.sequence >= from)
+				.cloned()
+				.collect();
+
+			self.subscribers.push(sender);
+			(backlog, receiver)
+		}
+
+		/*
+		 * Get events that pass the filter
+		 */
+		pub fn query(&self, filter: &CxlFmEventFilter) -> Vec<CxlFmEvent> {
+			self.events.iter()
+				.filter(|event| filter.matches(event))
+				.cloned()
+				.collect()
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use fm_library::cxl_fm_lib::CxlFmRequest;
+		use fm_library::cxl_fm_lib::CXL_FM_GET_FM_EVENTS_COMMAND;
+		use fm_library::cxl_fm_lib::CXL_FM_SEVERITY_ARG;
+		use fm_library::cxl_fm_lib::CXL_FM_COMPONENT_ARG;
+		use fm_library::cxl_fm_lib::CXL_FM_SINCE_ARG;
+		use fm_library::cxl_fm_lib::CXL_FM_UNTIL_ARG;
+		use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+		use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_WARNING;
+		use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_FAILURE;
+		use fm_library::cxl_fm_event::CXL_FM_EVENT_LINK;
+		use fm_library::cxl_fm_event::CXL_FM_EVENT_HOT_PLUG;
+		use fm_library::cxl_fm_event::CXL_FM_EVENT_OPERATION;
+
+		fn event_log() -> CxlFmEventLog {
+			let mut log = CxlFmEventLog::new();
+
+			log.push_at(100, CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_OPERATION,
+				    "fm", "BIND_LD: 1 step(s) done, 0 failed");
+			log.push_at(200, CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_LINK,
+				    "sw0/port3", "link degraded");
+			log.push_at(300, CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_HOT_PLUG,
+				    "sw0", "surprise removal");
+			log.push_at(400, CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_LINK,
+				    "sw01/port1", "link down");
+			log
+		}
+
+		/*
+		 * Sequence numbers of events selected by GET_FM_EVENTS arguments
+		 */
+		fn query(args: &[(&str, &str)]) -> Vec<u64> {
+			let request = args.iter()
+				.fold(CxlFmRequest::new(CXL_FM_GET_FM_EVENTS_COMMAND),
+				      |request, (key, value)| request.arg(key, value));
+			let filter = CxlFmEventFilter::from_request(&request).unwrap();
+
+			event_log().query(&filter).iter()
+				.map(|event| event.sequence)
+				.collect()
+		}
+
+		#[test]
+		fn events_are_filtered_by_minimal_severity() {
+			assert_eq!(query(&[]), vec![1, 2, 3, 4]);
+			assert_eq!(query(&[(CXL_FM_SEVERITY_ARG, "1")]), vec![2, 3, 4]);
+			assert_eq!(query(&[(CXL_FM_SEVERITY_ARG, "2")]), vec![3, 4]);
+			assert!(query(&[(CXL_FM_SEVERITY_ARG, "3")]).is_empty());
+		}
+
+		#[test]
+		fn events_are_filtered_by_component_and_objects_inside_it() {
+			assert_eq!(query(&[(CXL_FM_COMPONENT_ARG, "sw0")]), vec![2, 3]);
+			assert_eq!(query(&[(CXL_FM_COMPONENT_ARG, "sw0/port3")]), vec![2]);
+			assert_eq!(query(&[(CXL_FM_COMPONENT_ARG, "sw01")]), vec![4]);
+			assert!(query(&[(CXL_FM_COMPONENT_ARG, "sw1")]).is_empty());
+		}
+
+		#[test]
+		fn events_are_filtered_by_time() {
+			assert_eq!(query(&[(CXL_FM_SINCE_ARG, "200")]), vec![2, 3, 4]);
+			assert_eq!(query(&[(CXL_FM_SINCE_ARG, "201")]), vec![3, 4]);
+			assert_eq!(query(&[(CXL_FM_SINCE_ARG, "200"), (CXL_FM_UNTIL_ARG, "300")]), vec![2, 3]);
+			assert!(query(&[(CXL_FM_SINCE_ARG, "401")]).is_empty());
+		}
+
+		#[test]
+		fn event_filters_are_combined() {
+			assert_eq!(query(&[(CXL_FM_SEVERITY_ARG, "2"), (CXL_FM_COMPONENT_ARG, "sw0")]), vec![3]);
+			assert_eq!(query(&[(CXL_FM_COMPONENT_ARG, "sw0"), (CXL_FM_SINCE_ARG, "250")]), vec![3]);
+		}
+	}
+}
@@ -0,0 +1,194 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_failover {
+	use std::net::TcpStream;
+	use std::time::Duration;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_error;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_INFO_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_CONFIG_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_SET_FM_CONFIG_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_EVENTS_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_SUBSCRIBE_EVENTS_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_STATE_COMMAND;
+	use fm_library::cxl_fm_orchestrator::CxlFmRole;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_ACTIVE;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_STANDBY;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_WARNING;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_FAILOVER;
+	use crate::cxl_fm_context::CxlFmContext;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_recovery::recover;
+	use crate::cxl_fm_heartbeat::connect;
+	use crate::cxl_fm_heartbeat::CXL_FM_HEARTBEAT_LEASE;
+
+	/*
+	 * Commands that standby FM serves: they don't touch the fabric
+	 */
+	const CXL_FM_STANDBY_COMMANDS: [&str; 6] = [
+		CXL_FM_GET_FM_INFO_COMMAND,
+		CXL_FM_GET_FM_CONFIG_COMMAND,
+		CXL_FM_SET_FM_CONFIG_COMMAND,
+		CXL_FM_GET_FM_EVENTS_COMMAND,
+		CXL_FM_SUBSCRIBE_EVENTS_COMMAND,
+		CXL_FM_GET_FM_STATE_COMMAND,
+	];
+
+	/*
+	 * Can standby FM serve the request?
+	 */
+	pub fn is_served_by_standby(request: &CxlFmRequest) -> bool {
+		CXL_FM_STANDBY_COMMANDS.contains(&request.command.as_str())
+	}
+
+	/*
+	 * Send snapshot of the state store. Unfinished
+	 * operations are included, so standby FM can
+	 * take them over.
+	 */
+	pub fn get_state(stream: &TcpStream, ctx: &CxlFmContext, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", CXL_FM_GET_FM_STATE_COMMAND);
+		}
+
+		let data = lock_shared(&ctx.store)
+			.as_ref()
+			.map(|store| store.export());
+
+		match data {
+			Some(data) => send_payload(stream, &data, env),
+			None => send_error(stream, &CxlFmError::NotFound(String::from("state store")), env),
+		}
+	}
+
+	/*
+	 * Copy state of the active FM into the store.
+	 * The store is not rewritten if the state is the same
+	 * as the last replicated one.
+	 */
+	fn replicate(ctx: &CxlFmContext, role: &CxlFmRole, timeout: Duration,
+		     replicated: &mut Vec<u8>, env: &CxlFmOptions) -> Result<(), CxlFmError> {
+		let stream = connect(&role.address, timeout)?;
+		let request = CxlFmRequest::new(CXL_FM_GET_FM_STATE_COMMAND);
+		let data = from_hex(&send_request(&stream, &request, env)?)?;
+
+		if data == *replicated {
+			return Ok(());
+		}
+
+		if let Some(store) = lock_shared(&ctx.store).as_mut() {
+			store.import(&data)?;
+
+			if env.is_debug {
+				println!("Replicated state of {}: {} operation(s) in flight",
+					 role.active, store.state.operations.len());
+			}
+		}
+
+		*replicated = data;
+		Ok(())
+	}
+
+	/*
+	 * Take over the fabric: restore the state replicated
+	 * from the former active FM and resume its interrupted
+	 * operations. Their plans are rebuilt against the fabric,
+	 * so binds that have reached the hardware are not repeated.
+	 */
+	fn promote(ctx: &CxlFmContext, reason: &str) {
+		if *lock_shared(&ctx.role) == CXL_FM_ROLE_ACTIVE {
+			return;
+		}
+
+		let _operation = ctx.lock_operation();
+
+		let operations = lock_shared(&ctx.store)
+			.as_ref()
+			.map_or(0, |store| store.state.operations.len());
+
+		println!("Promoted to active FM: {}", reason);
+		*lock_shared(&ctx.role) = CXL_FM_ROLE_ACTIVE;
+		*lock_shared(&ctx.desired_state) = None;
+		lock_shared(&ctx.drift).clear();
+		lock_shared(&ctx.dc_extents).clear();
+		lock_shared(&ctx.unavailable).clear();
+		lock_shared(&ctx.lost).clear();
+
+		recover(ctx);
+
+		ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_FAILOVER, "fm",
+				 &format!("promoted to active FM ({}), {} operation(s) taken over",
+					  reason, operations));
+	}
+
+	/*
+	 * Stop managing the fabric. The operation
+	 * in progress is completed first.
+	 */
+	pub fn demote(ctx: &CxlFmContext, reason: &str) {
+		if *lock_shared(&ctx.role) == CXL_FM_ROLE_STANDBY {
+			return;
+		}
+
+		let _operation = ctx.lock_operation();
+
+		println!("Demoted to standby FM: {}", reason);
+		*lock_shared(&ctx.role) = CXL_FM_ROLE_STANDBY;
+
+		ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_FAILOVER, "fm",
+				 &format!("demoted to standby FM ({})", reason));
+	}
+
+	/*
+	 * Follow the role assigned by orchestrator. Active role
+	 * is leased for some heartbeat intervals, standby FM
+	 * replicates state of the active FM.
+	 */
+	pub fn follow_role(ctx: &CxlFmContext, role: &CxlFmRole, interval: Duration,
+			   replicated: &mut Vec<u8>, env: &CxlFmOptions) {
+		if role.role == CXL_FM_ROLE_ACTIVE {
+			ctx.renew_lease(interval * CXL_FM_HEARTBEAT_LEASE);
+			promote(ctx, "elected by orchestrator");
+			replicated.clear();
+			return;
+		}
+
+		match role.active.is_empty() {
+			true => demote(ctx, "active FM is not elected"),
+			false => demote(ctx, &format!("{} is active", role.active)),
+		}
+
+		if role.address.is_empty() {
+			return;
+		}
+
+		if let Err(e) = replicate(ctx, role, interval / 2, replicated, env) {
+			println!("Failed to replicate state of {}: {}", role.active, e);
+		}
+	}
+}
@@ -20,14 +20,43 @@
  */
 
 pub mod cxl_fm_command {
+	use std::io::Write;
 	use std::net::{TcpStream};
+	use std::sync::mpsc::RecvTimeoutError;
+	use std::time::Duration;
 	use fm_library::cxl_fm_lib::CxlFmOptions;
 	use fm_library::cxl_fm_lib::send_responce;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_INFO_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_SET_FM_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_EVENTS_COMMAND;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::CXL_FM_PAYLOAD_ARG;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_error;
+	use fm_library::cxl_fm_lib::to_hex;
+	use fm_library::cxl_fm_lib::CXL_FM_SUBSCRIBE_EVENTS_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_FROM_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_OK;
 	use fm_library::cxl_fm_lib::CXL_FM_NO_DATA;
+	use fm_library::cxl_fm_event::CxlFmEvent;
+	use fm_library::cxl_fm_event::CxlFmEventFilter;
+	use fm_library::cxl_fm_event::CxlFmEventList;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_POLICY;
+	use fm_library::cxl_config::CxlConfig;
+	use crate::cxl_fm_context::CxlFmContext;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_event_collector::collect_events;
+
+	/*
+	 * Subscriber gets NO_DATA if there were no events
+	 * for this time (seconds), so closed connection
+	 * is detected
+	 */
+	pub const CXL_FM_SUBSCRIPTION_KEEPALIVE: u64 = 30;
 
 	/*
 	 * Get Fabric Manager (FM) status/info
@@ -42,34 +71,148 @@ pub mod cxl_fm_command {
 
 	/*
 	 * Get Fabric Manager (FM) configuration
+	 *
+	 * The live configuration is returned
+	 * as configuration file.
 	 */
-	pub fn get_config(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn get_config(stream: &TcpStream, ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_GET_FM_CONFIG_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		let content = lock_shared(&ctx.config).to_config().to_toml();
+
+		send_payload(stream, content.as_bytes(), env);
+	}
+
+	/*
+	 * Change the live configuration by tables of
+	 * configuration file and return the new one
+	 */
+	fn change_config(request: &CxlFmRequest, ctx: &CxlFmContext) -> Result<String, CxlFmError> {
+		let payload = request.get(CXL_FM_PAYLOAD_ARG)
+			.ok_or(CxlFmError::InvalidInput(String::from("payload is required")))?;
+		let content = String::from_utf8(from_hex(payload)?)
+			.map_err(|_| CxlFmError::InvalidInput(String::from("configuration is not UTF-8 text")))?;
+		let changes = CxlConfig::parse(&content)?;
+		let mut config = lock_shared(&ctx.config);
+
+		if !config.security.allow_set_config {
+			return Err(CxlFmError::InvalidInput(String::from("configuration changes are not allowed")));
+		}
+
+		*config = config.update(&changes)?;
+
+		let content = config.to_config().to_toml();
+
+		drop(config);
+
+		for table in &changes.tables {
+			for (key, value) in &table.entries {
+				ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_POLICY, "fm",
+						 &format!("configuration is changed: [{}] {} = {}",
+							  table.name, key, value));
+			}
+		}
+
+		Ok(content)
 	}
 
 	/*
 	 * Set Fabric Manager (FM) configuration
 	 */
-	pub fn set_config(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn set_config(stream: &TcpStream, request: &CxlFmRequest,
+			  ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_SET_FM_CONFIG_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		match change_config(request, ctx) {
+			Ok(content) => send_payload(stream, content.as_bytes(), env),
+			Err(e) => send_error(stream, &e, env),
+		}
 	}
 
 	/*
 	 * Get Fabric Manager (FM) event records
+	 *
+	 * Pending event records of components are collected
+	 * before the event log is queried.
 	 */
-	pub fn get_events(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn get_events(stream: &TcpStream, request: &CxlFmRequest,
+			  ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_GET_FM_EVENTS_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		let filter = match CxlFmEventFilter::from_request(request) {
+			Ok(filter) => filter,
+			Err(e) => {
+				send_error(stream, &e, env);
+				return;
+			}
+		};
+
+		collect_events(ctx);
+
+		let events = lock_shared(&ctx.events).query(&filter);
+
+		send_payload(stream, &CxlFmEventList { events }.to_bytes(), env);
+	}
+
+	/*
+	 * Send event to subscriber
+	 */
+	fn send_event(mut stream: &TcpStream, event: &CxlFmEvent) -> std::io::Result<()> {
+		writeln!(stream, "{} {}", CXL_FM_OK, to_hex(&event.to_bytes()))
+	}
+
+	/*
+	 * Push Fabric Manager (FM) events to subscriber
+	 *
+	 * Every event that passes the filter is sent as OK responce
+	 * until the connection is closed. Kept events starting from
+	 * the requested sequence number are sent first.
+	 */
+	pub fn subscribe_events(mut stream: &TcpStream, request: &CxlFmRequest,
+				ctx: &CxlFmContext, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", CXL_FM_SUBSCRIBE_EVENTS_COMMAND);
+		}
+
+		let filter = CxlFmEventFilter::from_request(request);
+		let from = request.get_number_or::<u64>(CXL_FM_FROM_ARG, 0);
+
+		let (filter, from) = match (filter, from) {
+			(Ok(filter), Ok(from)) => (filter, from),
+			(Err(e), _) | (_, Err(e)) => {
+				send_error(stream, &e, env);
+				return;
+			}
+		};
+
+		let (backlog, receiver) = lock_shared(&ctx.events).subscribe(from);
+
+		for event in backlog.iter().filter(|event| filter.matches(event)) {
+			if send_event(stream, event).is_err() {
+				return;
+			}
+		}
+
+		loop {
+			let result = match receiver.recv_timeout(Duration::from_secs(CXL_FM_SUBSCRIPTION_KEEPALIVE)) {
+				Ok(event) if filter.matches(&event) => send_event(stream, &event),
+				Ok(_) => Ok(()),
+				Err(RecvTimeoutError::Timeout) => writeln!(stream, "{}", CXL_FM_NO_DATA),
+				Err(RecvTimeoutError::Disconnected) => return,
+			};
+
+			if result.is_err() {
+				if env.is_debug {
+					println!("Subscriber is disconnected");
+				}
+				return;
+			}
+		}
 	}
 }
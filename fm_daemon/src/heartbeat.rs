@@ -0,0 +1,300 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub mod cxl_fm_heartbeat {
+	use std::net::{Ipv4Addr, SocketAddrV4, TcpStream, ToSocketAddrs, UdpSocket};
+	use std::sync::Arc;
+	use std::thread;
+	use std::time::{Duration, Instant};
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_lib::CXL_FM_REGISTER_FM_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_HEARTBEAT_FM_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_ANNOUNCE_FM_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_ID_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ADDRESS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_VERSION_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_SWITCHES_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICES_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_CCIS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_UPTIME_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_INTERVAL_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ROLE_ARG;
+	use fm_library::cxl_fm_orchestrator::CxlFmRole;
+	use fm_library::cxl_fm_announce::announce_socket;
+	use fm_library::cxl_fm_announce::announce;
+	use fm_library::cxl_fm_orchestrator::fm_role_name;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_ACTIVE;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_STANDBY;
+	use fm_library::cxl_topology::is_device_kind;
+	use fm_library::cxl_topology::CXL_NODE_SWITCH;
+	use crate::cxl_fm_context::CxlFmContext;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_discover_command::discover_fabric;
+	use crate::cxl_fm_failover::follow_role;
+	use crate::cxl_fm_failover::demote;
+
+	/*
+	 * Active FM that has not reported to orchestrator for this
+	 * number of heartbeat intervals steps down (e.g. after it
+	 * was paused). Orchestrator promotes standby FM only after
+	 * more missed heartbeats, so two FMs are never active
+	 * for the same switch.
+	 */
+	pub const CXL_FM_HEARTBEAT_LEASE: u32 = 2;
+
+	/*
+	 * struct CxlFmRegistration - registration of FM in orchestrator
+	 * @orchestrator: IP address + port of orchestrator
+	 * @group: multicast group of announcements
+	 * @interface: IP address of the interface of announcements
+	 * @id: FM identifier
+	 * @address: IP address + port of FM API
+	 * @version: FM version
+	 * @interval: heartbeat and announcement interval (seconds)
+	 */
+	#[derive(Debug, Clone)]
+	pub struct CxlFmRegistration {
+		pub orchestrator: Option<String>,
+		pub group: Option<SocketAddrV4>,
+		pub interface: Ipv4Addr,
+		pub id: String,
+		pub address: String,
+		pub version: String,
+		pub interval: u64,
+	}
+
+	/*
+	 * Add names of switches and devices managed by FM, names of
+	 * its CCIs by CCI index and its current role into request. The fabric is discovered
+	 * if it wasn't yet, so standby FM identifies its switches once.
+	 */
+	fn managed_components(ctx: &CxlFmContext, request: CxlFmRequest) -> CxlFmRequest {
+		if lock_shared(&ctx.topology).nodes.is_empty() {
+			let _operation = ctx.lock_operation();
+
+			if let Err(e) = discover_fabric(ctx, false) {
+				println!("Failed to discover fabric: {}", e);
+			}
+		}
+
+		let topology = lock_shared(&ctx.topology);
+		let names = |is_kind: fn(u8) -> bool| topology.nodes.iter()
+			.filter(|node| is_kind(node.kind))
+			.map(|node| node.name.clone())
+			.collect::<Vec<String>>()
+			.join(",");
+
+		let ccis = lock_shared(&ctx.devices)
+			.iter()
+			.map(|cci| cci.name())
+			.collect::<Vec<String>>()
+			.join(",");
+
+		let role = match ctx.is_standby() {
+			true => CXL_FM_ROLE_STANDBY,
+			false => CXL_FM_ROLE_ACTIVE,
+		};
+
+		request.arg(CXL_FM_SWITCHES_ARG, &names(|kind| kind == CXL_NODE_SWITCH))
+			.arg(CXL_FM_DEVICES_ARG, &names(is_device_kind))
+			.arg(CXL_FM_CCIS_ARG, &ccis)
+			.arg(CXL_FM_ROLE_ARG, fm_role_name(role))
+	}
+
+	/*
+	 * Connect to orchestrator or another FM. Peer that doesn't
+	 * answer within the timeout (e.g. it is paused) must not
+	 * stop the heartbeats.
+	 */
+	pub fn connect(address: &str, timeout: Duration) -> Result<TcpStream, CxlFmError> {
+		let socket = address.to_socket_addrs()
+			.map_err(|e| CxlFmError::Io(e.to_string()))?
+			.next()
+			.ok_or(CxlFmError::NotFound(String::from(address)))?;
+		let stream = TcpStream::connect_timeout(&socket, timeout)
+			.map_err(|e| CxlFmError::Io(e.to_string()))?;
+
+		stream.set_read_timeout(Some(timeout))
+			.map_err(|e| CxlFmError::Io(e.to_string()))?;
+
+		Ok(stream)
+	}
+
+	/*
+	 * Send request to orchestrator and get role of FM.
+	 * Orchestrator has to answer within half of
+	 * the heartbeat interval.
+	 */
+	fn send(registration: &CxlFmRegistration, request: &CxlFmRequest,
+		env: &CxlFmOptions) -> Result<CxlFmRole, CxlFmError> {
+		let stream = connect(&env.ip_port, Duration::from_secs(registration.interval) / 2)?;
+		let data = from_hex(&send_request(&stream, request, env)?)?;
+
+		CxlFmRole::from_bytes(&data)
+	}
+
+	/*
+	 * Build registration or announcement of FM
+	 */
+	fn registration_request(command: &str, ctx: &CxlFmContext,
+				registration: &CxlFmRegistration, uptime: u64) -> CxlFmRequest {
+		let request = CxlFmRequest::new(command)
+			.arg(CXL_FM_ID_ARG, &registration.id)
+			.arg(CXL_FM_ADDRESS_ARG, &registration.address)
+			.arg(CXL_FM_VERSION_ARG, &registration.version)
+			.arg(CXL_FM_UPTIME_ARG, &uptime.to_string())
+			.arg(CXL_FM_INTERVAL_ARG, &registration.interval.to_string());
+
+		managed_components(ctx, request)
+	}
+
+	/*
+	 * Register FM in orchestrator
+	 */
+	fn register(ctx: &CxlFmContext, registration: &CxlFmRegistration,
+		    uptime: u64, env: &CxlFmOptions) -> Result<CxlFmRole, CxlFmError> {
+		let request = registration_request(CXL_FM_REGISTER_FM_COMMAND, ctx,
+						   registration, uptime);
+
+		send(registration, &request, env)
+	}
+
+	/*
+	 * Send heartbeat to orchestrator
+	 */
+	fn heartbeat(ctx: &CxlFmContext, registration: &CxlFmRegistration,
+		     uptime: u64, env: &CxlFmOptions) -> Result<CxlFmRole, CxlFmError> {
+		let request = CxlFmRequest::new(CXL_FM_HEARTBEAT_FM_COMMAND)
+			.arg(CXL_FM_ID_ARG, &registration.id)
+			.arg(CXL_FM_UPTIME_ARG, &uptime.to_string());
+		let request = managed_components(ctx, request);
+
+		send(registration, &request, env)
+	}
+
+	/*
+	 * Open socket of announcements if FM announces itself
+	 */
+	fn announcer(registration: &CxlFmRegistration) -> Option<(UdpSocket, SocketAddrV4)> {
+		let group = registration.group?;
+
+		match announce_socket(&registration.interface) {
+			Ok(socket) => Some((socket, group)),
+			Err(e) => {
+				println!("Failed to open socket of announcements: {}", e);
+				None
+			},
+		}
+	}
+
+	/*
+	 * Register FM and send heartbeats every interval.
+	 * FM that is not known to orchestrator (it was restarted
+	 * or it declared FM lost) registers again. FM follows
+	 * the role in the orchestrator's answer, active FM
+	 * steps down when its lease expires. FM that announces
+	 * itself sends announcement into multicast group
+	 * every interval too.
+	 */
+	pub fn start_heartbeat(ctx: Arc<CxlFmContext>, registration: CxlFmRegistration,
+			       env: &CxlFmOptions) {
+		let env = CxlFmOptions {
+			ip_port: registration.orchestrator.clone().unwrap_or_default(),
+			..env.clone()
+		};
+		let announcer = announcer(&registration);
+
+		thread::spawn(move || {
+			let started = Instant::now();
+			let interval = Duration::from_secs(registration.interval);
+			let mut replicated = Vec::new();
+			let mut is_registered = false;
+			let mut is_reachable = true;
+			let mut is_announced = true;
+
+			loop {
+				let uptime = started.elapsed().as_secs();
+
+				if let Some((socket, group)) = &announcer {
+					let request = registration_request(CXL_FM_ANNOUNCE_FM_COMMAND, &ctx,
+									   &registration, uptime);
+
+					match announce(socket, group, &request) {
+						Ok(_) => is_announced = true,
+						Err(e) => {
+							if is_announced {
+								println!("Failed to announce FM into {}: {}", group, e);
+							}
+
+							is_announced = false;
+						},
+					}
+				}
+
+				if registration.orchestrator.is_none() {
+					thread::sleep(interval);
+					continue;
+				}
+
+				let result = match is_registered {
+					true => heartbeat(&ctx, &registration, uptime, &env)
+						.or_else(|e| match e {
+							CxlFmError::Remote(_) => {
+								register(&ctx, &registration, uptime, &env)
+							},
+							e => Err(e),
+						}),
+					false => register(&ctx, &registration, uptime, &env),
+				};
+
+				match result {
+					Ok(role) => {
+						if !is_registered || !is_reachable {
+							println!("Registered in orchestrator {} as {}",
+								 env.ip_port, registration.id);
+						}
+
+						is_registered = true;
+						is_reachable = true;
+						follow_role(&ctx, &role, interval, &mut replicated, &env);
+					},
+					Err(e) => {
+						if is_reachable {
+							println!("Failed to report to orchestrator {}: {}",
+								 env.ip_port, e);
+						}
+
+						is_reachable = false;
+
+						if ctx.is_standby() {
+							demote(&ctx, "orchestrator is unreachable");
+						}
+					},
+				}
+
+				thread::sleep(interval);
+			}
+		});
+	}
+}
@@ -0,0 +1,694 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+
+pub mod cxl_fm_hot_plug {
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CXL_FM_PORT_ARG;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_fm_lib::CXL_FM_OPERATION_ARG;
+	use fm_library::cxl_fm_api::CXL_FM_API_PHYSICAL_PORT_CONTROL;
+	use fm_library::cxl_fm_api::CXL_FM_API_IDENTIFY_SWITCH_DEVICE;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_VCS_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_UNBIND_VPPB;
+	use fm_library::cxl_switch_lib::CxlPhysicalPortControlRequest;
+	use fm_library::cxl_switch_lib::CxlIdentifySwitchResponse;
+	use fm_library::cxl_switch_lib::CxlGetVcsInfoRequest;
+	use fm_library::cxl_switch_lib::CxlGetVcsInfoResponse;
+	use fm_library::cxl_switch_lib::CxlUnbindVppbRequest;
+	use fm_library::cxl_switch_lib::bitmask_bits;
+	use fm_library::cxl_switch_lib::unbind_option_name;
+	use fm_library::cxl_switch_lib::port_control_name;
+	use fm_library::cxl_switch_lib::port_state_name;
+	use fm_library::cxl_switch_lib::port_device_name;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_switch_lib::slot_operation_name;
+	use fm_library::cxl_switch_lib::CXL_PORT_STATE_DSP;
+	use fm_library::cxl_switch_lib::CXL_PORT_DEVICE_NONE;
+	use fm_library::cxl_switch_lib::CXL_PORT_CONTROL_ASSERT_PERST;
+	use fm_library::cxl_switch_lib::CXL_PORT_CONTROL_DEASSERT_PERST;
+	use fm_library::cxl_switch_lib::CXL_PORT_LINK_PERST;
+	use fm_library::cxl_switch_lib::CXL_VPPB_BOUND_PORT;
+	use fm_library::cxl_switch_lib::CXL_VPPB_BOUND_LD;
+	use fm_library::cxl_switch_lib::CXL_UNBIND_MANAGED_HOT_REMOVE;
+	use fm_library::cxl_switch_lib::CXL_UNBIND_SURPRISE_HOT_REMOVE;
+	use fm_library::cxl_desired_state::CxlFabricSpec;
+	use fm_library::cxl_desired_state::CxlBindingSpec;
+	use fm_library::cxl_desired_state::CxlFabricPlan;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_DONE;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_FAILED;
+	use fm_library::cxl_topology::FabricTopology;
+	use fm_library::cxl_topology::CxlTopologyNode;
+	use fm_library::cxl_topology::node_kind_name;
+	use fm_library::cxl_topology::CXL_NODE_PHYSICAL_PORT;
+	use fm_library::cxl_topology::CXL_NODE_VPPB;
+	use fm_library::cxl_topology::CXL_EDGE_CONTAINS;
+	use fm_library::cxl_topology::CXL_EDGE_LINK;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_WARNING;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_FAILURE;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_HOT_PLUG;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_LINK;
+	use fm_library::cxl_fm_event::CXL_SWITCH_EVENT_SLOT_STATUS;
+	use fm_library::cxl_fm_event::CXL_SWITCH_EVENT_LINK_STATE;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_context::CxlCciPath;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_state_store::CxlFmLostPort;
+	use crate::cxl_fm_state_store::CxlFmLostBinding;
+	use crate::cxl_fm_discover_command::discover_fabric;
+	use crate::cxl_fm_plan::CxlPlanEntry;
+	use crate::cxl_fm_plan::CxlPlanAction;
+	use crate::cxl_fm_plan::plan_step;
+	use crate::cxl_fm_plan::switch_arg;
+	use crate::cxl_fm_plan::switch_cci;
+	use crate::cxl_fm_plan::vppb_ids;
+	use crate::cxl_fm_plan::find_vppb;
+	use crate::cxl_fm_plan::bound_target;
+	use crate::cxl_fm_plan::target_bindings;
+	use crate::cxl_fm_plan::unbind_entry;
+	use crate::cxl_fm_plan::execute_plan;
+	use crate::cxl_fm_plan::run_plan;
+	use crate::cxl_desired_state_command::build_plan;
+
+	/*
+	 * Get device linked to downstream port
+	 */
+	fn linked_device<'a>(topology: &'a FabricTopology,
+			     port: &CxlTopologyNode) -> Option<&'a CxlTopologyNode> {
+		topology.children(port.id, CXL_EDGE_LINK)
+			.first()
+			.and_then(|device| topology.node(*device))
+	}
+
+	/*
+	 * Plan managed removal of the device attached to downstream
+	 * port: vPPBs bound to the port or its LDs are unbound in order
+	 * with managed hot-remove semantics, then the slot is powered
+	 * off by PERST#. Port that is already prepared needs no steps.
+	 */
+	fn remove_plan(request: &CxlFmRequest,
+		       ctx: &CxlFmContext) -> Result<(String, Vec<CxlPlanEntry>), CxlFmError> {
+		let switch = switch_arg(request)?;
+		let port = request.get_number::<u8>(CXL_FM_PORT_ARG)?;
+		let port_name = format!("{}/port{}", switch, port);
+
+		let topology = discover_fabric(ctx, false)?;
+		let cci = switch_cci(ctx, &topology, switch)?;
+		let node = topology.find(CXL_NODE_PHYSICAL_PORT, &port_name)
+			.ok_or_else(|| CxlFmError::NotFound(format!("port {}", port_name)))?;
+
+		if node.get("state") != Some(port_state_name(CXL_PORT_STATE_DSP)) {
+			return Err(CxlFmError::InvalidInput(format!("{} is not downstream port",
+								    port_name)));
+		}
+
+		let device = match linked_device(&topology, node) {
+			Some(device) => device.name.clone(),
+			None if ctx.is_unavailable(&port_name) => return Ok((port_name, Vec::new())),
+			None => return Err(CxlFmError::InvalidInput(format!("no device at {}", port_name))),
+		};
+
+		let mut vppbs: Vec<(u8, u8)> = target_bindings(&topology, &port_name).iter()
+			.filter_map(|vppb| topology.find(CXL_NODE_VPPB, vppb))
+			.filter_map(vppb_ids)
+			.collect();
+		let mut entries = Vec::new();
+
+		vppbs.sort();
+
+		for (vcs, vppb) in vppbs {
+			entries.push(unbind_entry(ctx, &topology, switch, vcs, vppb,
+						  CXL_UNBIND_MANAGED_HOT_REMOVE)?);
+		}
+
+		let fm_api_request = CxlPhysicalPortControlRequest {
+			ppb_id: port,
+			operation: CXL_PORT_CONTROL_ASSERT_PERST,
+		};
+
+		entries.push((plan_step(CXL_FM_API_PHYSICAL_PORT_CONTROL, switch,
+					format!("{} of {}, power off {}",
+						port_control_name(CXL_PORT_CONTROL_ASSERT_PERST),
+						port_name, device)),
+			      CxlPlanAction::Command(CxlCciPath::Direct(cci),
+						     CXL_FM_API_PHYSICAL_PORT_CONTROL,
+						     fm_api_request.to_bytes())));
+
+		Ok((port_name, entries))
+	}
+
+	/*
+	 * Prepare device for managed removal. Resources of the port
+	 * become unavailable when all the steps are done.
+	 */
+	pub fn remove_port(request: &CxlFmRequest,
+			   ctx: &CxlFmContext) -> Result<CxlFabricPlan, CxlFmError> {
+		let (port_name, entries) = remove_plan(request, ctx)?;
+		let plan = run_plan(request, ctx, entries)?;
+
+		if !request.is_dry_run() &&
+		   plan.steps.iter().all(|step| step.status == CXL_PLAN_STEP_DONE) &&
+		   ctx.set_unavailable(&port_name, true) {
+			ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_HOT_PLUG, &port_name,
+					 "device is ready for removal");
+		}
+
+		Ok(plan)
+	}
+
+	/*
+	 * Insert device into the slot of emulated switch or pull it out
+	 */
+	#[cfg(feature = "emulation")]
+	pub fn emulate_hot_plug(request: &CxlFmRequest, ctx: &CxlFmContext) -> Result<(), CxlFmError> {
+		let switch = switch_arg(request)?;
+		let port = request.get_number::<u8>(CXL_FM_PORT_ARG)?;
+		let operation = request.get_number::<u8>(CXL_FM_OPERATION_ARG)?;
+		let cci = ctx.device_index(switch)
+			.ok_or_else(|| CxlFmError::NotFound(format!("switch {}", switch)))?;
+
+		if slot_operation_name(operation) == "unknown" {
+			return Err(CxlFmError::InvalidInput(format!("unknown slot operation: {}",
+								    operation)));
+		}
+
+		ctx.hot_plug(cci, port, operation)
+	}
+
+	/*
+	 * Get unavailable or lost downstream port
+	 * the resources of desired state depend on
+	 */
+	pub fn unavailable_port(ctx: &CxlFmContext, resources: &CxlFabricSpec) -> Option<String> {
+		let mut unavailable: Vec<String> = lock_shared(&ctx.unavailable).iter().cloned().collect();
+		let mut objects: Vec<String> = resources.bindings.iter()
+			.filter_map(|binding| binding.port.map(|port| format!("{}/port{}",
+									      binding.switch, port)))
+			.collect();
+
+		objects.extend(resources.allocations.iter().map(|allocation| allocation.device.clone()));
+		objects.extend(resources.qos.iter().map(|qos| qos.device.clone()));
+		objects.extend(resources.capacity.iter().map(|capacity| capacity.device.clone()));
+		unavailable.extend(lock_shared(&ctx.lost).keys().cloned());
+
+		objects.iter()
+			.find_map(|object| unavailable.iter()
+				  .find(|port| object == *port || object.starts_with(&format!("{}/", port))))
+			.cloned()
+	}
+
+	/*
+	 * Get resources of desired state that belong to hot-added
+	 * device: LD allocations, QoS and Dynamic Capacity of the device
+	 * and vPPB bindings of the port. Resources under manual control
+	 * are left to the administrator.
+	 */
+	fn device_resources(ctx: &CxlFmContext, switch: &str, port: u8, device: &str) -> CxlFabricSpec {
+		let mut resources = CxlFabricSpec::default();
+		let spec = match lock_shared(&ctx.desired_state).clone() {
+			Some(spec) => spec,
+			None => return resources,
+		};
+
+		for (_, is_manual, resource) in spec.resources() {
+			let is_bound_here = resource.bindings.iter()
+				.any(|binding| binding.switch == switch && binding.port == Some(port));
+			let is_device = resource.allocations.iter().any(|allocation| allocation.device == device) ||
+					resource.qos.iter().any(|qos| qos.device == device) ||
+					resource.capacity.iter().any(|capacity| capacity.device == device);
+
+			if !is_manual && (is_bound_here || is_device) {
+				resources.merge(resource);
+			}
+		}
+
+		resources
+	}
+
+	/*
+	 * Report done and failed steps of the plan as events
+	 */
+	fn report_steps(ctx: &CxlFmContext, plan: &CxlFabricPlan) {
+		for step in &plan.steps {
+			match step.status {
+				CXL_PLAN_STEP_DONE => {
+					ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_HOT_PLUG,
+							 &step.target, &step.description);
+				},
+				CXL_PLAN_STEP_FAILED => {
+					ctx.report_event(CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_HOT_PLUG,
+							 &step.target,
+							 &format!("{}: {}", step.description, step.error));
+				},
+				_ => {},
+			}
+		}
+	}
+
+	/*
+	 * Bring resources of hot-added device to the state
+	 */
+	fn apply_resources(ctx: &CxlFmContext, topology: &FabricTopology,
+			   resources: &CxlFabricSpec) -> Result<(), CxlFmError> {
+		let plan = execute_plan(ctx, build_plan(ctx, topology, resources)?);
+
+		report_steps(ctx, &plan);
+
+		if plan.steps.iter().any(|step| step.status == CXL_PLAN_STEP_DONE) {
+			discover_fabric(ctx, false)?;
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Get name of the object lost binding was bound to
+	 */
+	fn lost_target(lost: &CxlFmLostPort, binding: &CxlFmLostBinding) -> String {
+		match binding.ld {
+			Some(ld) => format!("{}/ld{}", lost.device, ld),
+			None => lost.port.clone(),
+		}
+	}
+
+	/*
+	 * Add bindings lost with the device to the resources
+	 * of returned device. Desired state of vPPB wins,
+	 * vPPBs that are bound again by administrator are left
+	 * as they are. Lost resources of another device are dropped.
+	 */
+	fn restore_lost(ctx: &CxlFmContext, topology: &FabricTopology, lost: CxlFmLostPort,
+			device: &CxlTopologyNode, switch: &str, port: u8,
+			resources: &mut CxlFabricSpec) {
+		let serial = device.get("serial").unwrap_or("unknown");
+
+		if !lost.serial.is_empty() && lost.serial != serial {
+			ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_HOT_PLUG, &device.name,
+					 &format!("device (serial {}) replaces lost one (serial {}), lost resources are dropped",
+						  serial, lost.serial));
+			return;
+		}
+
+		let mut restored = Vec::new();
+
+		for binding in &lost.bindings {
+			let is_desired = resources.bindings.iter()
+				.any(|desired| desired.switch == switch &&
+					       desired.vcs == binding.vcs &&
+					       desired.vppb == binding.vppb);
+			let is_bound = find_vppb(topology, switch, binding.vcs, binding.vppb)
+				.map_or(true, |vppb| bound_target(topology, vppb).is_some());
+
+			if is_desired || is_bound {
+				continue;
+			}
+
+			resources.bindings.push(CxlBindingSpec {
+				switch: String::from(switch),
+				vcs: binding.vcs,
+				vppb: binding.vppb,
+				port: Some(port),
+				ld: binding.ld,
+				manual: false,
+			});
+			restored.push(format!("{}/vcs{}/vppb{} to {}", switch, binding.vcs, binding.vppb,
+					      lost_target(&lost, binding)));
+		}
+
+		let extents: usize = lost.extents.iter().map(|(_, extents)| extents.len()).sum();
+		let mut message = match restored.is_empty() {
+			true => String::from("device is back"),
+			false => format!("device is back, restore binding of {}", restored.join(", ")),
+		};
+
+		if extents != 0 {
+			message.push_str(&format!("; {} lost DC extent(s) are restored by desired state only",
+						  extents));
+		}
+
+		ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_HOT_PLUG, &lost.port, &message);
+	}
+
+	/*
+	 * Handle device inserted into downstream port or device whose
+	 * link is up again: slot powered off by managed removal is powered
+	 * on, the device is identified and added to the inventory, then
+	 * lost bindings and desired state are applied to it
+	 */
+	fn hot_add(ctx: &CxlFmContext, switch: &str, port: u8) -> Result<(), CxlFmError> {
+		let port_name = format!("{}/port{}", switch, port);
+
+		if ctx.set_unavailable(&port_name, false) {
+			let topology = lock_shared(&ctx.topology).clone();
+			let fm_api_request = CxlPhysicalPortControlRequest {
+				ppb_id: port,
+				operation: CXL_PORT_CONTROL_DEASSERT_PERST,
+			};
+
+			ctx.execute(switch_cci(ctx, &topology, switch)?, CXL_FM_API_PHYSICAL_PORT_CONTROL,
+				    &fm_api_request.to_bytes())?;
+		}
+
+		let topology = discover_fabric(ctx, false)?;
+		let device = topology.find(CXL_NODE_PHYSICAL_PORT, &port_name)
+			.and_then(|node| linked_device(&topology, node));
+
+		let device = match device {
+			Some(device) => device,
+			None => {
+				ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_HOT_PLUG, &port_name,
+						 "device is inserted, but the link is down");
+				return Ok(());
+			},
+		};
+
+		ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_HOT_PLUG, &device.name,
+				 &format!("{} (serial {}) is identified", node_kind_name(device.kind),
+					  device.get("serial").unwrap_or("unknown")));
+
+		let mut resources = device_resources(ctx, switch, port, &device.name);
+
+		if let Some(lost) = ctx.take_lost(&port_name) {
+			restore_lost(ctx, &topology, lost, device, switch, port, &mut resources);
+		}
+
+		apply_resources(ctx, &topology, &resources)
+	}
+
+	/*
+	 * Read vPPB bindings of the port and LDs of its device from
+	 * the switch. Topology doesn't show LDs of the device that is gone.
+	 */
+	fn port_bindings(ctx: &CxlFmContext, cci: usize,
+			 port: u8) -> Result<Vec<CxlFmLostBinding>, CxlFmError> {
+		let data = ctx.execute(cci, CXL_FM_API_IDENTIFY_SWITCH_DEVICE, &[])?;
+		let info = CxlIdentifySwitchResponse::from_bytes(&data)?;
+		let fm_api_request = CxlGetVcsInfoRequest {
+			start_vppb: 0,
+			vppb_list_limit: u8::MAX,
+			vcs_ids: bitmask_bits(&info.active_vcs),
+		};
+		let data = ctx.execute(cci, CXL_FM_API_GET_VCS_INFO, &fm_api_request.to_bytes())?;
+		let responce = CxlGetVcsInfoResponse::from_bytes(&fm_api_request, &data)?;
+		let mut bindings = Vec::new();
+
+		for vcs in responce.vcs {
+			for (index, vppb) in vcs.vppbs.iter().enumerate() {
+				let ld = match vppb.binding_status {
+					CXL_VPPB_BOUND_PORT => None,
+					CXL_VPPB_BOUND_LD => Some(vppb.bound_ld as u16),
+					_ => continue,
+				};
+
+				if vppb.bound_port == port {
+					bindings.push(CxlFmLostBinding {
+						vcs: vcs.vcs_id,
+						vppb: index as u8,
+						ld,
+					});
+				}
+			}
+		}
+
+		Ok(bindings)
+	}
+
+	/*
+	 * Describe lost resources and hosts that used them
+	 */
+	fn describe_lost(lost: &CxlFmLostPort, switch: &str) -> String {
+		let mut objects: Vec<String> = lost.bindings.iter()
+			.map(|binding| lost_target(lost, binding))
+			.collect();
+		let mut hosts: Vec<String> = lost.bindings.iter()
+			.map(|binding| format!("{}/vcs{}", switch, binding.vcs))
+			.collect();
+
+		for (host_id, extents) in &lost.extents {
+			objects.push(format!("{} DC extent(s) of host{}", extents.len(), host_id));
+			hosts.push(format!("host{}", host_id));
+		}
+
+		objects.sort();
+		objects.dedup();
+		hosts.sort();
+		hosts.dedup();
+
+		let list = |items: Vec<String>| match items.is_empty() {
+			true => String::from("none"),
+			false => items.join(", "),
+		};
+
+		format!("{} (serial {}) is lost: lost {}; impacted hosts: {}",
+			lost.device,
+			if lost.serial.is_empty() { "unknown" } else { &lost.serial },
+			list(objects), list(hosts))
+	}
+
+	/*
+	 * Handle surprise removal or link failure of device attached
+	 * to downstream port. Bindings of the port and LDs of the device
+	 * and DC extents granted by the device are recorded as lost
+	 * before the vPPBs are unbound with surprise hot-remove semantics,
+	 * so the device gets them back even after restart of the daemon.
+	 * Subscribers are notified with the hosts that have lost memory.
+	 * Returns false if nothing was attached to the port.
+	 */
+	fn surprise_remove(ctx: &CxlFmContext, previous: &FabricTopology,
+			   switch: &str, port: u8, is_present: bool) -> Result<bool, CxlFmError> {
+		let port_name = format!("{}/port{}", switch, port);
+		let topology = lock_shared(&ctx.topology).clone();
+		let cci = switch_cci(ctx, &topology, switch)?;
+		let bindings = port_bindings(ctx, cci, port)?;
+		let device = previous.find(CXL_NODE_PHYSICAL_PORT, &port_name)
+			.and_then(|node| linked_device(previous, node));
+
+		if device.is_none() && bindings.is_empty() {
+			return Ok(ctx.is_lost(&port_name));
+		}
+
+		let mut lost = lock_shared(&ctx.lost).get(&port_name).cloned()
+			.unwrap_or_else(|| CxlFmLostPort {
+				port: port_name.clone(),
+				device: format!("{}/dev", port_name),
+				..Default::default()
+			});
+
+		if let Some(device) = device {
+			lost.device = device.name.clone();
+			lost.serial = String::from(device.get("serial").unwrap_or_default());
+
+			if let Some(index) = device.get("cci").and_then(|cci| ctx.device_index(cci)) {
+				let mut extents: Vec<(u16, Vec<_>)> = lock_shared(&ctx.dc_extents).iter()
+					.filter(|((device, _), extents)| *device == index && !extents.is_empty())
+					.map(|((_, host_id), extents)| (*host_id, extents.clone()))
+					.collect();
+
+				extents.sort_by_key(|(host_id, _)| *host_id);
+				lost.extents = extents;
+			}
+		}
+
+		for binding in &bindings {
+			if !lost.bindings.contains(binding) {
+				lost.bindings.push(binding.clone());
+			}
+		}
+
+		ctx.set_lost(lost.clone());
+
+		let (event_type, cause) = match is_present {
+			true => (CXL_FM_EVENT_LINK, "link failure"),
+			false => (CXL_FM_EVENT_HOT_PLUG, "surprise removal"),
+		};
+
+		ctx.report_event(CXL_EVENT_SEVERITY_FAILURE, event_type, &port_name,
+				 &format!("{}: {}", cause, describe_lost(&lost, switch)));
+
+		let entries: Vec<CxlPlanEntry> = bindings.iter()
+			.map(|binding| {
+				let fm_api_request = CxlUnbindVppbRequest {
+					vcs_id: binding.vcs,
+					vppb_id: binding.vppb,
+					option: CXL_UNBIND_SURPRISE_HOT_REMOVE,
+				};
+
+				(plan_step(CXL_FM_API_UNBIND_VPPB, switch,
+					   format!("unbind {}/vcs{}/vppb{} from {} ({})", switch,
+						   binding.vcs, binding.vppb, lost_target(&lost, binding),
+						   unbind_option_name(CXL_UNBIND_SURPRISE_HOT_REMOVE))),
+				 CxlPlanAction::Command(CxlCciPath::Direct(cci), CXL_FM_API_UNBIND_VPPB,
+							fm_api_request.to_bytes()))
+			})
+			.collect();
+		let plan = execute_plan(ctx, entries);
+
+		report_steps(ctx, &plan);
+
+		if plan.steps.iter().any(|step| step.status == CXL_PLAN_STEP_DONE) {
+			discover_fabric(ctx, false)?;
+		}
+
+		Ok(true)
+	}
+
+	/*
+	 * Is PERST# of the port asserted by FM?
+	 */
+	fn is_in_reset(ctx: &CxlFmContext, topology: &FabricTopology, switch: &str, port: u8) -> bool {
+		switch_cci(ctx, topology, switch).ok()
+			.and_then(|cci| lock_shared(&ctx.port_states)
+				  .get(&(cci, port))
+				  .map(|info| info.link_state_flags & CXL_PORT_LINK_PERST != 0))
+			.unwrap_or(false)
+	}
+
+	/*
+	 * Handle state change of downstream port. Port prepared for
+	 * managed removal follows its slot only. Device of the port
+	 * with lost resources gets them back when its link is up.
+	 * Link that goes down without PERST# asserted by FM means
+	 * surprise removal or link failure of the device.
+	 */
+	fn handle_port(ctx: &CxlFmContext, previous: &FabricTopology, topology: &FabricTopology,
+		       switch: &str, port: u8, is_slot_event: bool) -> Result<(), CxlFmError> {
+		let port_name = format!("{}/port{}", switch, port);
+		let node = match topology.find(CXL_NODE_PHYSICAL_PORT, &port_name) {
+			Some(node) => node,
+			None => return Ok(()),
+		};
+		let is_present = node.get("device") != Some(port_device_name(CXL_PORT_DEVICE_NONE));
+		let is_linked = linked_device(topology, node).is_some();
+
+		if ctx.is_unavailable(&port_name) {
+			match (is_slot_event, is_present) {
+				(true, true) => hot_add(ctx, switch, port)?,
+				(true, false) => {
+					ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_HOT_PLUG, &port_name,
+							 "managed removal is complete");
+				},
+				_ => {},
+			}
+
+			return Ok(());
+		}
+
+		if is_linked {
+			if is_slot_event || ctx.is_lost(&port_name) {
+				hot_add(ctx, switch, port)?;
+			}
+
+			return Ok(());
+		}
+
+		if is_in_reset(ctx, topology, switch, port) {
+			return Ok(());
+		}
+
+		let is_lost = surprise_remove(ctx, previous, switch, port, is_present)?;
+
+		if is_slot_event && is_present && !is_lost {
+			ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_HOT_PLUG, &port_name,
+					 "device is inserted, but the link is down");
+		}
+
+		Ok(())
+	}
+
+	/*
+	 * Handle port state change notifications of switches:
+	 * (switch, port, event type) of Physical Switch Event Records.
+	 * Every port is handled once by the current state of its slot
+	 * and link, the topology before the change shows the device
+	 * that could be lost.
+	 */
+	pub fn handle_port_events(ctx: &CxlFmContext, events: &[(String, u8, u8)]) {
+		let mut ports: Vec<(String, u8)> = events.iter()
+			.map(|(switch, port, _)| (switch.clone(), *port))
+			.collect();
+
+		ports.sort();
+		ports.dedup();
+
+		if ports.is_empty() {
+			return;
+		}
+
+		let previous = lock_shared(&ctx.topology).clone();
+		let topology = match discover_fabric(ctx, false) {
+			Ok(topology) => topology,
+			Err(e) => {
+				println!("Failed to handle port events: {}", e);
+				return;
+			},
+		};
+
+		for (switch, port) in ports {
+			let is_slot_event = events.iter()
+				.any(|event| event.0 == switch && event.1 == port &&
+					     event.2 == CXL_SWITCH_EVENT_SLOT_STATUS);
+
+			if let Err(e) = handle_port(ctx, &previous, &topology, &switch, port, is_slot_event) {
+				ctx.report_event(CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_HOT_PLUG,
+						 &format!("{}/port{}", switch, port),
+						 &format!("cannot handle port state change: {}", e));
+			}
+		}
+	}
+
+	/*
+	 * Revalidate downstream ports on startup. Device found at
+	 * unavailable port was inserted while the daemon was stopped,
+	 * it is hot-added. Device of the port with lost resources
+	 * gets them back, vPPBs left bound to the port without device
+	 * are unbound.
+	 */
+	pub fn revalidate_ports(ctx: &CxlFmContext) {
+		let topology = lock_shared(&ctx.topology).clone();
+		let mut ports = Vec::new();
+
+		for node in topology.nodes_of_kind(CXL_NODE_PHYSICAL_PORT) {
+			if node.get("state") != Some(port_state_name(CXL_PORT_STATE_DSP)) {
+				continue;
+			}
+
+			let is_linked = linked_device(&topology, node).is_some();
+			let event_type = match (ctx.is_unavailable(&node.name), is_linked) {
+				(true, true) => CXL_SWITCH_EVENT_SLOT_STATUS,
+				(true, false) => continue,
+				(false, _) => CXL_SWITCH_EVENT_LINK_STATE,
+			};
+
+			let switch = topology.parent(node.id, CXL_EDGE_CONTAINS)
+				.and_then(|switch| topology.node(switch))
+				.map(|switch| switch.name.clone());
+			let port = node.get("id").and_then(|id| id.parse::<u8>().ok());
+
+			if let (Some(switch), Some(port)) = (switch, port) {
+				ports.push((switch, port, event_type));
+			}
+		}
+
+		handle_port_events(ctx, &ports);
+	}
+}
@@ -38,27 +38,57 @@ pub mod cxl_logical_device_command {
 	use fm_library::cxl_fm_lib::CXL_FM_SET_QOS_BANDWIDTH_LIMIT_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_LD_ERASE;
 	use fm_library::cxl_fm_lib::CXL_FM_NO_DATA;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CXL_FM_LD_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_RANGE1_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_RANGE2_ARG;
+	use fm_library::cxl_fm_api::CxlCciMessage;
+	use fm_library::cxl_fm_api::CXL_CCI_MESSAGE_REQUEST;
+	use fm_library::cxl_fm_api::CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND;
+	use fm_library::cxl_fm_api::CXL_MEMDEV_SECURE_ERASE;
+	use fm_library::cxl_switch_lib::CxlTunnelRequest;
+	use fm_library::cxl_desired_state::CxlFabricSpec;
+	use fm_library::cxl_desired_state::CxlAllocationSpec;
+	use fm_library::cxl_topology::is_device_kind;
+	use fm_library::cxl_topology::CXL_EDGE_LINK;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_plan::CxlPlanEntry;
+	use crate::cxl_fm_plan::CxlPlanAction;
+	use crate::cxl_fm_plan::plan_step;
+	use crate::cxl_fm_plan::device_arg;
+	use crate::cxl_fm_plan::target_bindings;
+	use crate::cxl_fm_plan::send_plan;
+	use crate::cxl_ppb_command::bind_plan;
+	use crate::cxl_ppb_command::unbind_plan;
+	use crate::cxl_desired_state_command::plan_allocations;
+	use crate::cxl_fm_discover_command::discover_fabric;
 
 	/*
 	 * Bind Logical Device (LD)
 	 */
-	pub fn bind(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn bind(stream: &TcpStream, request: &CxlFmRequest,
+		    ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_BIND_LD_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		let entries = request.get_number::<u16>(CXL_FM_LD_ARG)
+			.and_then(|_| bind_plan(request, ctx));
+
+		send_plan(stream, request, ctx, entries, env)
 	}
 
 	/*
 	 * Unbind Logical Device (LD)
 	 */
-	pub fn unbind(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn unbind(stream: &TcpStream, request: &CxlFmRequest,
+		      ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_UNBIND_LD_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_plan(stream, request, ctx, unbind_plan(request, ctx), env)
 	}
 
 	/*
@@ -94,15 +124,36 @@ pub mod cxl_logical_device_command {
 		send_responce(stream, CXL_FM_NO_DATA, env);
 	}
 
+	/*
+	 * Plan memory allocation change of one LD
+	 */
+	pub fn allocation_plan(request: &CxlFmRequest,
+			       ctx: &CxlFmContext) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let topology = discover_fabric(ctx, false)?;
+		let spec = CxlFabricSpec {
+			allocations: vec![CxlAllocationSpec {
+				device: device_arg(request, ctx, &topology)?,
+				ld: request.get_number::<u8>(CXL_FM_LD_ARG)?,
+				range1: request.get_number::<u64>(CXL_FM_RANGE1_ARG)?,
+				range2: request.get_number_or::<u64>(CXL_FM_RANGE2_ARG, 0)?,
+				manual: false,
+			}],
+			..Default::default()
+		};
+
+		plan_allocations(ctx, &topology, &spec)
+	}
+
 	/*
 	 * Set Logical Device (LD) allocations
 	 */
-	pub fn set_allocation(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn set_allocation(stream: &TcpStream, request: &CxlFmRequest,
+			      ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_SET_LD_ALLOCATION_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_plan(stream, request, ctx, allocation_plan(request, ctx), env)
 	}
 
 	/*
@@ -182,14 +233,74 @@ pub mod cxl_logical_device_command {
 		send_responce(stream, CXL_FM_NO_DATA, env);
 	}
 
+	/*
+	 * Plan Secure Erase of LD. LD of MLD is reached by
+	 * tunneling, SLD executes the command itself. LD has
+	 * to be unbound from every vPPB.
+	 */
+	fn erase_plan(request: &CxlFmRequest,
+		      ctx: &CxlFmContext) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let topology = discover_fabric(ctx, false)?;
+		let device = device_arg(request, ctx, &topology)?;
+		let ld = request.get_number_or::<u8>(CXL_FM_LD_ARG, 0)?;
+		let node = topology.nodes.iter()
+			.find(|node| is_device_kind(node.kind) && node.name == device)
+			.ok_or_else(|| CxlFmError::NotFound(format!("device {}", device)))?;
+		let lds = node.get("lds")
+			.and_then(|lds| lds.parse::<u16>().ok())
+			.unwrap_or(1);
+
+		if ld as u16 >= lds {
+			return Err(CxlFmError::InvalidInput(format!("{} has no LD {}", device, ld)));
+		}
+
+		let target = if lds > 1 {
+			format!("{}/ld{}", device, ld)
+		} else {
+			topology.parent(node.id, CXL_EDGE_LINK)
+				.and_then(|port| topology.node(port))
+				.map_or_else(|| device.clone(), |port| port.name.clone())
+		};
+
+		if let Some(owner) = target_bindings(&topology, &target).first() {
+			return Err(CxlFmError::InvalidInput(format!("{} is bound to {}",
+								    target, owner)));
+		}
+
+		let path = ctx.path_of(&topology, &device)?;
+		let action = if lds > 1 {
+			let message = CxlCciMessage {
+				category: CXL_CCI_MESSAGE_REQUEST,
+				tag: 0,
+				opcode: CXL_MEMDEV_SECURE_ERASE,
+				return_code: 0,
+				payload: Vec::new(),
+			};
+			let fm_api_request = CxlTunnelRequest {
+				port_or_ld: ld,
+				message: message.to_bytes(),
+			};
+
+			CxlPlanAction::Command(path, CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND,
+					       fm_api_request.to_bytes())
+		} else {
+			CxlPlanAction::Command(path, CXL_MEMDEV_SECURE_ERASE, Vec::new())
+		};
+
+		Ok(vec![(plan_step(CXL_MEMDEV_SECURE_ERASE, &device,
+				   format!("secure erase LD {} of {}", ld, device)),
+			 action)])
+	}
+
 	/*
 	 * Secure erase after unbinding
 	 */
-	pub fn erase(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn erase(stream: &TcpStream, request: &CxlFmRequest,
+		     ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_LD_ERASE);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_plan(stream, request, ctx, erase_plan(request, ctx), env)
 	}
 }
@@ -0,0 +1,195 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_mailbox {
+	use std::fs;
+	use std::fs::File;
+	use std::fs::OpenOptions;
+	use std::io;
+	use std::os::unix::io::AsRawFd;
+	use std::path::Path;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_api::CXL_FM_API_SUCCESS;
+	use crate::cxl_backend::CxlCciBackend;
+
+	/*
+	 * CXL_MEM_SEND_COMMAND ioctl of CXL device node
+	 * (_IOWR(0xCE, 2, struct cxl_send_command))
+	 */
+	const CXL_MEM_SEND_COMMAND: u64			= 0xC030CE02;
+
+	/*
+	 * Command ID of raw mailbox command
+	 */
+	const CXL_MEM_COMMAND_ID_RAW: u32		= 2;
+
+	/*
+	 * Minimal payload size of CXL mailbox
+	 */
+	const CXL_MAILBOX_MIN_PAYLOAD_SIZE: usize	= 256;
+
+	/*
+	 * struct CxlSendCommand - struct cxl_send_command of Linux
+	 * @id: command ID
+	 * @flags: flags of the command (none are defined)
+	 * @opcode: opcode of raw command
+	 * @rsvd: reserved
+	 * @retval: return code of the mailbox
+	 * @in_size: size of input payload
+	 * @in_rsvd: reserved
+	 * @in_payload: address of input payload
+	 * @out_size: size of output buffer, size of output payload on return
+	 * @out_rsvd: reserved
+	 * @out_payload: address of output buffer
+	 */
+	#[repr(C)]
+	#[derive(Default)]
+	struct CxlSendCommand {
+		id: u32,
+		flags: u32,
+		opcode: u16,
+		rsvd: u16,
+		retval: u32,
+		in_size: u32,
+		in_rsvd: u32,
+		in_payload: u64,
+		out_size: u32,
+		out_rsvd: u32,
+		out_payload: u64,
+	}
+
+	/*
+	 * struct CxlMailboxBackend - CCI of real component
+	 * @name: name of the component
+	 * @path: device node of the CCI (/dev/cxl/...)
+	 * @locality: locality domain of the component
+	 * @file: opened device node
+	 *
+	 * FM API commands are sent as raw mailbox commands,
+	 * so the kernel has to allow raw commands
+	 * (CONFIG_CXL_MEM_RAW_COMMANDS). Device node is opened
+	 * on the first command and it is reopened after failure,
+	 * so the component may appear after FM start.
+	 */
+	pub struct CxlMailboxBackend {
+		name: String,
+		path: String,
+		locality: u16,
+		file: Option<File>,
+	}
+
+	impl CxlMailboxBackend {
+		pub fn new(name: &str, path: &str, locality: u16) -> CxlMailboxBackend {
+			CxlMailboxBackend {
+				name: String::from(name),
+				path: String::from(path),
+				locality,
+				file: None,
+			}
+		}
+
+		/*
+		 * Get payload size of the mailbox that kernel
+		 * reports for the device node
+		 */
+		fn payload_size(&self) -> usize {
+			Path::new(&self.path)
+				.file_name()
+				.and_then(|name| fs::read_to_string(Path::new("/sys/bus/cxl/devices")
+									.join(name)
+									.join("payload_max")).ok())
+				.and_then(|size| size.trim().parse::<usize>().ok())
+				.unwrap_or(CXL_MAILBOX_MIN_PAYLOAD_SIZE)
+		}
+
+		fn io_error(&self, e: io::Error) -> CxlFmError {
+			CxlFmError::Io(format!("{}: {}", self.path, e))
+		}
+	}
+
+	impl CxlCciBackend for CxlMailboxBackend {
+		fn name(&self) -> String {
+			self.name.clone()
+		}
+
+		fn locality(&self) -> u16 {
+			self.locality
+		}
+
+		fn execute(&mut self, opcode: u16, payload: &[u8]) -> Result<Vec<u8>, CxlFmError> {
+			let file = match self.file.take() {
+				Some(file) => file,
+				None => OpenOptions::new()
+					.read(true)
+					.write(true)
+					.open(&self.path)
+					.map_err(|e| self.io_error(e))?,
+			};
+			let mut responce = vec![0u8; self.payload_size()];
+			let mut command = CxlSendCommand {
+				id: CXL_MEM_COMMAND_ID_RAW,
+				opcode,
+				in_size: payload.len() as u32,
+				in_payload: payload.as_ptr() as u64,
+				out_size: responce.len() as u32,
+				out_payload: responce.as_mut_ptr() as u64,
+				..Default::default()
+			};
+
+			let result = unsafe {
+				libc::ioctl(file.as_raw_fd(), CXL_MEM_SEND_COMMAND as _,
+					    &mut command as *mut CxlSendCommand)
+			};
+
+			if result < 0 {
+				return Err(self.io_error(io::Error::last_os_error()));
+			}
+
+			self.file = Some(file);
+
+			if command.retval != CXL_FM_API_SUCCESS as u32 {
+				return Err(CxlFmError::Device(command.retval as u16));
+			}
+
+			responce.truncate(command.out_size as usize);
+			Ok(responce)
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		#[test]
+		fn send_command_matches_linux_layout() {
+			assert_eq!(std::mem::size_of::<CxlSendCommand>(), 48);
+			assert_eq!(CXL_MEM_SEND_COMMAND >> 16 & 0x3fff, 48);
+		}
+
+		#[test]
+		fn absent_device_node_is_io_error() {
+			let mut backend = CxlMailboxBackend::new("sw0", "/dev/cxl/fm_daemon_absent", 0);
+
+			assert!(matches!(backend.execute(0x0001, &[]), Err(CxlFmError::Io(_))));
+			assert_eq!(backend.payload_size(), CXL_MAILBOX_MIN_PAYLOAD_SIZE);
+		}
+	}
+}
@@ -28,15 +28,30 @@ mod pci2pci_bridge;
 mod physical_port;
 mod mld_port;
 mod dynamic_capacity_device;
+mod backend;
+mod emulator;
+mod emulated_switch;
+mod mailbox;
+mod context;
+mod capacity_pool;
+mod desired_state;
+mod plan;
+mod event_log;
+mod reconcile;
+mod state_store;
+mod recovery;
+mod event_collector;
+mod hot_plug;
+mod heartbeat;
+mod failover;
 
-extern crate daemonize;
-
-use std::fs::File;
-use daemonize::Daemonize;
-use clap::{Arg, Command};
+use std::fs;
+use clap::{Arg, ArgMatches, Command};
 use std::{
 	io::{prelude::*, BufReader},
 	net::{TcpListener, TcpStream},
+	sync::Arc,
+	thread,
 };
 pub use crate::discover::cxl_fm_discover_command;
 pub use crate::fm::cxl_fm_command;
@@ -47,8 +62,33 @@ pub use crate::pci2pci_bridge::cxl_ppb_command;
 pub use crate::physical_port::cxl_physical_port_command;
 pub use crate::mld_port::cxl_mld_port_command;
 pub use crate::dynamic_capacity_device::cxl_dcd_command;
+pub use crate::backend::cxl_backend;
+pub use crate::emulator::cxl_emulator;
+pub use crate::emulated_switch::cxl_emulated_switch;
+pub use crate::mailbox::cxl_mailbox;
+pub use crate::context::cxl_fm_context;
+pub use crate::capacity_pool::cxl_capacity_pool_command;
+pub use crate::desired_state::cxl_desired_state_command;
+pub use crate::plan::cxl_fm_plan;
+pub use crate::event_log::cxl_fm_event_log;
+pub use crate::reconcile::cxl_reconcile_command;
+pub use crate::state_store::cxl_fm_state_store;
+pub use crate::recovery::cxl_fm_recovery;
+pub use crate::event_collector::cxl_fm_event_collector;
+pub use crate::hot_plug::cxl_fm_hot_plug;
+pub use crate::heartbeat::cxl_fm_heartbeat;
+pub use crate::heartbeat::cxl_fm_heartbeat::CxlFmRegistration;
+pub use crate::failover::cxl_fm_failover;
+pub use crate::context::cxl_fm_context::CxlFmContext;
+pub use crate::context::cxl_fm_context::lock_shared;
+pub use crate::state_store::cxl_fm_state_store::CxlFmStateStore;
 pub use fm_library::cxl_fm_lib::send_responce;
+pub use fm_library::cxl_fm_lib::send_error;
 pub use fm_library::cxl_fm_lib::CxlFmOptions;
+pub use fm_library::cxl_fm_lib::CxlFmRequest;
+pub use fm_library::cxl_fm_lib::CxlFmError;
+pub use fm_library::cxl_config::CxlConfig;
+pub use fm_library::cxl_fm_config::CxlFmDaemonConfig;
 
 /*
  * CXL FM daemon version
@@ -66,14 +106,18 @@ const CXL_FM_DAEMON_IP_ADDRESS_OPTION: &str = "ip";
 const CXL_FM_DAEMON_IP_ADDRESS_OPTION_SHORT: char = 'i';
 const CXL_FM_DAEMON_PORT_OPTION: &str = "port";
 const CXL_FM_DAEMON_PORT_OPTION_SHORT: char = 'p';
-
-const CXL_FM_DAEMON_WORKING_DIRECTORY: &str = "/tmp";
-const CXL_FM_DAEMON_LOG_FILE_PATH: &str = "/tmp/fm_daemon.log";
-const CXL_FM_DAEMON_ERROR_MESSAGES_FILE_PATH: &str = "/tmp/fm_daemon.err";
-const CXL_FM_DAEMON_USER: &str = "nobody";
-const CXL_FM_DAEMON_GROUP: &str = "bin";
-const CXL_FM_DAEMON_GROUP_ID: u32 = 2;
-const CXL_FM_DAEMON_UMASK: u32 = 0o777;
+const CXL_FM_DAEMON_CONFIG_OPTION: &str = "config";
+const CXL_FM_DAEMON_RECONCILE_INTERVAL_OPTION: &str = "reconcile-interval";
+const CXL_FM_DAEMON_REMEDIATE_OPTION: &str = "remediate";
+const CXL_FM_DAEMON_STATE_DIRECTORY_OPTION: &str = "state-dir";
+const CXL_FM_DAEMON_EVENT_POLL_INTERVAL_OPTION: &str = "event-poll-interval";
+const CXL_FM_DAEMON_FOREGROUND_OPTION: &str = "foreground";
+const CXL_FM_DAEMON_ORCHESTRATOR_OPTION: &str = "orchestrator";
+const CXL_FM_DAEMON_FM_ID_OPTION: &str = "fm-id";
+const CXL_FM_DAEMON_HEARTBEAT_INTERVAL_OPTION: &str = "heartbeat-interval";
+const CXL_FM_DAEMON_HEARTBEAT_INTERVAL_OPTION_DEFAULT: &str = "5";
+const CXL_FM_DAEMON_ANNOUNCE_GROUP_OPTION: &str = "announce-group";
+const CXL_FM_DAEMON_ANNOUNCE_INTERFACE_OPTION: &str = "announce-interface";
 
 /*
  * Command line interface definition
@@ -90,51 +134,159 @@ fn cli() -> Command {
 		.arg(Arg::new(CXL_FM_DAEMON_IP_ADDRESS_OPTION)
 			.short(CXL_FM_DAEMON_IP_ADDRESS_OPTION_SHORT)
 			.long(CXL_FM_DAEMON_IP_ADDRESS_OPTION)
-			.action(clap::ArgAction::Set)
-			.required(true))
+			.action(clap::ArgAction::Set))
 		.arg(Arg::new(CXL_FM_DAEMON_PORT_OPTION)
 			.short(CXL_FM_DAEMON_PORT_OPTION_SHORT)
 			.long(CXL_FM_DAEMON_PORT_OPTION)
 			.action(clap::ArgAction::Set)
-			.required(true))
+			.value_parser(clap::value_parser!(u16)))
+		.arg(Arg::new(CXL_FM_DAEMON_CONFIG_OPTION)
+			.long(CXL_FM_DAEMON_CONFIG_OPTION)
+			.action(clap::ArgAction::Set))
+		.arg(Arg::new(CXL_FM_DAEMON_RECONCILE_INTERVAL_OPTION)
+			.long(CXL_FM_DAEMON_RECONCILE_INTERVAL_OPTION)
+			.action(clap::ArgAction::Set)
+			.value_parser(clap::value_parser!(u64)))
+		.arg(Arg::new(CXL_FM_DAEMON_REMEDIATE_OPTION)
+			.long(CXL_FM_DAEMON_REMEDIATE_OPTION)
+			.action(clap::ArgAction::SetTrue))
+		.arg(Arg::new(CXL_FM_DAEMON_STATE_DIRECTORY_OPTION)
+			.long(CXL_FM_DAEMON_STATE_DIRECTORY_OPTION)
+			.action(clap::ArgAction::Set))
+		.arg(Arg::new(CXL_FM_DAEMON_EVENT_POLL_INTERVAL_OPTION)
+			.long(CXL_FM_DAEMON_EVENT_POLL_INTERVAL_OPTION)
+			.action(clap::ArgAction::Set)
+			.value_parser(clap::value_parser!(u64)))
+		.arg(Arg::new(CXL_FM_DAEMON_FOREGROUND_OPTION)
+			.long(CXL_FM_DAEMON_FOREGROUND_OPTION)
+			.action(clap::ArgAction::SetTrue))
+		.arg(Arg::new(CXL_FM_DAEMON_ORCHESTRATOR_OPTION)
+			.long(CXL_FM_DAEMON_ORCHESTRATOR_OPTION)
+			.action(clap::ArgAction::Set))
+		.arg(Arg::new(CXL_FM_DAEMON_FM_ID_OPTION)
+			.long(CXL_FM_DAEMON_FM_ID_OPTION)
+			.action(clap::ArgAction::Set))
+		.arg(Arg::new(CXL_FM_DAEMON_HEARTBEAT_INTERVAL_OPTION)
+			.long(CXL_FM_DAEMON_HEARTBEAT_INTERVAL_OPTION)
+			.action(clap::ArgAction::Set)
+			.value_parser(clap::value_parser!(u64).range(1..))
+			.default_value(CXL_FM_DAEMON_HEARTBEAT_INTERVAL_OPTION_DEFAULT))
+		.arg(Arg::new(CXL_FM_DAEMON_ANNOUNCE_GROUP_OPTION)
+			.long(CXL_FM_DAEMON_ANNOUNCE_GROUP_OPTION)
+			.action(clap::ArgAction::Set))
+		.arg(Arg::new(CXL_FM_DAEMON_ANNOUNCE_INTERFACE_OPTION)
+			.long(CXL_FM_DAEMON_ANNOUNCE_INTERFACE_OPTION)
+			.action(clap::ArgAction::Set)
+			.default_value(fm_library::cxl_fm_announce::CXL_FM_ANNOUNCE_INTERFACE_DEFAULT))
 }
 
 /*
-OD * Connection request processing logic
+ * Connection request processing logic
  */
-fn handle_connection(mut stream: &TcpStream, env: &CxlFmOptions) {
+fn handle_connection(mut stream: &TcpStream, ctx: &CxlFmContext, env: &CxlFmOptions) {
 	if env.is_debug {
 		println!("Process request...");
 	}
 
 	let buf_reader = BufReader::new(&mut stream);
-	let request_line = buf_reader.lines().next().unwrap().unwrap();
+	/*
+	 * Connection closed without request is empty request
+	 */
+	let request_line = match buf_reader.lines().next().unwrap_or(Ok(String::new())) {
+		Ok(line) => line,
+		Err(e) => {
+			send_error(stream, &CxlFmError::Protocol(e.to_string()), env);
+			return;
+		}
+	};
 
 	if env.is_debug {
 		println!("Request: {:#?}", request_line);
 	}
 
-	match request_line.as_str() {
+	let request = match CxlFmRequest::parse(&request_line) {
+		Ok(request) => request,
+		Err(e) => {
+			send_error(stream, &e, env);
+			return;
+		}
+	};
+
+	/*
+	 * Subscription keeps the connection open,
+	 * so it doesn't block fabric changes
+	 */
+	if request.command == fm_library::cxl_fm_lib::CXL_FM_SUBSCRIBE_EVENTS_COMMAND {
+		cxl_fm_command::subscribe_events(stream, &request, ctx, env);
+		return;
+	}
+
+	/*
+	 * State is replicated with the operation in progress
+	 */
+	if request.command == fm_library::cxl_fm_lib::CXL_FM_GET_FM_STATE_COMMAND {
+		cxl_fm_failover::get_state(stream, ctx, env);
+		return;
+	}
+
+	/*
+	 * Requests are not interleaved with reconciliation passes
+	 */
+	let _operation = ctx.lock_operation();
+
+	/*
+	 * Only the active FM manages the fabric
+	 */
+	if ctx.is_standby() && !cxl_fm_failover::is_served_by_standby(&request) {
+		send_error(stream, &fm_library::cxl_fm_lib::CxlFmError::InvalidInput(
+				String::from("FM is standby, the fabric is managed by the active FM")), env);
+		return;
+	}
+
+	if cxl_fm_plan::is_unsupported_dry_run(&request) {
+		send_error(stream, &fm_library::cxl_fm_lib::CxlFmError::InvalidInput(
+				String::from("dry-run not supported")), env);
+		return;
+	}
+
+	/*
+	 * Fabric change is journaled before its execution
+	 */
+	let is_journaled = cxl_fm_recovery::is_journaled(&request);
+
+	if is_journaled {
+		if let Err(e) = ctx.begin_operation(&request) {
+			send_error(stream, &e, env);
+			return;
+		}
+	}
+
+	let mut is_done = true;
+
+	match request.command.as_str() {
 		fm_library::cxl_fm_lib::CXL_FM_DISCOVER_CXL_DEVICE_COMMAND => {
-			cxl_fm_discover_command::discover_cxl_devices(stream, env);
+			cxl_fm_discover_command::discover_cxl_devices(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_DISCOVER_CXL_SWITCH_COMMAND => {
-			cxl_fm_discover_command::discover_cxl_switches(stream, env);
+			cxl_fm_discover_command::discover_cxl_switches(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_DISCOVER_LD_COMMAND => {
-			cxl_fm_discover_command::discover_logical_devices(stream, env);
+			cxl_fm_discover_command::discover_logical_devices(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_DISCOVER_TOPOLOGY_COMMAND => {
+			cxl_fm_discover_command::discover_topology(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_GET_FM_INFO_COMMAND => {
 			cxl_fm_command::get_info(stream, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_GET_FM_CONFIG_COMMAND => {
-			cxl_fm_command::get_config(stream, env);
+			cxl_fm_command::get_config(stream, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_SET_FM_CONFIG_COMMAND => {
-			cxl_fm_command::set_config(stream, env);
+			cxl_fm_command::set_config(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_GET_FM_EVENTS_COMMAND => {
-			cxl_fm_command::get_events(stream, env);
+			cxl_fm_command::get_events(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_GET_SWITCH_INFO_COMMAND => {
 			cxl_switch_command::get_info(stream, env);
@@ -146,13 +298,16 @@ fn handle_connection(mut stream: &TcpStream, env: &CxlFmOptions) {
 			cxl_switch_command::set_config(stream, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_GET_MHD_INFO_COMMAND => {
-			cxl_mh_device_command::get_info(stream, env);
+			cxl_mh_device_command::get_info(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_GET_MHD_HEAD_INFO_COMMAND => {
+			cxl_mh_device_command::get_head_info(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_BIND_LD_COMMAND => {
-			cxl_logical_device_command::bind(stream, env);
+			is_done = cxl_logical_device_command::bind(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_UNBIND_LD_COMMAND => {
-			cxl_logical_device_command::unbind(stream, env);
+			is_done = cxl_logical_device_command::unbind(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_CONNECT_MLD_COMMAND => {
 			cxl_logical_device_command::connect(stream, env);
@@ -164,7 +319,7 @@ fn handle_connection(mut stream: &TcpStream, env: &CxlFmOptions) {
 			cxl_logical_device_command::get_allocation(stream, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_SET_LD_ALLOCATION_COMMAND => {
-			cxl_logical_device_command::set_allocation(stream, env);
+			is_done = cxl_logical_device_command::set_allocation(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_GET_QOS_CONTROL_COMMAND => {
 			cxl_logical_device_command::get_qos_control(stream, env);
@@ -188,28 +343,38 @@ fn handle_connection(mut stream: &TcpStream, env: &CxlFmOptions) {
 			cxl_logical_device_command::set_qos_bandwidth_limit(stream, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_LD_ERASE => {
-			cxl_logical_device_command::erase(stream, env);
+			is_done = cxl_logical_device_command::erase(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_GET_PPB_CONFIG_COMMAND => {
 			cxl_ppb_command::config(stream, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_PPB_BIND_COMMAND => {
-			cxl_ppb_command::bind(stream, env);
+			is_done = cxl_ppb_command::bind(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_PPB_UNBIND_COMMAND => {
-			cxl_ppb_command::unbind(stream, env);
+			is_done = cxl_ppb_command::unbind(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_PPB_GENERATE_AER_COMMAND => {
+			cxl_ppb_command::generate_aer(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_GET_PHYSICAL_PORT_INFO_COMMAND => {
 			cxl_physical_port_command::get_info(stream, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND => {
-			cxl_physical_port_command::control(stream, env);
+			is_done = cxl_physical_port_command::control(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_BIND_PHYSICAL_PORT_COMMAND => {
-			cxl_physical_port_command::bind(stream, env);
+			is_done = cxl_physical_port_command::bind(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND => {
-			cxl_physical_port_command::unbind(stream, env);
+			is_done = cxl_physical_port_command::unbind(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND => {
+			is_done = cxl_physical_port_command::remove(stream, &request, ctx, env);
+		},
+		#[cfg(feature = "emulation")]
+		fm_library::cxl_fm_lib::CXL_FM_EMULATE_HOT_PLUG_COMMAND => {
+			cxl_physical_port_command::hot_plug(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_MLD_PORT_TUNNEL_COMMAND => {
 			cxl_mld_port_command::tunnel(stream, env);
@@ -221,36 +386,77 @@ fn handle_connection(mut stream: &TcpStream, env: &CxlFmOptions) {
 			cxl_mld_port_command::send_memory_request(stream, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_DCD_GET_INFO_COMMAND => {
-			cxl_dcd_command::get_info(stream, env);
+			cxl_dcd_command::get_info(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_DCD_GET_CONFIG_COMMAND => {
-			cxl_dcd_command::get_capacity_config(stream, env);
+			cxl_dcd_command::get_capacity_config(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_DCD_SET_CONFIG_COMMAND => {
-			cxl_dcd_command::set_capacity_config(stream, env);
+			is_done = cxl_dcd_command::set_capacity_config(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_DCD_GET_EXTENT_COMMAND => {
-			cxl_dcd_command::get_extent_list(stream, env);
+			cxl_dcd_command::get_extent_list(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_DCD_ADD_CAPACITY_COMMAND => {
-			cxl_dcd_command::add_capacity(stream, env);
+			is_done = cxl_dcd_command::add_capacity(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_DCD_RELEASE_CAPACITY_COMMAND => {
-			cxl_dcd_command::release_capacity(stream, env);
+			is_done = cxl_dcd_command::release_capacity(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_POOL_GET_INFO_COMMAND => {
+			cxl_capacity_pool_command::get_info(stream, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_POOL_ALLOCATE_COMMAND => {
+			is_done = cxl_capacity_pool_command::allocate(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_APPLY_COMMAND => {
+			is_done = cxl_desired_state_command::apply(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_RECONCILE_COMMAND => {
+			is_done = cxl_reconcile_command::reconcile_now(stream, &request, ctx, env);
 		},
 		_ => send_responce(stream, fm_library::cxl_fm_lib::CXL_FM_UNKNOWN_COMMAND, env),
 	}
+
+	if is_journaled {
+		ctx.end_operation(true, is_done);
+	}
 }
 
 /*
  * Main logic of daemon
  */
-fn fm_daemon_logic(env: &CxlFmOptions) {
+fn fm_daemon_logic(env: &CxlFmOptions, config: CxlFmDaemonConfig, store: CxlFmStateStore,
+		   registration: Option<CxlFmRegistration>) {
 	if env.is_debug {
 		println!("{} {}: Daemonized!",
 			 CXL_FM_DAEMON_NAME, CXL_FM_DAEMON_VERSION);
 	}
 
+	let ctx = Arc::new(CxlFmContext::new(config));
+
+	*lock_shared(&ctx.store) = Some(store);
+
+	/*
+	 * FM registered in orchestrator starts as standby and
+	 * recovers the state when it is elected active
+	 */
+	match registration.as_ref().and_then(|registration| registration.orchestrator.as_ref()) {
+		Some(_) => *lock_shared(&ctx.role) = fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_STANDBY,
+		None => cxl_fm_recovery::recover(&ctx),
+	}
+
+	/*
+	 * Periodic work follows the live policies,
+	 * zero interval suspends it
+	 */
+	cxl_fm_event_collector::start_collection(Arc::clone(&ctx), env);
+	cxl_reconcile_command::start_reconciliation(Arc::clone(&ctx), env);
+
+	if let Some(registration) = registration {
+		cxl_fm_heartbeat::start_heartbeat(Arc::clone(&ctx), registration, env);
+	}
+
 	loop {
 		let listener = TcpListener::bind(&env.ip_port).unwrap();
 
@@ -259,49 +465,252 @@ fn fm_daemon_logic(env: &CxlFmOptions) {
 				 env.ip_port);
 		}
 
+		/*
+		 * Every connection has its own thread,
+		 * requests are serialized by the operation lock
+		 */
 		for stream in listener.incoming() {
-			handle_connection(&stream.unwrap(), env);
+			let stream = stream.unwrap();
+			let ctx = Arc::clone(&ctx);
+			let env = env.clone();
+
+			/*
+			 * Clients are checked by the live security rules
+			 */
+			match stream.peer_addr() {
+				Ok(client) if ctx.security().is_allowed(&client.ip()) => {},
+				Ok(client) => {
+					send_error(&stream, &CxlFmError::InvalidInput(
+							format!("client {} is not allowed", client.ip())), &env);
+					continue;
+				},
+				Err(_) => continue,
+			}
+
+			thread::spawn(move || handle_connection(&stream, &ctx, &env));
 		}
 	};
 }
 
+/*
+ * Get configuration of the daemon: defaults, configuration
+ * file and command line options
+ */
+fn daemon_config(matches: &ArgMatches) -> Result<CxlFmDaemonConfig, String> {
+	let mut config = CxlFmDaemonConfig::new(CXL_FM_DAEMON_NAME);
+
+	if let Some(path) = matches.get_one::<String>(CXL_FM_DAEMON_CONFIG_OPTION) {
+		fs::read_to_string(path)
+			.map_err(|e| CxlFmError::Io(e.to_string()))
+			.and_then(|content| CxlConfig::parse(&content))
+			.and_then(|file| config.read(&file))
+			.map_err(|e| format!("cannot read configuration {}: {}", path, e))?;
+	}
+
+	/*
+	 * Command line options override the configuration file
+	 */
+	if let Some(ip) = matches.get_one::<String>(CXL_FM_DAEMON_IP_ADDRESS_OPTION) {
+		config.process.listen.ip = Some(ip.clone());
+	}
+
+	if let Some(port) = matches.get_one::<u16>(CXL_FM_DAEMON_PORT_OPTION) {
+		config.process.listen.port = Some(*port);
+	}
+
+	if let Some(directory) = matches.get_one::<String>(CXL_FM_DAEMON_STATE_DIRECTORY_OPTION) {
+		config.state.directory = directory.clone();
+	}
+
+	if let Some(interval) = matches.get_one::<u64>(CXL_FM_DAEMON_RECONCILE_INTERVAL_OPTION) {
+		config.policies.reconcile_interval = *interval;
+	}
+
+	if let Some(interval) = matches.get_one::<u64>(CXL_FM_DAEMON_EVENT_POLL_INTERVAL_OPTION) {
+		config.policies.event_poll_interval = *interval;
+	}
+
+	if matches.get_flag(CXL_FM_DAEMON_REMEDIATE_OPTION) {
+		config.policies.remediate = true;
+	}
+
+	if matches.get_flag(CXL_FM_DAEMON_DEBUG_OPTION) {
+		config.process.log.debug = true;
+	}
+
+	Ok(config)
+}
+
 /*
  * Application logic
  */
 fn main() {
-	let stdout = File::create(CXL_FM_DAEMON_LOG_FILE_PATH).unwrap();
-	let stderr = File::create(CXL_FM_DAEMON_ERROR_MESSAGES_FILE_PATH).unwrap();
-
 	let matches = cli().get_matches();
+	let config = match daemon_config(&matches) {
+		Ok(config) => config,
+		Err(e) => {
+			eprintln!("Error, {}", e);
+			return;
+		}
+	};
+
+	let ip_port = match config.process.listen.ip_port() {
+		Ok(ip_port) => ip_port,
+		Err(e) => {
+			eprintln!("Error, {}", e);
+			return;
+		}
+	};
 
-	let ip = matches.get_one::<String>(CXL_FM_DAEMON_IP_ADDRESS_OPTION).unwrap();
-	let port = matches.get_one::<String>(CXL_FM_DAEMON_PORT_OPTION).unwrap();
-	let ip_port = format!("{ip}:{port}");
+	let is_foreground = matches.get_flag(CXL_FM_DAEMON_FOREGROUND_OPTION);
+	let orchestrator = matches.get_one::<String>(CXL_FM_DAEMON_ORCHESTRATOR_OPTION).cloned();
+	let group = matches.get_one::<String>(CXL_FM_DAEMON_ANNOUNCE_GROUP_OPTION)
+		.map(|group| fm_library::cxl_fm_announce::parse_group(group))
+		.transpose();
+	let interface = fm_library::cxl_fm_announce::parse_interface(
+		matches.get_one::<String>(CXL_FM_DAEMON_ANNOUNCE_INTERFACE_OPTION).unwrap());
+
+	let (group, interface) = match (group, interface) {
+		(Ok(group), Ok(interface)) => (group, interface),
+		(Err(e), _) | (_, Err(e)) => {
+			eprintln!("Error, {}", e);
+			return;
+		}
+	};
+
+	/*
+	 * FM reports itself to orchestrator, into multicast group or both
+	 */
+	let registration = match orchestrator.is_some() || group.is_some() {
+		true => Some(CxlFmRegistration {
+			orchestrator,
+			group,
+			interface,
+			id: matches.get_one::<String>(CXL_FM_DAEMON_FM_ID_OPTION)
+				.cloned()
+				.unwrap_or(ip_port.clone()),
+			address: ip_port.clone(),
+			version: String::from(CXL_FM_DAEMON_VERSION),
+			interval: *matches.get_one::<u64>(CXL_FM_DAEMON_HEARTBEAT_INTERVAL_OPTION).unwrap(),
+		}),
+		false => None,
+	};
 
 	let options = CxlFmOptions {
-		ip_port: String::from(ip_port),
-		is_debug: matches.get_flag(CXL_FM_DAEMON_DEBUG_OPTION) == true,
+		ip_port,
+		is_debug: config.process.log.debug,
+		is_dry_run: false,
+		fm: None,
 	};
 
 	if options.is_debug {
 		println!("{} {}", CXL_FM_DAEMON_NAME, CXL_FM_DAEMON_VERSION);
 	}
 
-	let daemonize = Daemonize::new()
-			// Every method except `new` and `start`
-			// is optional, see `Daemonize` documentation
-			// for default behaviour.
-			.working_directory(CXL_FM_DAEMON_WORKING_DIRECTORY)
-			.user(CXL_FM_DAEMON_USER)
-			.group(CXL_FM_DAEMON_GROUP)	// Group name
-			.group(CXL_FM_DAEMON_GROUP_ID)	// or group id.
-			.umask(CXL_FM_DAEMON_UMASK)	// Set umask, `0o027` by default.
-			.stdout(stdout)			// Redirect stdout to log file.
-			.stderr(stderr)			// Redirect stderr to error messages file.
-			.privileged_action(|| "Executed before drop privileges");
-
-	match daemonize.start() {
-		Ok(_) => fm_daemon_logic(&options),
+	/*
+	 * State store is opened before privileges are dropped
+	 */
+	let store = match CxlFmStateStore::open(&config.state.directory) {
+		Ok(store) => store,
+		Err(e) => {
+			eprintln!("Error, cannot open state store: {}", e);
+			return;
+		}
+	};
+
+	/*
+	 * Process started by orchestrator is not detached,
+	 * the orchestrator tracks it and redirects its output
+	 */
+	if is_foreground {
+		fm_daemon_logic(&options, config, store, registration);
+		return;
+	}
+
+	match config.process.daemonize() {
+		Ok(_) => fm_daemon_logic(&options, config, store, registration),
 		Err(e) => eprintln!("Error, {}", e),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config_file(name: &str, content: &str) -> String {
+		let path = std::env::temp_dir().join(format!("fm_daemon_{}_{}.toml", name, std::process::id()));
+
+		fs::write(&path, content).unwrap();
+		path.to_string_lossy().into_owned()
+	}
+
+	fn config(args: &[&str]) -> Result<CxlFmDaemonConfig, String> {
+		let mut argv = vec![CXL_FM_DAEMON_NAME];
+
+		argv.extend_from_slice(args);
+		daemon_config(&cli().try_get_matches_from(argv).unwrap())
+	}
+
+	#[test]
+	fn configuration_file_overrides_defaults() {
+		let path = config_file("file", "[listen]\nip = \"127.0.0.1\"\nport = 7500\n\n\
+					       [state]\ndirectory = \"/tmp/fm_state\"\n\n\
+					       [policies]\nreconcile_interval = 10\n");
+		let config = config(&["--config", &path]).unwrap();
+
+		fs::remove_file(&path).unwrap();
+		assert_eq!(config.process.listen.ip_port().unwrap(), "127.0.0.1:7500");
+		assert_eq!(config.state.directory, "/tmp/fm_state");
+		assert_eq!(config.policies.reconcile_interval, 10);
+		assert_eq!(config.policies.event_poll_interval,
+			   fm_library::cxl_fm_config::CXL_FM_EVENT_POLL_INTERVAL_DEFAULT);
+	}
+
+	#[test]
+	fn command_line_overrides_configuration_file() {
+		let path = config_file("options", "[listen]\nip = \"127.0.0.1\"\nport = 7500\n\n\
+						  [policies]\nreconcile_interval = 10\nremediate = false\n\n\
+						  [log]\ndebug = false\n");
+		let config = config(&["--config", &path, "-p", "7600", "--reconcile-interval", "20",
+				      "--remediate", "-d"]).unwrap();
+
+		fs::remove_file(&path).unwrap();
+		assert_eq!(config.process.listen.ip_port().unwrap(), "127.0.0.1:7600");
+		assert_eq!(config.policies.reconcile_interval, 20);
+		assert!(config.policies.remediate);
+		assert!(config.process.log.debug);
+	}
+
+	#[test]
+	fn invalid_configuration_file_is_rejected() {
+		let path = config_file("invalid", "[listen]\nport = \"7500\"\n");
+		let result = config(&["--config", &path, "-p", "7600"]);
+
+		fs::remove_file(&path).unwrap();
+		assert!(result.is_err());
+		assert!(config(&["--config", "/nonexistent/fm_daemon.toml"]).is_err());
+	}
+
+	#[test]
+	fn connection_closed_without_request_gets_error() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+		let (server, _) = listener.accept().unwrap();
+		let ctx = CxlFmContext::with_devices(Vec::new());
+		let env = CxlFmOptions {
+			ip_port: String::new(),
+			is_debug: false,
+			is_dry_run: false,
+			fm: None,
+		};
+
+		client.shutdown(std::net::Shutdown::Write).unwrap();
+		handle_connection(&server, &ctx, &env);
+		drop(server);
+
+		let mut responce = String::new();
+
+		client.read_to_string(&mut responce).unwrap();
+		assert!(responce.starts_with(fm_library::cxl_fm_lib::CXL_FM_ERROR));
+	}
+}
@@ -22,18 +22,104 @@
 pub mod cxl_mh_device_command {
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
-	use fm_library::cxl_fm_lib::send_responce;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_error;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_MHD_INFO_COMMAND;
-	use fm_library::cxl_fm_lib::CXL_FM_NO_DATA;
+	use fm_library::cxl_fm_lib::CXL_FM_GET_MHD_HEAD_INFO_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_START_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_COUNT_ARG;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_MHD_INFO;
+	use fm_library::cxl_fm_api::CXL_FM_API_GET_MHD_HEAD_INFO;
+	use fm_library::cxl_mh_device_lib::CxlGetMhdInfoRequest;
+	use fm_library::cxl_mh_device_lib::CxlGetMhdInfoResponse;
+	use fm_library::cxl_mh_device_lib::CxlGetHeadInfoRequest;
+	use fm_library::cxl_mh_device_lib::CXL_MHD_LD_MAP_LIST_LIMIT;
+	use crate::cxl_fm_context::CxlFmContext;
+
+	/*
+	 * Read the whole LD Map of Multi Headed Device (MHD)
+	 */
+	fn read_mhd_info(request: &CxlFmRequest,
+			 ctx: &CxlFmContext) -> Result<CxlGetMhdInfoResponse, CxlFmError> {
+		let mut info = CxlGetMhdInfoResponse {
+			num_lds: 0,
+			num_heads: 0,
+			start_ld: 0,
+			ld_map: Vec::new(),
+		};
+
+		loop {
+			let fm_api_request = CxlGetMhdInfoRequest {
+				start_ld: info.ld_map.len() as u8,
+				ld_map_list_limit: CXL_MHD_LD_MAP_LIST_LIMIT,
+			};
+
+			let payload = ctx.execute_for(request,
+						      CXL_FM_API_GET_MHD_INFO,
+						      &fm_api_request.to_bytes())?;
+			let part = CxlGetMhdInfoResponse::from_bytes(&payload)?;
+
+			if part.start_ld != fm_api_request.start_ld {
+				return Err(CxlFmError::Protocol(format!("unexpected start LD: {}",
+									part.start_ld)));
+			}
+
+			info.num_lds = part.num_lds;
+			info.num_heads = part.num_heads;
+			info.ld_map.extend_from_slice(&part.ld_map);
+
+			if part.ld_map.is_empty() ||
+			   info.ld_map.len() >= info.num_lds as usize {
+				break;
+			}
+		}
+
+		Ok(info)
+	}
 
 	/*
 	 * Get Multi Headed Device (MHD) status/info
 	 */
-	pub fn get_info(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn get_info(stream: &TcpStream, request: &CxlFmRequest,
+			ctx: &CxlFmContext, env: &CxlFmOptions) {
 		if env.is_debug {
 			println!("{}", CXL_FM_GET_MHD_INFO_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		match read_mhd_info(request, ctx) {
+			Ok(info) => send_payload(stream, &info.to_bytes(), env),
+			Err(e) => send_error(stream, &e, env),
+		}
+	}
+
+	/*
+	 * Get link state of Multi Headed Device (MHD) heads
+	 */
+	pub fn get_head_info(stream: &TcpStream, request: &CxlFmRequest,
+			     ctx: &CxlFmContext, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", CXL_FM_GET_MHD_HEAD_INFO_COMMAND);
+		}
+
+		let result = request.get_number::<u8>(CXL_FM_START_ARG)
+			.and_then(|start_head| {
+				let num_heads = request.get_number::<u8>(CXL_FM_COUNT_ARG)?;
+
+				let fm_api_request = CxlGetHeadInfoRequest {
+					start_head,
+					num_heads,
+				};
+
+				ctx.execute_for(request,
+						CXL_FM_API_GET_MHD_HEAD_INFO,
+						&fm_api_request.to_bytes())
+			});
+
+		match result {
+			Ok(payload) => send_payload(stream, &payload, env),
+			Err(e) => send_error(stream, &e, env),
+		}
 	}
 }
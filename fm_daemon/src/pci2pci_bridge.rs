@@ -22,11 +22,43 @@
 pub mod cxl_ppb_command {
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
 	use fm_library::cxl_fm_lib::send_responce;
+	use fm_library::cxl_fm_lib::send_ok;
+	use fm_library::cxl_fm_lib::send_error;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_PPB_CONFIG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_PPB_BIND_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_PPB_UNBIND_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_PPB_GENERATE_AER_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_NO_DATA;
+	use fm_library::cxl_fm_lib::CXL_FM_VCS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_VPPB_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_PORT_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_LD_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_MODE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ERROR_TYPE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_HEADER_ARG;
+	use fm_library::cxl_fm_api::CXL_FM_API_GENERATE_AER_EVENT;
+	use fm_library::cxl_switch_lib::CxlGenerateAerRequest;
+	use fm_library::cxl_switch_lib::aer_error_name;
+	use fm_library::cxl_switch_lib::parse_aer_error;
+	use fm_library::cxl_switch_lib::parse_aer_header;
+	use fm_library::cxl_switch_lib::CXL_UNBIND_WAIT;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_DEVICE;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_plan::CxlPlanEntry;
+	use crate::cxl_fm_plan::switch_arg;
+	use crate::cxl_fm_plan::switch_cci;
+	use crate::cxl_fm_plan::find_vppb;
+	use crate::cxl_fm_plan::bound_target;
+	use crate::cxl_fm_plan::binding_target;
+	use crate::cxl_fm_plan::target_bindings;
+	use crate::cxl_fm_plan::bind_entry;
+	use crate::cxl_fm_plan::unbind_entry;
+	use crate::cxl_fm_plan::send_plan;
+	use crate::cxl_fm_discover_command::discover_fabric;
 
 	/*
 	 * Send PCI-to-PCI Bridge (PPB) configuration request
@@ -39,25 +71,131 @@ pub mod cxl_ppb_command {
 		send_responce(stream, CXL_FM_NO_DATA, env);
 	}
 
+	/*
+	 * Plan binding of vPPB to physical port or LD.
+	 * Both vPPB and the target have to be unbound.
+	 * Binding that is already in place needs no steps,
+	 * so the request can be repeated safely.
+	 */
+	pub fn bind_plan(request: &CxlFmRequest,
+			 ctx: &CxlFmContext) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let switch = switch_arg(request)?;
+		let vcs = request.get_number::<u8>(CXL_FM_VCS_ARG)?;
+		let vppb = request.get_number::<u8>(CXL_FM_VPPB_ARG)?;
+		let port = request.get_number::<u8>(CXL_FM_PORT_ARG)?;
+		let ld = match request.get(CXL_FM_LD_ARG) {
+			Some(_) => Some(request.get_number::<u16>(CXL_FM_LD_ARG)?),
+			None => None,
+		};
+
+		let topology = discover_fabric(ctx, false)?;
+		let vppb_node = find_vppb(&topology, switch, vcs, vppb)?;
+		let target = binding_target(&topology, switch, Some(port), ld)?
+				.unwrap_or_default();
+
+		match bound_target(&topology, vppb_node) {
+			Some(current) if current == target => return Ok(Vec::new()),
+			Some(current) => {
+				return Err(CxlFmError::InvalidInput(format!("{} is bound to {}",
+									    vppb_node.name, current)));
+			},
+			None => {},
+		}
+
+		if let Some(owner) = target_bindings(&topology, &target).first() {
+			return Err(CxlFmError::InvalidInput(format!("{} is in use by {}",
+								    target, owner)));
+		}
+
+		Ok(vec![bind_entry(ctx, &topology, switch, vcs, vppb, port, ld)?])
+	}
+
+	/*
+	 * Plan unbinding of vPPB, unbound vPPB needs no steps
+	 */
+	pub fn unbind_plan(request: &CxlFmRequest,
+			   ctx: &CxlFmContext) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let switch = switch_arg(request)?;
+		let vcs = request.get_number::<u8>(CXL_FM_VCS_ARG)?;
+		let vppb = request.get_number::<u8>(CXL_FM_VPPB_ARG)?;
+		let option = request.get_number_or::<u8>(CXL_FM_MODE_ARG, CXL_UNBIND_WAIT)?;
+
+		let topology = discover_fabric(ctx, false)?;
+
+		if bound_target(&topology, find_vppb(&topology, switch, vcs, vppb)?).is_none() {
+			return Ok(Vec::new());
+		}
+
+		Ok(vec![unbind_entry(ctx, &topology, switch, vcs, vppb, option)?])
+	}
+
 	/*
 	 * Bind Virtual PCI-to-PCI Bridge (vPPB) inside a CXL switch
 	 */
-	pub fn bind(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn bind(stream: &TcpStream, request: &CxlFmRequest,
+		    ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_PPB_BIND_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_plan(stream, request, ctx, bind_plan(request, ctx), env)
 	}
 
 	/*
 	 * Unbind Virtual PCI-to-PCI Bridge (vPPB) inside a CXL switch
 	 */
-	pub fn unbind(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn unbind(stream: &TcpStream, request: &CxlFmRequest,
+		      ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_PPB_UNBIND_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_plan(stream, request, ctx, unbind_plan(request, ctx), env)
+	}
+
+	/*
+	 * Inject AER event toward the host of the VCS.
+	 * Injected events are logged, so they are not
+	 * mistaken for real errors.
+	 */
+	fn inject_aer(request: &CxlFmRequest, ctx: &CxlFmContext) -> Result<(), CxlFmError> {
+		let switch = switch_arg(request)?;
+		let vcs = request.get_number::<u8>(CXL_FM_VCS_ARG)?;
+		let vppb = request.get_number::<u8>(CXL_FM_VPPB_ARG)?;
+		let aer_error = request.get(CXL_FM_ERROR_TYPE_ARG)
+			.ok_or_else(|| CxlFmError::InvalidInput(format!("{} is not defined",
+									CXL_FM_ERROR_TYPE_ARG)))
+			.and_then(parse_aer_error)?;
+		let header = parse_aer_header(request.get(CXL_FM_HEADER_ARG).unwrap_or_default())?;
+
+		let topology = discover_fabric(ctx, false)?;
+		let vppb_node = find_vppb(&topology, switch, vcs, vppb)?;
+		let fm_api_request = CxlGenerateAerRequest {
+			vcs_id: vcs,
+			vppb_id: vppb,
+			aer_error,
+			header,
+		};
+
+		ctx.execute(switch_cci(ctx, &topology, switch)?, CXL_FM_API_GENERATE_AER_EVENT,
+			    &fm_api_request.to_bytes())?;
+		ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_DEVICE, &vppb_node.name,
+				 &format!("AER {} is injected", aer_error_name(aer_error)));
+		Ok(())
+	}
+
+	/*
+	 * Generate AER event on Virtual PCI-to-PCI Bridge (vPPB)
+	 */
+	pub fn generate_aer(stream: &TcpStream, request: &CxlFmRequest,
+			    ctx: &CxlFmContext, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", CXL_FM_PPB_GENERATE_AER_COMMAND);
+		}
+
+		match inject_aer(request, ctx) {
+			Ok(()) => send_ok(stream, "", env),
+			Err(e) => send_error(stream, &e, env),
+		}
 	}
 }
@@ -22,12 +22,46 @@
 pub mod cxl_physical_port_command {
 	use std::net::{TcpStream};
 	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
 	use fm_library::cxl_fm_lib::send_responce;
+	use fm_library::cxl_fm_lib::send_payload;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_fm_lib::send_ok;
+	use fm_library::cxl_fm_lib::send_error;
 	use fm_library::cxl_fm_lib::CXL_FM_GET_PHYSICAL_PORT_INFO_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_BIND_PHYSICAL_PORT_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND;
+	#[cfg(feature = "emulation")]
+	use fm_library::cxl_fm_lib::CXL_FM_EMULATE_HOT_PLUG_COMMAND;
 	use fm_library::cxl_fm_lib::CXL_FM_NO_DATA;
+	use fm_library::cxl_fm_lib::CXL_FM_PORT_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_OPERATION_ARG;
+	use fm_library::cxl_fm_api::CXL_FM_API_PHYSICAL_PORT_CONTROL;
+	use fm_library::cxl_switch_lib::CxlPhysicalPortControlRequest;
+	use fm_library::cxl_switch_lib::port_control_name;
+	use fm_library::cxl_switch_lib::port_state_name;
+	use fm_library::cxl_switch_lib::CXL_PORT_CONTROL_RESET_PPB;
+	use fm_library::cxl_switch_lib::CXL_PORT_STATE_DSP;
+	use fm_library::cxl_topology::CXL_NODE_PHYSICAL_PORT;
+	use fm_library::cxl_topology::CXL_EDGE_LINK;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_context::CxlCciPath;
+	use crate::cxl_fm_plan::CxlPlanEntry;
+	use crate::cxl_fm_plan::CxlPlanAction;
+	use crate::cxl_fm_plan::plan_step;
+	use crate::cxl_fm_plan::switch_arg;
+	use crate::cxl_fm_plan::switch_cci;
+	use crate::cxl_fm_plan::target_bindings;
+	use crate::cxl_fm_plan::send_plan;
+	use crate::cxl_ppb_command::bind_plan;
+	use crate::cxl_ppb_command::unbind_plan;
+	use crate::cxl_fm_discover_command::discover_fabric;
+	use crate::cxl_fm_hot_plug::remove_port;
+	#[cfg(feature = "emulation")]
+	use crate::cxl_fm_hot_plug::emulate_hot_plug;
 
 	/*
 	 * Get state of physical port
@@ -40,36 +74,126 @@ pub mod cxl_physical_port_command {
 		send_responce(stream, CXL_FM_NO_DATA, env);
 	}
 
+	/*
+	 * Plan control of downstream port. The description
+	 * shows the device and vPPBs affected by the operation.
+	 */
+	fn control_plan(request: &CxlFmRequest,
+			ctx: &CxlFmContext) -> Result<Vec<CxlPlanEntry>, CxlFmError> {
+		let switch = switch_arg(request)?;
+		let port = request.get_number::<u8>(CXL_FM_PORT_ARG)?;
+		let operation = request.get_number_or::<u8>(CXL_FM_OPERATION_ARG,
+							    CXL_PORT_CONTROL_RESET_PPB)?;
+
+		if port_control_name(operation) == "unknown" {
+			return Err(CxlFmError::InvalidInput(format!("unknown port operation: {}",
+								    operation)));
+		}
+
+		let topology = discover_fabric(ctx, false)?;
+		let cci = switch_cci(ctx, &topology, switch)?;
+		let port_name = format!("{}/port{}", switch, port);
+		let node = topology.find(CXL_NODE_PHYSICAL_PORT, &port_name)
+			.ok_or_else(|| CxlFmError::NotFound(format!("port {}", port_name)))?;
+
+		if node.get("state") != Some(port_state_name(CXL_PORT_STATE_DSP)) {
+			return Err(CxlFmError::InvalidInput(format!("{} is not downstream port",
+								    port_name)));
+		}
+
+		let mut description = format!("{} of {}", port_control_name(operation), port_name);
+
+		if let Some(device) = topology.children(node.id, CXL_EDGE_LINK)
+					.first()
+					.and_then(|device| topology.node(*device)) {
+			description.push_str(&format!(", affects {}", device.name));
+		}
+
+		let bindings = target_bindings(&topology, &port_name);
+
+		if !bindings.is_empty() {
+			description.push_str(&format!(" (bound to {})", bindings.join(", ")));
+		}
+
+		let fm_api_request = CxlPhysicalPortControlRequest {
+			ppb_id: port,
+			operation,
+		};
+
+		Ok(vec![(plan_step(CXL_FM_API_PHYSICAL_PORT_CONTROL, switch, description),
+			 CxlPlanAction::Command(CxlCciPath::Direct(cci),
+						CXL_FM_API_PHYSICAL_PORT_CONTROL,
+						fm_api_request.to_bytes()))])
+	}
+
 	/*
 	 * Control physical port
 	 */
-	pub fn control(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn control(stream: &TcpStream, request: &CxlFmRequest,
+		       ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_plan(stream, request, ctx, control_plan(request, ctx), env)
 	}
 
 	/*
 	 * Bind physical port to Virtual PCI-to-PCI Bridge (vPPB)
 	 */
-	pub fn bind(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn bind(stream: &TcpStream, request: &CxlFmRequest,
+		    ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_BIND_PHYSICAL_PORT_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_plan(stream, request, ctx, bind_plan(request, ctx), env)
 	}
 
 	/*
 	 * Unbind physical port from Virtual PCI-to-PCI Bridge (vPPB)
 	 */
-	pub fn unbind(stream: &TcpStream, env: &CxlFmOptions) {
+	pub fn unbind(stream: &TcpStream, request: &CxlFmRequest,
+		      ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
 		if env.is_debug {
 			println!("{}", CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND);
 		}
 
-		send_responce(stream, CXL_FM_NO_DATA, env);
+		send_plan(stream, request, ctx, unbind_plan(request, ctx), env)
+	}
+
+	/*
+	 * Prepare device attached to physical port for managed removal
+	 */
+	pub fn remove(stream: &TcpStream, request: &CxlFmRequest,
+		      ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
+		if env.is_debug {
+			println!("{}", CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND);
+		}
+
+		let result = remove_port(request, ctx);
+
+		match &result {
+			Ok(plan) => send_payload(stream, &plan.to_bytes(), env),
+			Err(e) => send_error(stream, e, env),
+		}
+
+		result.is_ok()
+	}
+
+	/*
+	 * Insert device into the slot of emulated switch or pull it out
+	 */
+	#[cfg(feature = "emulation")]
+	pub fn hot_plug(stream: &TcpStream, request: &CxlFmRequest,
+			ctx: &CxlFmContext, env: &CxlFmOptions) {
+		if env.is_debug {
+			println!("{}", CXL_FM_EMULATE_HOT_PLUG_COMMAND);
+		}
+
+		match emulate_hot_plug(request, ctx) {
+			Ok(()) => send_ok(stream, "", env),
+			Err(e) => send_error(stream, &e, env),
+		}
 	}
 }
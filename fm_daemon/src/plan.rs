@@ -0,0 +1,366 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_plan {
+	use std::net::{TcpStream};
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_error;
+	use fm_library::cxl_fm_lib::format_size;
+	use fm_library::cxl_fm_lib::CXL_FM_SWITCH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_PLANNED_COMMANDS;
+	use fm_library::cxl_fm_api::CXL_FM_API_BIND_VPPB;
+	use fm_library::cxl_fm_api::CXL_FM_API_UNBIND_VPPB;
+	use fm_library::cxl_switch_lib::CxlBindVppbRequest;
+	use fm_library::cxl_switch_lib::CxlUnbindVppbRequest;
+	use fm_library::cxl_switch_lib::unbind_option_name;
+	use fm_library::cxl_switch_lib::port_state_name;
+	use fm_library::cxl_switch_lib::CXL_BIND_PORT_LD_ID;
+	use fm_library::cxl_switch_lib::CXL_PORT_STATE_DSP;
+	use fm_library::cxl_desired_state::CxlFabricPlan;
+	use fm_library::cxl_desired_state::CxlPlanStep;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_PENDING;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_DONE;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_FAILED;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_SKIPPED;
+	use fm_library::cxl_topology::FabricTopology;
+	use fm_library::cxl_topology::CxlTopologyNode;
+	use fm_library::cxl_topology::is_device_kind;
+	use fm_library::cxl_topology::CXL_NODE_SWITCH;
+	use fm_library::cxl_topology::CXL_NODE_VPPB;
+	use fm_library::cxl_topology::CXL_NODE_PHYSICAL_PORT;
+	use fm_library::cxl_topology::CXL_EDGE_CONTAINS;
+	use fm_library::cxl_topology::CXL_EDGE_LINK;
+	use fm_library::cxl_topology::CXL_EDGE_BINDING;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_context::CxlCciPath;
+	use crate::cxl_fm_discover_command::discover_fabric;
+	use crate::cxl_dcd_command::add_extents;
+	use crate::cxl_dcd_command::release_extents;
+
+	/*
+	 * enum CxlPlanAction - the way to execute plan step
+	 * @Command: FM API command sent to the component
+	 * @AddCapacity: DC add with extent list bookkeeping
+	 * @ReleaseCapacity: DC release with extent list bookkeeping
+	 */
+	pub enum CxlPlanAction {
+		Command(CxlCciPath, u16, Vec<u8>),
+		AddCapacity(CxlFmRequest),
+		ReleaseCapacity(CxlFmRequest),
+	}
+
+	pub type CxlPlanEntry = (CxlPlanStep, CxlPlanAction);
+
+	/*
+	 * Is request a dry run of command that cannot be planned?
+	 * Such request would be executed, so it is rejected.
+	 */
+	pub fn is_unsupported_dry_run(request: &CxlFmRequest) -> bool {
+		request.is_dry_run() && !CXL_FM_PLANNED_COMMANDS.contains(&request.command.as_str())
+	}
+
+	pub fn plan_step(opcode: u16, target: &str, description: String) -> CxlPlanStep {
+		CxlPlanStep {
+			opcode,
+			target: String::from(target),
+			description,
+			status: CXL_PLAN_STEP_PENDING,
+			error: String::new(),
+		}
+	}
+
+	/*
+	 * Get switch name defined by request
+	 */
+	pub fn switch_arg(request: &CxlFmRequest) -> Result<&str, CxlFmError> {
+		request.get(CXL_FM_SWITCH_ARG)
+			.ok_or_else(|| CxlFmError::InvalidInput(format!("{} is not defined",
+									CXL_FM_SWITCH_ARG)))
+	}
+
+	/*
+	 * Get name of discovered device defined by request
+	 * by its name or by index of its CCI
+	 */
+	pub fn device_arg(request: &CxlFmRequest, ctx: &CxlFmContext,
+			  topology: &FabricTopology) -> Result<String, CxlFmError> {
+		let device = request.get(CXL_FM_DEVICE_ARG).unwrap_or("0");
+
+		if topology.nodes.iter().any(|node| is_device_kind(node.kind) && node.name == device) {
+			return Ok(String::from(device));
+		}
+
+		device.parse::<usize>().ok()
+			.and_then(|index| ctx.device_name(index))
+			.ok_or_else(|| CxlFmError::NotFound(format!("device {}", device)))
+	}
+
+	/*
+	 * Get CCI index of discovered switch
+	 */
+	pub fn switch_cci(ctx: &CxlFmContext, topology: &FabricTopology,
+			  switch: &str) -> Result<usize, CxlFmError> {
+		topology.find(CXL_NODE_SWITCH, switch)
+			.and_then(|node| node.get("cci"))
+			.and_then(|cci| ctx.device_index(cci))
+			.ok_or_else(|| CxlFmError::NotFound(format!("switch {}", switch)))
+	}
+
+	/*
+	 * Find discovered vPPB
+	 */
+	pub fn find_vppb<'a>(topology: &'a FabricTopology, switch: &str,
+			     vcs: u8, vppb: u8) -> Result<&'a CxlTopologyNode, CxlFmError> {
+		let name = format!("{}/vcs{}/vppb{}", switch, vcs, vppb);
+
+		topology.find(CXL_NODE_VPPB, &name)
+			.ok_or_else(|| CxlFmError::NotFound(format!("vPPB {}", name)))
+	}
+
+	/*
+	 * Get VCS and vPPB IDs of discovered vPPB
+	 */
+	pub fn vppb_ids(vppb: &CxlTopologyNode) -> Option<(u8, u8)> {
+		let vcs = vppb.get("vcs").and_then(|vcs| vcs.parse::<u8>().ok())?;
+		let id = vppb.get("id").and_then(|id| id.parse::<u8>().ok())?;
+
+		Some((vcs, id))
+	}
+
+	/*
+	 * Get name of the object the vPPB is bound to
+	 */
+	pub fn bound_target(topology: &FabricTopology, vppb: &CxlTopologyNode) -> Option<String> {
+		topology.children(vppb.id, CXL_EDGE_BINDING)
+			.first()
+			.and_then(|target| topology.node(*target))
+			.map(|target| target.name.clone())
+	}
+
+	/*
+	 * Get name of the object the vPPB should be bound to: downstream
+	 * port with attached device or LD of the device
+	 */
+	pub fn binding_target(topology: &FabricTopology, switch: &str,
+			      port: Option<u8>, ld: Option<u16>) -> Result<Option<String>, CxlFmError> {
+		let port = match port {
+			Some(port) => format!("{}/port{}", switch, port),
+			None => return Ok(None),
+		};
+
+		let node = topology.find(CXL_NODE_PHYSICAL_PORT, &port)
+				.ok_or_else(|| CxlFmError::NotFound(format!("port {}", port)))?;
+
+		if node.get("state") != Some(port_state_name(CXL_PORT_STATE_DSP)) {
+			return Err(CxlFmError::InvalidInput(format!("{} is not downstream port", port)));
+		}
+
+		let device = topology.children(node.id, CXL_EDGE_LINK)
+			.first()
+			.and_then(|device| topology.node(*device))
+			.ok_or_else(|| CxlFmError::InvalidInput(format!("no device at {}", port)))?;
+
+		match ld {
+			Some(ld) => {
+				let lds = device.get("lds")
+					.and_then(|lds| lds.parse::<u16>().ok())
+					.unwrap_or(1);
+
+				if lds < 2 || ld >= lds {
+					return Err(CxlFmError::InvalidInput(format!("{} has no LD {}",
+										    device.name, ld)));
+				}
+
+				Ok(Some(format!("{}/ld{}", device.name, ld)))
+			},
+			None => Ok(Some(port)),
+		}
+	}
+
+	/*
+	 * Find vPPBs bound to the target or to the objects overlapping it:
+	 * the whole port overlaps every LD of its device
+	 */
+	pub fn target_bindings(topology: &FabricTopology, target: &str) -> Vec<String> {
+		let node = match topology.nodes.iter().find(|node| node.name == target) {
+			Some(node) => node,
+			None => return Vec::new(),
+		};
+		let mut overlapping = vec![node.id];
+
+		if node.kind == CXL_NODE_PHYSICAL_PORT {
+			for device in topology.children(node.id, CXL_EDGE_LINK) {
+				overlapping.extend(topology.children(device, CXL_EDGE_CONTAINS));
+			}
+		} else if let Some(device) = topology.parent(node.id, CXL_EDGE_CONTAINS) {
+			overlapping.extend(topology.parent(device, CXL_EDGE_LINK));
+		}
+
+		topology.bindings().iter()
+			.filter(|(_, target)| overlapping.contains(target))
+			.filter_map(|(vppb, _)| topology.node(*vppb))
+			.map(|vppb| vppb.name.clone())
+			.collect()
+	}
+
+	/*
+	 * Build Bind vPPB step (LD is not defined for the whole port).
+	 * Resources of unavailable port cannot be bound.
+	 */
+	pub fn bind_entry(ctx: &CxlFmContext, topology: &FabricTopology, switch: &str,
+			  vcs: u8, vppb: u8, port: u8, ld: Option<u16>) -> Result<CxlPlanEntry, CxlFmError> {
+		let port_name = format!("{}/port{}", switch, port);
+
+		if ctx.is_unavailable(&port_name) {
+			return Err(CxlFmError::InvalidInput(format!("{} is unavailable", port_name)));
+		}
+
+		let cci = switch_cci(ctx, topology, switch)?;
+		let vppb_node = find_vppb(topology, switch, vcs, vppb)?;
+		let target = binding_target(topology, switch, Some(port), ld)?
+				.unwrap_or_default();
+		let fm_api_request = CxlBindVppbRequest {
+			vcs_id: vcs,
+			vppb_id: vppb,
+			port_id: port,
+			ld_id: ld.unwrap_or(CXL_BIND_PORT_LD_ID),
+		};
+
+		Ok((plan_step(CXL_FM_API_BIND_VPPB, switch,
+			      format!("bind {} to {}", vppb_node.name, target)),
+		    CxlPlanAction::Command(CxlCciPath::Direct(cci), CXL_FM_API_BIND_VPPB,
+					   fm_api_request.to_bytes())))
+	}
+
+	/*
+	 * Build Unbind vPPB step
+	 */
+	pub fn unbind_entry(ctx: &CxlFmContext, topology: &FabricTopology, switch: &str,
+			    vcs: u8, vppb: u8, option: u8) -> Result<CxlPlanEntry, CxlFmError> {
+		let cci = switch_cci(ctx, topology, switch)?;
+		let vppb_node = find_vppb(topology, switch, vcs, vppb)?;
+		let current = bound_target(topology, vppb_node)
+			.ok_or_else(|| CxlFmError::InvalidInput(format!("{} is not bound",
+									vppb_node.name)))?;
+		let fm_api_request = CxlUnbindVppbRequest {
+			vcs_id: vcs,
+			vppb_id: vppb,
+			option,
+		};
+
+		Ok((plan_step(CXL_FM_API_UNBIND_VPPB, switch,
+			      format!("unbind {} from {} ({})", vppb_node.name, current,
+				      unbind_option_name(option))),
+		    CxlPlanAction::Command(CxlCciPath::Direct(cci), CXL_FM_API_UNBIND_VPPB,
+					   fm_api_request.to_bytes())))
+	}
+
+	/*
+	 * Execute plan steps in order, steps after
+	 * the first failed one are skipped. Every step is
+	 * journaled before and after its execution.
+	 */
+	pub fn execute_plan(ctx: &CxlFmContext, entries: Vec<CxlPlanEntry>) -> CxlFabricPlan {
+		let mut plan = CxlFabricPlan::default();
+		let mut failed = false;
+
+		for (index, (mut step, action)) in entries.into_iter().enumerate() {
+			if failed {
+				step.status = CXL_PLAN_STEP_SKIPPED;
+				plan.steps.push(step);
+				continue;
+			}
+
+			ctx.journal_step(index, CXL_PLAN_STEP_PENDING);
+
+			let result = match action {
+				CxlPlanAction::Command(path, opcode, payload) => {
+					ctx.execute_path(path, opcode, &payload).map(|_| ())
+				},
+				CxlPlanAction::AddCapacity(request) => {
+					add_extents(&request, ctx).and_then(|added| match added.pending_length {
+						0 => Ok(()),
+						pending => Err(CxlFmError::Remote(format!("host has not accepted {}",
+											  format_size(pending)))),
+					})
+				},
+				CxlPlanAction::ReleaseCapacity(request) => release_extents(&request, ctx).map(|_| ()),
+			};
+
+			match result {
+				Ok(()) => step.status = CXL_PLAN_STEP_DONE,
+				Err(e) => {
+					step.status = CXL_PLAN_STEP_FAILED;
+					step.error = e.to_string();
+					failed = true;
+				},
+			}
+
+			ctx.journal_step(index, step.status);
+			plan.steps.push(step);
+		}
+
+		plan
+	}
+
+	/*
+	 * Execute the plan or only report it for dry run request.
+	 * Topology is refreshed after any change of the fabric.
+	 */
+	pub fn run_plan(request: &CxlFmRequest, ctx: &CxlFmContext,
+			entries: Vec<CxlPlanEntry>) -> Result<CxlFabricPlan, CxlFmError> {
+		if request.is_dry_run() {
+			return Ok(CxlFabricPlan {
+				steps: entries.into_iter().map(|(step, _)| step).collect(),
+			});
+		}
+
+		let plan = execute_plan(ctx, entries);
+
+		if plan.steps.iter().any(|step| step.status == CXL_PLAN_STEP_DONE) {
+			discover_fabric(ctx, false)?;
+		}
+
+		Ok(plan)
+	}
+
+	/*
+	 * Run the plan and send it as responce,
+	 * returns false if the plan could not be run
+	 */
+	pub fn send_plan(stream: &TcpStream, request: &CxlFmRequest, ctx: &CxlFmContext,
+			 entries: Result<Vec<CxlPlanEntry>, CxlFmError>, env: &CxlFmOptions) -> bool {
+		match entries.and_then(|entries| run_plan(request, ctx, entries)) {
+			Ok(plan) => {
+				send_payload(stream, &plan.to_bytes(), env);
+				true
+			},
+			Err(e) => {
+				send_error(stream, &e, env);
+				false
+			},
+		}
+	}
+}
@@ -0,0 +1,231 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_reconcile_command {
+	use std::net::{TcpStream};
+	use std::sync::Arc;
+	use std::thread;
+	use std::time::Instant;
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::send_payload;
+	use fm_library::cxl_fm_lib::send_error;
+	use fm_library::cxl_fm_lib::CXL_FM_RECONCILE_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_REMEDIATE_ARG;
+	use fm_library::cxl_desired_state::CxlFabricSpec;
+	use fm_library::cxl_desired_state::CxlFabricPlan;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_DONE;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_FAILED;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_SKIPPED;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_WARNING;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_FAILURE;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_DRIFT;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_REMEDIATION;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_discover_command::discover_fabric;
+	use crate::cxl_fm_plan::plan_step;
+	use crate::cxl_fm_plan::execute_plan;
+	use crate::cxl_desired_state_command::build_plan;
+	use crate::cxl_fm_hot_plug::unavailable_port;
+
+	/*
+	 * Report drift that was not reported by the previous pass
+	 */
+	fn report_drift(ctx: &CxlFmContext, drift: Vec<(u8, String, String)>) {
+		let mut reported = lock_shared(&ctx.drift);
+
+		for (severity, component, message) in &drift {
+			let key = format!("{}: {}", component, message);
+
+			if !reported.contains(&key) {
+				ctx.report_event(*severity, CXL_FM_EVENT_DRIFT, component, message);
+			}
+		}
+
+		*reported = drift.into_iter()
+			.map(|(_, component, message)| format!("{}: {}", component, message))
+			.collect();
+	}
+
+	/*
+	 * Compare the fabric with stored desired state and report drift.
+	 * Drift of resources under manual control is only reported,
+	 * other resources are remediated if it is requested.
+	 * Resources that cannot be reconciled don't block the others,
+	 * resources of unavailable and lost ports are skipped.
+	 */
+	pub fn reconcile(ctx: &CxlFmContext,
+			 is_remediate: bool) -> Result<CxlFabricPlan, CxlFmError> {
+		let spec = lock_shared(&ctx.desired_state).clone()
+			.ok_or_else(|| CxlFmError::NotFound(String::from("desired state is not applied")))?;
+
+		let topology = discover_fabric(ctx, true)?;
+		let mut plan = CxlFabricPlan::default();
+		let mut automatic = CxlFabricSpec::default();
+		let mut manual = CxlFabricSpec::default();
+		let mut drift = Vec::new();
+
+		for (name, is_manual, resource) in spec.resources() {
+			if let Some(port) = unavailable_port(ctx, &resource) {
+				let status = format!("{} is {}", port, ctx.port_status(&port));
+				let mut step = plan_step(0, &name, status);
+
+				step.status = CXL_PLAN_STEP_SKIPPED;
+				plan.steps.push(step);
+				continue;
+			}
+
+			match build_plan(ctx, &topology, &resource) {
+				Ok(entries) if entries.is_empty() => {},
+				Ok(_) if is_manual => manual.merge(resource),
+				Ok(_) => automatic.merge(resource),
+				Err(e) => {
+					let mut step = plan_step(0, &name, String::from("reconcile"));
+
+					drift.push((CXL_EVENT_SEVERITY_FAILURE, name,
+						    format!("cannot reconcile: {}", e)));
+					step.status = CXL_PLAN_STEP_FAILED;
+					step.error = e.to_string();
+					plan.steps.push(step);
+				},
+			}
+		}
+
+		for (mut step, _) in build_plan(ctx, &topology, &manual)? {
+			drift.push((CXL_EVENT_SEVERITY_WARNING, step.target.clone(),
+				    format!("{} (manual)", step.description)));
+			step.status = CXL_PLAN_STEP_SKIPPED;
+			plan.steps.push(step);
+		}
+
+		let entries = build_plan(ctx, &topology, &automatic)?;
+
+		for (step, _) in &entries {
+			drift.push((CXL_EVENT_SEVERITY_WARNING, step.target.clone(),
+				    step.description.clone()));
+		}
+
+		report_drift(ctx, drift);
+
+		if !is_remediate {
+			plan.steps.extend(entries.into_iter().map(|(step, _)| step));
+			return Ok(plan);
+		}
+
+		let executed = execute_plan(ctx, entries);
+
+		for step in &executed.steps {
+			match step.status {
+				CXL_PLAN_STEP_DONE => {
+					ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_REMEDIATION,
+							 &step.target, &step.description);
+				},
+				CXL_PLAN_STEP_FAILED => {
+					ctx.report_event(CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_REMEDIATION,
+							 &step.target,
+							 &format!("{}: {}", step.description, step.error));
+				},
+				_ => {},
+			}
+		}
+
+		if executed.steps.iter().any(|step| step.status == CXL_PLAN_STEP_DONE) {
+			discover_fabric(ctx, false)?;
+		}
+
+		plan.steps.extend(executed.steps);
+		Ok(plan)
+	}
+
+	/*
+	 * Does request ask to fix the drift?
+	 */
+	pub fn is_remediate(request: &CxlFmRequest) -> bool {
+		!request.is_dry_run() &&
+		request.get(CXL_FM_REMEDIATE_ARG).is_some_and(|value| value != "0")
+	}
+
+	/*
+	 * Start periodic reconciliation by the live policies.
+	 * The pass is skipped until desired state is applied.
+	 * Remediation pass is journaled as operation of the daemon.
+	 */
+	pub fn start_reconciliation(ctx: Arc<CxlFmContext>, env: &CxlFmOptions) {
+		let is_debug = env.is_debug;
+		let request = CxlFmRequest::new(CXL_FM_RECONCILE_COMMAND)
+				.arg(CXL_FM_REMEDIATE_ARG, "1");
+		let mut last = Instant::now();
+
+		thread::spawn(move || loop {
+			ctx.wait_interval(&mut last, |policies| policies.reconcile_interval);
+
+			let is_remediate = ctx.policies().remediate;
+			let _operation = ctx.lock_operation();
+
+			if ctx.is_standby() || lock_shared(&ctx.desired_state).is_none() {
+				continue;
+			}
+
+			if is_remediate {
+				if let Err(e) = ctx.begin_operation(&request) {
+					println!("Reconciliation is skipped: {}", e);
+					continue;
+				}
+			}
+
+			let result = reconcile(&ctx, is_remediate);
+
+			match &result {
+				Ok(plan) if is_debug => {
+					println!("Reconciliation: {} drifted step(s)", plan.steps.len());
+				},
+				Ok(_) => {},
+				Err(e) => println!("Reconciliation failed: {}", e),
+			}
+
+			if is_remediate {
+				ctx.end_operation(false, result.is_ok());
+			}
+		});
+	}
+
+	/*
+	 * Run reconciliation pass on request
+	 */
+	pub fn reconcile_now(stream: &TcpStream, request: &CxlFmRequest,
+			     ctx: &CxlFmContext, env: &CxlFmOptions) -> bool {
+		if env.is_debug {
+			println!("{}", CXL_FM_RECONCILE_COMMAND);
+		}
+
+		let result = reconcile(ctx, is_remediate(request));
+
+		match &result {
+			Ok(plan) => send_payload(stream, &plan.to_bytes(), env),
+			Err(e) => send_error(stream, e, env),
+		}
+
+		result.is_ok()
+	}
+}
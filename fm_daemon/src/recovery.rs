@@ -0,0 +1,260 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_recovery {
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_BIND_LD_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_UNBIND_LD_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_SET_LD_ALLOCATION_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_PPB_BIND_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_PPB_UNBIND_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_BIND_PHYSICAL_PORT_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DCD_SET_CONFIG_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_DCD_GET_EXTENT_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_APPLY_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_RECONCILE_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_PLANNED_COMMANDS;
+	use fm_library::cxl_desired_state::CxlFabricSpec;
+	use fm_library::cxl_desired_state::CxlFabricPlan;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_PENDING;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_DONE;
+	use fm_library::cxl_topology::FabricTopology;
+	use fm_library::cxl_topology::node_kind_name;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_INFO;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_WARNING;
+	use fm_library::cxl_fm_event::CXL_EVENT_SEVERITY_FAILURE;
+	use fm_library::cxl_fm_event::CXL_FM_EVENT_RECOVERY;
+	use crate::CxlFmContext;
+	use crate::cxl_fm_context::lock_shared;
+	use crate::cxl_fm_state_store::CxlFmOperation;
+	use crate::cxl_fm_state_store::CxlInventoryItem;
+	use crate::cxl_fm_state_store::inventory_of;
+	use crate::cxl_fm_discover_command::discover_fabric;
+	use crate::cxl_fm_plan::run_plan;
+	use crate::cxl_ppb_command::bind_plan;
+	use crate::cxl_ppb_command::unbind_plan;
+	use crate::cxl_logical_device_command::allocation_plan;
+	use crate::cxl_dcd_command::read_extent_list;
+	use crate::cxl_desired_state_command::apply_spec;
+	use crate::cxl_reconcile_command::reconcile;
+	use crate::cxl_reconcile_command::is_remediate;
+	use crate::cxl_fm_hot_plug::remove_port;
+	use crate::cxl_fm_hot_plug::revalidate_ports;
+
+	/*
+	 * Should request be journaled as operation?
+	 * Dry run and read-only requests don't change the fabric.
+	 * DC region configuration is changed without plan.
+	 */
+	pub fn is_journaled(request: &CxlFmRequest) -> bool {
+		match request.command.as_str() {
+			CXL_FM_RECONCILE_COMMAND => is_remediate(request),
+			CXL_FM_DCD_SET_CONFIG_COMMAND => !request.is_dry_run(),
+			command => !request.is_dry_run() && CXL_FM_PLANNED_COMMANDS.contains(&command),
+		}
+	}
+
+	/*
+	 * Execute interrupted operation again. The plan is rebuilt
+	 * against the fabric, so the changes that have reached
+	 * the hardware are not repeated. Operations that cannot
+	 * be re-planned are not repeated because their first
+	 * execution could have reached the hardware.
+	 */
+	fn resume_request(request: &CxlFmRequest,
+			  ctx: &CxlFmContext) -> Result<CxlFabricPlan, CxlFmError> {
+		match request.command.as_str() {
+			CXL_FM_APPLY_COMMAND => apply_spec(request, ctx),
+			CXL_FM_RECONCILE_COMMAND => reconcile(ctx, true),
+			CXL_FM_PPB_BIND_COMMAND |
+			CXL_FM_BIND_PHYSICAL_PORT_COMMAND |
+			CXL_FM_BIND_LD_COMMAND => run_plan(request, ctx, bind_plan(request, ctx)?),
+			CXL_FM_PPB_UNBIND_COMMAND |
+			CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND |
+			CXL_FM_UNBIND_LD_COMMAND => run_plan(request, ctx, unbind_plan(request, ctx)?),
+			CXL_FM_SET_LD_ALLOCATION_COMMAND => {
+				run_plan(request, ctx, allocation_plan(request, ctx)?)
+			},
+			CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND => remove_port(request, ctx),
+			command => Err(CxlFmError::InvalidInput(format!("{} is not repeated, its outcome is unknown",
+									command))),
+		}
+	}
+
+	/*
+	 * Resume operation that was interrupted by restart of the daemon
+	 */
+	fn resume_operation(ctx: &CxlFmContext, id: u64, operation: &CxlFmOperation) {
+		let component = format!("operation{}", id);
+		let pending = operation.steps.iter()
+			.filter(|(_, status)| **status == CXL_PLAN_STEP_PENDING)
+			.count();
+
+		ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_RECOVERY, &component,
+				 &format!("resume interrupted {} ({}, {} step(s) in doubt)",
+					  operation.request.split(' ').next().unwrap_or_default(),
+					  operation.outcome(), pending));
+
+		if let Some(store) = lock_shared(&ctx.store).as_mut() {
+			store.resume_operation(id);
+		}
+
+		let result = CxlFmRequest::parse(&operation.request)
+			.and_then(|request| resume_request(&request, ctx));
+
+		match &result {
+			Ok(plan) => {
+				let done = plan.steps.iter()
+					.filter(|step| step.status == CXL_PLAN_STEP_DONE)
+					.count();
+
+				ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_RECOVERY, &component,
+						 &format!("resumed, {} step(s) done", done));
+			},
+			Err(e) => {
+				ctx.report_event(CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_RECOVERY, &component,
+						 &format!("cannot be resumed: {}", e));
+			},
+		}
+
+		/*
+		 * Operation that could not be resumed has unknown outcome
+		 */
+		ctx.end_operation(true, result.is_ok());
+	}
+
+	/*
+	 * Compare known switches and devices with discovered ones
+	 */
+	fn revalidate_inventory(ctx: &CxlFmContext, known: &[CxlInventoryItem],
+				topology: &FabricTopology) {
+		let discovered = inventory_of(topology);
+		let find = |items: &[CxlInventoryItem], item: &CxlInventoryItem| {
+			items.iter()
+				.find(|other| other.kind == item.kind && other.name == item.name)
+				.cloned()
+		};
+
+		for item in known {
+			match find(&discovered, item) {
+				None => {
+					ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_RECOVERY, &item.name,
+							 &format!("{} is missing", node_kind_name(item.kind)));
+				},
+				Some(found) if found.serial != item.serial => {
+					ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_RECOVERY, &item.name,
+							 &format!("serial number is changed from {} to {}",
+								  item.serial, found.serial));
+				},
+				Some(_) => {},
+			}
+		}
+
+		for item in &discovered {
+			if find(known, item).is_none() {
+				ctx.report_event(CXL_EVENT_SEVERITY_INFO, CXL_FM_EVENT_RECOVERY, &item.name,
+						 &format!("new {}", node_kind_name(item.kind)));
+			}
+		}
+	}
+
+	/*
+	 * Compare stored DC extents with extents reported by devices.
+	 * Device is the source of truth, the store follows it.
+	 */
+	fn revalidate_extents(ctx: &CxlFmContext) {
+		let stored: Vec<_> = lock_shared(&ctx.dc_extents)
+			.iter()
+			.map(|(key, extents)| (*key, extents.clone()))
+			.collect();
+
+		for ((device, host_id), extents) in stored {
+			let component = format!("{}/host{}",
+						ctx.device_name(device).unwrap_or_default(), host_id);
+			let request = CxlFmRequest::new(CXL_FM_DCD_GET_EXTENT_COMMAND)
+					.arg(CXL_FM_DEVICE_ARG, &device.to_string());
+
+			match read_extent_list(&request, ctx, host_id) {
+				Ok(list) if list.extents == extents => {},
+				Ok(list) => {
+					ctx.report_event(CXL_EVENT_SEVERITY_WARNING, CXL_FM_EVENT_RECOVERY, &component,
+							 &format!("device has {} extent(s), {} stored",
+								  list.extents.len(), extents.len()));
+					ctx.update_dc_extents(device, host_id, list.extents);
+				},
+				Err(e) => {
+					ctx.report_event(CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_RECOVERY, &component,
+							 &format!("cannot read extents: {}", e));
+				},
+			}
+		}
+	}
+
+	/*
+	 * Restore the daemon state from the store: re-read the fabric
+	 * and compare it with the stored state, then resume
+	 * the interrupted operations
+	 */
+	pub fn recover(ctx: &CxlFmContext) {
+		let state = match lock_shared(&ctx.store).as_ref() {
+			Some(store) => store.state.clone(),
+			None => return,
+		};
+
+		lock_shared(&ctx.events).restore(state.events.iter().cloned());
+
+		if let Some(content) = &state.desired_state {
+			match CxlFabricSpec::parse(content) {
+				Ok(spec) => *lock_shared(&ctx.desired_state) = Some(spec),
+				Err(e) => {
+					ctx.report_event(CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_RECOVERY, "fm",
+							 &format!("stored desired state is invalid: {}", e));
+				},
+			}
+		}
+
+		lock_shared(&ctx.dc_extents).extend(state.dc_extents.clone());
+		lock_shared(&ctx.unavailable).extend(state.unavailable.iter().cloned());
+		lock_shared(&ctx.lost).extend(state.lost.clone());
+
+		match discover_fabric(ctx, true) {
+			Ok(topology) if !state.inventory.is_empty() => {
+				revalidate_inventory(ctx, &state.inventory, &topology);
+			},
+			Ok(_) => {},
+			Err(e) => {
+				ctx.report_event(CXL_EVENT_SEVERITY_FAILURE, CXL_FM_EVENT_RECOVERY, "fm",
+						 &format!("cannot discover fabric: {}", e));
+			},
+		}
+
+		revalidate_extents(ctx);
+		revalidate_ports(ctx);
+
+		for (id, operation) in &state.operations {
+			resume_operation(ctx, *id, operation);
+		}
+	}
+}
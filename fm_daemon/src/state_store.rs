@@ -0,0 +1,1009 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM daemon implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_state_store {
+	use std::collections::BTreeMap;
+	use std::collections::BTreeSet;
+	use std::collections::VecDeque;
+	use std::fs;
+	use std::fs::{File, OpenOptions};
+	use std::io::{prelude::*, SeekFrom};
+	use std::path::Path;
+	use std::time::{SystemTime, UNIX_EPOCH};
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::to_hex;
+	use fm_library::cxl_fm_lib::from_hex;
+	use fm_library::cxl_fm_api::CxlPayloadReader;
+	use fm_library::cxl_fm_api::CxlPayloadWriter;
+	use fm_library::cxl_dcd_lib::CxlDcExtent;
+	use fm_library::cxl_dcd_lib::CxlDcExtentList;
+	use fm_library::cxl_switch_lib::CXL_BIND_PORT_LD_ID;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_DONE;
+	use fm_library::cxl_desired_state::CXL_PLAN_STEP_FAILED;
+	use fm_library::cxl_topology::FabricTopology;
+	use fm_library::cxl_topology::is_device_kind;
+	use fm_library::cxl_topology::CXL_NODE_SWITCH;
+	use fm_library::cxl_fm_event::CxlFmEvent;
+	use crate::cxl_fm_event_log::CXL_FM_EVENT_LOG_CAPACITY;
+
+	/*
+	 * Files of the state store
+	 */
+	pub const CXL_FM_STATE_JOURNAL_FILE: &str = "journal";
+	pub const CXL_FM_STATE_SNAPSHOT_FILES: [&str; 2] = ["snapshot.0", "snapshot.1"];
+
+	/*
+	 * Number of journal records that triggers a new snapshot
+	 */
+	pub const CXL_FM_STATE_SNAPSHOT_INTERVAL: usize = 256;
+
+	/*
+	 * Maximal number of audit records kept by the store
+	 */
+	pub const CXL_FM_STATE_AUDIT_CAPACITY: usize = 1024;
+
+	/*
+	 * Snapshot header and trailer
+	 */
+	const CXL_FM_STATE_MAGIC: &str = "CXL_FM_STATE";
+	const CXL_FM_STATE_TRAILER: &str = "END";
+
+	/*
+	 * Record types
+	 */
+	const CXL_FM_RECORD_INVENTORY: &str = "INVENTORY";
+	const CXL_FM_RECORD_DESIRED_STATE: &str = "DESIRED_STATE";
+	const CXL_FM_RECORD_EXTENTS: &str = "EXTENTS";
+	const CXL_FM_RECORD_BEGIN: &str = "BEGIN";
+	const CXL_FM_RECORD_STEP: &str = "STEP";
+	const CXL_FM_RECORD_FINISH: &str = "FINISH";
+	const CXL_FM_RECORD_AUDIT: &str = "AUDIT";
+	const CXL_FM_RECORD_EVENT: &str = "EVENT";
+	const CXL_FM_RECORD_UNAVAILABLE: &str = "UNAVAILABLE";
+	const CXL_FM_RECORD_LOST: &str = "LOST";
+	const CXL_FM_RECORD_RECOVERED: &str = "RECOVERED";
+
+	/*
+	 * struct CxlInventoryItem - fabric component known to FM
+	 * @kind: topology node kind
+	 * @name: component name
+	 * @serial: serial number (empty if component has no one)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlInventoryItem {
+		pub kind: u8,
+		pub name: String,
+		pub serial: String,
+	}
+
+	/*
+	 * Get switches and devices of discovered fabric
+	 */
+	pub fn inventory_of(topology: &FabricTopology) -> Vec<CxlInventoryItem> {
+		topology.nodes.iter()
+			.filter(|node| node.kind == CXL_NODE_SWITCH || is_device_kind(node.kind))
+			.map(|node| CxlInventoryItem {
+				kind: node.kind,
+				name: node.name.clone(),
+				serial: String::from(node.get("serial").unwrap_or_default()),
+			})
+			.collect()
+	}
+
+	/*
+	 * struct CxlFmLostBinding - vPPB binding lost with the device
+	 * @vcs: VCS ID
+	 * @vppb: vPPB ID
+	 * @ld: LD ID (None for the whole port)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmLostBinding {
+		pub vcs: u8,
+		pub vppb: u8,
+		pub ld: Option<u16>,
+	}
+
+	/*
+	 * struct CxlFmLostPort - resources of downstream port lost
+	 *                        by surprise removal or link failure
+	 * @port: downstream port
+	 * @device: device that was attached to the port
+	 * @serial: serial number of the device (empty if it is unknown)
+	 * @bindings: vPPB bindings of the port and LDs of the device
+	 * @extents: DC extents of hosts granted by the device
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmLostPort {
+		pub port: String,
+		pub device: String,
+		pub serial: String,
+		pub bindings: Vec<CxlFmLostBinding>,
+		pub extents: Vec<(u16, Vec<CxlDcExtent>)>,
+	}
+
+	impl CxlFmLostPort {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_string(&self.port);
+			writer.write_string(&self.device);
+			writer.write_string(&self.serial);
+			writer.write_u16(self.bindings.len() as u16);
+
+			for binding in &self.bindings {
+				writer.write_u8(binding.vcs);
+				writer.write_u8(binding.vppb);
+				writer.write_u16(binding.ld.unwrap_or(CXL_BIND_PORT_LD_ID));
+			}
+
+			writer.write_u16(self.extents.len() as u16);
+
+			for (host_id, extents) in &self.extents {
+				writer.write_u16(*host_id);
+				writer.write_u32(extents.len() as u32);
+
+				for extent in extents {
+					extent.write(&mut writer);
+				}
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlFmLostPort, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+			let mut lost = CxlFmLostPort {
+				port: reader.read_string()?,
+				device: reader.read_string()?,
+				serial: reader.read_string()?,
+				..Default::default()
+			};
+
+			for _ in 0..reader.read_u16()? {
+				let vcs = reader.read_u8()?;
+				let vppb = reader.read_u8()?;
+				let ld = reader.read_u16()?;
+
+				lost.bindings.push(CxlFmLostBinding {
+					vcs,
+					vppb,
+					ld: (ld != CXL_BIND_PORT_LD_ID).then_some(ld),
+				});
+			}
+
+			for _ in 0..reader.read_u16()? {
+				let host_id = reader.read_u16()?;
+				let mut extents = Vec::new();
+
+				for _ in 0..reader.read_u32()? {
+					extents.push(CxlDcExtent::read(&mut reader)?);
+				}
+
+				lost.extents.push((host_id, extents));
+			}
+
+			Ok(lost)
+		}
+	}
+
+	/*
+	 * struct CxlFmOperation - fabric change started by FM
+	 * @request: request line of the operation
+	 * @steps: status of plan steps that were started
+	 *
+	 * Step is recorded as pending before it is executed,
+	 * so pending step of interrupted operation could
+	 * be executed or not.
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmOperation {
+		pub request: String,
+		pub steps: BTreeMap<u16, u8>,
+	}
+
+	impl CxlFmOperation {
+		/*
+		 * Describe outcome of the operation
+		 */
+		pub fn outcome(&self) -> String {
+			let count = |status| self.steps.values()
+				.filter(|step| **step == status)
+				.count();
+
+			if self.steps.is_empty() {
+				return String::from("no steps executed");
+			}
+
+			format!("{} step(s) done, {} failed",
+				count(CXL_PLAN_STEP_DONE), count(CXL_PLAN_STEP_FAILED))
+		}
+
+		/*
+		 * Describe result of the finished operation
+		 */
+		pub fn result(&self, is_done: bool) -> String {
+			match is_done {
+				true => self.outcome(),
+				false => format!("failed, {}", self.outcome()),
+			}
+		}
+	}
+
+	/*
+	 * struct CxlFmAuditRecord - finished operation
+	 * @timestamp: time of completion (nanoseconds since Unix epoch)
+	 * @request: request line of the operation
+	 * @result: outcome of the operation
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmAuditRecord {
+		pub timestamp: u64,
+		pub request: String,
+		pub result: String,
+	}
+
+	/*
+	 * enum CxlFmStateRecord - change of persistent state
+	 * @Inventory: discovered switches and devices
+	 * @DesiredState: applied desired state (TOML)
+	 * @Extents: DC extents of (device, host) confirmed by device
+	 * @Begin: operation is started
+	 * @Step: status of the operation step is changed
+	 * @Finish: operation is finished
+	 * @Audit: audit record of finished operation
+	 * @Event: FM event
+	 * @Unavailable: downstream port is unavailable (or available again)
+	 * @Lost: resources of downstream port are lost
+	 * @Recovered: device of the port with lost resources is back
+	 *
+	 * Record is stored as one line:
+	 * TYPE [field] [field] ...
+	 * Binary and free-form fields are encoded as hex.
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub enum CxlFmStateRecord {
+		Inventory(Vec<CxlInventoryItem>),
+		DesiredState(String),
+		Extents(usize, CxlDcExtentList),
+		Begin(u64, String),
+		Step(u64, u16, u8),
+		Finish(u64),
+		Audit(CxlFmAuditRecord),
+		Event(CxlFmEvent),
+		Unavailable(String, bool),
+		Lost(CxlFmLostPort),
+		Recovered(String),
+	}
+
+	fn malformed(line: &str) -> CxlFmError {
+		CxlFmError::Protocol(format!("malformed state record: {}", line))
+	}
+
+	fn io_error(path: &Path, e: std::io::Error) -> CxlFmError {
+		CxlFmError::Io(format!("{}: {}", path.display(), e))
+	}
+
+	fn hex_string(hex: &str) -> Result<String, CxlFmError> {
+		String::from_utf8(from_hex(hex)?)
+			.map_err(|_| CxlFmError::Protocol(String::from("string is not UTF-8")))
+	}
+
+	impl CxlFmStateRecord {
+		/*
+		 * Convert record into line
+		 */
+		pub fn to_line(&self) -> String {
+			match self {
+				CxlFmStateRecord::Inventory(items) => {
+					let mut writer = CxlPayloadWriter::new();
+
+					writer.write_u16(items.len() as u16);
+
+					for item in items {
+						writer.write_u8(item.kind);
+						writer.write_string(&item.name);
+						writer.write_string(&item.serial);
+					}
+
+					format!("{} {}", CXL_FM_RECORD_INVENTORY, to_hex(&writer.into_bytes()))
+				},
+				CxlFmStateRecord::DesiredState(content) => {
+					format!("{} {}", CXL_FM_RECORD_DESIRED_STATE, to_hex(content.as_bytes()))
+				},
+				CxlFmStateRecord::Extents(device, list) => {
+					format!("{} {} {}", CXL_FM_RECORD_EXTENTS, device, to_hex(&list.to_bytes()))
+				},
+				CxlFmStateRecord::Begin(id, request) => {
+					format!("{} {} {}", CXL_FM_RECORD_BEGIN, id, to_hex(request.as_bytes()))
+				},
+				CxlFmStateRecord::Step(id, index, status) => {
+					format!("{} {} {} {}", CXL_FM_RECORD_STEP, id, index, status)
+				},
+				CxlFmStateRecord::Finish(id) => format!("{} {}", CXL_FM_RECORD_FINISH, id),
+				CxlFmStateRecord::Audit(record) => {
+					format!("{} {} {} {}", CXL_FM_RECORD_AUDIT, record.timestamp,
+						to_hex(record.request.as_bytes()),
+						to_hex(record.result.as_bytes()))
+				},
+				CxlFmStateRecord::Event(event) => {
+					format!("{} {}", CXL_FM_RECORD_EVENT, to_hex(&event.to_bytes()))
+				},
+				CxlFmStateRecord::Unavailable(port, is_unavailable) => {
+					format!("{} {} {}", CXL_FM_RECORD_UNAVAILABLE, to_hex(port.as_bytes()),
+						*is_unavailable as u8)
+				},
+				CxlFmStateRecord::Lost(lost) => {
+					format!("{} {}", CXL_FM_RECORD_LOST, to_hex(&lost.to_bytes()))
+				},
+				CxlFmStateRecord::Recovered(port) => {
+					format!("{} {}", CXL_FM_RECORD_RECOVERED, to_hex(port.as_bytes()))
+				},
+			}
+		}
+
+		/*
+		 * Parse record line
+		 */
+		pub fn parse(line: &str) -> Result<CxlFmStateRecord, CxlFmError> {
+			let fields: Vec<&str> = line.split(' ').collect();
+			let field = |index: usize| fields.get(index).copied().ok_or_else(|| malformed(line));
+			let number = |index: usize| field(index)?.parse::<u64>().map_err(|_| malformed(line));
+
+			let record = match field(0)? {
+				CXL_FM_RECORD_INVENTORY => {
+					let data = from_hex(field(1)?)?;
+					let mut reader = CxlPayloadReader::new(&data);
+					let mut items = Vec::new();
+
+					for _ in 0..reader.read_u16()? {
+						items.push(CxlInventoryItem {
+							kind: reader.read_u8()?,
+							name: reader.read_string()?,
+							serial: reader.read_string()?,
+						});
+					}
+
+					CxlFmStateRecord::Inventory(items)
+				},
+				CXL_FM_RECORD_DESIRED_STATE => CxlFmStateRecord::DesiredState(hex_string(field(1)?)?),
+				CXL_FM_RECORD_EXTENTS => {
+					CxlFmStateRecord::Extents(number(1)? as usize,
+								  CxlDcExtentList::from_bytes(&from_hex(field(2)?)?)?)
+				},
+				CXL_FM_RECORD_BEGIN => CxlFmStateRecord::Begin(number(1)?, hex_string(field(2)?)?),
+				CXL_FM_RECORD_STEP => {
+					CxlFmStateRecord::Step(number(1)?, number(2)? as u16, number(3)? as u8)
+				},
+				CXL_FM_RECORD_FINISH => CxlFmStateRecord::Finish(number(1)?),
+				CXL_FM_RECORD_AUDIT => {
+					CxlFmStateRecord::Audit(CxlFmAuditRecord {
+						timestamp: number(1)?,
+						request: hex_string(field(2)?)?,
+						result: hex_string(field(3)?)?,
+					})
+				},
+				CXL_FM_RECORD_EVENT => CxlFmStateRecord::Event(CxlFmEvent::from_bytes(&from_hex(field(1)?)?)?),
+				CXL_FM_RECORD_UNAVAILABLE => {
+					CxlFmStateRecord::Unavailable(hex_string(field(1)?)?, number(2)? != 0)
+				},
+				CXL_FM_RECORD_LOST => CxlFmStateRecord::Lost(CxlFmLostPort::from_bytes(&from_hex(field(1)?)?)?),
+				CXL_FM_RECORD_RECOVERED => CxlFmStateRecord::Recovered(hex_string(field(1)?)?),
+				_ => return Err(malformed(line)),
+			};
+
+			Ok(record)
+		}
+	}
+
+	/*
+	 * struct CxlFmPersistentState - state of FM that survives restart
+	 * @inventory: switches and devices of the last discovery
+	 * @desired_state: the last applied desired state (TOML)
+	 * @dc_extents: DC extents confirmed by device for (device, host)
+	 * @operations: operations that are not finished yet
+	 * @audit: records of finished operations
+	 * @events: the newest FM events
+	 * @unavailable: downstream ports with unavailable resources
+	 * @lost: downstream ports with lost resources, by name
+	 * @next_operation: ID of the next operation
+	 */
+	#[derive(Debug, Clone, Default)]
+	pub struct CxlFmPersistentState {
+		pub inventory: Vec<CxlInventoryItem>,
+		pub desired_state: Option<String>,
+		pub dc_extents: BTreeMap<(usize, u16), Vec<CxlDcExtent>>,
+		pub operations: BTreeMap<u64, CxlFmOperation>,
+		pub audit: VecDeque<CxlFmAuditRecord>,
+		pub events: VecDeque<CxlFmEvent>,
+		pub unavailable: BTreeSet<String>,
+		pub lost: BTreeMap<String, CxlFmLostPort>,
+		pub next_operation: u64,
+	}
+
+	impl CxlFmPersistentState {
+		/*
+		 * Change the state by the record
+		 */
+		pub fn apply(&mut self, record: CxlFmStateRecord) {
+			match record {
+				CxlFmStateRecord::Inventory(items) => self.inventory = items,
+				CxlFmStateRecord::DesiredState(content) => self.desired_state = Some(content),
+				CxlFmStateRecord::Extents(device, list) => {
+					self.dc_extents.insert((device, list.host_id), list.extents);
+				},
+				CxlFmStateRecord::Begin(id, request) => {
+					self.operations.insert(id, CxlFmOperation {
+						request,
+						steps: BTreeMap::new(),
+					});
+					self.next_operation = self.next_operation.max(id + 1);
+				},
+				CxlFmStateRecord::Step(id, index, status) => {
+					if let Some(operation) = self.operations.get_mut(&id) {
+						operation.steps.insert(index, status);
+					}
+				},
+				CxlFmStateRecord::Finish(id) => {
+					self.operations.remove(&id);
+				},
+				CxlFmStateRecord::Audit(record) => {
+					if self.audit.len() >= CXL_FM_STATE_AUDIT_CAPACITY {
+						self.audit.pop_front();
+					}
+
+					self.audit.push_back(record);
+				},
+				CxlFmStateRecord::Event(event) => {
+					if self.events.len() >= CXL_FM_EVENT_LOG_CAPACITY {
+						self.events.pop_front();
+					}
+
+					self.events.push_back(event);
+				},
+				CxlFmStateRecord::Unavailable(port, true) => {
+					self.unavailable.insert(port);
+				},
+				CxlFmStateRecord::Unavailable(port, false) => {
+					self.unavailable.remove(&port);
+				},
+				CxlFmStateRecord::Lost(lost) => {
+					self.lost.insert(lost.port.clone(), lost);
+				},
+				CxlFmStateRecord::Recovered(port) => {
+					self.lost.remove(&port);
+				},
+			}
+		}
+
+		/*
+		 * Get records that rebuild the state from scratch
+		 */
+		pub fn records(&self) -> Vec<CxlFmStateRecord> {
+			let mut records = vec![CxlFmStateRecord::Inventory(self.inventory.clone())];
+
+			if let Some(content) = &self.desired_state {
+				records.push(CxlFmStateRecord::DesiredState(content.clone()));
+			}
+
+			for ((device, host_id), extents) in &self.dc_extents {
+				records.push(CxlFmStateRecord::Extents(*device, CxlDcExtentList {
+					host_id: *host_id,
+					start_index: 0,
+					total_extents: extents.len() as u32,
+					generation: 0,
+					extents: extents.clone(),
+				}));
+			}
+
+			for (id, operation) in &self.operations {
+				records.push(CxlFmStateRecord::Begin(*id, operation.request.clone()));
+
+				for (index, status) in &operation.steps {
+					records.push(CxlFmStateRecord::Step(*id, *index, *status));
+				}
+			}
+
+			records.extend(self.audit.iter().cloned().map(CxlFmStateRecord::Audit));
+			records.extend(self.events.iter().cloned().map(CxlFmStateRecord::Event));
+			records.extend(self.unavailable.iter()
+				       .map(|port| CxlFmStateRecord::Unavailable(port.clone(), true)));
+			records.extend(self.lost.values().cloned().map(CxlFmStateRecord::Lost));
+			records
+		}
+	}
+
+	/*
+	 * struct CxlFmStateStore - durable state of FM daemon
+	 * @state: the current persistent state
+	 * @current: operation that receives step records
+	 * @journal: write-ahead journal of records since the last snapshot
+	 * @snapshots: two snapshot slots that are written in turn
+	 * @generation: generation of the last snapshot
+	 * @sequence: sequence number of the last record
+	 * @journaled: number of records since the last snapshot
+	 *
+	 * Every record is synced to the journal before the state
+	 * is changed. Snapshot is written into the older slot,
+	 * so torn snapshot leaves the previous one and the journal
+	 * intact. Snapshot line is "CXL_FM_STATE generation sequence
+	 * next_operation", then records and "END" line. Journal line
+	 * is "sequence record", records that are already included
+	 * into the snapshot are skipped on replay.
+	 *
+	 * Files are opened once, so the store is still writable
+	 * after the daemon drops privileges.
+	 */
+	pub struct CxlFmStateStore {
+		pub state: CxlFmPersistentState,
+		pub current: Option<u64>,
+		journal: File,
+		snapshots: Vec<File>,
+		generation: u64,
+		sequence: u64,
+		journaled: usize,
+	}
+
+	/*
+	 * Parse snapshot: generation, sequence and the state
+	 */
+	fn parse_snapshot(content: &str) -> Result<(u64, u64, CxlFmPersistentState), CxlFmError> {
+		let invalid = || CxlFmError::Protocol(String::from("invalid snapshot"));
+		let mut lines = content.lines();
+		let header: Vec<&str> = lines.next().ok_or_else(invalid)?.split(' ').collect();
+
+		if header.len() != 4 || header[0] != CXL_FM_STATE_MAGIC {
+			return Err(invalid());
+		}
+
+		let number = |index: usize| header[index].parse::<u64>().map_err(|_| invalid());
+		let mut state = CxlFmPersistentState {
+			next_operation: number(3)?,
+			..Default::default()
+		};
+
+		for line in lines {
+			if line == CXL_FM_STATE_TRAILER {
+				return Ok((number(1)?, number(2)?, state));
+			}
+
+			state.apply(CxlFmStateRecord::parse(line)?);
+		}
+
+		Err(invalid())
+	}
+
+	impl CxlFmStateStore {
+		/*
+		 * Open the store in the directory and restore the state
+		 * from the newest valid snapshot and the journal
+		 */
+		pub fn open(directory: &str) -> Result<CxlFmStateStore, CxlFmError> {
+			let directory = Path::new(directory);
+
+			fs::create_dir_all(directory).map_err(|e| io_error(directory, e))?;
+
+			let open = |name: &str, is_append: bool| {
+				let path = directory.join(name);
+
+				OpenOptions::new()
+					.read(true)
+					.write(!is_append)
+					.append(is_append)
+					.create(true)
+					.truncate(false)
+					.open(&path)
+					.map_err(|e| io_error(&path, e))
+			};
+
+			let mut store = CxlFmStateStore {
+				state: CxlFmPersistentState::default(),
+				current: None,
+				journal: open(CXL_FM_STATE_JOURNAL_FILE, true)?,
+				snapshots: CXL_FM_STATE_SNAPSHOT_FILES.iter()
+					.map(|name| open(name, false))
+					.collect::<Result<Vec<File>, CxlFmError>>()?,
+				generation: 0,
+				sequence: 0,
+				journaled: 0,
+			};
+
+			store.load()?;
+			store.snapshot()?;
+			Ok(store)
+		}
+
+		fn read(file: &mut File) -> Result<String, CxlFmError> {
+			let mut content = String::new();
+
+			file.seek(SeekFrom::Start(0))
+				.and_then(|_| file.read_to_string(&mut content))
+				.map_err(|e| CxlFmError::Io(e.to_string()))?;
+
+			Ok(content)
+		}
+
+		/*
+		 * Restore the state. Journal replay stops
+		 * at the first torn or malformed record.
+		 */
+		fn load(&mut self) -> Result<(), CxlFmError> {
+			for file in &mut self.snapshots {
+				if let Ok((generation, sequence, state)) = parse_snapshot(&Self::read(file)?) {
+					if generation > self.generation {
+						self.generation = generation;
+						self.sequence = sequence;
+						self.state = state;
+					}
+				}
+			}
+
+			for line in Self::read(&mut self.journal)?.split_inclusive('\n') {
+				let record = line.strip_suffix('\n')
+					.and_then(|line| line.split_once(' '))
+					.and_then(|(sequence, record)| {
+						Some((sequence.parse::<u64>().ok()?,
+						      CxlFmStateRecord::parse(record).ok()?))
+					});
+
+				let (sequence, record) = match record {
+					Some(record) => record,
+					None => {
+						println!("State journal is torn: {}", line.trim_end());
+						break;
+					},
+				};
+
+				if sequence > self.sequence {
+					self.state.apply(record);
+					self.sequence = sequence;
+				}
+			}
+
+			Ok(())
+		}
+
+		/*
+		 * Get snapshot of the whole state
+		 */
+		fn content(&self, generation: u64) -> String {
+			let mut content = format!("{} {} {} {}\n", CXL_FM_STATE_MAGIC, generation,
+						  self.sequence, self.state.next_operation);
+
+			for record in self.state.records() {
+				content.push_str(&record.to_line());
+				content.push('\n');
+			}
+
+			content.push_str(CXL_FM_STATE_TRAILER);
+			content.push('\n');
+			content
+		}
+
+		/*
+		 * Get snapshot of the state for replication.
+		 * Unfinished operations are included with their steps.
+		 */
+		pub fn export(&self) -> Vec<u8> {
+			self.content(self.generation).into_bytes()
+		}
+
+		/*
+		 * Replace the state by snapshot of another store
+		 * and make it durable
+		 */
+		pub fn import(&mut self, data: &[u8]) -> Result<(), CxlFmError> {
+			let content = String::from_utf8(data.to_vec())
+				.map_err(|_| CxlFmError::Protocol(String::from("invalid snapshot")))?;
+			let (_, _, state) = parse_snapshot(&content)?;
+
+			self.state = state;
+			self.current = None;
+			self.snapshot()
+		}
+
+		/*
+		 * Write the whole state into the older snapshot slot
+		 * and truncate the journal
+		 */
+		pub fn snapshot(&mut self) -> Result<(), CxlFmError> {
+			let generation = self.generation + 1;
+			let content = self.content(generation);
+			let file = &mut self.snapshots[(generation % 2) as usize];
+
+			file.set_len(0)
+				.and_then(|_| file.seek(SeekFrom::Start(0)))
+				.and_then(|_| file.write_all(content.as_bytes()))
+				.and_then(|_| file.sync_all())
+				.and_then(|_| self.journal.set_len(0))
+				.and_then(|_| self.journal.sync_all())
+				.map_err(|e| CxlFmError::Io(e.to_string()))?;
+
+			self.generation = generation;
+			self.journaled = 0;
+			Ok(())
+		}
+
+		/*
+		 * Write record into the journal and apply it to the state
+		 */
+		pub fn append(&mut self, record: CxlFmStateRecord) -> Result<(), CxlFmError> {
+			let sequence = self.sequence + 1;
+
+			writeln!(self.journal, "{} {}", sequence, record.to_line())
+				.and_then(|_| self.journal.sync_data())
+				.map_err(|e| CxlFmError::Io(e.to_string()))?;
+
+			self.sequence = sequence;
+			self.state.apply(record);
+			self.journaled += 1;
+
+			if self.journaled >= CXL_FM_STATE_SNAPSHOT_INTERVAL {
+				self.snapshot()?;
+			}
+
+			Ok(())
+		}
+
+		/*
+		 * Record start of the operation
+		 */
+		pub fn begin_operation(&mut self, request: &str) -> Result<u64, CxlFmError> {
+			let id = self.state.next_operation;
+
+			self.append(CxlFmStateRecord::Begin(id, String::from(request)))?;
+			self.current = Some(id);
+			Ok(id)
+		}
+
+		/*
+		 * Continue the operation restored from the store
+		 */
+		pub fn resume_operation(&mut self, id: u64) {
+			self.current = Some(id);
+		}
+
+		/*
+		 * Record status of the step of the current operation
+		 */
+		pub fn record_step(&mut self, index: u16, status: u8) -> Result<(), CxlFmError> {
+			match self.current {
+				Some(id) => self.append(CxlFmStateRecord::Step(id, index, status)),
+				None => Ok(()),
+			}
+		}
+
+		/*
+		 * Record end of the current operation. Requested operations
+		 * are always audited, background ones only if they have
+		 * changed the fabric.
+		 */
+		pub fn end_operation(&mut self, is_requested: bool, is_done: bool) -> Result<(), CxlFmError> {
+			let id = match self.current.take() {
+				Some(id) => id,
+				None => return Ok(()),
+			};
+
+			if let Some(operation) = self.state.operations.get(&id).cloned() {
+				if is_requested || !operation.steps.is_empty() {
+					let timestamp = SystemTime::now()
+						.duration_since(UNIX_EPOCH)
+						.map_or(0, |time| time.as_nanos() as u64);
+
+					self.append(CxlFmStateRecord::Audit(CxlFmAuditRecord {
+						timestamp,
+						request: operation.request.clone(),
+						result: operation.result(is_done),
+					}))?;
+				}
+			}
+
+			self.append(CxlFmStateRecord::Finish(id))
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use std::path::PathBuf;
+
+		fn directory(name: &str) -> PathBuf {
+			let directory = std::env::temp_dir()
+				.join(format!("fm_state_{}_{}", name, std::process::id()));
+
+			let _ = fs::remove_dir_all(&directory);
+			directory
+		}
+
+		fn open(directory: &Path) -> CxlFmStateStore {
+			CxlFmStateStore::open(directory.to_str().unwrap()).unwrap()
+		}
+
+		fn unavailable(port: &str) -> CxlFmStateRecord {
+			CxlFmStateRecord::Unavailable(String::from(port), true)
+		}
+
+		#[test]
+		fn journal_is_replayed() {
+			let directory = directory("journal");
+			let mut store = open(&directory);
+			let id = store.begin_operation("BIND_LD switch=sw0").unwrap();
+
+			store.record_step(0, CXL_PLAN_STEP_DONE).unwrap();
+			store.append(unavailable("sw0/port1")).unwrap();
+			drop(store);
+
+			let store = open(&directory);
+			let operation = &store.state.operations[&id];
+
+			assert_eq!(operation.request, "BIND_LD switch=sw0");
+			assert_eq!(operation.steps.get(&0), Some(&CXL_PLAN_STEP_DONE));
+			assert!(store.state.unavailable.contains("sw0/port1"));
+			assert_eq!(store.state.next_operation, id + 1);
+			assert_eq!(store.current, None);
+
+			fs::remove_dir_all(directory).unwrap();
+		}
+
+		#[test]
+		fn finished_operation_is_not_restored() {
+			let directory = directory("finished");
+			let mut store = open(&directory);
+
+			store.begin_operation("UNBIND_LD switch=sw0").unwrap();
+			store.end_operation(true, true).unwrap();
+			drop(store);
+
+			let store = open(&directory);
+
+			assert!(store.state.operations.is_empty());
+			assert_eq!(store.state.audit.len(), 1);
+			assert_eq!(store.state.next_operation, 1);
+
+			fs::remove_dir_all(directory).unwrap();
+		}
+
+		#[test]
+		fn failed_operation_is_audited_as_failed() {
+			let directory = directory("failed");
+			let mut store = open(&directory);
+
+			store.begin_operation("BIND_LD switch=sw0").unwrap();
+			store.record_step(0, CXL_PLAN_STEP_DONE).unwrap();
+			store.end_operation(true, false).unwrap();
+			drop(store);
+
+			let store = open(&directory);
+
+			assert!(store.state.operations.is_empty());
+			assert_eq!(store.state.audit[0].result, "failed, 1 step(s) done, 0 failed");
+
+			fs::remove_dir_all(directory).unwrap();
+		}
+
+		#[test]
+		fn snapshot_and_journal_are_combined() {
+			let directory = directory("snapshot");
+			let mut store = open(&directory);
+
+			store.append(unavailable("sw0/port1")).unwrap();
+			store.snapshot().unwrap();
+			store.append(unavailable("sw0/port2")).unwrap();
+			drop(store);
+
+			let store = open(&directory);
+
+			assert!(store.state.unavailable.contains("sw0/port1"));
+			assert!(store.state.unavailable.contains("sw0/port2"));
+
+			fs::remove_dir_all(directory).unwrap();
+		}
+
+		#[test]
+		fn records_included_into_snapshot_are_skipped() {
+			let directory = directory("skipped");
+			let mut store = open(&directory);
+
+			store.append(CxlFmStateRecord::Audit(CxlFmAuditRecord {
+				timestamp: 1,
+				request: String::from("APPLY"),
+				result: String::from("done"),
+			})).unwrap();
+
+			/*
+			 * Crash after the snapshot is written,
+			 * but before the journal is truncated
+			 */
+			let journal = fs::read(directory.join(CXL_FM_STATE_JOURNAL_FILE)).unwrap();
+
+			store.snapshot().unwrap();
+			drop(store);
+			fs::write(directory.join(CXL_FM_STATE_JOURNAL_FILE), journal).unwrap();
+
+			let store = open(&directory);
+
+			assert_eq!(store.state.audit.len(), 1);
+
+			fs::remove_dir_all(directory).unwrap();
+		}
+
+		#[test]
+		fn torn_journal_record_is_dropped() {
+			let directory = directory("torn_journal");
+			let mut store = open(&directory);
+
+			store.append(unavailable("sw0/port1")).unwrap();
+			drop(store);
+
+			let mut journal = OpenOptions::new()
+				.append(true)
+				.open(directory.join(CXL_FM_STATE_JOURNAL_FILE))
+				.unwrap();
+
+			write!(journal, "2 {} sw0/po", CXL_FM_RECORD_UNAVAILABLE).unwrap();
+			drop(journal);
+
+			let mut store = open(&directory);
+
+			assert_eq!(store.state.unavailable.len(), 1);
+			assert!(store.state.unavailable.contains("sw0/port1"));
+
+			/*
+			 * The store is writable after recovery
+			 */
+			store.append(unavailable("sw0/port3")).unwrap();
+			drop(store);
+
+			assert_eq!(open(&directory).state.unavailable.len(), 2);
+
+			fs::remove_dir_all(directory).unwrap();
+		}
+
+		#[test]
+		fn torn_snapshot_falls_back_to_previous_one() {
+			let directory = directory("torn_snapshot");
+			let mut store = open(&directory);
+
+			store.append(unavailable("sw0/port1")).unwrap();
+
+			/*
+			 * Crash while the next snapshot is written:
+			 * it has no trailer and the journal is intact
+			 */
+			let journal = fs::read(directory.join(CXL_FM_STATE_JOURNAL_FILE)).unwrap();
+			let slot = ((store.generation + 1) % 2) as usize;
+
+			store.snapshot().unwrap();
+			drop(store);
+
+			let path = directory.join(CXL_FM_STATE_SNAPSHOT_FILES[slot]);
+			let content = fs::read_to_string(&path).unwrap();
+
+			fs::write(&path, content.trim_end().strip_suffix(CXL_FM_STATE_TRAILER).unwrap()).unwrap();
+			fs::write(directory.join(CXL_FM_STATE_JOURNAL_FILE), journal).unwrap();
+
+			let store = open(&directory);
+
+			assert!(store.state.unavailable.contains("sw0/port1"));
+
+			fs::remove_dir_all(directory).unwrap();
+		}
+	}
+}
@@ -0,0 +1,220 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * Announcement of FM instances by UDP multicast.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_announce {
+	use std::io;
+	use std::mem;
+	use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+	use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_lib::CxlFmRequest;
+	use crate::cxl_fm_lib::CXL_FM_ANNOUNCE_FM_COMMAND;
+	use crate::cxl_fm_lib::CXL_FM_ADDRESS_ARG;
+
+	/*
+	 * Default multicast group (organization-local scope)
+	 * and interface of announcements
+	 */
+	pub const CXL_FM_ANNOUNCE_GROUP_DEFAULT: &str = "239.255.76.1:7400";
+	pub const CXL_FM_ANNOUNCE_INTERFACE_DEFAULT: &str = "0.0.0.0";
+
+	/*
+	 * Announcements are not routed outside of the local network
+	 */
+	const CXL_FM_ANNOUNCE_TTL: u32 = 1;
+
+	/*
+	 * Maximal size of UDP datagram
+	 */
+	const CXL_FM_ANNOUNCE_MAX_SIZE: usize = 65536;
+
+	fn io_error(e: io::Error) -> CxlFmError {
+		CxlFmError::Io(e.to_string())
+	}
+
+	/*
+	 * Parse multicast group: IPv4 multicast address + port
+	 */
+	pub fn parse_group(group: &str) -> Result<SocketAddrV4, CxlFmError> {
+		group.parse::<SocketAddrV4>()
+			.ok()
+			.filter(|address| address.ip().is_multicast())
+			.ok_or(CxlFmError::InvalidInput(format!("{} is not IPv4 multicast group:port", group)))
+	}
+
+	/*
+	 * Parse IPv4 address of the interface, 0.0.0.0 lets the kernel
+	 * choose it, 127.0.0.1 keeps announcements on the host
+	 */
+	pub fn parse_interface(interface: &str) -> Result<Ipv4Addr, CxlFmError> {
+		interface.parse::<Ipv4Addr>()
+			.map_err(|_| CxlFmError::InvalidInput(format!("{} is not IPv4 address", interface)))
+	}
+
+	/*
+	 * Set socket option that std doesn't provide
+	 */
+	fn set_option<T>(fd: RawFd, level: libc::c_int, name: libc::c_int,
+			 value: T) -> Result<(), CxlFmError> {
+		let result = unsafe {
+			libc::setsockopt(fd, level, name, &value as *const T as *const libc::c_void,
+					 mem::size_of::<T>() as libc::socklen_t)
+		};
+
+		match result {
+			0 => Ok(()),
+			_ => Err(io_error(io::Error::last_os_error())),
+		}
+	}
+
+	fn in_addr(address: &Ipv4Addr) -> libc::in_addr {
+		libc::in_addr {
+			s_addr: u32::from(*address).to_be(),
+		}
+	}
+
+	/*
+	 * Open socket that sends announcements into the group
+	 * through the interface
+	 */
+	pub fn announce_socket(interface: &Ipv4Addr) -> Result<UdpSocket, CxlFmError> {
+		let socket = UdpSocket::bind((*interface, 0)).map_err(io_error)?;
+
+		socket.set_multicast_ttl_v4(CXL_FM_ANNOUNCE_TTL).map_err(io_error)?;
+		socket.set_multicast_loop_v4(true).map_err(io_error)?;
+
+		if !interface.is_unspecified() {
+			set_option(socket.as_raw_fd(), libc::IPPROTO_IP, libc::IP_MULTICAST_IF,
+				   in_addr(interface))?;
+		}
+
+		Ok(socket)
+	}
+
+	/*
+	 * Send announcement: request line of ANNOUNCE_FM command
+	 */
+	pub fn announce(socket: &UdpSocket, group: &SocketAddrV4,
+			request: &CxlFmRequest) -> Result<(), CxlFmError> {
+		socket.send_to(request.to_line().as_bytes(), group)
+			.map(|_| ())
+			.map_err(io_error)
+	}
+
+	/*
+	 * Open socket that receives announcements of the group
+	 * on the interface. The port is shared, so orchestrator
+	 * and fm_cli can listen on the same host.
+	 */
+	pub fn listen(group: &SocketAddrV4, interface: &Ipv4Addr) -> Result<UdpSocket, CxlFmError> {
+		let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+
+		if fd < 0 {
+			return Err(io_error(io::Error::last_os_error()));
+		}
+
+		/*
+		 * Socket owns the descriptor from now on
+		 */
+		let socket = unsafe { UdpSocket::from_raw_fd(fd) };
+		let address = libc::sockaddr_in {
+			sin_family: libc::AF_INET as libc::sa_family_t,
+			sin_port: group.port().to_be(),
+			sin_addr: in_addr(group.ip()),
+			sin_zero: [0; 8],
+		};
+
+		set_option(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR, 1 as libc::c_int)?;
+
+		let result = unsafe {
+			libc::bind(fd, &address as *const libc::sockaddr_in as *const libc::sockaddr,
+				   mem::size_of::<libc::sockaddr_in>() as libc::socklen_t)
+		};
+
+		if result != 0 {
+			return Err(io_error(io::Error::last_os_error()));
+		}
+
+		socket.join_multicast_v4(group.ip(), interface).map_err(io_error)?;
+		Ok(socket)
+	}
+
+	/*
+	 * Receive the next announcement. FM that listens on all
+	 * interfaces announces unspecified address, the sender
+	 * address of the datagram is used instead.
+	 */
+	pub fn receive(socket: &UdpSocket) -> Result<CxlFmRequest, CxlFmError> {
+		let mut buffer = vec![0; CXL_FM_ANNOUNCE_MAX_SIZE];
+		let (size, sender) = socket.recv_from(&mut buffer).map_err(io_error)?;
+		let line = std::str::from_utf8(&buffer[..size])
+			.map_err(|_| CxlFmError::Protocol(format!("invalid announcement from {}", sender)))?;
+		let mut request = CxlFmRequest::parse(line.trim_end())?;
+
+		if request.command != CXL_FM_ANNOUNCE_FM_COMMAND {
+			return Err(CxlFmError::Protocol(format!("{} from {} is not announcement",
+								request.command, sender)));
+		}
+
+		let address = request.get(CXL_FM_ADDRESS_ARG)
+			.and_then(|address| address.parse::<SocketAddr>().ok())
+			.ok_or(CxlFmError::Protocol(format!("announcement from {} has no address", sender)))?;
+
+		if address.ip().is_unspecified() {
+			let address = SocketAddr::new(sender.ip(), address.port()).to_string();
+
+			for (key, value) in request.args.iter_mut() {
+				if key == CXL_FM_ADDRESS_ARG {
+					*value = address.clone();
+				}
+			}
+		}
+
+		Ok(request)
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use std::time::Duration;
+		use crate::cxl_fm_lib::CXL_FM_ID_ARG;
+
+		#[test]
+		fn unspecified_address_is_replaced_by_sender() {
+			let interface = Ipv4Addr::LOCALHOST;
+			let port = 20000 + std::process::id() % 20000;
+			let group = parse_group(&format!("239.255.76.1:{}", port)).unwrap();
+			let listener = listen(&group, &interface).unwrap();
+			let socket = announce_socket(&interface).unwrap();
+
+			listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+			announce(&socket, &group, &CxlFmRequest::new(CXL_FM_ANNOUNCE_FM_COMMAND)
+				 .arg(CXL_FM_ADDRESS_ARG, "0.0.0.0:7500")
+				 .arg(CXL_FM_ID_ARG, "fm0")).unwrap();
+
+			let request = receive(&listener).unwrap();
+
+			assert_eq!(request.get(CXL_FM_ADDRESS_ARG), Some("127.0.0.1:7500"));
+			assert_eq!(request.get(CXL_FM_ID_ARG), Some("fm0"));
+		}
+	}
+}
@@ -0,0 +1,251 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * Fabric-wide Dynamic Capacity pool.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_capacity_pool_lib {
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+	use crate::cxl_dcd_lib::CxlDcExtent;
+
+	/*
+	 * Placement policies of capacity pool
+	 */
+	pub const CXL_POOL_PLACEMENT_PACK: u8		= 0x0;
+	pub const CXL_POOL_PLACEMENT_SPREAD: u8		= 0x1;
+	pub const CXL_POOL_PLACEMENT_LOCALITY: u8	= 0x2;
+
+	/*
+	 * Get human-readable name of placement policy
+	 */
+	pub fn placement_policy_name(policy: u8) -> &'static str {
+		match policy {
+			CXL_POOL_PLACEMENT_PACK => "pack",
+			CXL_POOL_PLACEMENT_SPREAD => "spread",
+			CXL_POOL_PLACEMENT_LOCALITY => "locality",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Get placement policy by its name
+	 */
+	pub fn placement_policy_by_name(name: &str) -> Option<u8> {
+		match name {
+			"pack" => Some(CXL_POOL_PLACEMENT_PACK),
+			"spread" => Some(CXL_POOL_PLACEMENT_SPREAD),
+			"locality" => Some(CXL_POOL_PLACEMENT_LOCALITY),
+			_ => None,
+		}
+	}
+
+	/*
+	 * struct CxlPoolRegion - DC region of the capacity pool
+	 * @device: device index
+	 * @region: DC region number
+	 * @locality: locality domain (NUMA node or switch) of the device
+	 * @block_size: region block size
+	 * @capacity: region length
+	 * @free: capacity not allocated to any host
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlPoolRegion {
+		pub device: u16,
+		pub region: u8,
+		pub locality: u16,
+		pub block_size: u64,
+		pub capacity: u64,
+		pub free: u64,
+	}
+
+	impl CxlPoolRegion {
+		fn write(&self, writer: &mut CxlPayloadWriter) {
+			writer.write_u16(self.device);
+			writer.write_u8(self.region);
+			writer.reserve(1);
+			writer.write_u16(self.locality);
+			writer.reserve(2);
+			writer.write_u64(self.block_size);
+			writer.write_u64(self.capacity);
+			writer.write_u64(self.free);
+		}
+
+		fn read(reader: &mut CxlPayloadReader) -> Result<CxlPoolRegion, CxlFmError> {
+			let device = reader.read_u16()?;
+			let region = reader.read_u8()?;
+			reader.skip(1)?;
+			let locality = reader.read_u16()?;
+			reader.skip(2)?;
+
+			Ok(CxlPoolRegion {
+				device,
+				region,
+				locality,
+				block_size: reader.read_u64()?,
+				capacity: reader.read_u64()?,
+				free: reader.read_u64()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlPoolInfo - state of the capacity pool
+	 * @regions: DC regions of all devices
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlPoolInfo {
+		pub regions: Vec<CxlPoolRegion>,
+	}
+
+	impl CxlPoolInfo {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u32(self.regions.len() as u32);
+
+			for region in &self.regions {
+				region.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlPoolInfo, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let count = reader.read_u32()?;
+
+			let mut regions = Vec::new();
+			for _ in 0..count {
+				regions.push(CxlPoolRegion::read(&mut reader)?);
+			}
+
+			Ok(CxlPoolInfo {
+				regions,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlPoolGrant - capacity granted by one DC region
+	 * @device: device index
+	 * @region: DC region number
+	 * @extents: extents accepted by the host
+	 * @pending: capacity offered to the host but not accepted yet
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlPoolGrant {
+		pub device: u16,
+		pub region: u8,
+		pub extents: Vec<CxlDcExtent>,
+		pub pending: u64,
+	}
+
+	/*
+	 * struct CxlPoolAllocation - capacity granted to the host by the pool
+	 * @host_id: host ID
+	 * @policy: placement policy
+	 * @grants: capacity granted by every DC region
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlPoolAllocation {
+		pub host_id: u16,
+		pub policy: u8,
+		pub grants: Vec<CxlPoolGrant>,
+	}
+
+	impl CxlPoolAllocation {
+		/*
+		 * Get total granted capacity
+		 */
+		pub fn length(&self) -> u64 {
+			self.grants.iter()
+				.flat_map(|grant| grant.extents.iter())
+				.map(|extent| extent.length)
+				.sum()
+		}
+
+		/*
+		 * Get capacity not accepted by the host yet
+		 */
+		pub fn pending_length(&self) -> u64 {
+			self.grants.iter().map(|grant| grant.pending).sum()
+		}
+
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.host_id);
+			writer.write_u8(self.policy);
+			writer.reserve(1);
+			writer.write_u32(self.grants.len() as u32);
+
+			for grant in &self.grants {
+				writer.write_u16(grant.device);
+				writer.write_u8(grant.region);
+				writer.reserve(1);
+				writer.write_u64(grant.pending);
+				writer.write_u32(grant.extents.len() as u32);
+
+				for extent in &grant.extents {
+					extent.write(&mut writer);
+				}
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlPoolAllocation, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let host_id = reader.read_u16()?;
+			let policy = reader.read_u8()?;
+			reader.skip(1)?;
+			let count = reader.read_u32()?;
+
+			let mut grants = Vec::new();
+			for _ in 0..count {
+				let device = reader.read_u16()?;
+				let region = reader.read_u8()?;
+				reader.skip(1)?;
+				let pending = reader.read_u64()?;
+				let extent_count = reader.read_u32()?;
+
+				let mut extents = Vec::new();
+				for _ in 0..extent_count {
+					extents.push(CxlDcExtent::read(&mut reader)?);
+				}
+
+				grants.push(CxlPoolGrant {
+					device,
+					region,
+					extents,
+					pending,
+				});
+			}
+
+			Ok(CxlPoolAllocation {
+				host_id,
+				policy,
+				grants,
+			})
+		}
+	}
+}
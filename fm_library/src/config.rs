@@ -0,0 +1,410 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * Configuration file (TOML) parser and writer.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_config {
+	use std::fmt;
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_lib::parse_size;
+
+	/*
+	 * enum CxlConfigValue - value of configuration key
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub enum CxlConfigValue {
+		String(String),
+		Integer(i64),
+		Boolean(bool),
+		Array(Vec<CxlConfigValue>),
+	}
+
+	impl fmt::Display for CxlConfigValue {
+		/*
+		 * Write value in TOML syntax
+		 */
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			write!(f, "{}", to_value(self))
+		}
+	}
+
+	/*
+	 * struct CxlConfigTable - set of key/value pairs
+	 * @name: table name ("" for the root table)
+	 * @is_array: table is entry of array of tables ([[name]])
+	 * @entries: keys with values in file order
+	 */
+	#[derive(Debug, Clone, PartialEq, Default)]
+	pub struct CxlConfigTable {
+		pub name: String,
+		pub is_array: bool,
+		pub entries: Vec<(String, CxlConfigValue)>,
+	}
+
+	impl CxlConfigTable {
+		pub fn new(name: &str) -> CxlConfigTable {
+			CxlConfigTable {
+				name: String::from(name),
+				..Default::default()
+			}
+		}
+
+		pub fn get(&self, key: &str) -> Option<&CxlConfigValue> {
+			self.entries.iter()
+				.find(|(k, _)| k == key)
+				.map(|(_, v)| v)
+		}
+
+		/*
+		 * Set value of the key, new key is added to the end
+		 */
+		pub fn set(&mut self, key: &str, value: CxlConfigValue) {
+			match self.entries.iter_mut().find(|(k, _)| k == key) {
+				Some((_, v)) => *v = value,
+				None => self.entries.push((String::from(key), value)),
+			}
+		}
+
+		/*
+		 * Set value of the key if it is defined
+		 */
+		pub fn set_some(&mut self, key: &str, value: Option<CxlConfigValue>) {
+			if let Some(value) = value {
+				self.set(key, value);
+			}
+		}
+
+		fn invalid(&self, key: &str, expected: &str) -> CxlFmError {
+			CxlFmError::InvalidInput(format!("[{}] {}: {} is expected",
+							 self.name, key, expected))
+		}
+
+		/*
+		 * Get string value of the key
+		 */
+		pub fn get_str(&self, key: &str) -> Result<Option<&str>, CxlFmError> {
+			match self.get(key) {
+				Some(CxlConfigValue::String(value)) => Ok(Some(value)),
+				Some(_) => Err(self.invalid(key, "string")),
+				None => Ok(None),
+			}
+		}
+
+		/*
+		 * Get integer value of the key converted to the type
+		 */
+		pub fn get_number<T: TryFrom<i64>>(&self, key: &str) -> Result<Option<T>, CxlFmError> {
+			match self.get(key) {
+				Some(CxlConfigValue::Integer(value)) => T::try_from(*value)
+					.map(Some)
+					.map_err(|_| self.invalid(key, "number in range")),
+				Some(_) => Err(self.invalid(key, "number")),
+				None => Ok(None),
+			}
+		}
+
+		/*
+		 * Get boolean value of the key
+		 */
+		pub fn get_bool(&self, key: &str) -> Result<Option<bool>, CxlFmError> {
+			match self.get(key) {
+				Some(CxlConfigValue::Boolean(value)) => Ok(Some(*value)),
+				Some(_) => Err(self.invalid(key, "boolean")),
+				None => Ok(None),
+			}
+		}
+
+		/*
+		 * Get array of strings of the key
+		 */
+		pub fn get_strings(&self, key: &str) -> Result<Option<Vec<String>>, CxlFmError> {
+			match self.get(key) {
+				Some(CxlConfigValue::Array(values)) => values.iter()
+					.map(|value| match value {
+						CxlConfigValue::String(value) => Ok(value.clone()),
+						_ => Err(self.invalid(key, "array of strings")),
+					})
+					.collect::<Result<Vec<String>, CxlFmError>>()
+					.map(Some),
+				Some(_) => Err(self.invalid(key, "array of strings")),
+				None => Ok(None),
+			}
+		}
+
+		/*
+		 * Get size given as number or string with suffix ("4GiB")
+		 */
+		pub fn get_size(&self, key: &str) -> Result<Option<u64>, CxlFmError> {
+			match self.get(key) {
+				Some(CxlConfigValue::Integer(value)) if *value >= 0 => Ok(Some(*value as u64)),
+				Some(CxlConfigValue::String(value)) => parse_size(value).map(Some),
+				Some(_) => Err(self.invalid(key, "size")),
+				None => Ok(None),
+			}
+		}
+
+		/*
+		 * Get value of the mandatory key
+		 */
+		pub fn require<T>(&self, key: &str,
+				  value: Result<Option<T>, CxlFmError>) -> Result<T, CxlFmError> {
+			value?.ok_or_else(|| CxlFmError::InvalidInput(format!("[{}] {} is missing",
+									      self.name, key)))
+		}
+	}
+
+	/*
+	 * struct CxlConfig - parsed configuration file
+	 * @root: keys defined before the first table header
+	 * @tables: tables ([name]) and array of tables ([[name]]) entries
+	 *          in file order
+	 *
+	 * Files are parsed by the toml crate, values of keys are
+	 * strings, integers, booleans and arrays of them.
+	 */
+	#[derive(Debug, Clone, PartialEq, Default)]
+	pub struct CxlConfig {
+		pub root: CxlConfigTable,
+		pub tables: Vec<CxlConfigTable>,
+	}
+
+	impl CxlConfig {
+		/*
+		 * Get table by name
+		 */
+		pub fn table(&self, name: &str) -> Option<&CxlConfigTable> {
+			self.tables.iter().find(|table| table.name == name)
+		}
+
+		/*
+		 * Get table by name for change, missing table is added
+		 */
+		pub fn table_mut(&mut self, name: &str) -> &mut CxlConfigTable {
+			let index = match self.tables.iter().position(|table| table.name == name) {
+				Some(index) => index,
+				None => {
+					self.tables.push(CxlConfigTable::new(name));
+					self.tables.len() - 1
+				}
+			};
+
+			&mut self.tables[index]
+		}
+
+		/*
+		 * Get all entries of array of tables
+		 */
+		pub fn array(&self, name: &str) -> Vec<&CxlConfigTable> {
+			self.tables.iter().filter(|table| table.name == name).collect()
+		}
+
+		/*
+		 * Write configuration in TOML syntax
+		 */
+		pub fn to_toml(&self) -> String {
+			let mut document = toml::Table::new();
+
+			for (key, value) in &self.root.entries {
+				document.insert(key.clone(), to_value(value));
+			}
+
+			for table in &self.tables {
+				let entries: toml::Table = table.entries.iter()
+					.map(|(key, value)| (key.clone(), to_value(value)))
+					.collect();
+
+				if !table.is_array {
+					document.insert(table.name.clone(), toml::Value::Table(entries));
+					continue;
+				}
+
+				if let toml::Value::Array(array) = document.entry(table.name.clone())
+					.or_insert_with(|| toml::Value::Array(Vec::new())) {
+					array.push(toml::Value::Table(entries));
+				}
+			}
+
+			toml::to_string(&document).unwrap_or_default()
+		}
+
+		pub fn parse(content: &str) -> Result<CxlConfig, CxlFmError> {
+			let document = toml::from_str::<toml::Table>(content).map_err(|e| {
+				let line = e.span()
+					.map(|span| content[..span.start].matches('\n').count() + 1)
+					.unwrap_or(1);
+
+				CxlFmError::InvalidInput(format!("line {}: {}", line, e.message().trim()))
+			})?;
+			let mut config = CxlConfig::default();
+
+			for (key, value) in document {
+				match value {
+					toml::Value::Table(entries) => {
+						config.tables.push(table_from(&key, false, entries)?);
+					},
+					toml::Value::Array(items) if !items.is_empty() &&
+								     items.iter().all(toml::Value::is_table) => {
+						for item in items {
+							if let toml::Value::Table(entries) = item {
+								config.tables.push(table_from(&key, true, entries)?);
+							}
+						}
+					},
+					value => {
+						let value = from_value(&value)
+							.map_err(|e| CxlFmError::InvalidInput(format!("{}: {}", key, e)))?;
+
+						config.root.entries.push((key, value));
+					},
+				}
+			}
+
+			Ok(config)
+		}
+	}
+
+	/*
+	 * Convert TOML table into table of configuration.
+	 * Nested tables are not used by the configuration files.
+	 */
+	fn table_from(name: &str, is_array: bool, entries: toml::Table) -> Result<CxlConfigTable, CxlFmError> {
+		let mut table = CxlConfigTable {
+			name: String::from(name),
+			is_array,
+			entries: Vec::new(),
+		};
+
+		for (key, value) in entries {
+			let value = from_value(&value)
+				.map_err(|e| CxlFmError::InvalidInput(format!("[{}] {}: {}", name, key, e)))?;
+
+			table.entries.push((key, value));
+		}
+
+		Ok(table)
+	}
+
+	fn from_value(value: &toml::Value) -> Result<CxlConfigValue, String> {
+		match value {
+			toml::Value::String(value) => Ok(CxlConfigValue::String(value.clone())),
+			toml::Value::Integer(value) => Ok(CxlConfigValue::Integer(*value)),
+			toml::Value::Boolean(value) => Ok(CxlConfigValue::Boolean(*value)),
+			toml::Value::Array(values) => values.iter()
+				.map(from_value)
+				.collect::<Result<Vec<CxlConfigValue>, String>>()
+				.map(CxlConfigValue::Array),
+			value => Err(format!("unsupported {} value", value.type_str())),
+		}
+	}
+
+	fn to_value(value: &CxlConfigValue) -> toml::Value {
+		match value {
+			CxlConfigValue::String(value) => toml::Value::String(value.clone()),
+			CxlConfigValue::Integer(value) => toml::Value::Integer(*value),
+			CxlConfigValue::Boolean(value) => toml::Value::Boolean(*value),
+			CxlConfigValue::Array(values) => toml::Value::Array(values.iter().map(to_value).collect()),
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const CONFIG: &str = r#"
+# FM daemon
+[listen]
+ip = "127.0.0.1"   # loopback only
+port = 7400
+
+[[backend]]
+type = "emulated_mhd"
+name = "mhd0"
+serial_number = 0x1000_0000
+
+[[backend]]
+type = "emulated_switch"
+name = "sw0"
+
+[security]
+allowed_clients = [
+	"10.0.0.1",
+	'10.0.0.2',
+]
+allow_set_config = false
+"#;
+
+		#[test]
+		fn tables_and_values_are_parsed() {
+			let config = CxlConfig::parse(CONFIG).unwrap();
+			let listen = config.table("listen").unwrap();
+			let security = config.table("security").unwrap();
+
+			assert!(config.root.entries.is_empty());
+			assert_eq!(listen.get_str("ip").unwrap(), Some("127.0.0.1"));
+			assert_eq!(listen.get_number::<u16>("port").unwrap(), Some(7400));
+			assert_eq!(security.get_strings("allowed_clients").unwrap(),
+				   Some(vec![String::from("10.0.0.1"), String::from("10.0.0.2")]));
+			assert_eq!(security.get_bool("allow_set_config").unwrap(), Some(false));
+
+			let backends = config.array("backend");
+
+			assert_eq!(backends.len(), 2);
+			assert!(backends.iter().all(|backend| backend.is_array));
+			assert_eq!(backends[0].get_number::<u64>("serial_number").unwrap(), Some(0x10000000));
+			assert_eq!(backends[1].get_str("name").unwrap(), Some("sw0"));
+		}
+
+		#[test]
+		fn values_are_checked_by_type() {
+			let config = CxlConfig::parse("[t]\nport = 70000\nname = 1\nsize = \"4GiB\"\n").unwrap();
+			let table = config.table("t").unwrap();
+
+			assert!(table.get_number::<u16>("port").is_err());
+			assert!(table.get_str("name").is_err());
+			assert_eq!(table.get_size("size").unwrap(), Some(4 << 30));
+			assert!(table.require("missing", table.get_str("missing")).is_err());
+		}
+
+		#[test]
+		fn invalid_files_are_rejected() {
+			let error = |content: &str| match CxlConfig::parse(content) {
+				Err(CxlFmError::InvalidInput(message)) => message,
+				other => panic!("unexpected result: {:?}", other),
+			};
+
+			assert!(error("[t]\nkey = \"unterminated\n").starts_with("line 2:"));
+			assert!(error("[t]\nkey = 1\nkey = 2\n").starts_with("line 3:"));
+			assert!(error("[t]\n[t]\n").starts_with("line 2:"));
+			assert_eq!(error("[t]\nratio = 0.5\n"), "[t] ratio: unsupported float value");
+			assert_eq!(error("[t.nested]\nkey = 1\n"), "[t] nested: unsupported table value");
+		}
+
+		#[test]
+		fn written_configuration_is_parsed_back() {
+			let mut config = CxlConfig::parse(CONFIG).unwrap();
+
+			config.table_mut("log").set("file", CxlConfigValue::String(String::from("a \"quoted\"\\path")));
+
+			let content = config.to_toml();
+
+			assert!(content.contains("[[backend]]"));
+			assert_eq!(CxlConfig::parse(&content).unwrap(), config);
+		}
+	}
+}
@@ -0,0 +1,352 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * Desired state of the fabric and plan to reach it.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_desired_state {
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+	use crate::cxl_config::CxlConfig;
+	use crate::cxl_config::CxlConfigTable;
+	use crate::cxl_dcd_lib::parse_tag;
+	use crate::cxl_dcd_lib::CXL_DCD_TAG_SIZE;
+
+	/*
+	 * Names of tables in desired state file
+	 */
+	pub const CXL_SPEC_BINDING_TABLE: &str		= "binding";
+	pub const CXL_SPEC_ALLOCATION_TABLE: &str	= "allocation";
+	pub const CXL_SPEC_QOS_TABLE: &str		= "qos";
+	pub const CXL_SPEC_CAPACITY_TABLE: &str		= "capacity";
+
+	/*
+	 * struct CxlBindingSpec - desired binding of vPPB
+	 * @switch: switch name
+	 * @vcs: VCS ID
+	 * @vppb: vPPB index inside the VCS
+	 * @port: physical port ID (None means unbound vPPB)
+	 * @ld: LD ID of MLD connected to the port
+	 * @manual: drift is reported but not remediated
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlBindingSpec {
+		pub switch: String,
+		pub vcs: u8,
+		pub vppb: u8,
+		pub port: Option<u8>,
+		pub ld: Option<u16>,
+		pub manual: bool,
+	}
+
+	/*
+	 * struct CxlAllocationSpec - desired memory allocation of LD
+	 * @device: MLD name
+	 * @ld: LD ID
+	 * @range1: size of range 1 in bytes
+	 * @range2: size of range 2 in bytes
+	 * @manual: drift is reported but not remediated
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlAllocationSpec {
+		pub device: String,
+		pub ld: u8,
+		pub range1: u64,
+		pub range2: u64,
+		pub manual: bool,
+	}
+
+	/*
+	 * struct CxlQosSpec - desired QoS control of MLD
+	 * (fields that are not defined keep current value)
+	 * @device: MLD name
+	 * @egress_port_congestion: enable egress port congestion telemetry
+	 * @temporary_throughput_reduction: enable temporary throughput reduction
+	 * @egress_moderate_percentage: moderate congestion threshold
+	 * @egress_severe_percentage: severe congestion threshold
+	 * @backpressure_sample_interval: backpressure sample interval
+	 * @req_cmp_basis: ReqCmpBasis
+	 * @completion_collection_interval: completion collection interval
+	 * @manual: drift is reported but not remediated
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlQosSpec {
+		pub device: String,
+		pub egress_port_congestion: Option<bool>,
+		pub temporary_throughput_reduction: Option<bool>,
+		pub egress_moderate_percentage: Option<u8>,
+		pub egress_severe_percentage: Option<u8>,
+		pub backpressure_sample_interval: Option<u8>,
+		pub req_cmp_basis: Option<u16>,
+		pub completion_collection_interval: Option<u8>,
+		pub manual: bool,
+	}
+
+	/*
+	 * struct CxlCapacitySpec - desired Dynamic Capacity grant
+	 * @device: DCD name
+	 * @host: host ID
+	 * @region: DC region used to add capacity
+	 * @length: total capacity of the host with the tag
+	 * @tag: tag of the granted extents
+	 * @manual: drift is reported but not remediated
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlCapacitySpec {
+		pub device: String,
+		pub host: u16,
+		pub region: u8,
+		pub length: u64,
+		pub tag: [u8; CXL_DCD_TAG_SIZE],
+		pub manual: bool,
+	}
+
+	/*
+	 * struct CxlFabricSpec - desired state of the fabric
+	 * @bindings: vPPB bindings
+	 * @allocations: LD allocations
+	 * @qos: QoS control settings
+	 * @capacity: Dynamic Capacity grants
+	 *
+	 * Only declared objects are managed, everything else
+	 * is left in its current state.
+	 */
+	#[derive(Debug, Clone, PartialEq, Default)]
+	pub struct CxlFabricSpec {
+		pub bindings: Vec<CxlBindingSpec>,
+		pub allocations: Vec<CxlAllocationSpec>,
+		pub qos: Vec<CxlQosSpec>,
+		pub capacity: Vec<CxlCapacitySpec>,
+	}
+
+	fn binding_spec(table: &CxlConfigTable) -> Result<CxlBindingSpec, CxlFmError> {
+		Ok(CxlBindingSpec {
+			switch: String::from(table.require("switch", table.get_str("switch"))?),
+			vcs: table.require("vcs", table.get_number::<u8>("vcs"))?,
+			vppb: table.require("vppb", table.get_number::<u8>("vppb"))?,
+			port: table.get_number::<u8>("port")?,
+			ld: table.get_number::<u16>("ld")?,
+			manual: table.get_bool("manual")?.unwrap_or(false),
+		})
+	}
+
+	fn allocation_spec(table: &CxlConfigTable) -> Result<CxlAllocationSpec, CxlFmError> {
+		Ok(CxlAllocationSpec {
+			device: String::from(table.require("device", table.get_str("device"))?),
+			ld: table.require("ld", table.get_number::<u8>("ld"))?,
+			range1: table.require("range1", table.get_size("range1"))?,
+			range2: table.get_size("range2")?.unwrap_or(0),
+			manual: table.get_bool("manual")?.unwrap_or(false),
+		})
+	}
+
+	fn qos_spec(table: &CxlConfigTable) -> Result<CxlQosSpec, CxlFmError> {
+		Ok(CxlQosSpec {
+			device: String::from(table.require("device", table.get_str("device"))?),
+			egress_port_congestion: table.get_bool("egress_port_congestion")?,
+			temporary_throughput_reduction: table.get_bool("temporary_throughput_reduction")?,
+			egress_moderate_percentage: table.get_number::<u8>("egress_moderate_percentage")?,
+			egress_severe_percentage: table.get_number::<u8>("egress_severe_percentage")?,
+			backpressure_sample_interval: table.get_number::<u8>("backpressure_sample_interval")?,
+			req_cmp_basis: table.get_number::<u16>("req_cmp_basis")?,
+			completion_collection_interval: table.get_number::<u8>("completion_collection_interval")?,
+			manual: table.get_bool("manual")?.unwrap_or(false),
+		})
+	}
+
+	fn capacity_spec(table: &CxlConfigTable) -> Result<CxlCapacitySpec, CxlFmError> {
+		let tag = match table.get_str("tag")? {
+			Some(tag) => parse_tag(tag)?,
+			None => [0; CXL_DCD_TAG_SIZE],
+		};
+
+		Ok(CxlCapacitySpec {
+			device: String::from(table.require("device", table.get_str("device"))?),
+			host: table.require("host", table.get_number::<u16>("host"))?,
+			region: table.get_number::<u8>("region")?.unwrap_or(0),
+			length: table.require("length", table.get_size("length"))?,
+			tag,
+			manual: table.get_bool("manual")?.unwrap_or(false),
+		})
+	}
+
+	impl CxlFabricSpec {
+		pub fn from_config(config: &CxlConfig) -> Result<CxlFabricSpec, CxlFmError> {
+			let mut spec = CxlFabricSpec::default();
+
+			for table in &config.tables {
+				match table.name.as_str() {
+					CXL_SPEC_BINDING_TABLE => spec.bindings.push(binding_spec(table)?),
+					CXL_SPEC_ALLOCATION_TABLE => spec.allocations.push(allocation_spec(table)?),
+					CXL_SPEC_QOS_TABLE => spec.qos.push(qos_spec(table)?),
+					CXL_SPEC_CAPACITY_TABLE => spec.capacity.push(capacity_spec(table)?),
+					name => return Err(CxlFmError::InvalidInput(format!("unknown table: [{}]",
+											    name))),
+				}
+			}
+
+			Ok(spec)
+		}
+
+		pub fn parse(content: &str) -> Result<CxlFabricSpec, CxlFmError> {
+			Self::from_config(&CxlConfig::parse(content)?)
+		}
+
+		/*
+		 * Split the spec into specs of single resources.
+		 * Every resource is returned with its name and
+		 * manual control flag.
+		 */
+		pub fn resources(&self) -> Vec<(String, bool, CxlFabricSpec)> {
+			let mut resources = Vec::new();
+
+			for binding in &self.bindings {
+				resources.push((format!("{}/vcs{}/vppb{}", binding.switch, binding.vcs, binding.vppb),
+						binding.manual,
+						CxlFabricSpec {
+							bindings: vec![binding.clone()],
+							..Default::default()
+						}));
+			}
+
+			for allocation in &self.allocations {
+				resources.push((format!("{}/ld{}", allocation.device, allocation.ld),
+						allocation.manual,
+						CxlFabricSpec {
+							allocations: vec![allocation.clone()],
+							..Default::default()
+						}));
+			}
+
+			for qos in &self.qos {
+				resources.push((qos.device.clone(),
+						qos.manual,
+						CxlFabricSpec {
+							qos: vec![qos.clone()],
+							..Default::default()
+						}));
+			}
+
+			for capacity in &self.capacity {
+				resources.push((format!("{}/host{}", capacity.device, capacity.host),
+						capacity.manual,
+						CxlFabricSpec {
+							capacity: vec![capacity.clone()],
+							..Default::default()
+						}));
+			}
+
+			resources
+		}
+
+		/*
+		 * Add resources of other spec
+		 */
+		pub fn merge(&mut self, other: CxlFabricSpec) {
+			self.bindings.extend(other.bindings);
+			self.allocations.extend(other.allocations);
+			self.qos.extend(other.qos);
+			self.capacity.extend(other.capacity);
+		}
+	}
+
+	/*
+	 * Plan step states
+	 */
+	pub const CXL_PLAN_STEP_PENDING: u8		= 0x0;
+	pub const CXL_PLAN_STEP_DONE: u8		= 0x1;
+	pub const CXL_PLAN_STEP_FAILED: u8		= 0x2;
+	pub const CXL_PLAN_STEP_SKIPPED: u8		= 0x3;
+
+	/*
+	 * Get human-readable name of plan step state
+	 */
+	pub fn plan_step_status_name(status: u8) -> &'static str {
+		match status {
+			CXL_PLAN_STEP_PENDING => "pending",
+			CXL_PLAN_STEP_DONE => "done",
+			CXL_PLAN_STEP_FAILED => "failed",
+			CXL_PLAN_STEP_SKIPPED => "skipped",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * struct CxlPlanStep - FM API operation of the plan
+	 * @opcode: FM API command opcode
+	 * @target: name of the component executing the command
+	 * @description: change made by the command
+	 * @status: execution state
+	 * @error: error message of failed step
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlPlanStep {
+		pub opcode: u16,
+		pub target: String,
+		pub description: String,
+		pub status: u8,
+		pub error: String,
+	}
+
+	/*
+	 * struct CxlFabricPlan - ordered operations to reach desired state
+	 * @steps: operations in execution order
+	 */
+	#[derive(Debug, Clone, PartialEq, Default)]
+	pub struct CxlFabricPlan {
+		pub steps: Vec<CxlPlanStep>,
+	}
+
+	impl CxlFabricPlan {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.steps.len() as u16);
+
+			for step in &self.steps {
+				writer.write_u16(step.opcode);
+				writer.write_string(&step.target);
+				writer.write_string(&step.description);
+				writer.write_u8(step.status);
+				writer.write_string(&step.error);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlFabricPlan, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+			let mut steps = Vec::new();
+
+			for _ in 0..reader.read_u16()? {
+				steps.push(CxlPlanStep {
+					opcode: reader.read_u16()?,
+					target: reader.read_string()?,
+					description: reader.read_string()?,
+					status: reader.read_u8()?,
+					error: reader.read_string()?,
+				});
+			}
+
+			Ok(CxlFabricPlan {
+				steps,
+			})
+		}
+	}
+}
@@ -0,0 +1,861 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL Dynamic Capacity Device (DCD) FM API payloads.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_dcd_lib {
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_lib::parse_number;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+
+	/*
+	 * Maximal number of DC regions
+	 */
+	pub const CXL_DCD_MAX_REGIONS: usize = 8;
+
+	/*
+	 * Size of region configuration in Get Host DC Region Configuration
+	 */
+	pub const CXL_DCD_REGION_CONFIG_SIZE: usize = 40;
+
+	/*
+	 * Size of extent tag
+	 */
+	pub const CXL_DCD_TAG_SIZE: usize = 16;
+
+	/*
+	 * Add capacity selection policies
+	 */
+	pub const CXL_DCD_SELECTION_POLICY_FREE: u8		= 0x0;
+	pub const CXL_DCD_SELECTION_POLICY_CONTIGUOUS: u8	= 0x1;
+	pub const CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE: u8	= 0x2;
+	pub const CXL_DCD_SELECTION_POLICY_SHARED_ACCESS: u8	= 0x3;
+
+	/*
+	 * Release capacity removal policies
+	 */
+	pub const CXL_DCD_REMOVAL_POLICY_TAG_BASED: u8		= 0x0;
+	pub const CXL_DCD_REMOVAL_POLICY_PRESCRIPTIVE: u8	= 0x1;
+
+	/*
+	 * Release capacity flags
+	 */
+	pub const CXL_DCD_RELEASE_FORCED: u8			= 0x10;
+	pub const CXL_DCD_RELEASE_SANITIZE_ON_RELEASE: u8	= 0x20;
+
+	/*
+	 * DC region flags
+	 */
+	pub const CXL_DCD_REGION_SANITIZE_ON_RELEASE: u8	= 0x1;
+
+	/*
+	 * Get human-readable name of selection policy
+	 */
+	pub fn selection_policy_name(policy: u8) -> &'static str {
+		match policy {
+			CXL_DCD_SELECTION_POLICY_FREE => "free",
+			CXL_DCD_SELECTION_POLICY_CONTIGUOUS => "contiguous",
+			CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE => "prescriptive",
+			CXL_DCD_SELECTION_POLICY_SHARED_ACCESS => "enable shared access",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Get selection policy by its name
+	 */
+	pub fn selection_policy_by_name(name: &str) -> Option<u8> {
+		match name {
+			"free" => Some(CXL_DCD_SELECTION_POLICY_FREE),
+			"contiguous" => Some(CXL_DCD_SELECTION_POLICY_CONTIGUOUS),
+			"prescriptive" => Some(CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE),
+			"shared-access" => Some(CXL_DCD_SELECTION_POLICY_SHARED_ACCESS),
+			_ => None,
+		}
+	}
+
+	/*
+	 * Get human-readable name of removal policy
+	 */
+	pub fn removal_policy_name(policy: u8) -> &'static str {
+		match policy {
+			CXL_DCD_REMOVAL_POLICY_TAG_BASED => "tag-based",
+			CXL_DCD_REMOVAL_POLICY_PRESCRIPTIVE => "prescriptive",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Convert policies bitmask into list of names
+	 */
+	pub fn policy_names(mask: u16, name: fn(u8) -> &'static str) -> Vec<&'static str> {
+		(0..16u8)
+			.filter(|bit| mask & (1 << bit) != 0)
+			.map(name)
+			.collect()
+	}
+
+	/*
+	 * Convert supported block size mask into list of block sizes
+	 * (bit N means 2^N bytes)
+	 */
+	pub fn block_sizes(mask: u64) -> Vec<u64> {
+		(0..64u32)
+			.filter(|bit| mask & (1 << bit) != 0)
+			.map(|bit| 1u64 << bit)
+			.collect()
+	}
+
+	/*
+	 * Convert extent tag into UUID-like string
+	 */
+	pub fn format_tag(tag: &[u8; CXL_DCD_TAG_SIZE]) -> String {
+		let hex: String = tag.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+		format!("{}-{}-{}-{}-{}",
+			&hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+	}
+
+	/*
+	 * Parse extent tag from UUID-like or plain hex string
+	 */
+	pub fn parse_tag(value: &str) -> Result<[u8; CXL_DCD_TAG_SIZE], CxlFmError> {
+		let hex: String = value.chars().filter(|c| *c != '-').collect();
+		let error = || CxlFmError::InvalidInput(format!("invalid tag: {}", value));
+
+		if hex.len() != CXL_DCD_TAG_SIZE * 2 {
+			return Err(error());
+		}
+
+		let mut tag = [0; CXL_DCD_TAG_SIZE];
+		for (i, byte) in tag.iter_mut().enumerate() {
+			*byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| error())?;
+		}
+
+		Ok(tag)
+	}
+
+	/*
+	 * Convert extents into "DPA:length,DPA:length" list
+	 */
+	pub fn format_extent_ranges(extents: &[CxlDcExtent]) -> String {
+		extents.iter()
+			.map(|extent| format!("{:#x}:{:#x}", extent.start_dpa, extent.length))
+			.collect::<Vec<String>>()
+			.join(",")
+	}
+
+	/*
+	 * Parse "DPA:length,DPA:length" list into extents with the tag
+	 */
+	pub fn parse_extent_ranges(value: &str,
+				   tag: [u8; CXL_DCD_TAG_SIZE]) -> Result<Vec<CxlDcExtent>, CxlFmError> {
+		let mut extents = Vec::new();
+
+		for range in value.split(',').filter(|range| !range.is_empty()) {
+			let (dpa, length) = match range.split_once(':') {
+				Some(pair) => pair,
+				None => return Err(CxlFmError::InvalidInput(format!("invalid extent: {}",
+										    range))),
+			};
+
+			extents.push(CxlDcExtent {
+				start_dpa: parse_number::<u64>(dpa)?,
+				length: parse_number::<u64>(length)?,
+				tag,
+				shared_seq: 0,
+			});
+		}
+
+		Ok(extents)
+	}
+
+	/*
+	 * struct CxlDcdInfo - Get DCD Info responce
+	 * @num_hosts: number of hosts supported by the device
+	 * @num_regions: number of supported DC regions
+	 * @selection_policies: supported add capacity selection policies
+	 * @removal_policies: supported release capacity removal policies
+	 * @sanitize_on_release_mask: regions that support sanitize on release
+	 * @total_capacity: total dynamic capacity
+	 * @block_size_masks: supported block sizes of every region
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlDcdInfo {
+		pub num_hosts: u8,
+		pub num_regions: u8,
+		pub selection_policies: u16,
+		pub removal_policies: u16,
+		pub sanitize_on_release_mask: u8,
+		pub total_capacity: u64,
+		pub block_size_masks: [u64; CXL_DCD_MAX_REGIONS],
+	}
+
+	impl CxlDcdInfo {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.num_hosts);
+			writer.write_u8(self.num_regions);
+			writer.reserve(2);
+			writer.write_u16(self.selection_policies);
+			writer.reserve(2);
+			writer.write_u16(self.removal_policies);
+			writer.write_u8(self.sanitize_on_release_mask);
+			writer.reserve(1);
+			writer.write_u64(self.total_capacity);
+
+			for mask in &self.block_size_masks {
+				writer.write_u64(*mask);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlDcdInfo, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let num_hosts = reader.read_u8()?;
+			let num_regions = reader.read_u8()?;
+			reader.skip(2)?;
+			let selection_policies = reader.read_u16()?;
+			reader.skip(2)?;
+			let removal_policies = reader.read_u16()?;
+			let sanitize_on_release_mask = reader.read_u8()?;
+			reader.skip(1)?;
+			let total_capacity = reader.read_u64()?;
+
+			let mut block_size_masks = [0; CXL_DCD_MAX_REGIONS];
+			for mask in block_size_masks.iter_mut() {
+				*mask = reader.read_u64()?;
+			}
+
+			Ok(CxlDcdInfo {
+				num_hosts,
+				num_regions,
+				selection_policies,
+				removal_policies,
+				sanitize_on_release_mask,
+				total_capacity,
+				block_size_masks,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlGetHostDcRegionConfigRequest - Get Host DC Region
+	 *                                          Configuration request
+	 * @host_id: host ID
+	 * @region_count: number of requested regions
+	 * @start_region: index of the first requested region
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetHostDcRegionConfigRequest {
+		pub host_id: u16,
+		pub region_count: u8,
+		pub start_region: u8,
+	}
+
+	impl CxlGetHostDcRegionConfigRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.host_id);
+			writer.write_u8(self.region_count);
+			writer.write_u8(self.start_region);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetHostDcRegionConfigRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlGetHostDcRegionConfigRequest {
+				host_id: reader.read_u16()?,
+				region_count: reader.read_u8()?,
+				start_region: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlDcRegionConfig - configuration of DC region
+	 * @base: region base DPA
+	 * @decode_length: region decode length
+	 * @length: region length
+	 * @block_size: region block size
+	 * @dsmad_handle: DSMAD handle of the region
+	 * @flags: region flags
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlDcRegionConfig {
+		pub base: u64,
+		pub decode_length: u64,
+		pub length: u64,
+		pub block_size: u64,
+		pub dsmad_handle: u32,
+		pub flags: u8,
+	}
+
+	impl CxlDcRegionConfig {
+		pub fn is_sanitize_on_release(&self) -> bool {
+			self.flags & CXL_DCD_REGION_SANITIZE_ON_RELEASE != 0
+		}
+
+		fn write(&self, writer: &mut CxlPayloadWriter) {
+			writer.write_u64(self.base);
+			writer.write_u64(self.decode_length);
+			writer.write_u64(self.length);
+			writer.write_u64(self.block_size);
+			writer.write_u32(self.dsmad_handle);
+			writer.write_u8(self.flags);
+			writer.reserve(3);
+		}
+
+		fn read(reader: &mut CxlPayloadReader) -> Result<CxlDcRegionConfig, CxlFmError> {
+			let config = CxlDcRegionConfig {
+				base: reader.read_u64()?,
+				decode_length: reader.read_u64()?,
+				length: reader.read_u64()?,
+				block_size: reader.read_u64()?,
+				dsmad_handle: reader.read_u32()?,
+				flags: reader.read_u8()?,
+			};
+
+			reader.skip(3)?;
+			Ok(config)
+		}
+	}
+
+	/*
+	 * struct CxlHostDcRegionConfig - Get Host DC Region Configuration
+	 *                                responce
+	 * @host_id: host ID
+	 * @num_regions: number of available regions
+	 * @start_region: index of the first returned region
+	 * @regions: configuration of returned regions
+	 * @total_extents: total number of supported extents
+	 * @available_extents: number of available extents
+	 * @total_tags: total number of supported tags
+	 * @available_tags: number of available tags
+	 *
+	 * Start region is not a part of FM API payload,
+	 * it is restored from the request.
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlHostDcRegionConfig {
+		pub host_id: u16,
+		pub num_regions: u8,
+		pub start_region: u8,
+		pub regions: Vec<CxlDcRegionConfig>,
+		pub total_extents: u32,
+		pub available_extents: u32,
+		pub total_tags: u32,
+		pub available_tags: u32,
+	}
+
+	impl CxlHostDcRegionConfig {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.host_id);
+			writer.write_u8(self.num_regions);
+			writer.write_u8(self.regions.len() as u8);
+
+			for region in &self.regions {
+				region.write(&mut writer);
+			}
+
+			writer.write_u32(self.total_extents);
+			writer.write_u32(self.available_extents);
+			writer.write_u32(self.total_tags);
+			writer.write_u32(self.available_tags);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(start_region: u8,
+				  data: &[u8]) -> Result<CxlHostDcRegionConfig, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let host_id = reader.read_u16()?;
+			let num_regions = reader.read_u8()?;
+			let returned_regions = reader.read_u8()?;
+
+			let mut regions = Vec::new();
+			for _ in 0..returned_regions {
+				regions.push(CxlDcRegionConfig::read(&mut reader)?);
+			}
+
+			Ok(CxlHostDcRegionConfig {
+				host_id,
+				num_regions,
+				start_region,
+				regions,
+				total_extents: reader.read_u32()?,
+				available_extents: reader.read_u32()?,
+				total_tags: reader.read_u32()?,
+				available_tags: reader.read_u32()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlSetDcRegionConfigRequest - Set DC Region Configuration
+	 *                                      request
+	 * @region_id: region ID
+	 * @block_size: new block size of the region
+	 * @flags: new region flags
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlSetDcRegionConfigRequest {
+		pub region_id: u8,
+		pub block_size: u64,
+		pub flags: u8,
+	}
+
+	impl CxlSetDcRegionConfigRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.region_id);
+			writer.reserve(3);
+			writer.write_u64(self.block_size);
+			writer.write_u8(self.flags);
+			writer.reserve(3);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlSetDcRegionConfigRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let region_id = reader.read_u8()?;
+			reader.skip(3)?;
+			let block_size = reader.read_u64()?;
+			let flags = reader.read_u8()?;
+
+			Ok(CxlSetDcRegionConfigRequest {
+				region_id,
+				block_size,
+				flags,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlDcExtent - Dynamic Capacity extent
+	 * @start_dpa: starting DPA of the extent
+	 * @length: length of the extent
+	 * @tag: tag of the extent
+	 * @shared_seq: shared extent sequence
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlDcExtent {
+		pub start_dpa: u64,
+		pub length: u64,
+		pub tag: [u8; CXL_DCD_TAG_SIZE],
+		pub shared_seq: u16,
+	}
+
+	impl CxlDcExtent {
+		/*
+		 * Get DPA right after the extent
+		 */
+		pub fn end_dpa(&self) -> u64 {
+			self.start_dpa + self.length
+		}
+
+		pub fn write(&self, writer: &mut CxlPayloadWriter) {
+			writer.write_u64(self.start_dpa);
+			writer.write_u64(self.length);
+			writer.write_bytes(&self.tag);
+			writer.write_u16(self.shared_seq);
+			writer.reserve(6);
+		}
+
+		pub fn read(reader: &mut CxlPayloadReader) -> Result<CxlDcExtent, CxlFmError> {
+			let start_dpa = reader.read_u64()?;
+			let length = reader.read_u64()?;
+			let tag = reader.read_bytes(CXL_DCD_TAG_SIZE)?.try_into().unwrap();
+			let shared_seq = reader.read_u16()?;
+			reader.skip(6)?;
+
+			Ok(CxlDcExtent {
+				start_dpa,
+				length,
+				tag,
+				shared_seq,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlGetDcExtentListRequest - Get DC Region Extent Lists request
+	 * @host_id: host ID
+	 * @extent_count: maximal number of returned extents
+	 * @start_index: index of the first requested extent
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetDcExtentListRequest {
+		pub host_id: u16,
+		pub extent_count: u32,
+		pub start_index: u32,
+	}
+
+	impl CxlGetDcExtentListRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.host_id);
+			writer.reserve(2);
+			writer.write_u32(self.extent_count);
+			writer.write_u32(self.start_index);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetDcExtentListRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let host_id = reader.read_u16()?;
+			reader.skip(2)?;
+
+			Ok(CxlGetDcExtentListRequest {
+				host_id,
+				extent_count: reader.read_u32()?,
+				start_index: reader.read_u32()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlDcExtentList - Get DC Region Extent Lists responce
+	 * @host_id: host ID
+	 * @start_index: index of the first returned extent
+	 * @total_extents: total number of extents of the host
+	 * @generation: extent list generation number
+	 * @extents: returned extents
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlDcExtentList {
+		pub host_id: u16,
+		pub start_index: u32,
+		pub total_extents: u32,
+		pub generation: u32,
+		pub extents: Vec<CxlDcExtent>,
+	}
+
+	impl CxlDcExtentList {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.host_id);
+			writer.reserve(2);
+			writer.write_u32(self.start_index);
+			writer.write_u32(self.extents.len() as u32);
+			writer.write_u32(self.total_extents);
+			writer.write_u32(self.generation);
+			writer.reserve(4);
+
+			for extent in &self.extents {
+				extent.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlDcExtentList, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let host_id = reader.read_u16()?;
+			reader.skip(2)?;
+			let start_index = reader.read_u32()?;
+			let returned_extents = reader.read_u32()?;
+			let total_extents = reader.read_u32()?;
+			let generation = reader.read_u32()?;
+			reader.skip(4)?;
+
+			let mut extents = Vec::new();
+			for _ in 0..returned_extents {
+				extents.push(CxlDcExtent::read(&mut reader)?);
+			}
+
+			Ok(CxlDcExtentList {
+				host_id,
+				start_index,
+				total_extents,
+				generation,
+				extents,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlInitiateDcAddRequest - Initiate Dynamic Capacity Add request
+	 * @host_id: host ID
+	 * @selection_policy: selection policy
+	 * @region: DC region number
+	 * @length: total length of requested capacity
+	 * @tag: tag of added extents
+	 * @extents: extents to add (prescriptive policy only)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlInitiateDcAddRequest {
+		pub host_id: u16,
+		pub selection_policy: u8,
+		pub region: u8,
+		pub length: u64,
+		pub tag: [u8; CXL_DCD_TAG_SIZE],
+		pub extents: Vec<CxlDcExtent>,
+	}
+
+	impl CxlInitiateDcAddRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.host_id);
+			writer.write_u8(self.selection_policy & 0xF);
+			writer.write_u8(self.region);
+			writer.write_u64(self.length);
+			writer.write_bytes(&self.tag);
+			writer.write_u32(self.extents.len() as u32);
+
+			for extent in &self.extents {
+				extent.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlInitiateDcAddRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let host_id = reader.read_u16()?;
+			let selection_policy = reader.read_u8()? & 0xF;
+			let region = reader.read_u8()?;
+			let length = reader.read_u64()?;
+			let tag = reader.read_bytes(CXL_DCD_TAG_SIZE)?.try_into().unwrap();
+			let extent_count = reader.read_u32()?;
+
+			let mut extents = Vec::new();
+			for _ in 0..extent_count {
+				extents.push(CxlDcExtent::read(&mut reader)?);
+			}
+
+			Ok(CxlInitiateDcAddRequest {
+				host_id,
+				selection_policy,
+				region,
+				length,
+				tag,
+				extents,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlDcAddResult - capacity added to the host
+	 * @host_id: host ID
+	 * @generation: extent list generation number
+	 * @pending_length: offered capacity not accepted by the host yet
+	 * @accepted: extents accepted by the host
+	 * @pending: offered extents not accepted by the host yet
+	 *           (known for prescriptive policy only)
+	 *
+	 * The host accepts offered capacity asynchronously. Capacity
+	 * of enable shared access policy is not known in advance,
+	 * so nothing of it is pending.
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlDcAddResult {
+		pub host_id: u16,
+		pub generation: u32,
+		pub pending_length: u64,
+		pub accepted: Vec<CxlDcExtent>,
+		pub pending: Vec<CxlDcExtent>,
+	}
+
+	impl CxlDcAddResult {
+		/*
+		 * Split capacity offered by the request into
+		 * the accepted extents and the pending rest
+		 */
+		pub fn new(request: &CxlInitiateDcAddRequest, generation: u32,
+			   accepted: Vec<CxlDcExtent>) -> CxlDcAddResult {
+			let accepted_length: u64 = accepted.iter().map(|extent| extent.length).sum();
+			let mut pending = Vec::new();
+
+			let pending_length = match request.selection_policy {
+				CXL_DCD_SELECTION_POLICY_PRESCRIPTIVE => {
+					pending = request.extents.iter()
+						.filter(|extent| !accepted.iter().any(|added| {
+							added.start_dpa == extent.start_dpa
+						}))
+						.cloned()
+						.collect();
+					pending.iter().map(|extent| extent.length).sum()
+				},
+				CXL_DCD_SELECTION_POLICY_SHARED_ACCESS => 0,
+				_ => request.length.saturating_sub(accepted_length),
+			};
+
+			CxlDcAddResult {
+				host_id: request.host_id,
+				generation,
+				pending_length,
+				accepted,
+				pending,
+			}
+		}
+
+		/*
+		 * Has the host accepted all offered capacity?
+		 */
+		pub fn is_complete(&self) -> bool {
+			self.pending_length == 0 && !self.accepted.is_empty()
+		}
+
+		/*
+		 * Get accepted capacity
+		 */
+		pub fn accepted_length(&self) -> u64 {
+			self.accepted.iter().map(|extent| extent.length).sum()
+		}
+
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.host_id);
+			writer.reserve(2);
+			writer.write_u32(self.generation);
+			writer.write_u64(self.pending_length);
+			writer.write_u32(self.accepted.len() as u32);
+			writer.write_u32(self.pending.len() as u32);
+
+			for extent in self.accepted.iter().chain(self.pending.iter()) {
+				extent.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlDcAddResult, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let host_id = reader.read_u16()?;
+			reader.skip(2)?;
+			let generation = reader.read_u32()?;
+			let pending_length = reader.read_u64()?;
+			let accepted_count = reader.read_u32()?;
+			let pending_count = reader.read_u32()?;
+
+			let mut accepted = Vec::new();
+			for _ in 0..accepted_count {
+				accepted.push(CxlDcExtent::read(&mut reader)?);
+			}
+
+			let mut pending = Vec::new();
+			for _ in 0..pending_count {
+				pending.push(CxlDcExtent::read(&mut reader)?);
+			}
+
+			Ok(CxlDcAddResult {
+				host_id,
+				generation,
+				pending_length,
+				accepted,
+				pending,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlInitiateDcReleaseRequest - Initiate Dynamic Capacity
+	 *                                      Release request
+	 * @host_id: host ID
+	 * @removal_policy: removal policy
+	 * @forced: release capacity without host response
+	 * @sanitize: sanitize released capacity
+	 * @length: total length of released capacity
+	 * @tag: tag of released extents
+	 * @extents: extents to release (prescriptive policy only)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlInitiateDcReleaseRequest {
+		pub host_id: u16,
+		pub removal_policy: u8,
+		pub forced: bool,
+		pub sanitize: bool,
+		pub length: u64,
+		pub tag: [u8; CXL_DCD_TAG_SIZE],
+		pub extents: Vec<CxlDcExtent>,
+	}
+
+	impl CxlInitiateDcReleaseRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+			let mut flags = self.removal_policy & 0xF;
+
+			if self.forced {
+				flags |= CXL_DCD_RELEASE_FORCED;
+			}
+
+			if self.sanitize {
+				flags |= CXL_DCD_RELEASE_SANITIZE_ON_RELEASE;
+			}
+
+			writer.write_u16(self.host_id);
+			writer.write_u8(flags);
+			writer.reserve(1);
+			writer.write_u64(self.length);
+			writer.write_bytes(&self.tag);
+			writer.write_u32(self.extents.len() as u32);
+
+			for extent in &self.extents {
+				extent.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlInitiateDcReleaseRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let host_id = reader.read_u16()?;
+			let flags = reader.read_u8()?;
+			reader.skip(1)?;
+			let length = reader.read_u64()?;
+			let tag = reader.read_bytes(CXL_DCD_TAG_SIZE)?.try_into().unwrap();
+			let extent_count = reader.read_u32()?;
+
+			let mut extents = Vec::new();
+			for _ in 0..extent_count {
+				extents.push(CxlDcExtent::read(&mut reader)?);
+			}
+
+			Ok(CxlInitiateDcReleaseRequest {
+				host_id,
+				removal_policy: flags & 0xF,
+				forced: flags & CXL_DCD_RELEASE_FORCED != 0,
+				sanitize: flags & CXL_DCD_RELEASE_SANITIZE_ON_RELEASE != 0,
+				length,
+				tag,
+				extents,
+			})
+		}
+	}
+}
@@ -0,0 +1,692 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * FM event records.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_event {
+	use std::time::{SystemTime, UNIX_EPOCH};
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_lib::CxlFmRequest;
+	use crate::cxl_fm_lib::format_size;
+	use crate::cxl_fm_lib::parse_number;
+	use crate::cxl_fm_lib::CXL_FM_SEVERITY_ARG;
+	use crate::cxl_fm_lib::CXL_FM_COMPONENT_ARG;
+	use crate::cxl_fm_lib::CXL_FM_EVENT_TYPE_ARG;
+	use crate::cxl_fm_lib::CXL_FM_SINCE_ARG;
+	use crate::cxl_fm_lib::CXL_FM_UNTIL_ARG;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+	use crate::cxl_fm_api::CXL_LTSSM_L0;
+	use crate::cxl_switch_lib::port_device_name;
+	use crate::cxl_switch_lib::CXL_PORT_DEVICE_NONE;
+	use crate::cxl_dcd_lib::CxlDcExtent;
+	use crate::cxl_dcd_lib::format_tag;
+	use crate::cxl_dcd_lib::CXL_DCD_TAG_SIZE;
+
+	/*
+	 * Event severity (the same values as Event Record Flags
+	 * of CXL Specification 3.0 Common Event Record)
+	 */
+	pub const CXL_EVENT_SEVERITY_INFO: u8		= 0x0;
+	pub const CXL_EVENT_SEVERITY_WARNING: u8	= 0x1;
+	pub const CXL_EVENT_SEVERITY_FAILURE: u8	= 0x2;
+	pub const CXL_EVENT_SEVERITY_FATAL: u8		= 0x3;
+
+	/*
+	 * Get human-readable name of event severity
+	 */
+	pub fn event_severity_name(severity: u8) -> &'static str {
+		match severity {
+			CXL_EVENT_SEVERITY_INFO => "info",
+			CXL_EVENT_SEVERITY_WARNING => "warning",
+			CXL_EVENT_SEVERITY_FAILURE => "failure",
+			CXL_EVENT_SEVERITY_FATAL => "fatal",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Get event severity by its name
+	 */
+	pub fn event_severity_by_name(name: &str) -> Option<u8> {
+		(CXL_EVENT_SEVERITY_INFO..=CXL_EVENT_SEVERITY_FATAL)
+			.find(|severity| event_severity_name(*severity) == name)
+	}
+
+	/*
+	 * Types of FM events
+	 */
+	pub const CXL_FM_EVENT_DRIFT: u8		= 0x1;
+	pub const CXL_FM_EVENT_REMEDIATION: u8		= 0x2;
+	pub const CXL_FM_EVENT_RECOVERY: u8		= 0x3;
+	pub const CXL_FM_EVENT_DEVICE: u8		= 0x4;
+	pub const CXL_FM_EVENT_LINK: u8			= 0x5;
+	pub const CXL_FM_EVENT_HOT_PLUG: u8		= 0x6;
+	pub const CXL_FM_EVENT_CAPACITY: u8		= 0x7;
+	pub const CXL_FM_EVENT_OPERATION: u8		= 0x8;
+	pub const CXL_FM_EVENT_POLICY: u8		= 0x9;
+	pub const CXL_FM_EVENT_FAILOVER: u8		= 0xA;
+
+	/*
+	 * Get human-readable name of event type
+	 */
+	pub fn event_type_name(event_type: u8) -> &'static str {
+		match event_type {
+			CXL_FM_EVENT_DRIFT => "drift",
+			CXL_FM_EVENT_REMEDIATION => "remediation",
+			CXL_FM_EVENT_RECOVERY => "recovery",
+			CXL_FM_EVENT_DEVICE => "device",
+			CXL_FM_EVENT_LINK => "link",
+			CXL_FM_EVENT_HOT_PLUG => "hot-plug",
+			CXL_FM_EVENT_CAPACITY => "capacity",
+			CXL_FM_EVENT_OPERATION => "operation",
+			CXL_FM_EVENT_POLICY => "policy",
+			CXL_FM_EVENT_FAILOVER => "failover",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Get event type by its name
+	 */
+	pub fn event_type_by_name(name: &str) -> Option<u8> {
+		(CXL_FM_EVENT_DRIFT..=CXL_FM_EVENT_FAILOVER)
+			.find(|event_type| event_type_name(*event_type) == name)
+	}
+
+	/*
+	 * Get current time (nanoseconds since Unix epoch)
+	 */
+	pub fn event_timestamp() -> u64 {
+		SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |time| time.as_nanos() as u64)
+	}
+
+	/*
+	 * Convert timestamp into "YYYY-MM-DD hh:mm:ss.mmm" (UTC)
+	 */
+	pub fn format_timestamp(timestamp: u64) -> String {
+		let seconds = timestamp / 1_000_000_000;
+		let millis = timestamp % 1_000_000_000 / 1_000_000;
+		let days = (seconds / 86400) as i64;
+		let time = seconds % 86400;
+
+		/*
+		 * Civil date from days since epoch (proleptic Gregorian)
+		 */
+		let z = days + 719468;
+		let era = z.div_euclid(146097);
+		let doe = z.rem_euclid(146097);
+		let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+		let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+		let mp = (5 * doy + 2) / 153;
+		let day = doy - (153 * mp + 2) / 5 + 1;
+		let month = if mp < 10 { mp + 3 } else { mp - 9 };
+		let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+		format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+			year, month, day, time / 3600, time % 3600 / 60, time % 60, millis)
+	}
+
+	/*
+	 * Parse time as Unix time in seconds or as time
+	 * before now: 30s, 10m, 2h, 1d
+	 */
+	pub fn parse_event_time(value: &str, now: u64) -> Result<u64, CxlFmError> {
+		let units = [('s', 1), ('m', 60), ('h', 3600), ('d', 86400)];
+
+		for (suffix, seconds) in units {
+			if let Some(number) = value.strip_suffix(suffix) {
+				let ago = parse_number::<u64>(number)?
+						.saturating_mul(seconds * 1_000_000_000);
+
+				return Ok(now.saturating_sub(ago));
+			}
+		}
+
+		Ok(parse_number::<u64>(value)?.saturating_mul(1_000_000_000))
+	}
+
+	/*
+	 * struct CxlFmEvent - event record of FM
+	 * @sequence: sequence number of the event
+	 * @timestamp: time of the event (nanoseconds since Unix epoch)
+	 * @severity: event severity
+	 * @event_type: type of the event
+	 * @component: name of the fabric object
+	 * @message: event description
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmEvent {
+		pub sequence: u64,
+		pub timestamp: u64,
+		pub severity: u8,
+		pub event_type: u8,
+		pub component: String,
+		pub message: String,
+	}
+
+	impl CxlFmEvent {
+		pub fn write(&self, writer: &mut CxlPayloadWriter) {
+			writer.write_u64(self.sequence);
+			writer.write_u64(self.timestamp);
+			writer.write_u8(self.severity);
+			writer.write_u8(self.event_type);
+			writer.write_string(&self.component);
+			writer.write_string(&self.message);
+		}
+
+		pub fn read(reader: &mut CxlPayloadReader) -> Result<CxlFmEvent, CxlFmError> {
+			Ok(CxlFmEvent {
+				sequence: reader.read_u64()?,
+				timestamp: reader.read_u64()?,
+				severity: reader.read_u8()?,
+				event_type: reader.read_u8()?,
+				component: reader.read_string()?,
+				message: reader.read_string()?,
+			})
+		}
+
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			self.write(&mut writer);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlFmEvent, CxlFmError> {
+			Self::read(&mut CxlPayloadReader::new(data))
+		}
+	}
+
+	/*
+	 * struct CxlFmEventList - events selected by filter
+	 * @events: events from the oldest to the newest
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmEventList {
+		pub events: Vec<CxlFmEvent>,
+	}
+
+	impl CxlFmEventList {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u32(self.events.len() as u32);
+
+			for event in &self.events {
+				event.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlFmEventList, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+			let mut events = Vec::new();
+
+			for _ in 0..reader.read_u32()? {
+				events.push(CxlFmEvent::read(&mut reader)?);
+			}
+
+			Ok(CxlFmEventList { events })
+		}
+	}
+
+	/*
+	 * struct CxlFmEventFilter - selection of FM events
+	 * @severity: minimal severity
+	 * @component: fabric object and objects inside it
+	 * @event_type: type of events
+	 * @since: the earliest time (nanoseconds since Unix epoch)
+	 * @until: the latest time (nanoseconds since Unix epoch)
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmEventFilter {
+		pub severity: u8,
+		pub component: Option<String>,
+		pub event_type: Option<u8>,
+		pub since: u64,
+		pub until: Option<u64>,
+	}
+
+	impl CxlFmEventFilter {
+		/*
+		 * Get filter defined by request arguments
+		 */
+		pub fn from_request(request: &CxlFmRequest) -> Result<CxlFmEventFilter, CxlFmError> {
+			let until = match request.get(CXL_FM_UNTIL_ARG) {
+				Some(value) => Some(parse_number::<u64>(value)?),
+				None => None,
+			};
+			let event_type = match request.get(CXL_FM_EVENT_TYPE_ARG) {
+				Some(value) => Some(parse_number::<u8>(value)?),
+				None => None,
+			};
+
+			Ok(CxlFmEventFilter {
+				severity: request.get_number_or::<u8>(CXL_FM_SEVERITY_ARG,
+								      CXL_EVENT_SEVERITY_INFO)?,
+				component: request.get(CXL_FM_COMPONENT_ARG).map(String::from),
+				event_type,
+				since: request.get_number_or::<u64>(CXL_FM_SINCE_ARG, 0)?,
+				until,
+			})
+		}
+
+		/*
+		 * Add filter arguments into request
+		 */
+		pub fn apply(&self, mut request: CxlFmRequest) -> CxlFmRequest {
+			if self.severity != CXL_EVENT_SEVERITY_INFO {
+				request = request.arg(CXL_FM_SEVERITY_ARG, &self.severity.to_string());
+			}
+
+			if let Some(component) = &self.component {
+				request = request.arg(CXL_FM_COMPONENT_ARG, component);
+			}
+
+			if let Some(event_type) = self.event_type {
+				request = request.arg(CXL_FM_EVENT_TYPE_ARG, &event_type.to_string());
+			}
+
+			if self.since != 0 {
+				request = request.arg(CXL_FM_SINCE_ARG, &self.since.to_string());
+			}
+
+			if let Some(until) = self.until {
+				request = request.arg(CXL_FM_UNTIL_ARG, &until.to_string());
+			}
+
+			request
+		}
+
+		/*
+		 * Does event pass the filter? Component matches
+		 * the object itself and objects inside it ("sw0"
+		 * matches "sw0/port3").
+		 */
+		pub fn matches(&self, event: &CxlFmEvent) -> bool {
+			let is_component = self.component.as_ref().is_none_or(|component| {
+				event.component == *component ||
+				event.component.starts_with(&format!("{}/", component))
+			});
+
+			event.severity >= self.severity &&
+			is_component &&
+			self.event_type.is_none_or(|event_type| event.event_type == event_type) &&
+			event.timestamp >= self.since &&
+			self.until.is_none_or(|until| event.timestamp <= until)
+		}
+	}
+
+	/*
+	 * Event logs of CXL component (CXL Specification 3.0, 8.2.9.2.2)
+	 */
+	pub const CXL_EVENT_LOG_INFORMATIONAL: u8	= 0x0;
+	pub const CXL_EVENT_LOG_WARNING: u8		= 0x1;
+	pub const CXL_EVENT_LOG_FAILURE: u8		= 0x2;
+	pub const CXL_EVENT_LOG_FATAL: u8		= 0x3;
+	pub const CXL_EVENT_LOG_DYNAMIC_CAPACITY: u8	= 0x4;
+
+	pub const CXL_EVENT_LOGS: [u8; 5] = [
+		CXL_EVENT_LOG_INFORMATIONAL,
+		CXL_EVENT_LOG_WARNING,
+		CXL_EVENT_LOG_FAILURE,
+		CXL_EVENT_LOG_FATAL,
+		CXL_EVENT_LOG_DYNAMIC_CAPACITY,
+	];
+
+	/*
+	 * Get Event Records flags
+	 */
+	pub const CXL_EVENT_RECORDS_OVERFLOW: u8	= 1 << 0;
+	pub const CXL_EVENT_RECORDS_MORE: u8		= 1 << 1;
+
+	/*
+	 * Clear Event Records flags
+	 */
+	pub const CXL_EVENT_CLEAR_ALL: u8		= 1 << 0;
+
+	/*
+	 * Common Event Record geometry
+	 */
+	pub const CXL_EVENT_RECORD_SIZE: u8		= 128;
+	pub const CXL_EVENT_RECORD_DATA_SIZE: usize	= 80;
+	pub const CXL_EVENT_RECORD_SEVERITY_MASK: u8	= 0x3;
+
+	/*
+	 * Event Record Identifiers
+	 */
+	pub const CXL_EVENT_RECORD_GENERAL_MEDIA: &str		= "fbcd0a77-c260-417f-85a9-088b1621eba6";
+	pub const CXL_EVENT_RECORD_DRAM: &str			= "601dcbb3-9c06-4eab-b8af-4e9bfb5c9624";
+	pub const CXL_EVENT_RECORD_MEMORY_MODULE: &str		= "fe927475-dd59-4339-a586-79bab113b774";
+	pub const CXL_EVENT_RECORD_PHYSICAL_SWITCH: &str	= "77cf9271-9c02-470b-9fe4-bc7b75f2da97";
+	pub const CXL_EVENT_RECORD_VIRTUAL_SWITCH: &str		= "40d26425-3396-4c4d-a5da-3d47263af425";
+	pub const CXL_EVENT_RECORD_MLD_PORT: &str		= "8dc44363-0c96-4710-b7bf-04bb99534c3f";
+	pub const CXL_EVENT_RECORD_DYNAMIC_CAPACITY: &str	= "ca95afa7-f183-4018-8c2f-95268e101a2a";
+
+	/*
+	 * Physical Switch Event types
+	 */
+	pub const CXL_SWITCH_EVENT_LINK_STATE: u8	= 0x0;
+	pub const CXL_SWITCH_EVENT_SLOT_STATUS: u8	= 0x1;
+
+	/*
+	 * Dynamic Capacity Event types
+	 */
+	pub const CXL_DC_EVENT_ADD_CAPACITY: u8		= 0x0;
+	pub const CXL_DC_EVENT_RELEASE_CAPACITY: u8	= 0x1;
+	pub const CXL_DC_EVENT_FORCED_RELEASE: u8	= 0x2;
+	pub const CXL_DC_EVENT_REGION_UPDATED: u8	= 0x3;
+
+	/*
+	 * struct CxlEventRecord - Common Event Record
+	 * @uuid: Event Record Identifier
+	 * @flags: Event Record Flags (severity in bits 1:0)
+	 * @handle: Event Record Handle
+	 * @related_handle: Related Event Record Handle
+	 * @timestamp: Event Record Timestamp
+	 * @maintenance_class: Maintenance Operation Class
+	 * @data: record specific data
+	 *
+	 * Record specific data used by FM:
+	 * Physical Switch Event - port ID, event type,
+	 *     port configuration state, connected device type,
+	 *     LTSSM state;
+	 * Dynamic Capacity Event - event type, validity flags,
+	 *     host ID (16 bits), region index, 3 reserved bytes,
+	 *     extent.
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlEventRecord {
+		pub uuid: [u8; CXL_DCD_TAG_SIZE],
+		pub flags: u8,
+		pub handle: u16,
+		pub related_handle: u16,
+		pub timestamp: u64,
+		pub maintenance_class: u8,
+		pub data: Vec<u8>,
+	}
+
+	impl CxlEventRecord {
+		pub fn write(&self, writer: &mut CxlPayloadWriter) {
+			let mut data = self.data.clone();
+
+			data.resize(CXL_EVENT_RECORD_DATA_SIZE, 0);
+
+			writer.write_bytes(&self.uuid);
+			writer.write_u8(CXL_EVENT_RECORD_SIZE);
+			writer.write_u8(self.flags);
+			writer.reserve(2);
+			writer.write_u16(self.handle);
+			writer.write_u16(self.related_handle);
+			writer.write_u64(self.timestamp);
+			writer.write_u8(self.maintenance_class);
+			writer.reserve(15);
+			writer.write_bytes(&data);
+		}
+
+		pub fn read(reader: &mut CxlPayloadReader) -> Result<CxlEventRecord, CxlFmError> {
+			let uuid = reader.read_bytes(CXL_DCD_TAG_SIZE)?.try_into().unwrap();
+			let length = reader.read_u8()?;
+
+			if length != CXL_EVENT_RECORD_SIZE {
+				return Err(CxlFmError::Protocol(format!("invalid event record length: {}",
+									length)));
+			}
+
+			let flags = reader.read_u8()?;
+			reader.skip(2)?;
+			let handle = reader.read_u16()?;
+			let related_handle = reader.read_u16()?;
+			let timestamp = reader.read_u64()?;
+			let maintenance_class = reader.read_u8()?;
+			reader.skip(15)?;
+			let data = reader.read_bytes(CXL_EVENT_RECORD_DATA_SIZE)?.to_vec();
+
+			Ok(CxlEventRecord {
+				uuid,
+				flags,
+				handle,
+				related_handle,
+				timestamp,
+				maintenance_class,
+				data,
+			})
+		}
+
+		/*
+		 * Get severity of the event
+		 */
+		pub fn severity(&self) -> u8 {
+			self.flags & CXL_EVENT_RECORD_SEVERITY_MASK
+		}
+
+		/*
+		 * Get physical port and event type (link state
+		 * or slot status) of Physical Switch Event Record
+		 */
+		pub fn port_event(&self) -> Option<(u8, u8)> {
+			if format_tag(&self.uuid) != CXL_EVENT_RECORD_PHYSICAL_SWITCH {
+				return None;
+			}
+
+			Some((*self.data.first()?, *self.data.get(1)?))
+		}
+
+		/*
+		 * Convert record into FM event: type of the event,
+		 * object inside the component (empty for the component
+		 * itself) and description
+		 */
+		pub fn describe(&self) -> (u8, String, String) {
+			let byte = |index: usize| self.data.get(index).copied().unwrap_or(0);
+
+			match format_tag(&self.uuid).as_str() {
+				CXL_EVENT_RECORD_PHYSICAL_SWITCH => {
+					let port = format!("port{}", byte(0));
+
+					match byte(1) {
+						CXL_SWITCH_EVENT_SLOT_STATUS if byte(3) == CXL_PORT_DEVICE_NONE => {
+							(CXL_FM_EVENT_HOT_PLUG, port, String::from("device is removed"))
+						},
+						CXL_SWITCH_EVENT_SLOT_STATUS => {
+							(CXL_FM_EVENT_HOT_PLUG, port,
+							 format!("{} device is added", port_device_name(byte(3))))
+						},
+						_ if byte(4) == CXL_LTSSM_L0 => {
+							(CXL_FM_EVENT_LINK, port, String::from("link is up"))
+						},
+						_ => (CXL_FM_EVENT_LINK, port, String::from("link is down")),
+					}
+				},
+				CXL_EVENT_RECORD_DYNAMIC_CAPACITY => {
+					let host = format!("host{}", u16::from_le_bytes([byte(2), byte(3)]));
+					let extent = self.data.get(8..)
+						.and_then(|data| CxlDcExtent::read(&mut CxlPayloadReader::new(data)).ok());
+					let range = extent.map_or(String::new(), |extent| {
+						format!(" {} at DPA {:#x}", format_size(extent.length), extent.start_dpa)
+					});
+					let message = match byte(0) {
+						CXL_DC_EVENT_ADD_CAPACITY => format!("capacity{} is added", range),
+						CXL_DC_EVENT_RELEASE_CAPACITY => format!("capacity{} is released", range),
+						CXL_DC_EVENT_FORCED_RELEASE => format!("capacity{} is released by force", range),
+						CXL_DC_EVENT_REGION_UPDATED => format!("region {} configuration is updated", byte(4)),
+						event => format!("dynamic capacity event {}", event),
+					};
+
+					(CXL_FM_EVENT_CAPACITY, host, message)
+				},
+				CXL_EVENT_RECORD_GENERAL_MEDIA => {
+					(CXL_FM_EVENT_DEVICE, String::new(), String::from("general media event"))
+				},
+				CXL_EVENT_RECORD_DRAM => {
+					(CXL_FM_EVENT_DEVICE, String::new(), String::from("DRAM event"))
+				},
+				CXL_EVENT_RECORD_MEMORY_MODULE => {
+					(CXL_FM_EVENT_DEVICE, String::new(), String::from("memory module event"))
+				},
+				CXL_EVENT_RECORD_VIRTUAL_SWITCH => {
+					(CXL_FM_EVENT_DEVICE, String::new(), String::from("virtual switch event"))
+				},
+				CXL_EVENT_RECORD_MLD_PORT => {
+					(CXL_FM_EVENT_DEVICE, String::new(), String::from("MLD port event"))
+				},
+				uuid => (CXL_FM_EVENT_DEVICE, String::new(), format!("event record {}", uuid)),
+			}
+		}
+	}
+
+	/*
+	 * struct CxlGetEventRecordsRequest - Get Event Records request
+	 * @event_log: event log to read
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetEventRecordsRequest {
+		pub event_log: u8,
+	}
+
+	impl CxlGetEventRecordsRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			vec![self.event_log]
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetEventRecordsRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlGetEventRecordsRequest {
+				event_log: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlGetEventRecordsResponse - Get Event Records responce
+	 * @flags: overflow and more records flags
+	 * @overflow_count: number of lost records
+	 * @first_overflow: time of the first lost record
+	 * @last_overflow: time of the last lost record
+	 * @records: event records
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlGetEventRecordsResponse {
+		pub flags: u8,
+		pub overflow_count: u16,
+		pub first_overflow: u64,
+		pub last_overflow: u64,
+		pub records: Vec<CxlEventRecord>,
+	}
+
+	impl CxlGetEventRecordsResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.flags);
+			writer.reserve(1);
+			writer.write_u16(self.overflow_count);
+			writer.write_u64(self.first_overflow);
+			writer.write_u64(self.last_overflow);
+			writer.write_u16(self.records.len() as u16);
+			writer.reserve(10);
+
+			for record in &self.records {
+				record.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetEventRecordsResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let flags = reader.read_u8()?;
+			reader.skip(1)?;
+			let overflow_count = reader.read_u16()?;
+			let first_overflow = reader.read_u64()?;
+			let last_overflow = reader.read_u64()?;
+			let count = reader.read_u16()?;
+			reader.skip(10)?;
+
+			let mut records = Vec::new();
+			for _ in 0..count {
+				records.push(CxlEventRecord::read(&mut reader)?);
+			}
+
+			Ok(CxlGetEventRecordsResponse {
+				flags,
+				overflow_count,
+				first_overflow,
+				last_overflow,
+				records,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlClearEventRecordsRequest - Clear Event Records request
+	 * @event_log: event log to clear
+	 * @flags: clear all records or only listed ones
+	 * @handles: handles of records to clear
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlClearEventRecordsRequest {
+		pub event_log: u8,
+		pub flags: u8,
+		pub handles: Vec<u16>,
+	}
+
+	impl CxlClearEventRecordsRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.event_log);
+			writer.write_u8(self.flags);
+			writer.write_u8(self.handles.len() as u8);
+			writer.reserve(3);
+
+			for handle in &self.handles {
+				writer.write_u16(*handle);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlClearEventRecordsRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let event_log = reader.read_u8()?;
+			let flags = reader.read_u8()?;
+			let count = reader.read_u8()?;
+			reader.skip(3)?;
+
+			let mut handles = Vec::new();
+			for _ in 0..count {
+				handles.push(reader.read_u16()?);
+			}
+
+			Ok(CxlClearEventRecordsRequest {
+				event_log,
+				flags,
+				handles,
+			})
+		}
+	}
+}
@@ -0,0 +1,440 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM API definitions.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_api {
+	use crate::cxl_fm_lib::CxlFmError;
+
+	/*
+	 * FM API command opcodes (CXL Specification 3.0)
+	 */
+	pub const CXL_FM_API_IDENTIFY: u16			= 0x0001;
+
+	pub const CXL_FM_API_GET_EVENT_RECORDS: u16		= 0x0100;
+	pub const CXL_FM_API_CLEAR_EVENT_RECORDS: u16		= 0x0101;
+
+	pub const CXL_FM_API_IDENTIFY_SWITCH_DEVICE: u16	= 0x5100;
+	pub const CXL_FM_API_GET_PHYSICAL_PORT_STATE: u16	= 0x5101;
+	pub const CXL_FM_API_PHYSICAL_PORT_CONTROL: u16		= 0x5102;
+	pub const CXL_FM_API_SEND_PPB_CONFIG_REQUEST: u16	= 0x5103;
+
+	pub const CXL_FM_API_GET_VCS_INFO: u16			= 0x5200;
+	pub const CXL_FM_API_BIND_VPPB: u16			= 0x5201;
+	pub const CXL_FM_API_UNBIND_VPPB: u16			= 0x5202;
+	pub const CXL_FM_API_GENERATE_AER_EVENT: u16		= 0x5203;
+
+	pub const CXL_FM_API_TUNNEL_MANAGEMENT_COMMAND: u16	= 0x5300;
+	pub const CXL_FM_API_SEND_LD_CONFIG_REQUEST: u16	= 0x5301;
+	pub const CXL_FM_API_SEND_LD_MEMORY_REQUEST: u16	= 0x5302;
+
+	pub const CXL_FM_API_GET_LD_INFO: u16			= 0x5400;
+	pub const CXL_FM_API_GET_LD_ALLOCATIONS: u16		= 0x5401;
+	pub const CXL_FM_API_SET_LD_ALLOCATIONS: u16		= 0x5402;
+	pub const CXL_FM_API_GET_QOS_CONTROL: u16		= 0x5403;
+	pub const CXL_FM_API_SET_QOS_CONTROL: u16		= 0x5404;
+	pub const CXL_FM_API_GET_QOS_STATUS: u16		= 0x5405;
+	pub const CXL_FM_API_GET_QOS_ALLOCATED_BW: u16		= 0x5406;
+	pub const CXL_FM_API_SET_QOS_ALLOCATED_BW: u16		= 0x5407;
+	pub const CXL_FM_API_GET_QOS_BW_LIMIT: u16		= 0x5408;
+	pub const CXL_FM_API_SET_QOS_BW_LIMIT: u16		= 0x5409;
+
+	pub const CXL_FM_API_GET_MHD_INFO: u16			= 0x5500;
+	pub const CXL_FM_API_GET_MHD_HEAD_INFO: u16		= 0x5501;
+
+	pub const CXL_FM_API_GET_DCD_INFO: u16			= 0x5600;
+	pub const CXL_FM_API_GET_HOST_DC_REGION_CONFIG: u16	= 0x5601;
+	pub const CXL_FM_API_SET_DC_REGION_CONFIG: u16		= 0x5602;
+	pub const CXL_FM_API_GET_DC_REGION_EXTENT_LIST: u16	= 0x5603;
+	pub const CXL_FM_API_INITIATE_DC_ADD: u16		= 0x5604;
+	pub const CXL_FM_API_INITIATE_DC_RELEASE: u16		= 0x5605;
+
+	/*
+	 * Memory device command opcodes (sent to LD by means of tunneling)
+	 */
+	pub const CXL_MEMDEV_SECURE_ERASE: u16			= 0x4401;
+
+	/*
+	 * FM API command return codes
+	 */
+	pub const CXL_FM_API_SUCCESS: u16			= 0x0000;
+	pub const CXL_FM_API_BACKGROUND_COMMAND_STARTED: u16	= 0x0001;
+	pub const CXL_FM_API_INVALID_INPUT: u16			= 0x0002;
+	pub const CXL_FM_API_UNSUPPORTED: u16			= 0x0003;
+	pub const CXL_FM_API_INTERNAL_ERROR: u16		= 0x0004;
+	pub const CXL_FM_API_RETRY_REQUIRED: u16		= 0x0005;
+	pub const CXL_FM_API_BUSY: u16				= 0x0006;
+	pub const CXL_FM_API_INVALID_PAYLOAD_LENGTH: u16	= 0x0016;
+	pub const CXL_FM_API_RESOURCES_EXHAUSTED: u16		= 0x001D;
+	pub const CXL_FM_API_INVALID_EXTENT_LIST: u16		= 0x001E;
+
+	/*
+	 * Get human-readable name of FM API return code
+	 */
+	pub fn return_code_name(code: u16) -> &'static str {
+		match code {
+			CXL_FM_API_SUCCESS => "Success",
+			CXL_FM_API_BACKGROUND_COMMAND_STARTED => "Background Command Started",
+			CXL_FM_API_INVALID_INPUT => "Invalid Input",
+			CXL_FM_API_UNSUPPORTED => "Unsupported",
+			CXL_FM_API_INTERNAL_ERROR => "Internal Error",
+			CXL_FM_API_RETRY_REQUIRED => "Retry Required",
+			CXL_FM_API_BUSY => "Busy",
+			CXL_FM_API_INVALID_PAYLOAD_LENGTH => "Invalid Payload Length",
+			CXL_FM_API_RESOURCES_EXHAUSTED => "Resources Exhausted",
+			CXL_FM_API_INVALID_EXTENT_LIST => "Invalid Extent List",
+			_ => "Unknown",
+		}
+	}
+
+	/*
+	 * Component types in Identify responce
+	 */
+	pub const CXL_COMPONENT_SWITCH: u8			= 0x0;
+	pub const CXL_COMPONENT_TYPE3_DEVICE: u8		= 0x3;
+
+	/*
+	 * Get human-readable name of component type
+	 */
+	pub fn component_type_name(component_type: u8) -> &'static str {
+		match component_type {
+			CXL_COMPONENT_SWITCH => "switch",
+			CXL_COMPONENT_TYPE3_DEVICE => "type 3 device",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Link Training and Status State Machine (LTSSM) states
+	 */
+	pub const CXL_LTSSM_DETECT: u8				= 0x0;
+	pub const CXL_LTSSM_POLLING: u8				= 0x1;
+	pub const CXL_LTSSM_CONFIGURATION: u8			= 0x2;
+	pub const CXL_LTSSM_RECOVERY: u8			= 0x3;
+	pub const CXL_LTSSM_L0: u8				= 0x4;
+	pub const CXL_LTSSM_L0S: u8				= 0x5;
+	pub const CXL_LTSSM_L1: u8				= 0x6;
+	pub const CXL_LTSSM_L2: u8				= 0x7;
+	pub const CXL_LTSSM_DISABLED: u8			= 0x8;
+	pub const CXL_LTSSM_LOOPBACK: u8			= 0x9;
+	pub const CXL_LTSSM_HOT_RESET: u8			= 0xA;
+
+	/*
+	 * Get human-readable name of LTSSM state
+	 */
+	pub fn ltssm_state_name(state: u8) -> &'static str {
+		match state {
+			CXL_LTSSM_DETECT => "Detect",
+			CXL_LTSSM_POLLING => "Polling",
+			CXL_LTSSM_CONFIGURATION => "Configuration",
+			CXL_LTSSM_RECOVERY => "Recovery",
+			CXL_LTSSM_L0 => "L0",
+			CXL_LTSSM_L0S => "L0s",
+			CXL_LTSSM_L1 => "L1",
+			CXL_LTSSM_L2 => "L2",
+			CXL_LTSSM_DISABLED => "Disabled",
+			CXL_LTSSM_LOOPBACK => "Loopback",
+			CXL_LTSSM_HOT_RESET => "Hot Reset",
+			_ => "Unknown",
+		}
+	}
+
+	/*
+	 * Link speeds (PCIe generation encoding)
+	 */
+	pub const CXL_LINK_SPEED_2_5GT: u8			= 0x1;
+	pub const CXL_LINK_SPEED_5GT: u8			= 0x2;
+	pub const CXL_LINK_SPEED_8GT: u8			= 0x3;
+	pub const CXL_LINK_SPEED_16GT: u8			= 0x4;
+	pub const CXL_LINK_SPEED_32GT: u8			= 0x5;
+	pub const CXL_LINK_SPEED_64GT: u8			= 0x6;
+
+	/*
+	 * Get human-readable name of link speed
+	 */
+	pub fn link_speed_name(speed: u8) -> &'static str {
+		match speed {
+			CXL_LINK_SPEED_2_5GT => "2.5GT/s",
+			CXL_LINK_SPEED_5GT => "5GT/s",
+			CXL_LINK_SPEED_8GT => "8GT/s",
+			CXL_LINK_SPEED_16GT => "16GT/s",
+			CXL_LINK_SPEED_32GT => "32GT/s",
+			CXL_LINK_SPEED_64GT => "64GT/s",
+			_ => "Unknown",
+		}
+	}
+
+	/*
+	 * Convert supported link speeds vector into list of speeds
+	 * (bit 0 is 2.5GT/s, bit 1 is 5GT/s, and so on)
+	 */
+	pub fn link_speeds_vector(vector: u8) -> Vec<&'static str> {
+		(0..6)
+			.filter(|bit| vector & (1 << bit) != 0)
+			.map(|bit| link_speed_name(bit + 1))
+			.collect()
+	}
+
+	/*
+	 * struct CxlPayloadReader - parser of FM API payload
+	 * @data: payload
+	 * @offset: current position in payload
+	 *
+	 * All multi-byte fields are little-endian.
+	 */
+	pub struct CxlPayloadReader<'a> {
+		data: &'a [u8],
+		offset: usize,
+	}
+
+	impl<'a> CxlPayloadReader<'a> {
+		pub fn new(data: &'a [u8]) -> CxlPayloadReader<'a> {
+			CxlPayloadReader {
+				data,
+				offset: 0,
+			}
+		}
+
+		/*
+		 * Get slice of requested size and move position
+		 */
+		pub fn read_bytes(&mut self, size: usize) -> Result<&'a [u8], CxlFmError> {
+			if self.remaining() < size {
+				return Err(CxlFmError::Protocol(format!("payload is too short: offset {}, requested {}, size {}",
+									self.offset, size, self.data.len())));
+			}
+
+			let bytes = &self.data[self.offset..self.offset + size];
+			self.offset += size;
+			Ok(bytes)
+		}
+
+		/*
+		 * Skip reserved bytes
+		 */
+		pub fn skip(&mut self, size: usize) -> Result<(), CxlFmError> {
+			self.read_bytes(size).map(|_| ())
+		}
+
+		pub fn read_u8(&mut self) -> Result<u8, CxlFmError> {
+			Ok(self.read_bytes(1)?[0])
+		}
+
+		pub fn read_u16(&mut self) -> Result<u16, CxlFmError> {
+			let bytes = self.read_bytes(2)?;
+			Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+		}
+
+		pub fn read_u32(&mut self) -> Result<u32, CxlFmError> {
+			let bytes = self.read_bytes(4)?;
+			Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+		}
+
+		pub fn read_u64(&mut self) -> Result<u64, CxlFmError> {
+			let bytes = self.read_bytes(8)?;
+			Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+		}
+
+		/*
+		 * Read string prefixed by 16-bit length
+		 */
+		pub fn read_string(&mut self) -> Result<String, CxlFmError> {
+			let length = self.read_u16()? as usize;
+			let bytes = self.read_bytes(length)?;
+
+			String::from_utf8(bytes.to_vec())
+				.map_err(|_| CxlFmError::Protocol(String::from("invalid UTF-8 string")))
+		}
+
+		/*
+		 * Get number of unread bytes
+		 */
+		pub fn remaining(&self) -> usize {
+			self.data.len() - self.offset
+		}
+	}
+
+	/*
+	 * struct CxlPayloadWriter - builder of FM API payload
+	 * @data: payload
+	 */
+	#[derive(Default)]
+	pub struct CxlPayloadWriter {
+		data: Vec<u8>,
+	}
+
+	impl CxlPayloadWriter {
+		pub fn new() -> CxlPayloadWriter {
+			CxlPayloadWriter {
+				data: Vec::new(),
+			}
+		}
+
+		pub fn write_bytes(&mut self, bytes: &[u8]) {
+			self.data.extend_from_slice(bytes);
+		}
+
+		/*
+		 * Add zeroed reserved bytes
+		 */
+		pub fn reserve(&mut self, size: usize) {
+			self.data.resize(self.data.len() + size, 0);
+		}
+
+		pub fn write_u8(&mut self, value: u8) {
+			self.data.push(value);
+		}
+
+		pub fn write_u16(&mut self, value: u16) {
+			self.write_bytes(&value.to_le_bytes());
+		}
+
+		pub fn write_u32(&mut self, value: u32) {
+			self.write_bytes(&value.to_le_bytes());
+		}
+
+		pub fn write_u64(&mut self, value: u64) {
+			self.write_bytes(&value.to_le_bytes());
+		}
+
+		/*
+		 * Write string prefixed by 16-bit length
+		 */
+		pub fn write_string(&mut self, value: &str) {
+			self.write_u16(value.len() as u16);
+			self.write_bytes(value.as_bytes());
+		}
+
+		pub fn into_bytes(self) -> Vec<u8> {
+			self.data
+		}
+	}
+
+	/*
+	 * struct CxlIdentifyResponse - Identify responce
+	 * @vendor_id: PCIe vendor ID
+	 * @device_id: PCIe device ID
+	 * @subsystem_vendor_id: PCIe subsystem vendor ID
+	 * @subsystem_id: PCIe subsystem ID
+	 * @serial_number: device serial number
+	 * @max_message_size: maximal supported message size (2^n bytes)
+	 * @component_type: type of the component
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlIdentifyResponse {
+		pub vendor_id: u16,
+		pub device_id: u16,
+		pub subsystem_vendor_id: u16,
+		pub subsystem_id: u16,
+		pub serial_number: u64,
+		pub max_message_size: u8,
+		pub component_type: u8,
+	}
+
+	impl CxlIdentifyResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.vendor_id);
+			writer.write_u16(self.device_id);
+			writer.write_u16(self.subsystem_vendor_id);
+			writer.write_u16(self.subsystem_id);
+			writer.write_u64(self.serial_number);
+			writer.write_u8(self.max_message_size);
+			writer.write_u8(self.component_type);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlIdentifyResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlIdentifyResponse {
+				vendor_id: reader.read_u16()?,
+				device_id: reader.read_u16()?,
+				subsystem_vendor_id: reader.read_u16()?,
+				subsystem_id: reader.read_u16()?,
+				serial_number: reader.read_u64()?,
+				max_message_size: reader.read_u8()?,
+				component_type: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * CCI message categories
+	 */
+	pub const CXL_CCI_MESSAGE_REQUEST: u8			= 0x0;
+	pub const CXL_CCI_MESSAGE_RESPONCE: u8			= 0x1;
+
+	/*
+	 * struct CxlCciMessage - CCI message (used by tunnelling)
+	 * @category: request or responce
+	 * @tag: tag to match responce with request
+	 * @opcode: command opcode
+	 * @return_code: return code of responce
+	 * @payload: command payload
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlCciMessage {
+		pub category: u8,
+		pub tag: u8,
+		pub opcode: u16,
+		pub return_code: u16,
+		pub payload: Vec<u8>,
+	}
+
+	impl CxlCciMessage {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+			let length = self.payload.len() as u32 & 0x1FFFFF;
+
+			writer.write_u8(self.category & 0xF);
+			writer.write_u8(self.tag);
+			writer.reserve(1);
+			writer.write_u16(self.opcode);
+			writer.write_bytes(&length.to_le_bytes()[0..3]);
+			writer.write_u16(self.return_code);
+			writer.reserve(2);
+			writer.write_bytes(&self.payload);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlCciMessage, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let category = reader.read_u8()? & 0xF;
+			let tag = reader.read_u8()?;
+			reader.skip(1)?;
+			let opcode = reader.read_u16()?;
+			let length = reader.read_bytes(3)?;
+			let length = u32::from_le_bytes([length[0], length[1], length[2] & 0x1F, 0]);
+			let return_code = reader.read_u16()?;
+			reader.skip(2)?;
+			let payload = reader.read_bytes(length as usize)?.to_vec();
+
+			Ok(CxlCciMessage {
+				category,
+				tag,
+				opcode,
+				return_code,
+				payload,
+			})
+		}
+	}
+}
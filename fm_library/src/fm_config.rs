@@ -0,0 +1,808 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * Configuration of FM daemon and orchestrator processes.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_config {
+	use std::collections::HashSet;
+	use std::fs::File;
+	use std::net::IpAddr;
+	use daemonize::Daemonize;
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_config::CxlConfig;
+	use crate::cxl_config::CxlConfigTable;
+	use crate::cxl_config::CxlConfigValue;
+
+	/*
+	 * Tables of configuration file
+	 */
+	pub const CXL_FM_CONFIG_LISTEN_TABLE: &str		= "listen";
+	pub const CXL_FM_CONFIG_BACKEND_TABLE: &str		= "backend";
+	pub const CXL_FM_CONFIG_STATE_TABLE: &str		= "state";
+	pub const CXL_FM_CONFIG_LOG_TABLE: &str			= "log";
+	pub const CXL_FM_CONFIG_PRIVILEGES_TABLE: &str		= "privileges";
+	pub const CXL_FM_CONFIG_POLICIES_TABLE: &str		= "policies";
+	pub const CXL_FM_CONFIG_SECURITY_TABLE: &str		= "security";
+
+	/*
+	 * Tables that take effect while FM is running,
+	 * other tables are read on start only
+	 */
+	const CXL_FM_CONFIG_LIVE_TABLES: [&str; 2] = [
+		CXL_FM_CONFIG_POLICIES_TABLE,
+		CXL_FM_CONFIG_SECURITY_TABLE,
+	];
+
+	/*
+	 * Types of CCI backends
+	 */
+	pub const CXL_FM_BACKEND_EMULATED_MHD: &str		= "emulated_mhd";
+	pub const CXL_FM_BACKEND_EMULATED_SWITCH: &str		= "emulated_switch";
+	pub const CXL_FM_BACKEND_MAILBOX: &str			= "cxl_mailbox";
+
+	/*
+	 * Defaults of daemon processes (fm_daemon and orchestrator)
+	 */
+	pub const CXL_FM_WORKING_DIRECTORY_DEFAULT: &str	= "/tmp";
+	pub const CXL_FM_LOG_DIRECTORY_DEFAULT: &str		= "/tmp";
+	pub const CXL_FM_USER_DEFAULT: &str			= "nobody";
+	pub const CXL_FM_GROUP_DEFAULT: &str			= "bin";
+	pub const CXL_FM_UMASK_DEFAULT: u32			= 0o027;
+
+	/*
+	 * Defaults of FM daemon
+	 */
+	pub const CXL_FM_STATE_DIRECTORY_DEFAULT: &str		= "/var/lib/fm_daemon";
+	pub const CXL_FM_RECONCILE_INTERVAL_DEFAULT: u64	= 60;
+	pub const CXL_FM_EVENT_POLL_INTERVAL_DEFAULT: u64	= 5;
+
+	/*
+	 * Maximal number of LDs of MLD
+	 */
+	const CXL_FM_MAX_LDS: u16 = 16;
+
+	/*
+	 * Check that table has no keys except the known ones
+	 */
+	fn check_keys(table: &CxlConfigTable, keys: &[&str]) -> Result<(), CxlFmError> {
+		match table.entries.iter().find(|(key, _)| !keys.contains(&key.as_str())) {
+			Some((key, _)) => Err(CxlFmError::InvalidInput(format!("[{}] unknown key: {}",
+									       table.name, key))),
+			None => Ok(()),
+		}
+	}
+
+	fn string_value(value: &str) -> CxlConfigValue {
+		CxlConfigValue::String(String::from(value))
+	}
+
+	fn number_value<T: Into<i64>>(value: T) -> CxlConfigValue {
+		CxlConfigValue::Integer(value.into())
+	}
+
+	/*
+	 * struct CxlFmListenConfig - address of FM API
+	 * @ip: IP address to listen on
+	 * @port: port to listen on
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmListenConfig {
+		pub ip: Option<String>,
+		pub port: Option<u16>,
+	}
+
+	impl CxlFmListenConfig {
+		/*
+		 * Get ip:port, both of them are mandatory
+		 */
+		pub fn ip_port(&self) -> Result<String, CxlFmError> {
+			match (&self.ip, self.port) {
+				(Some(ip), Some(port)) => Ok(format!("{}:{}", ip, port)),
+				_ => Err(CxlFmError::InvalidInput(String::from("IP address and port to listen on are required"))),
+			}
+		}
+
+		fn read(&mut self, table: &CxlConfigTable) -> Result<(), CxlFmError> {
+			check_keys(table, &["ip", "port"])?;
+
+			if let Some(ip) = table.get_str("ip")? {
+				self.ip = Some(String::from(ip));
+			}
+
+			if let Some(port) = table.get_number::<u16>("port")? {
+				self.port = Some(port);
+			}
+
+			Ok(())
+		}
+
+		fn write(&self, table: &mut CxlConfigTable) {
+			table.set_some("ip", self.ip.as_deref().map(string_value));
+			table.set_some("port", self.port.map(number_value));
+		}
+	}
+
+	/*
+	 * struct CxlFmLogConfig - output of daemon process
+	 * @file: file of standard output
+	 * @error_file: file of standard error output
+	 * @debug: print debug messages
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmLogConfig {
+		pub file: String,
+		pub error_file: String,
+		pub debug: bool,
+	}
+
+	impl CxlFmLogConfig {
+		/*
+		 * Logs of the process with given name
+		 */
+		pub fn new(name: &str) -> CxlFmLogConfig {
+			CxlFmLogConfig {
+				file: format!("{}/{}.log", CXL_FM_LOG_DIRECTORY_DEFAULT, name),
+				error_file: format!("{}/{}.err", CXL_FM_LOG_DIRECTORY_DEFAULT, name),
+				debug: false,
+			}
+		}
+
+		fn read(&mut self, table: &CxlConfigTable) -> Result<(), CxlFmError> {
+			check_keys(table, &["file", "error_file", "debug"])?;
+
+			if let Some(file) = table.get_str("file")? {
+				self.file = String::from(file);
+			}
+
+			if let Some(error_file) = table.get_str("error_file")? {
+				self.error_file = String::from(error_file);
+			}
+
+			if let Some(debug) = table.get_bool("debug")? {
+				self.debug = debug;
+			}
+
+			Ok(())
+		}
+
+		fn write(&self, table: &mut CxlConfigTable) {
+			table.set("file", string_value(&self.file));
+			table.set("error_file", string_value(&self.error_file));
+			table.set("debug", CxlConfigValue::Boolean(self.debug));
+		}
+	}
+
+	/*
+	 * struct CxlFmPrivilegesConfig - privileges of daemon process
+	 * @user: user to run as ("" keeps the user)
+	 * @group: group name or ID to run as ("" keeps the group)
+	 * @umask: file mode creation mask
+	 * @working_directory: working directory of the process
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmPrivilegesConfig {
+		pub user: String,
+		pub group: String,
+		pub umask: u32,
+		pub working_directory: String,
+	}
+
+	impl Default for CxlFmPrivilegesConfig {
+		fn default() -> Self {
+			CxlFmPrivilegesConfig {
+				user: String::from(CXL_FM_USER_DEFAULT),
+				group: String::from(CXL_FM_GROUP_DEFAULT),
+				umask: CXL_FM_UMASK_DEFAULT,
+				working_directory: String::from(CXL_FM_WORKING_DIRECTORY_DEFAULT),
+			}
+		}
+	}
+
+	impl CxlFmPrivilegesConfig {
+		/*
+		 * Get group ID if the group is given by number
+		 */
+		pub fn group_id(&self) -> Option<u32> {
+			self.group.parse::<u32>().ok()
+		}
+
+		fn read(&mut self, table: &CxlConfigTable) -> Result<(), CxlFmError> {
+			check_keys(table, &["user", "group", "umask", "working_directory"])?;
+
+			if let Some(user) = table.get_str("user")? {
+				self.user = String::from(user);
+			}
+
+			/*
+			 * Group is name or ID
+			 */
+			match table.get("group") {
+				Some(CxlConfigValue::Integer(_)) => {
+					self.group = table.get_number::<u32>("group")?.unwrap_or_default().to_string();
+				},
+				Some(_) => {
+					self.group = String::from(table.get_str("group")?.unwrap_or_default());
+				},
+				None => {},
+			}
+
+			/*
+			 * Umask is octal string ("027")
+			 */
+			if let Some(umask) = table.get_str("umask")? {
+				self.umask = u32::from_str_radix(umask, 8)
+					.ok()
+					.filter(|umask| *umask <= 0o777)
+					.ok_or(CxlFmError::InvalidInput(format!("[{}] umask: octal mode is expected",
+										table.name)))?;
+			}
+
+			if let Some(directory) = table.get_str("working_directory")? {
+				self.working_directory = String::from(directory);
+			}
+
+			Ok(())
+		}
+
+		fn write(&self, table: &mut CxlConfigTable) {
+			table.set("user", string_value(&self.user));
+			table.set("group", string_value(&self.group));
+			table.set("umask", CxlConfigValue::String(format!("{:03o}", self.umask)));
+			table.set("working_directory", string_value(&self.working_directory));
+		}
+	}
+
+	/*
+	 * struct CxlFmProcessConfig - configuration of daemon process
+	 * @listen: address of API
+	 * @log: output of the process
+	 * @privileges: privileges the process drops to
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmProcessConfig {
+		pub listen: CxlFmListenConfig,
+		pub log: CxlFmLogConfig,
+		pub privileges: CxlFmPrivilegesConfig,
+	}
+
+	impl CxlFmProcessConfig {
+		/*
+		 * Default configuration of the process with given name
+		 */
+		pub fn new(name: &str) -> CxlFmProcessConfig {
+			CxlFmProcessConfig {
+				listen: CxlFmListenConfig::default(),
+				log: CxlFmLogConfig::new(name),
+				privileges: CxlFmPrivilegesConfig::default(),
+			}
+		}
+
+		/*
+		 * Read table of the process configuration.
+		 * Returns false if the table is not one of them.
+		 */
+		fn read(&mut self, table: &CxlConfigTable) -> Result<bool, CxlFmError> {
+			match table.name.as_str() {
+				CXL_FM_CONFIG_LISTEN_TABLE => self.listen.read(table)?,
+				CXL_FM_CONFIG_LOG_TABLE => self.log.read(table)?,
+				CXL_FM_CONFIG_PRIVILEGES_TABLE => self.privileges.read(table)?,
+				_ => return Ok(false),
+			}
+
+			Ok(true)
+		}
+
+		fn write(&self, config: &mut CxlConfig) {
+			self.listen.write(config.table_mut(CXL_FM_CONFIG_LISTEN_TABLE));
+			self.log.write(config.table_mut(CXL_FM_CONFIG_LOG_TABLE));
+			self.privileges.write(config.table_mut(CXL_FM_CONFIG_PRIVILEGES_TABLE));
+		}
+
+		/*
+		 * Read configuration of process that has no other tables
+		 */
+		pub fn from_config(config: &CxlConfig, name: &str) -> Result<CxlFmProcessConfig, CxlFmError> {
+			let mut process = CxlFmProcessConfig::new(name);
+
+			check_keys(&config.root, &[])?;
+
+			for table in &config.tables {
+				if !process.read(table)? {
+					return Err(CxlFmError::InvalidInput(format!("unknown table: [{}]",
+										    table.name)));
+				}
+			}
+
+			Ok(process)
+		}
+
+		pub fn parse(content: &str, name: &str) -> Result<CxlFmProcessConfig, CxlFmError> {
+			Self::from_config(&CxlConfig::parse(content)?, name)
+		}
+
+		/*
+		 * Detach the process: redirect output into the logs,
+		 * change working directory and drop privileges
+		 */
+		pub fn daemonize(&self) -> Result<(), CxlFmError> {
+			let create = |path: &str| File::create(path)
+				.map_err(|e| CxlFmError::Io(format!("{}: {}", path, e)));
+			let privileges = &self.privileges;
+			let mut daemonize = Daemonize::new()
+				.working_directory(&privileges.working_directory)
+				.umask(privileges.umask)
+				.stdout(create(&self.log.file)?)
+				.stderr(create(&self.log.error_file)?);
+
+			if !privileges.user.is_empty() {
+				daemonize = daemonize.user(privileges.user.as_str());
+			}
+
+			daemonize = match privileges.group_id() {
+				Some(id) => daemonize.group(id),
+				None if !privileges.group.is_empty() => daemonize.group(privileges.group.as_str()),
+				None => daemonize,
+			};
+
+			daemonize.start().map_err(|e| CxlFmError::Io(e.to_string()))
+		}
+	}
+
+	/*
+	 * struct CxlFmBackendConfig - CCI backend of managed component
+	 * @backend_type: type of the backend
+	 * @name: name of the component
+	 * @path: device node of the CCI (backends of real devices)
+	 * @serial_number: serial number of emulated component
+	 *                 (assigned by the daemon if absent)
+	 * @locality: locality domain of the component
+	 * @heads: number of heads of emulated device
+	 * @lds_per_head: number of LDs per head of emulated device
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmBackendConfig {
+		pub backend_type: String,
+		pub name: String,
+		pub path: Option<String>,
+		pub serial_number: Option<u64>,
+		pub locality: Option<u16>,
+		pub heads: Option<u8>,
+		pub lds_per_head: Option<u8>,
+	}
+
+	impl CxlFmBackendConfig {
+		fn read(table: &CxlConfigTable) -> Result<CxlFmBackendConfig, CxlFmError> {
+			check_keys(table, &["type", "name", "path", "serial_number",
+					    "locality", "heads", "lds_per_head"])?;
+
+			let backend = CxlFmBackendConfig {
+				backend_type: String::from(table.require("type", table.get_str("type"))?),
+				name: String::from(table.require("name", table.get_str("name"))?),
+				path: table.get_str("path")?.map(String::from),
+				serial_number: table.get_number::<u64>("serial_number")?,
+				locality: table.get_number::<u16>("locality")?,
+				heads: table.get_number::<u8>("heads")?,
+				lds_per_head: table.get_number::<u8>("lds_per_head")?,
+			};
+
+			/*
+			 * Emulated components have no device node, CCI of
+			 * real component is defined by its device node only
+			 */
+			match backend.backend_type.as_str() {
+				CXL_FM_BACKEND_EMULATED_MHD | CXL_FM_BACKEND_EMULATED_SWITCH => {
+					if backend.path.is_some() {
+						return Err(CxlFmError::InvalidInput(format!("{}: emulated backend has no device path",
+											    backend.name)));
+					}
+				},
+				CXL_FM_BACKEND_MAILBOX => {
+					if backend.path.is_none() {
+						return Err(CxlFmError::InvalidInput(format!("{}: device path is required",
+											    backend.name)));
+					}
+
+					if backend.serial_number.is_some() || backend.heads.is_some() ||
+					   backend.lds_per_head.is_some() {
+						return Err(CxlFmError::InvalidInput(format!("{}: serial number, heads and LDs are reported by device",
+											    backend.name)));
+					}
+				},
+				other => {
+					return Err(CxlFmError::InvalidInput(format!("{}: unsupported backend type {}",
+										    backend.name, other)));
+				}
+			}
+
+			if backend.backend_type == CXL_FM_BACKEND_EMULATED_SWITCH &&
+			   (backend.heads.is_some() || backend.lds_per_head.is_some() ||
+			    backend.locality.is_some()) {
+				return Err(CxlFmError::InvalidInput(format!("{}: switch has no heads and locality",
+									    backend.name)));
+			}
+
+			let lds = backend.heads.unwrap_or(1) as u16 * backend.lds_per_head.unwrap_or(1) as u16;
+
+			if lds == 0 || lds > CXL_FM_MAX_LDS {
+				return Err(CxlFmError::InvalidInput(format!("{}: 1..{} LDs are expected",
+									    backend.name, CXL_FM_MAX_LDS)));
+			}
+
+			Ok(backend)
+		}
+
+		fn write(&self, table: &mut CxlConfigTable) {
+			table.is_array = true;
+			table.set("type", string_value(&self.backend_type));
+			table.set("name", string_value(&self.name));
+			table.set_some("path", self.path.as_deref().map(string_value));
+			table.set_some("serial_number", self.serial_number.map(|serial| number_value(serial as i64)));
+			table.set_some("locality", self.locality.map(number_value));
+			table.set_some("heads", self.heads.map(number_value));
+			table.set_some("lds_per_head", self.lds_per_head.map(number_value));
+		}
+	}
+
+	/*
+	 * Backends of the default emulated fabric: two MHDs
+	 * in their own locality domains and one switch
+	 */
+	fn default_backends() -> Vec<CxlFmBackendConfig> {
+		let mut backends: Vec<CxlFmBackendConfig> = (0..2)
+			.map(|index| CxlFmBackendConfig {
+				backend_type: String::from(CXL_FM_BACKEND_EMULATED_MHD),
+				name: format!("mhd{}", index),
+				locality: Some(index),
+				..Default::default()
+			})
+			.collect();
+
+		backends.push(CxlFmBackendConfig {
+			backend_type: String::from(CXL_FM_BACKEND_EMULATED_SWITCH),
+			name: String::from("sw0"),
+			..Default::default()
+		});
+
+		backends
+	}
+
+	/*
+	 * struct CxlFmStateConfig - durable state of FM
+	 * @directory: directory of the state store
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmStateConfig {
+		pub directory: String,
+	}
+
+	impl CxlFmStateConfig {
+		fn read(&mut self, table: &CxlConfigTable) -> Result<(), CxlFmError> {
+			check_keys(table, &["directory"])?;
+
+			if let Some(directory) = table.get_str("directory")? {
+				self.directory = String::from(directory);
+			}
+
+			Ok(())
+		}
+
+		fn write(&self, table: &mut CxlConfigTable) {
+			table.set("directory", string_value(&self.directory));
+		}
+	}
+
+	/*
+	 * struct CxlFmPoliciesConfig - policies of fabric management
+	 * @reconcile_interval: seconds between reconciliation passes
+	 *                      (0 disables them)
+	 * @remediate: re-apply drifted resources that are not
+	 *             under manual control
+	 * @event_poll_interval: seconds between event log polls
+	 *                       (0 disables them)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmPoliciesConfig {
+		pub reconcile_interval: u64,
+		pub remediate: bool,
+		pub event_poll_interval: u64,
+	}
+
+	impl CxlFmPoliciesConfig {
+		fn read(&mut self, table: &CxlConfigTable) -> Result<(), CxlFmError> {
+			check_keys(table, &["reconcile_interval", "remediate", "event_poll_interval"])?;
+
+			if let Some(interval) = table.get_number::<u64>("reconcile_interval")? {
+				self.reconcile_interval = interval;
+			}
+
+			if let Some(remediate) = table.get_bool("remediate")? {
+				self.remediate = remediate;
+			}
+
+			if let Some(interval) = table.get_number::<u64>("event_poll_interval")? {
+				self.event_poll_interval = interval;
+			}
+
+			Ok(())
+		}
+
+		fn write(&self, table: &mut CxlConfigTable) {
+			table.set("reconcile_interval", number_value(self.reconcile_interval as i64));
+			table.set("remediate", CxlConfigValue::Boolean(self.remediate));
+			table.set("event_poll_interval", number_value(self.event_poll_interval as i64));
+		}
+	}
+
+	/*
+	 * struct CxlFmSecurityConfig - access to FM API
+	 * @allowed_clients: IP addresses of clients that may connect
+	 *                   (any client if empty)
+	 * @allow_set_config: may clients change the configuration
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmSecurityConfig {
+		pub allowed_clients: Vec<IpAddr>,
+		pub allow_set_config: bool,
+	}
+
+	impl CxlFmSecurityConfig {
+		/*
+		 * May the client connect to FM API?
+		 */
+		pub fn is_allowed(&self, client: &IpAddr) -> bool {
+			self.allowed_clients.is_empty() || self.allowed_clients.contains(client)
+		}
+
+		fn read(&mut self, table: &CxlConfigTable) -> Result<(), CxlFmError> {
+			check_keys(table, &["allowed_clients", "allow_set_config"])?;
+
+			if let Some(clients) = table.get_strings("allowed_clients")? {
+				self.allowed_clients = clients.iter()
+					.map(|client| client.parse::<IpAddr>()
+						.map_err(|_| CxlFmError::InvalidInput(format!("[{}] allowed_clients: {} is not IP address",
+											      table.name, client))))
+					.collect::<Result<Vec<IpAddr>, CxlFmError>>()?;
+			}
+
+			if let Some(allow) = table.get_bool("allow_set_config")? {
+				self.allow_set_config = allow;
+			}
+
+			Ok(())
+		}
+
+		fn write(&self, table: &mut CxlConfigTable) {
+			table.set("allowed_clients", CxlConfigValue::Array(self.allowed_clients.iter()
+				.map(|client| CxlConfigValue::String(client.to_string()))
+				.collect()));
+			table.set("allow_set_config", CxlConfigValue::Boolean(self.allow_set_config));
+		}
+	}
+
+	/*
+	 * struct CxlFmDaemonConfig - configuration of FM daemon
+	 * @process: API address, logs and privileges
+	 * @backends: CCI backends of managed components
+	 * @state: durable state of FM
+	 * @policies: policies of fabric management
+	 * @security: access to FM API
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmDaemonConfig {
+		pub process: CxlFmProcessConfig,
+		pub backends: Vec<CxlFmBackendConfig>,
+		pub state: CxlFmStateConfig,
+		pub policies: CxlFmPoliciesConfig,
+		pub security: CxlFmSecurityConfig,
+	}
+
+	impl CxlFmDaemonConfig {
+		/*
+		 * Default configuration of daemon with given name
+		 */
+		pub fn new(name: &str) -> CxlFmDaemonConfig {
+			CxlFmDaemonConfig {
+				process: CxlFmProcessConfig::new(name),
+				backends: default_backends(),
+				state: CxlFmStateConfig {
+					directory: String::from(CXL_FM_STATE_DIRECTORY_DEFAULT),
+				},
+				policies: CxlFmPoliciesConfig {
+					reconcile_interval: CXL_FM_RECONCILE_INTERVAL_DEFAULT,
+					remediate: false,
+					event_poll_interval: CXL_FM_EVENT_POLL_INTERVAL_DEFAULT,
+				},
+				security: CxlFmSecurityConfig {
+					allowed_clients: Vec::new(),
+					allow_set_config: true,
+				},
+			}
+		}
+
+		/*
+		 * Apply configuration file to the configuration.
+		 * Backends of the file replace the default ones.
+		 */
+		pub fn read(&mut self, config: &CxlConfig) -> Result<(), CxlFmError> {
+			let backends = config.array(CXL_FM_CONFIG_BACKEND_TABLE);
+
+			check_keys(&config.root, &[])?;
+
+			if !backends.is_empty() {
+				self.backends = backends.into_iter()
+					.map(CxlFmBackendConfig::read)
+					.collect::<Result<Vec<CxlFmBackendConfig>, CxlFmError>>()?;
+			}
+
+			let mut names = HashSet::new();
+
+			if let Some(backend) = self.backends.iter().find(|backend| !names.insert(&backend.name)) {
+				return Err(CxlFmError::InvalidInput(format!("duplicated backend: {}",
+									    backend.name)));
+			}
+
+			for table in &config.tables {
+				if self.process.read(table)? {
+					continue;
+				}
+
+				match table.name.as_str() {
+					CXL_FM_CONFIG_BACKEND_TABLE => {},
+					CXL_FM_CONFIG_STATE_TABLE => self.state.read(table)?,
+					CXL_FM_CONFIG_POLICIES_TABLE => self.policies.read(table)?,
+					CXL_FM_CONFIG_SECURITY_TABLE => self.security.read(table)?,
+					name => return Err(CxlFmError::InvalidInput(format!("unknown table: [{}]",
+											    name))),
+				}
+			}
+
+			Ok(())
+		}
+
+		/*
+		 * Write configuration as configuration file
+		 */
+		pub fn to_config(&self) -> CxlConfig {
+			let mut config = CxlConfig::default();
+
+			self.process.write(&mut config);
+
+			for backend in &self.backends {
+				let mut table = CxlConfigTable::new(CXL_FM_CONFIG_BACKEND_TABLE);
+
+				backend.write(&mut table);
+				config.tables.push(table);
+			}
+
+			self.state.write(config.table_mut(CXL_FM_CONFIG_STATE_TABLE));
+			self.policies.write(config.table_mut(CXL_FM_CONFIG_POLICIES_TABLE));
+			self.security.write(config.table_mut(CXL_FM_CONFIG_SECURITY_TABLE));
+			config
+		}
+
+		/*
+		 * Get configuration with changes of running FM.
+		 * Only live tables may be changed.
+		 */
+		pub fn update(&self, changes: &CxlConfig) -> Result<CxlFmDaemonConfig, CxlFmError> {
+			let mut updated = self.clone();
+
+			check_keys(&changes.root, &[])?;
+
+			if let Some(table) = changes.tables.iter()
+				.find(|table| !CXL_FM_CONFIG_LIVE_TABLES.contains(&table.name.as_str())) {
+				return Err(CxlFmError::InvalidInput(format!("[{}] cannot be changed while FM is running",
+									    table.name)));
+			}
+
+			updated.read(changes)?;
+			Ok(updated)
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		fn daemon_config(content: &str) -> Result<CxlFmDaemonConfig, CxlFmError> {
+			let mut config = CxlFmDaemonConfig::new("fm_daemon");
+
+			config.read(&CxlConfig::parse(content)?)?;
+			Ok(config)
+		}
+
+		#[test]
+		fn backends_replace_default_ones() {
+			let config = daemon_config("[[backend]]\ntype = \"emulated_mhd\"\nname = \"mhd0\"\n\
+						    heads = 2\nlds_per_head = 4\n\n\
+						    [[backend]]\ntype = \"cxl_mailbox\"\nname = \"sw0\"\n\
+						    path = \"/dev/cxl/switch0\"\nlocality = 1\n").unwrap();
+
+			assert_eq!(config.backends.len(), 2);
+			assert_eq!(config.backends[0].heads, Some(2));
+			assert_eq!(config.backends[1].backend_type, CXL_FM_BACKEND_MAILBOX);
+			assert_eq!(config.backends[1].path.as_deref(), Some("/dev/cxl/switch0"));
+			assert_eq!(config.backends[1].locality, Some(1));
+			assert_eq!(daemon_config("").unwrap().backends, default_backends());
+		}
+
+		#[test]
+		fn invalid_backends_are_rejected() {
+			for backend in ["type = \"emulated_mhd\"\nname = \"mhd0\"\npath = \"/dev/cxl/mem0\"",
+					"type = \"cxl_mailbox\"\nname = \"sw0\"",
+					"type = \"cxl_mailbox\"\nname = \"mem0\"\npath = \"/dev/cxl/mem0\"\nheads = 2",
+					"type = \"emulated_switch\"\nname = \"sw0\"\nlocality = 1",
+					"type = \"emulated_mhd\"\nname = \"mhd0\"\nheads = 4\nlds_per_head = 8",
+					"type = \"pcie\"\nname = \"sw0\"",
+					"name = \"sw0\""] {
+				assert!(daemon_config(&format!("[[backend]]\n{}\n", backend)).is_err(), "{}", backend);
+			}
+
+			assert!(daemon_config("[[backend]]\ntype = \"emulated_switch\"\nname = \"sw0\"\n\
+					       [[backend]]\ntype = \"emulated_switch\"\nname = \"sw0\"\n").is_err());
+		}
+
+		#[test]
+		fn unknown_tables_and_keys_are_rejected() {
+			assert!(daemon_config("[listen]\naddress = \"127.0.0.1\"\n").is_err());
+			assert!(daemon_config("[logs]\ndebug = true\n").is_err());
+			assert!(daemon_config("debug = true\n").is_err());
+			assert!(daemon_config("[privileges]\numask = \"999\"\n").is_err());
+			assert!(daemon_config("[security]\nallowed_clients = [\"localhost\"]\n").is_err());
+		}
+
+		#[test]
+		fn only_live_tables_are_updated() {
+			let config = CxlFmDaemonConfig::new("fm_daemon");
+			let changes = CxlConfig::parse("[policies]\nremediate = true\n").unwrap();
+			let updated = config.update(&changes).unwrap();
+
+			assert!(updated.policies.remediate);
+			assert_eq!(updated.policies.reconcile_interval, config.policies.reconcile_interval);
+
+			let changes = CxlConfig::parse("[listen]\nport = 7500\n").unwrap();
+
+			assert!(config.update(&changes).is_err());
+		}
+
+		#[test]
+		fn written_configuration_is_read_back() {
+			let mut config = daemon_config("[[backend]]\ntype = \"cxl_mailbox\"\nname = \"sw0\"\n\
+							path = \"/dev/cxl/switch0\"\n\n\
+							[privileges]\ngroup = 10\numask = \"022\"\n\n\
+							[security]\nallowed_clients = [\"127.0.0.1\"]\n").unwrap();
+
+			config.process.listen.port = Some(7500);
+
+			let content = config.to_config().to_toml();
+
+			assert_eq!(daemon_config(&content).unwrap(), config);
+		}
+
+		#[test]
+		fn process_configuration_has_no_daemon_tables() {
+			let process = CxlFmProcessConfig::parse("[listen]\nip = \"127.0.0.1\"\nport = 7100\n",
+							       "orchestrator").unwrap();
+
+			assert_eq!(process.listen.ip_port().unwrap(), "127.0.0.1:7100");
+			assert_eq!(process.log.file, "/tmp/orchestrator.log");
+			assert!(CxlFmProcessConfig::parse("[policies]\nremediate = true\n", "orchestrator").is_err());
+		}
+	}
+}
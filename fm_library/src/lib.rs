@@ -19,7 +19,36 @@
  * limitations under the License.
  */
 
+mod fm_api;
+mod multi_headed_device;
+mod dynamic_capacity_device;
+mod capacity_pool;
+mod topology;
+mod switch;
+mod logical_device;
+mod config;
+mod desired_state;
+mod event;
+mod orchestrator;
+mod announce;
+mod fm_config;
+
+pub use crate::fm_api::cxl_fm_api;
+pub use crate::multi_headed_device::cxl_mh_device_lib;
+pub use crate::dynamic_capacity_device::cxl_dcd_lib;
+pub use crate::capacity_pool::cxl_capacity_pool_lib;
+pub use crate::topology::cxl_topology;
+pub use crate::switch::cxl_switch_lib;
+pub use crate::logical_device::cxl_ld_lib;
+pub use crate::config::cxl_config;
+pub use crate::desired_state::cxl_desired_state;
+pub use crate::event::cxl_fm_event;
+pub use crate::orchestrator::cxl_fm_orchestrator;
+pub use crate::announce::cxl_fm_announce;
+pub use crate::fm_config::cxl_fm_config;
+
 pub mod cxl_fm_lib {
+	use std::fmt;
 	use std::net::{TcpStream};
 	use std::io::Write;
 	use std::io::BufReader;
@@ -32,20 +61,28 @@ pub mod cxl_fm_lib {
 	pub const CXL_FM_DISCOVER_CXL_DEVICE_COMMAND: &str	= "DISCOVER_CXL_DEVICE";
 	pub const CXL_FM_DISCOVER_CXL_SWITCH_COMMAND: &str	= "DISCOVER_CXL_SWITCH";
 	pub const CXL_FM_DISCOVER_LD_COMMAND: &str		= "DISCOVER_LOGICAL_DEVICE";
+	pub const CXL_FM_DISCOVER_TOPOLOGY_COMMAND: &str	= "DISCOVER_TOPOLOGY";
 
 	pub const CXL_FM_GET_FM_INFO_COMMAND: &str		= "GET_FM_INFO";
 	pub const CXL_FM_START_FM_COMMAND: &str			= "START_FM";
 	pub const CXL_FM_RESTART_FM_COMMAND: &str		= "RESTART_FM";
 	pub const CXL_FM_STOP_FM_COMMAND: &str			= "STOP_FM";
+	pub const CXL_FM_GET_FM_STATUS_COMMAND: &str		= "GET_FM_STATUS";
+	pub const CXL_FM_REGISTER_FM_COMMAND: &str		= "REGISTER_FM";
+	pub const CXL_FM_HEARTBEAT_FM_COMMAND: &str		= "HEARTBEAT_FM";
+	pub const CXL_FM_GET_FM_STATE_COMMAND: &str		= "GET_FM_STATE";
+	pub const CXL_FM_ANNOUNCE_FM_COMMAND: &str		= "ANNOUNCE_FM";
 	pub const CXL_FM_GET_FM_CONFIG_COMMAND: &str		= "GET_FM_CONFIG";
 	pub const CXL_FM_SET_FM_CONFIG_COMMAND: &str		= "SET_FM_CONFIG";
 	pub const CXL_FM_GET_FM_EVENTS_COMMAND: &str		= "GET_FM_EVENTS";
+	pub const CXL_FM_SUBSCRIBE_EVENTS_COMMAND: &str		= "SUBSCRIBE_EVENTS";
 
 	pub const CXL_FM_GET_SWITCH_INFO_COMMAND: &str		= "GET_SWITCH_INFO";
 	pub const CXL_FM_GET_SWITCH_CONFIG_COMMAND: &str	= "GET_SWITCH_CONFIG";
 	pub const CXL_FM_SET_SWITCH_CONFIG_COMMAND: &str	= "SET_SWITCH_CONFIG";
 
 	pub const CXL_FM_GET_MHD_INFO_COMMAND: &str		= "GET_MHD_INFO";
+	pub const CXL_FM_GET_MHD_HEAD_INFO_COMMAND: &str	= "GET_MHD_HEAD_INFO";
 
 	pub const CXL_FM_BIND_LD_COMMAND: &str			= "BIND_LOGICAL_DEVICE";
 	pub const CXL_FM_UNBIND_LD_COMMAND: &str		= "UNBIND_LOGICAL_DEVICE";
@@ -65,11 +102,15 @@ pub mod cxl_fm_lib {
 	pub const CXL_FM_GET_PPB_CONFIG_COMMAND: &str		= "GET_PPB_CONFIG";
 	pub const CXL_FM_PPB_BIND_COMMAND: &str			= "PPB_BIND";
 	pub const CXL_FM_PPB_UNBIND_COMMAND: &str		= "PPB_UNBIND";
+	pub const CXL_FM_PPB_GENERATE_AER_COMMAND: &str		= "PPB_GENERATE_AER";
 
 	pub const CXL_FM_GET_PHYSICAL_PORT_INFO_COMMAND: &str	= "GET_PHYSICAL_PORT_INFO";
 	pub const CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND: &str	= "PHYSICAL_PORT_CONTROL";
 	pub const CXL_FM_BIND_PHYSICAL_PORT_COMMAND: &str	= "BIND_PHYSICAL_PORT";
 	pub const CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND: &str	= "UNBIND_PHYSICAL_PORT";
+	pub const CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND: &str	= "REMOVE_PHYSICAL_PORT";
+	#[cfg(feature = "emulation")]
+	pub const CXL_FM_EMULATE_HOT_PLUG_COMMAND: &str		= "EMULATE_HOT_PLUG";
 
 	pub const CXL_FM_MLD_PORT_TUNNEL_COMMAND: &str		= "MLD_PORT_TUNNEL";
 	pub const CXL_FM_MLD_PORT_SEND_CONFIG_COMMAND: &str	= "MLD_PORT_SEND_CONFIG";
@@ -82,27 +123,369 @@ pub mod cxl_fm_lib {
 	pub const CXL_FM_DCD_ADD_CAPACITY_COMMAND: &str		= "DCD_ADD_CAPACITY";
 	pub const CXL_FM_DCD_RELEASE_CAPACITY_COMMAND: &str	= "DCD_RELEASE_CAPACITY";
 
+	pub const CXL_FM_POOL_GET_INFO_COMMAND: &str		= "POOL_GET_INFO";
+	pub const CXL_FM_POOL_ALLOCATE_COMMAND: &str		= "POOL_ALLOCATE";
+
+	pub const CXL_FM_APPLY_COMMAND: &str			= "APPLY";
+	pub const CXL_FM_RECONCILE_COMMAND: &str		= "RECONCILE";
+
+	/*
+	 * Commands that change the fabric as plan. They are journaled
+	 * by FM and only they may be validated without execution.
+	 */
+	pub const CXL_FM_PLANNED_COMMANDS: [&str; 15] = [
+		CXL_FM_BIND_LD_COMMAND,
+		CXL_FM_UNBIND_LD_COMMAND,
+		CXL_FM_SET_LD_ALLOCATION_COMMAND,
+		CXL_FM_LD_ERASE,
+		CXL_FM_PPB_BIND_COMMAND,
+		CXL_FM_PPB_UNBIND_COMMAND,
+		CXL_FM_PHYSICAL_PORT_CONTROL_COMMAND,
+		CXL_FM_BIND_PHYSICAL_PORT_COMMAND,
+		CXL_FM_UNBIND_PHYSICAL_PORT_COMMAND,
+		CXL_FM_REMOVE_PHYSICAL_PORT_COMMAND,
+		CXL_FM_DCD_ADD_CAPACITY_COMMAND,
+		CXL_FM_DCD_RELEASE_CAPACITY_COMMAND,
+		CXL_FM_POOL_ALLOCATE_COMMAND,
+		CXL_FM_APPLY_COMMAND,
+		CXL_FM_RECONCILE_COMMAND,
+	];
+
 	/*
 	 * Service responces
 	 */
 	pub const CXL_FM_UNKNOWN_COMMAND: &str			= "UNKNOWN_COMMAND";
 	pub const CXL_FM_NO_DATA: &str				= "NO_DATA";
+	pub const CXL_FM_OK: &str				= "OK";
+	pub const CXL_FM_ERROR: &str				= "ERROR";
+
+	/*
+	 * Request arguments
+	 */
+	pub const CXL_FM_DEVICE_ARG: &str			= "device";
+	pub const CXL_FM_PAYLOAD_ARG: &str			= "payload";
+	pub const CXL_FM_START_ARG: &str			= "start";
+	pub const CXL_FM_COUNT_ARG: &str			= "count";
+	pub const CXL_FM_HOST_ARG: &str				= "host";
+	pub const CXL_FM_REGION_ARG: &str			= "region";
+	pub const CXL_FM_BLOCK_SIZE_ARG: &str			= "block_size";
+	pub const CXL_FM_SANITIZE_ARG: &str			= "sanitize";
+	pub const CXL_FM_POLICY_ARG: &str			= "policy";
+	pub const CXL_FM_LENGTH_ARG: &str			= "length";
+	pub const CXL_FM_TAG_ARG: &str				= "tag";
+	pub const CXL_FM_EXTENTS_ARG: &str			= "extents";
+	pub const CXL_FM_FORCED_ARG: &str			= "forced";
+	pub const CXL_FM_LOCALITY_ARG: &str			= "locality";
+	pub const CXL_FM_RESCAN_ARG: &str			= "rescan";
+	pub const CXL_FM_DRY_RUN_ARG: &str			= "dry_run";
+	pub const CXL_FM_SWITCH_ARG: &str			= "switch";
+	pub const CXL_FM_VCS_ARG: &str				= "vcs";
+	pub const CXL_FM_VPPB_ARG: &str				= "vppb";
+	pub const CXL_FM_PORT_ARG: &str				= "port";
+	pub const CXL_FM_LD_ARG: &str				= "ld";
+	pub const CXL_FM_MODE_ARG: &str				= "mode";
+	pub const CXL_FM_OPERATION_ARG: &str			= "operation";
+	pub const CXL_FM_RANGE1_ARG: &str			= "range1";
+	pub const CXL_FM_RANGE2_ARG: &str			= "range2";
+	pub const CXL_FM_REMEDIATE_ARG: &str			= "remediate";
+	pub const CXL_FM_SEVERITY_ARG: &str			= "severity";
+	pub const CXL_FM_COMPONENT_ARG: &str			= "component";
+	pub const CXL_FM_EVENT_TYPE_ARG: &str			= "type";
+	pub const CXL_FM_SINCE_ARG: &str			= "since";
+	pub const CXL_FM_UNTIL_ARG: &str			= "until";
+	pub const CXL_FM_FROM_ARG: &str				= "from";
+	pub const CXL_FM_ERROR_TYPE_ARG: &str			= "error_type";
+	pub const CXL_FM_HEADER_ARG: &str			= "header";
+	pub const CXL_FM_NAME_ARG: &str				= "name";
+	pub const CXL_FM_ADDRESS_ARG: &str			= "address";
+	pub const CXL_FM_STATE_DIR_ARG: &str			= "state_dir";
+	pub const CXL_FM_RECONCILE_INTERVAL_ARG: &str		= "reconcile_interval";
+	pub const CXL_FM_EVENT_POLL_INTERVAL_ARG: &str		= "event_poll_interval";
+	pub const CXL_FM_TIMEOUT_ARG: &str			= "timeout";
+	pub const CXL_FM_ID_ARG: &str				= "id";
+	pub const CXL_FM_VERSION_ARG: &str			= "version";
+	pub const CXL_FM_SWITCHES_ARG: &str			= "switches";
+	pub const CXL_FM_UPTIME_ARG: &str			= "uptime";
+	pub const CXL_FM_INTERVAL_ARG: &str			= "interval";
+	pub const CXL_FM_DEVICES_ARG: &str			= "devices";
+	pub const CXL_FM_CCIS_ARG: &str				= "ccis";
+	pub const CXL_FM_FM_ARG: &str				= "fm";
+	pub const CXL_FM_ROLE_ARG: &str				= "role";
+	pub const CXL_FM_CONFIG_ARG: &str			= "config";
 
 	/*
 	 * struct CxlFmOptions - configuration options
 	 * @ip_port: IP address + port
 	 * @is_debug: does it need to show debug output?
+	 * @is_dry_run: validate mutating commands without execution
+	 * @fm: FM that serves requests sent through orchestrator
 	 */
+	#[derive(Debug, Clone)]
 	pub struct CxlFmOptions {
 		pub ip_port: String,
 		pub is_debug: bool,
+		pub is_dry_run: bool,
+		pub fm: Option<String>,
+	}
+
+	/*
+	 * enum CxlFmError - errors of FM infrastructure
+	 * @Io: connection or stream failure
+	 * @Protocol: malformed request or responce
+	 * @InvalidInput: wrong argument of the command
+	 * @NotFound: requested object is absent
+	 * @Device: CXL component returned failure (FM API return code)
+	 * @Remote: FM returned error message
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub enum CxlFmError {
+		Io(String),
+		Protocol(String),
+		InvalidInput(String),
+		NotFound(String),
+		Device(u16),
+		Remote(String),
+	}
+
+	impl fmt::Display for CxlFmError {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			match self {
+				CxlFmError::Io(msg) => write!(f, "I/O error: {}", msg),
+				CxlFmError::Protocol(msg) => write!(f, "protocol error: {}", msg),
+				CxlFmError::InvalidInput(msg) => write!(f, "invalid input: {}", msg),
+				CxlFmError::NotFound(msg) => write!(f, "not found: {}", msg),
+				CxlFmError::Device(code) => {
+					write!(f, "device returned {:#06x} ({})",
+					       code,
+					       crate::cxl_fm_api::return_code_name(*code))
+				},
+				CxlFmError::Remote(msg) => write!(f, "{}", msg),
+			}
+		}
+	}
+
+	/*
+	 * struct CxlFmRequest - request to FM
+	 * @command: command name
+	 * @args: list of key=value arguments
+	 *
+	 * Request is transferred as one line:
+	 * COMMAND [key=value] [key=value] ...
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmRequest {
+		pub command: String,
+		pub args: Vec<(String, String)>,
+	}
+
+	impl CxlFmRequest {
+		pub fn new(command: &str) -> CxlFmRequest {
+			CxlFmRequest {
+				command: String::from(command),
+				args: Vec::new(),
+			}
+		}
+
+		/*
+		 * Add argument into request
+		 */
+		pub fn arg(mut self, key: &str, value: &str) -> CxlFmRequest {
+			self.args.push((String::from(key), String::from(value)));
+			self
+		}
+
+		/*
+		 * Request validation of the command without execution
+		 */
+		pub fn dry_run(self, is_dry_run: bool) -> CxlFmRequest {
+			if is_dry_run {
+				self.arg(CXL_FM_DRY_RUN_ARG, "1")
+			} else {
+				self
+			}
+		}
+
+		/*
+		 * Define FM that serves the request sent through orchestrator
+		 */
+		pub fn fm(self, fm: Option<&str>) -> CxlFmRequest {
+			match fm {
+				Some(fm) => self.arg(CXL_FM_FM_ARG, fm),
+				None => self,
+			}
+		}
+
+		/*
+		 * Does request ask for validation only?
+		 */
+		pub fn is_dry_run(&self) -> bool {
+			self.get(CXL_FM_DRY_RUN_ARG).is_some_and(|value| value != "0")
+		}
+
+		/*
+		 * Parse request line
+		 */
+		pub fn parse(line: &str) -> Result<CxlFmRequest, CxlFmError> {
+			let mut tokens = line.split_whitespace();
+
+			let command = match tokens.next() {
+				Some(command) => command,
+				None => return Err(CxlFmError::Protocol(String::from("empty request"))),
+			};
+
+			let mut request = CxlFmRequest::new(command);
+
+			for token in tokens {
+				match token.split_once('=') {
+					Some((key, value)) => {
+						request.args.push((String::from(key),
+								   String::from(value)));
+					},
+					None => {
+						return Err(CxlFmError::Protocol(format!("malformed argument: {}",
+											token)));
+					},
+				}
+			}
+
+			Ok(request)
+		}
+
+		/*
+		 * Get argument value
+		 */
+		pub fn get(&self, key: &str) -> Option<&str> {
+			self.args.iter()
+				.find(|(k, _)| k == key)
+				.map(|(_, v)| v.as_str())
+		}
+
+		/*
+		 * Get mandatory numeric argument
+		 */
+		pub fn get_number<T: std::str::FromStr>(&self, key: &str) -> Result<T, CxlFmError> {
+			match self.get(key) {
+				Some(value) => parse_number::<T>(value),
+				None => Err(CxlFmError::InvalidInput(format!("{} is not defined", key))),
+			}
+		}
+
+		/*
+		 * Get optional numeric argument
+		 */
+		pub fn get_number_or<T: std::str::FromStr>(&self, key: &str,
+							   default: T) -> Result<T, CxlFmError> {
+			match self.get(key) {
+				Some(value) => parse_number::<T>(value),
+				None => Ok(default),
+			}
+		}
+
+		/*
+		 * Convert request into line
+		 */
+		pub fn to_line(&self) -> String {
+			let mut line = self.command.clone();
+
+			for (key, value) in &self.args {
+				line.push_str(&format!(" {}={}", key, value));
+			}
+
+			line
+		}
+	}
+
+	/*
+	 * Parse decimal or hexadecimal (0x prefix) number
+	 */
+	pub fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, CxlFmError> {
+		let error = || CxlFmError::InvalidInput(format!("invalid number: {}", value));
+
+		match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+			Some(hex) => {
+				let number = u128::from_str_radix(hex, 16).map_err(|_| error())?;
+				number.to_string().parse::<T>().map_err(|_| error())
+			},
+			None => value.parse::<T>().map_err(|_| error()),
+		}
+	}
+
+	/*
+	 * Parse size with optional binary suffix (K, M, G, T)
+	 */
+	pub fn parse_size(value: &str) -> Result<u64, CxlFmError> {
+		if value.starts_with("0x") || value.starts_with("0X") {
+			return parse_number::<u64>(value);
+		}
+
+		let upper = value.to_uppercase();
+		let trimmed = upper.trim_end_matches('B').trim_end_matches('I');
+
+		let (number, shift) = match trimmed.chars().last() {
+			Some('K') => (&trimmed[..trimmed.len() - 1], 10),
+			Some('M') => (&trimmed[..trimmed.len() - 1], 20),
+			Some('G') => (&trimmed[..trimmed.len() - 1], 30),
+			Some('T') => (&trimmed[..trimmed.len() - 1], 40),
+			_ => (trimmed, 0),
+		};
+
+		let number = parse_number::<u64>(number)?;
+
+		number.checked_mul(1u64 << shift)
+			.ok_or_else(|| CxlFmError::InvalidInput(format!("size is too big: {}", value)))
+	}
+
+	/*
+	 * Convert size into human-readable string
+	 */
+	pub fn format_size(size: u64) -> String {
+		const SUFFIXES: [(&str, u32); 4] = [("TiB", 40), ("GiB", 30), ("MiB", 20), ("KiB", 10)];
+
+		for (suffix, shift) in SUFFIXES {
+			if size >= (1u64 << shift) && size.is_multiple_of(1u64 << shift) {
+				return format!("{}{}", size >> shift, suffix);
+			}
+		}
+
+		format!("{}B", size)
+	}
+
+	/*
+	 * Convert binary payload into hex string
+	 */
+	pub fn to_hex(data: &[u8]) -> String {
+		data.iter().map(|byte| format!("{:02x}", byte)).collect()
+	}
+
+	/*
+	 * Convert hex string into binary payload
+	 */
+	pub fn from_hex(hex: &str) -> Result<Vec<u8>, CxlFmError> {
+		if !hex.len().is_multiple_of(2) {
+			return Err(CxlFmError::Protocol(String::from("odd length of hex payload")));
+		}
+
+		(0..hex.len())
+			.step_by(2)
+			.map(|i| {
+				u8::from_str_radix(&hex[i..i + 2], 16)
+					.map_err(|_| CxlFmError::Protocol(String::from("corrupted hex payload")))
+			})
+			.collect()
 	}
 
 	/*
 	 * Send command to FM
 	 */
 	pub fn send_command(mut stream: &TcpStream, command: &str, env: &CxlFmOptions) {
-		let full_command = format!("{command}\n");
+		if env.is_dry_run && !CXL_FM_PLANNED_COMMANDS.contains(&command) {
+			println!("Dry run is not supported by {}", command);
+			return;
+		}
+
+		let full_command = format!("{}\n", CxlFmRequest::new(command)
+							.fm(env.fm.as_deref())
+							.to_line());
 
 		if env.is_debug {
 			println!("COMMAND: {:#?}", full_command);
@@ -118,6 +501,70 @@ pub mod cxl_fm_lib {
 		}
 	}
 
+	/*
+	 * Send request to FM and wait for the responce
+	 *
+	 * Returns data of OK responce or empty string for NO_DATA.
+	 */
+	pub fn send_request(mut stream: &TcpStream,
+			    request: &CxlFmRequest,
+			    env: &CxlFmOptions) -> Result<String, CxlFmError> {
+		/*
+		 * Other commands would be executed, not validated
+		 */
+		if env.is_dry_run && !CXL_FM_PLANNED_COMMANDS.contains(&request.command.as_str()) {
+			return Err(CxlFmError::InvalidInput(format!("dry run is not supported by {}",
+								    request.command)));
+		}
+
+		let full_command = format!("{}\n", request.clone()
+							.fm(env.fm.as_deref())
+							.to_line());
+
+		if env.is_debug {
+			println!("COMMAND: {:#?}", full_command);
+		}
+
+		stream.write_all(full_command.as_bytes())
+			.map_err(|e| CxlFmError::Io(e.to_string()))?;
+
+		let mut responce = String::new();
+		let mut buf_reader = BufReader::new(stream);
+
+		buf_reader.read_line(&mut responce)
+			.map_err(|e| CxlFmError::Io(e.to_string()))?;
+
+		let responce = responce.trim_end();
+
+		if env.is_debug {
+			println!("RESPONCE: {:#?}", responce);
+		}
+
+		parse_responce(responce)
+	}
+
+	/*
+	 * Parse responce line of FM
+	 */
+	pub fn parse_responce(responce: &str) -> Result<String, CxlFmError> {
+		let (status, data) = match responce.split_once(' ') {
+			Some((status, data)) => (status, data),
+			None => (responce, ""),
+		};
+
+		match status {
+			CXL_FM_OK => Ok(String::from(data)),
+			CXL_FM_NO_DATA => Ok(String::new()),
+			CXL_FM_ERROR => Err(CxlFmError::Remote(String::from(data))),
+			CXL_FM_UNKNOWN_COMMAND => {
+				Err(CxlFmError::Protocol(String::from("unknown command")))
+			},
+			"" => Err(CxlFmError::Io(String::from("connection closed"))),
+			_ => Err(CxlFmError::Protocol(format!("unexpected responce: {}",
+							      responce))),
+		}
+	}
+
 	/*
 	 * Send responce from FM
 	 */
@@ -130,4 +577,31 @@ pub mod cxl_fm_lib {
 
 		stream.write_all(full_responce.as_bytes()).unwrap();
 	}
+
+	/*
+	 * Send successful responce with data from FM
+	 */
+	pub fn send_ok(stream: &TcpStream, data: &str, env: &CxlFmOptions) {
+		if data.is_empty() {
+			send_responce(stream, CXL_FM_OK, env);
+		} else {
+			send_responce(stream, &format!("{} {}", CXL_FM_OK, data), env);
+		}
+	}
+
+	/*
+	 * Send successful responce with binary payload from FM
+	 */
+	pub fn send_payload(stream: &TcpStream, payload: &[u8], env: &CxlFmOptions) {
+		send_ok(stream, &to_hex(payload), env);
+	}
+
+	/*
+	 * Send error responce from FM
+	 */
+	pub fn send_error(stream: &TcpStream, error: &CxlFmError, env: &CxlFmOptions) {
+		let message = error.to_string().replace('\n', " ");
+
+		send_responce(stream, &format!("{} {}", CXL_FM_ERROR, message), env);
+	}
 }
@@ -0,0 +1,274 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL Multi-Logical Device (MLD) FM API payloads.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_ld_lib {
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+
+	/*
+	 * struct CxlGetLdInfoResponse - Get LD Info responce
+	 * @memory_size: total memory capacity of the MLD
+	 * @ld_count: number of LDs
+	 * @qos_telemetry: QoS telemetry capability
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetLdInfoResponse {
+		pub memory_size: u64,
+		pub ld_count: u16,
+		pub qos_telemetry: u8,
+	}
+
+	impl CxlGetLdInfoResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u64(self.memory_size);
+			writer.write_u16(self.ld_count);
+			writer.write_u8(self.qos_telemetry);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetLdInfoResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlGetLdInfoResponse {
+				memory_size: reader.read_u64()?,
+				ld_count: reader.read_u16()?,
+				qos_telemetry: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * Memory granularity of LD allocations
+	 */
+	pub const CXL_LD_GRANULARITY_256MB: u8		= 0x0;
+	pub const CXL_LD_GRANULARITY_512MB: u8		= 0x1;
+	pub const CXL_LD_GRANULARITY_1GB: u8		= 0x2;
+
+	/*
+	 * Get memory granularity in bytes
+	 */
+	pub fn granularity_size(granularity: u8) -> u64 {
+		(256 << 20) << granularity
+	}
+
+	/*
+	 * struct CxlLdAllocation - memory allocated to LD
+	 * @range1: number of granularity multiples in range 1
+	 * @range2: number of granularity multiples in range 2
+	 */
+	#[derive(Debug, Clone, Copy, PartialEq)]
+	pub struct CxlLdAllocation {
+		pub range1: u64,
+		pub range2: u64,
+	}
+
+	/*
+	 * struct CxlGetLdAllocationsRequest - Get LD Allocations request
+	 * @start_ld: ID of the first requested LD
+	 * @ld_list_limit: maximal number of returned LDs
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetLdAllocationsRequest {
+		pub start_ld: u8,
+		pub ld_list_limit: u8,
+	}
+
+	impl CxlGetLdAllocationsRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.start_ld);
+			writer.write_u8(self.ld_list_limit);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetLdAllocationsRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlGetLdAllocationsRequest {
+				start_ld: reader.read_u8()?,
+				ld_list_limit: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlGetLdAllocationsResponse - Get LD Allocations responce
+	 * @num_lds: number of LDs of the device
+	 * @granularity: memory granularity
+	 * @start_ld: ID of the first returned LD
+	 * @allocations: allocations of returned LDs
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetLdAllocationsResponse {
+		pub num_lds: u8,
+		pub granularity: u8,
+		pub start_ld: u8,
+		pub allocations: Vec<CxlLdAllocation>,
+	}
+
+	impl CxlGetLdAllocationsResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.num_lds);
+			writer.write_u8(self.granularity);
+			writer.write_u8(self.start_ld);
+			writer.write_u8(self.allocations.len() as u8);
+
+			for allocation in &self.allocations {
+				writer.write_u64(allocation.range1);
+				writer.write_u64(allocation.range2);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetLdAllocationsResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let num_lds = reader.read_u8()?;
+			let granularity = reader.read_u8()?;
+			let start_ld = reader.read_u8()?;
+			let count = reader.read_u8()?;
+
+			let mut allocations = Vec::new();
+			for _ in 0..count {
+				allocations.push(CxlLdAllocation {
+					range1: reader.read_u64()?,
+					range2: reader.read_u64()?,
+				});
+			}
+
+			Ok(CxlGetLdAllocationsResponse {
+				num_lds,
+				granularity,
+				start_ld,
+				allocations,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlSetLdAllocationsRequest - Set LD Allocations request
+	 * (responce has the same format)
+	 * @start_ld: ID of the first LD to configure
+	 * @allocations: new allocations of LDs
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlSetLdAllocationsRequest {
+		pub start_ld: u8,
+		pub allocations: Vec<CxlLdAllocation>,
+	}
+
+	impl CxlSetLdAllocationsRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.allocations.len() as u8);
+			writer.write_u8(self.start_ld);
+			writer.reserve(2);
+
+			for allocation in &self.allocations {
+				writer.write_u64(allocation.range1);
+				writer.write_u64(allocation.range2);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlSetLdAllocationsRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let count = reader.read_u8()?;
+			let start_ld = reader.read_u8()?;
+			reader.skip(2)?;
+
+			let mut allocations = Vec::new();
+			for _ in 0..count {
+				allocations.push(CxlLdAllocation {
+					range1: reader.read_u64()?,
+					range2: reader.read_u64()?,
+				});
+			}
+
+			Ok(CxlSetLdAllocationsRequest {
+				start_ld,
+				allocations,
+			})
+		}
+	}
+
+	/*
+	 * QoS telemetry control flags
+	 */
+	pub const CXL_QOS_EGRESS_PORT_CONGESTION: u8	= 0x1;
+	pub const CXL_QOS_TEMPORARY_THROUGHPUT_REDUCTION: u8 = 0x2;
+
+	/*
+	 * struct CxlQosControl - Get/Set QoS Control payload
+	 * @telemetry_control: QoS telemetry control flags
+	 * @egress_moderate_percentage: moderate egress port congestion threshold
+	 * @egress_severe_percentage: severe egress port congestion threshold
+	 * @backpressure_sample_interval: backpressure sample interval (ns)
+	 * @req_cmp_basis: ReqCmpBasis
+	 * @completion_collection_interval: completion collection interval (ns)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlQosControl {
+		pub telemetry_control: u8,
+		pub egress_moderate_percentage: u8,
+		pub egress_severe_percentage: u8,
+		pub backpressure_sample_interval: u8,
+		pub req_cmp_basis: u16,
+		pub completion_collection_interval: u8,
+	}
+
+	impl CxlQosControl {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.telemetry_control);
+			writer.write_u8(self.egress_moderate_percentage);
+			writer.write_u8(self.egress_severe_percentage);
+			writer.write_u8(self.backpressure_sample_interval);
+			writer.write_u16(self.req_cmp_basis);
+			writer.write_u8(self.completion_collection_interval);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlQosControl, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlQosControl {
+				telemetry_control: reader.read_u8()?,
+				egress_moderate_percentage: reader.read_u8()?,
+				egress_severe_percentage: reader.read_u8()?,
+				backpressure_sample_interval: reader.read_u8()?,
+				req_cmp_basis: reader.read_u16()?,
+				completion_collection_interval: reader.read_u8()?,
+			})
+		}
+	}
+}
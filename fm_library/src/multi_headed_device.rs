@@ -0,0 +1,270 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL Multi-Headed Device (MHD) FM API payloads.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_mh_device_lib {
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+
+	/*
+	 * Maximal number of LD Map entries in one Get Multi-Headed Info responce
+	 */
+	pub const CXL_MHD_LD_MAP_LIST_LIMIT: u8 = 64;
+
+	/*
+	 * struct CxlGetMhdInfoRequest - Get Multi-Headed Info request
+	 * @start_ld: ID of the first LD in the LD Map
+	 * @ld_map_list_limit: maximal number of LD Map entries
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetMhdInfoRequest {
+		pub start_ld: u8,
+		pub ld_map_list_limit: u8,
+	}
+
+	impl CxlGetMhdInfoRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.start_ld);
+			writer.write_u8(self.ld_map_list_limit);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetMhdInfoRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlGetMhdInfoRequest {
+				start_ld: reader.read_u8()?,
+				ld_map_list_limit: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlGetMhdInfoResponse - Get Multi-Headed Info responce
+	 * @num_lds: number of LDs supported by the device
+	 * @num_heads: number of heads of the device
+	 * @start_ld: ID of the first LD in the LD Map
+	 * @ld_map: head ID for every LD starting from @start_ld
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetMhdInfoResponse {
+		pub num_lds: u8,
+		pub num_heads: u8,
+		pub start_ld: u8,
+		pub ld_map: Vec<u8>,
+	}
+
+	impl CxlGetMhdInfoResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.num_lds);
+			writer.write_u8(self.num_heads);
+			writer.reserve(2);
+			writer.write_u8(self.start_ld);
+			writer.write_u8(self.ld_map.len() as u8);
+			writer.reserve(2);
+			writer.write_bytes(&self.ld_map);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetMhdInfoResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let num_lds = reader.read_u8()?;
+			let num_heads = reader.read_u8()?;
+			reader.skip(2)?;
+			let start_ld = reader.read_u8()?;
+			let ld_map_len = reader.read_u8()?;
+			reader.skip(2)?;
+			let ld_map = reader.read_bytes(ld_map_len as usize)?.to_vec();
+
+			Ok(CxlGetMhdInfoResponse {
+				num_lds,
+				num_heads,
+				start_ld,
+				ld_map,
+			})
+		}
+
+		/*
+		 * Build head-to-LD matrix: matrix[head][ld] is true
+		 * if LD (start_ld + ld) is mapped to the head
+		 */
+		pub fn head_to_ld_matrix(&self) -> Vec<Vec<bool>> {
+			let mut matrix = vec![vec![false; self.ld_map.len()];
+					      self.num_heads as usize];
+
+			for (ld, head) in self.ld_map.iter().enumerate() {
+				if let Some(row) = matrix.get_mut(*head as usize) {
+					row[ld] = true;
+				}
+			}
+
+			matrix
+		}
+	}
+
+	/*
+	 * Size of Head Info block in Get Head Info responce
+	 */
+	pub const CXL_MHD_HEAD_INFO_SIZE: usize = 16;
+
+	/*
+	 * struct CxlGetHeadInfoRequest - Get Head Info request
+	 * @start_head: ID of the first requested head
+	 * @num_heads: number of requested heads
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetHeadInfoRequest {
+		pub start_head: u8,
+		pub num_heads: u8,
+	}
+
+	impl CxlGetHeadInfoRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.start_head);
+			writer.write_u8(self.num_heads);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetHeadInfoRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlGetHeadInfoRequest {
+				start_head: reader.read_u8()?,
+				num_heads: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlHeadInfo - link state of MHD head
+	 * @port_number: port number of the head
+	 * @max_link_width: maximal link width
+	 * @negotiated_link_width: negotiated link width
+	 * @supported_link_speeds: supported link speeds vector
+	 * @max_link_speed: maximal link speed
+	 * @current_link_speed: current link speed
+	 * @ltssm_state: LTSSM state
+	 * @first_negotiated_lane: first negotiated lane number
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlHeadInfo {
+		pub port_number: u8,
+		pub max_link_width: u8,
+		pub negotiated_link_width: u8,
+		pub supported_link_speeds: u8,
+		pub max_link_speed: u8,
+		pub current_link_speed: u8,
+		pub ltssm_state: u8,
+		pub first_negotiated_lane: u8,
+	}
+
+	impl CxlHeadInfo {
+		/*
+		 * Does the head operate below its capabilities?
+		 */
+		pub fn is_degraded(&self) -> bool {
+			self.negotiated_link_width < self.max_link_width ||
+			self.current_link_speed < self.max_link_speed
+		}
+
+		fn write(&self, writer: &mut CxlPayloadWriter) {
+			writer.write_u8(self.port_number);
+			writer.write_u8(self.max_link_width & 0x3F);
+			writer.write_u8(self.negotiated_link_width & 0x3F);
+			writer.write_u8(self.supported_link_speeds & 0x3F);
+			writer.write_u8(self.max_link_speed & 0x3F);
+			writer.write_u8(self.current_link_speed & 0x3F);
+			writer.write_u8(self.ltssm_state);
+			writer.write_u8(self.first_negotiated_lane);
+			writer.reserve(CXL_MHD_HEAD_INFO_SIZE - 8);
+		}
+
+		fn read(reader: &mut CxlPayloadReader) -> Result<CxlHeadInfo, CxlFmError> {
+			let info = CxlHeadInfo {
+				port_number: reader.read_u8()?,
+				max_link_width: reader.read_u8()? & 0x3F,
+				negotiated_link_width: reader.read_u8()? & 0x3F,
+				supported_link_speeds: reader.read_u8()? & 0x3F,
+				max_link_speed: reader.read_u8()? & 0x3F,
+				current_link_speed: reader.read_u8()? & 0x3F,
+				ltssm_state: reader.read_u8()?,
+				first_negotiated_lane: reader.read_u8()?,
+			};
+
+			reader.skip(CXL_MHD_HEAD_INFO_SIZE - 8)?;
+			Ok(info)
+		}
+	}
+
+	/*
+	 * struct CxlGetHeadInfoResponse - Get Head Info responce
+	 * @start_head: ID of the first returned head
+	 * @heads: link state of returned heads
+	 *
+	 * Start head is not a part of FM API payload,
+	 * it is restored from the request.
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetHeadInfoResponse {
+		pub start_head: u8,
+		pub heads: Vec<CxlHeadInfo>,
+	}
+
+	impl CxlGetHeadInfoResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.heads.len() as u8);
+			writer.reserve(3);
+
+			for head in &self.heads {
+				head.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(start_head: u8,
+				  data: &[u8]) -> Result<CxlGetHeadInfoResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let num_heads = reader.read_u8()?;
+			reader.skip(3)?;
+
+			let mut heads = Vec::new();
+			for _ in 0..num_heads {
+				heads.push(CxlHeadInfo::read(&mut reader)?);
+			}
+
+			Ok(CxlGetHeadInfoResponse {
+				start_head,
+				heads,
+			})
+		}
+	}
+}
@@ -0,0 +1,341 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * FM instances and FM registry of orchestrator.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_fm_orchestrator {
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+
+	/*
+	 * States of FM instance
+	 */
+	pub const CXL_FM_INSTANCE_STOPPED: u8		= 0x0;
+	pub const CXL_FM_INSTANCE_RUNNING: u8		= 0x1;
+	pub const CXL_FM_INSTANCE_CRASHED: u8		= 0x2;
+
+	/*
+	 * Get human-readable name of FM instance state
+	 */
+	pub fn instance_state_name(state: u8) -> &'static str {
+		match state {
+			CXL_FM_INSTANCE_STOPPED => "stopped",
+			CXL_FM_INSTANCE_RUNNING => "running",
+			CXL_FM_INSTANCE_CRASHED => "crashed",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * struct CxlFmInstanceStatus - status of FM instance
+	 * @name: instance name
+	 * @address: IP address + port of FM API
+	 * @state: instance state
+	 * @pid: process ID (0 if the process is absent)
+	 * @restarts: number of restarts after crash
+	 * @uptime: time since the process start (seconds)
+	 * @restart_delay: time till the next restart attempt (seconds)
+	 * @last_exit: exit status of the last process
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmInstanceStatus {
+		pub name: String,
+		pub address: String,
+		pub state: u8,
+		pub pid: u32,
+		pub restarts: u32,
+		pub uptime: u64,
+		pub restart_delay: u64,
+		pub last_exit: String,
+	}
+
+	impl CxlFmInstanceStatus {
+		pub fn write(&self, writer: &mut CxlPayloadWriter) {
+			writer.write_string(&self.name);
+			writer.write_string(&self.address);
+			writer.write_u8(self.state);
+			writer.write_u32(self.pid);
+			writer.write_u32(self.restarts);
+			writer.write_u64(self.uptime);
+			writer.write_u64(self.restart_delay);
+			writer.write_string(&self.last_exit);
+		}
+
+		pub fn read(reader: &mut CxlPayloadReader) -> Result<CxlFmInstanceStatus, CxlFmError> {
+			Ok(CxlFmInstanceStatus {
+				name: reader.read_string()?,
+				address: reader.read_string()?,
+				state: reader.read_u8()?,
+				pid: reader.read_u32()?,
+				restarts: reader.read_u32()?,
+				uptime: reader.read_u64()?,
+				restart_delay: reader.read_u64()?,
+				last_exit: reader.read_string()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlFmInstanceList - status of FM instances
+	 * @instances: instances sorted by name
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmInstanceList {
+		pub instances: Vec<CxlFmInstanceStatus>,
+	}
+
+	impl CxlFmInstanceList {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u32(self.instances.len() as u32);
+
+			for instance in &self.instances {
+				instance.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlFmInstanceList, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+			let mut instances = Vec::new();
+
+			for _ in 0..reader.read_u32()? {
+				instances.push(CxlFmInstanceStatus::read(&mut reader)?);
+			}
+
+			Ok(CxlFmInstanceList { instances })
+		}
+	}
+
+	/*
+	 * Health of registered FM
+	 */
+	pub const CXL_FM_HEALTH_HEALTHY: u8		= 0x0;
+	pub const CXL_FM_HEALTH_LATE: u8		= 0x1;
+	pub const CXL_FM_HEALTH_LOST: u8		= 0x2;
+
+	/*
+	 * Get human-readable name of FM health
+	 */
+	pub fn fm_health_name(health: u8) -> &'static str {
+		match health {
+			CXL_FM_HEALTH_HEALTHY => "healthy",
+			CXL_FM_HEALTH_LATE => "late",
+			CXL_FM_HEALTH_LOST => "lost",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Roles of FM: only one FM is active for a switch CCI,
+	 * the standby FMs replicate state of the active one
+	 */
+	pub const CXL_FM_ROLE_ACTIVE: u8		= 0x0;
+	pub const CXL_FM_ROLE_STANDBY: u8		= 0x1;
+
+	/*
+	 * Get human-readable name of FM role
+	 */
+	pub fn fm_role_name(role: u8) -> &'static str {
+		match role {
+			CXL_FM_ROLE_ACTIVE => "active",
+			CXL_FM_ROLE_STANDBY => "standby",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Get FM role by its name
+	 */
+	pub fn fm_role_by_name(name: &str) -> Option<u8> {
+		(CXL_FM_ROLE_ACTIVE..=CXL_FM_ROLE_STANDBY)
+			.find(|role| fm_role_name(*role) == name)
+	}
+
+	/*
+	 * struct CxlFmRole - role assigned to FM by orchestrator
+	 * @role: FM role
+	 * @active: ID of the active FM (empty if it is not elected yet)
+	 * @address: IP address + port of FM API of the active FM
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmRole {
+		pub role: u8,
+		pub active: String,
+		pub address: String,
+	}
+
+	impl CxlFmRole {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.role);
+			writer.write_string(&self.active);
+			writer.write_string(&self.address);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlFmRole, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlFmRole {
+				role: reader.read_u8()?,
+				active: reader.read_string()?,
+				address: reader.read_string()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlFmInfo - FM registered in orchestrator
+	 * @id: FM identifier
+	 * @address: IP address + port of FM API
+	 * @version: FM version
+	 * @switches: names of switches managed by FM
+	 * @devices: names of devices managed by FM
+	 * @ccis: names of CCIs of FM in order of CCI indices
+	 * @health: health by heartbeats of FM
+	 * @role: role of FM elected by orchestrator
+	 * @uptime: time since FM start (seconds)
+	 * @heartbeat_age: time since the last heartbeat (seconds)
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmInfo {
+		pub id: String,
+		pub address: String,
+		pub version: String,
+		pub switches: Vec<String>,
+		pub devices: Vec<String>,
+		pub ccis: Vec<String>,
+		pub health: u8,
+		pub role: u8,
+		pub uptime: u64,
+		pub heartbeat_age: u64,
+	}
+
+	impl CxlFmInfo {
+		/*
+		 * Does FM manage the switch or device?
+		 */
+		pub fn manages(&self, component: &str) -> bool {
+			self.switches.iter().any(|switch| switch == component) ||
+			self.devices.iter().any(|device| device == component)
+		}
+
+		pub fn write(&self, writer: &mut CxlPayloadWriter) {
+			writer.write_string(&self.id);
+			writer.write_string(&self.address);
+			writer.write_string(&self.version);
+			writer.write_u16(self.switches.len() as u16);
+
+			for switch in &self.switches {
+				writer.write_string(switch);
+			}
+
+			writer.write_u16(self.devices.len() as u16);
+
+			for device in &self.devices {
+				writer.write_string(device);
+			}
+
+			writer.write_u16(self.ccis.len() as u16);
+
+			for cci in &self.ccis {
+				writer.write_string(cci);
+			}
+
+			writer.write_u8(self.health);
+			writer.write_u8(self.role);
+			writer.write_u64(self.uptime);
+			writer.write_u64(self.heartbeat_age);
+		}
+
+		pub fn read(reader: &mut CxlPayloadReader) -> Result<CxlFmInfo, CxlFmError> {
+			let id = reader.read_string()?;
+			let address = reader.read_string()?;
+			let version = reader.read_string()?;
+			let mut switches = Vec::new();
+
+			let mut devices = Vec::new();
+			let mut ccis = Vec::new();
+
+			for _ in 0..reader.read_u16()? {
+				switches.push(reader.read_string()?);
+			}
+
+			for _ in 0..reader.read_u16()? {
+				devices.push(reader.read_string()?);
+			}
+
+			for _ in 0..reader.read_u16()? {
+				ccis.push(reader.read_string()?);
+			}
+
+			Ok(CxlFmInfo {
+				id,
+				address,
+				version,
+				switches,
+				devices,
+				ccis,
+				health: reader.read_u8()?,
+				role: reader.read_u8()?,
+				uptime: reader.read_u64()?,
+				heartbeat_age: reader.read_u64()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlFmInfoList - FMs registered in orchestrator
+	 * @fms: FMs sorted by ID
+	 */
+	#[derive(Debug, Clone, Default, PartialEq)]
+	pub struct CxlFmInfoList {
+		pub fms: Vec<CxlFmInfo>,
+	}
+
+	impl CxlFmInfoList {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u32(self.fms.len() as u32);
+
+			for fm in &self.fms {
+				fm.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlFmInfoList, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+			let mut fms = Vec::new();
+
+			for _ in 0..reader.read_u32()? {
+				fms.push(CxlFmInfo::read(&mut reader)?);
+			}
+
+			Ok(CxlFmInfoList { fms })
+		}
+	}
+}
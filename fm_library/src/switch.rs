@@ -0,0 +1,861 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL switch FM API payloads.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_switch_lib {
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_lib::parse_number;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+
+	/*
+	 * Size of port and VCS bitmasks
+	 */
+	pub const CXL_SWITCH_BITMASK_SIZE: usize = 32;
+
+	/*
+	 * Size of port info block in Get Physical Port State responce
+	 */
+	pub const CXL_SWITCH_PORT_INFO_SIZE: usize = 16;
+
+	/*
+	 * Physical port configuration states
+	 */
+	pub const CXL_PORT_STATE_DISABLED: u8		= 0x0;
+	pub const CXL_PORT_STATE_BIND_IN_PROGRESS: u8	= 0x1;
+	pub const CXL_PORT_STATE_UNBIND_IN_PROGRESS: u8	= 0x2;
+	pub const CXL_PORT_STATE_DSP: u8		= 0x3;
+	pub const CXL_PORT_STATE_USP: u8		= 0x4;
+	pub const CXL_PORT_STATE_FABRIC_PORT: u8	= 0x5;
+	pub const CXL_PORT_STATE_INVALID: u8		= 0xF;
+
+	/*
+	 * Get human-readable name of port configuration state
+	 */
+	pub fn port_state_name(state: u8) -> &'static str {
+		match state {
+			CXL_PORT_STATE_DISABLED => "disabled",
+			CXL_PORT_STATE_BIND_IN_PROGRESS => "bind in progress",
+			CXL_PORT_STATE_UNBIND_IN_PROGRESS => "unbind in progress",
+			CXL_PORT_STATE_DSP => "DSP",
+			CXL_PORT_STATE_USP => "USP",
+			CXL_PORT_STATE_FABRIC_PORT => "fabric port",
+			CXL_PORT_STATE_INVALID => "invalid port ID",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Types of device connected to physical port
+	 */
+	pub const CXL_PORT_DEVICE_NONE: u8		= 0x0;
+	pub const CXL_PORT_DEVICE_PCIE: u8		= 0x1;
+	pub const CXL_PORT_DEVICE_TYPE1: u8		= 0x2;
+	pub const CXL_PORT_DEVICE_TYPE2: u8		= 0x3;
+	pub const CXL_PORT_DEVICE_TYPE3_SLD: u8		= 0x4;
+	pub const CXL_PORT_DEVICE_TYPE3_MLD: u8		= 0x5;
+
+	/*
+	 * Get human-readable name of connected device type
+	 */
+	pub fn port_device_name(device_type: u8) -> &'static str {
+		match device_type {
+			CXL_PORT_DEVICE_NONE => "no device",
+			CXL_PORT_DEVICE_PCIE => "PCIe device",
+			CXL_PORT_DEVICE_TYPE1 => "CXL type 1 device",
+			CXL_PORT_DEVICE_TYPE2 => "CXL type 2 device",
+			CXL_PORT_DEVICE_TYPE3_SLD => "CXL type 3 SLD",
+			CXL_PORT_DEVICE_TYPE3_MLD => "CXL type 3 MLD",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * vPPB binding statuses
+	 */
+	pub const CXL_VPPB_UNBOUND: u8			= 0x0;
+	pub const CXL_VPPB_BIND_IN_PROGRESS: u8		= 0x1;
+	pub const CXL_VPPB_BOUND_PORT: u8		= 0x2;
+	pub const CXL_VPPB_BOUND_LD: u8			= 0x3;
+
+	/*
+	 * Get list of bits set in bitmask
+	 */
+	pub fn bitmask_bits(mask: &[u8]) -> Vec<u8> {
+		(0..mask.len() * 8)
+			.filter(|bit| mask[bit / 8] & (1 << (bit % 8)) != 0)
+			.map(|bit| bit as u8)
+			.collect()
+	}
+
+	/*
+	 * Build bitmask from list of bits
+	 */
+	pub fn bits_bitmask(bits: &[u8]) -> [u8; CXL_SWITCH_BITMASK_SIZE] {
+		let mut mask = [0; CXL_SWITCH_BITMASK_SIZE];
+
+		for bit in bits {
+			mask[*bit as usize / 8] |= 1 << (bit % 8);
+		}
+
+		mask
+	}
+
+	/*
+	 * struct CxlIdentifySwitchResponse - Identify Switch Device responce
+	 * @ingress_port: port ID of the FM connection
+	 * @num_ports: number of physical ports
+	 * @num_vcs: number of VCSs
+	 * @active_ports: bitmask of active ports
+	 * @active_vcs: bitmask of active VCSs
+	 * @total_vppbs: total number of vPPBs
+	 * @bound_vppbs: number of bound vPPBs
+	 * @num_hdm_decoders: number of HDM decoders per USP
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlIdentifySwitchResponse {
+		pub ingress_port: u8,
+		pub num_ports: u8,
+		pub num_vcs: u8,
+		pub active_ports: [u8; CXL_SWITCH_BITMASK_SIZE],
+		pub active_vcs: [u8; CXL_SWITCH_BITMASK_SIZE],
+		pub total_vppbs: u16,
+		pub bound_vppbs: u16,
+		pub num_hdm_decoders: u8,
+	}
+
+	impl CxlIdentifySwitchResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.ingress_port);
+			writer.reserve(1);
+			writer.write_u8(self.num_ports);
+			writer.write_u8(self.num_vcs);
+			writer.write_bytes(&self.active_ports);
+			writer.write_bytes(&self.active_vcs);
+			writer.write_u16(self.total_vppbs);
+			writer.write_u16(self.bound_vppbs);
+			writer.write_u8(self.num_hdm_decoders);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlIdentifySwitchResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let ingress_port = reader.read_u8()?;
+			reader.skip(1)?;
+
+			Ok(CxlIdentifySwitchResponse {
+				ingress_port,
+				num_ports: reader.read_u8()?,
+				num_vcs: reader.read_u8()?,
+				active_ports: reader.read_bytes(CXL_SWITCH_BITMASK_SIZE)?.try_into().unwrap(),
+				active_vcs: reader.read_bytes(CXL_SWITCH_BITMASK_SIZE)?.try_into().unwrap(),
+				total_vppbs: reader.read_u16()?,
+				bound_vppbs: reader.read_u16()?,
+				num_hdm_decoders: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlGetPhysicalPortStateRequest - Get Physical Port State request
+	 * @ports: IDs of requested ports
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetPhysicalPortStateRequest {
+		pub ports: Vec<u8>,
+	}
+
+	impl CxlGetPhysicalPortStateRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.ports.len() as u8);
+			writer.write_bytes(&self.ports);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetPhysicalPortStateRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let num_ports = reader.read_u8()?;
+
+			Ok(CxlGetPhysicalPortStateRequest {
+				ports: reader.read_bytes(num_ports as usize)?.to_vec(),
+			})
+		}
+	}
+
+	/*
+	 * struct CxlPhysicalPortInfo - state of physical port
+	 * @port_id: port ID
+	 * @config_state: port configuration state
+	 * @connected_device_mode: mode of connected device
+	 * @connected_device_type: type of connected device
+	 * @supported_cxl_modes: supported CXL modes
+	 * @max_link_width: maximal link width
+	 * @negotiated_link_width: negotiated link width
+	 * @supported_link_speeds: supported link speeds vector
+	 * @max_link_speed: maximal link speed
+	 * @current_link_speed: current link speed
+	 * @ltssm_state: LTSSM state
+	 * @first_negotiated_lane: first negotiated lane number
+	 * @link_state_flags: link state flags
+	 * @supported_ld_count: number of LDs supported by connected device
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlPhysicalPortInfo {
+		pub port_id: u8,
+		pub config_state: u8,
+		pub connected_device_mode: u8,
+		pub connected_device_type: u8,
+		pub supported_cxl_modes: u8,
+		pub max_link_width: u8,
+		pub negotiated_link_width: u8,
+		pub supported_link_speeds: u8,
+		pub max_link_speed: u8,
+		pub current_link_speed: u8,
+		pub ltssm_state: u8,
+		pub first_negotiated_lane: u8,
+		pub link_state_flags: u16,
+		pub supported_ld_count: u8,
+	}
+
+	impl CxlPhysicalPortInfo {
+		fn write(&self, writer: &mut CxlPayloadWriter) {
+			writer.write_u8(self.port_id);
+			writer.write_u8(self.config_state & 0xF);
+			writer.write_u8(self.connected_device_mode);
+			writer.reserve(1);
+			writer.write_u8(self.connected_device_type);
+			writer.write_u8(self.supported_cxl_modes);
+			writer.write_u8(self.max_link_width & 0x3F);
+			writer.write_u8(self.negotiated_link_width & 0x3F);
+			writer.write_u8(self.supported_link_speeds & 0x3F);
+			writer.write_u8(self.max_link_speed & 0x3F);
+			writer.write_u8(self.current_link_speed & 0x3F);
+			writer.write_u8(self.ltssm_state);
+			writer.write_u8(self.first_negotiated_lane);
+			writer.write_u16(self.link_state_flags);
+			writer.write_u8(self.supported_ld_count);
+		}
+
+		fn read(reader: &mut CxlPayloadReader) -> Result<CxlPhysicalPortInfo, CxlFmError> {
+			let port_id = reader.read_u8()?;
+			let config_state = reader.read_u8()? & 0xF;
+			let connected_device_mode = reader.read_u8()?;
+			reader.skip(1)?;
+
+			Ok(CxlPhysicalPortInfo {
+				port_id,
+				config_state,
+				connected_device_mode,
+				connected_device_type: reader.read_u8()?,
+				supported_cxl_modes: reader.read_u8()?,
+				max_link_width: reader.read_u8()? & 0x3F,
+				negotiated_link_width: reader.read_u8()? & 0x3F,
+				supported_link_speeds: reader.read_u8()? & 0x3F,
+				max_link_speed: reader.read_u8()? & 0x3F,
+				current_link_speed: reader.read_u8()? & 0x3F,
+				ltssm_state: reader.read_u8()?,
+				first_negotiated_lane: reader.read_u8()?,
+				link_state_flags: reader.read_u16()?,
+				supported_ld_count: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlGetPhysicalPortStateResponse - Get Physical Port State responce
+	 * @ports: state of requested ports
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetPhysicalPortStateResponse {
+		pub ports: Vec<CxlPhysicalPortInfo>,
+	}
+
+	impl CxlGetPhysicalPortStateResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.ports.len() as u8);
+			writer.reserve(3);
+
+			for port in &self.ports {
+				port.write(&mut writer);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetPhysicalPortStateResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let num_ports = reader.read_u8()?;
+			reader.skip(3)?;
+
+			let mut ports = Vec::new();
+			for _ in 0..num_ports {
+				ports.push(CxlPhysicalPortInfo::read(&mut reader)?);
+			}
+
+			Ok(CxlGetPhysicalPortStateResponse {
+				ports,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlGetVcsInfoRequest - Get Virtual CXL Switch Info request
+	 * @start_vppb: index of the first requested vPPB
+	 * @vppb_list_limit: maximal number of vPPBs per VCS
+	 * @vcs_ids: IDs of requested VCSs
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetVcsInfoRequest {
+		pub start_vppb: u8,
+		pub vppb_list_limit: u8,
+		pub vcs_ids: Vec<u8>,
+	}
+
+	impl CxlGetVcsInfoRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.start_vppb);
+			writer.write_u8(self.vppb_list_limit);
+			writer.write_u8(self.vcs_ids.len() as u8);
+			writer.write_bytes(&self.vcs_ids);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGetVcsInfoRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let start_vppb = reader.read_u8()?;
+			let vppb_list_limit = reader.read_u8()?;
+			let num_vcs = reader.read_u8()?;
+
+			Ok(CxlGetVcsInfoRequest {
+				start_vppb,
+				vppb_list_limit,
+				vcs_ids: reader.read_bytes(num_vcs as usize)?.to_vec(),
+			})
+		}
+	}
+
+	/*
+	 * struct CxlVppbInfo - binding of vPPB
+	 * @binding_status: binding status
+	 * @bound_port: ID of bound physical port
+	 * @bound_ld: ID of bound LD
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlVppbInfo {
+		pub binding_status: u8,
+		pub bound_port: u8,
+		pub bound_ld: u8,
+	}
+
+	/*
+	 * struct CxlVcsInfo - state of Virtual CXL Switch
+	 * @vcs_id: VCS ID
+	 * @state: VCS state (0 - disabled, 1 - enabled)
+	 * @usp_id: ID of upstream port
+	 * @num_vppbs: total number of vPPBs of the VCS
+	 * @vppbs: returned vPPBs
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlVcsInfo {
+		pub vcs_id: u8,
+		pub state: u8,
+		pub usp_id: u8,
+		pub num_vppbs: u8,
+		pub vppbs: Vec<CxlVppbInfo>,
+	}
+
+	/*
+	 * struct CxlGetVcsInfoResponse - Get Virtual CXL Switch Info responce
+	 * @vcs: state of requested VCSs
+	 *
+	 * Number of returned vPPBs is not a part of FM API payload,
+	 * it is restored from the request (vPPB list limit).
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGetVcsInfoResponse {
+		pub vcs: Vec<CxlVcsInfo>,
+	}
+
+	impl CxlGetVcsInfoResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.vcs.len() as u8);
+			writer.reserve(3);
+
+			for vcs in &self.vcs {
+				writer.write_u8(vcs.vcs_id);
+				writer.write_u8(vcs.state);
+				writer.write_u8(vcs.usp_id);
+				writer.write_u8(vcs.num_vppbs);
+
+				for vppb in &vcs.vppbs {
+					writer.write_u8(vppb.binding_status);
+					writer.write_u8(vppb.bound_port);
+					writer.write_u8(vppb.bound_ld);
+					writer.reserve(1);
+				}
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(request: &CxlGetVcsInfoRequest,
+				  data: &[u8]) -> Result<CxlGetVcsInfoResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let num_vcs = reader.read_u8()?;
+			reader.skip(3)?;
+
+			let mut vcs = Vec::new();
+			for _ in 0..num_vcs {
+				let vcs_id = reader.read_u8()?;
+				let state = reader.read_u8()?;
+				let usp_id = reader.read_u8()?;
+				let num_vppbs = reader.read_u8()?;
+				let returned = std::cmp::min(num_vppbs.saturating_sub(request.start_vppb),
+							     request.vppb_list_limit);
+
+				let mut vppbs = Vec::new();
+				for _ in 0..returned {
+					let binding_status = reader.read_u8()?;
+					let bound_port = reader.read_u8()?;
+					let bound_ld = reader.read_u8()?;
+					reader.skip(1)?;
+
+					vppbs.push(CxlVppbInfo {
+						binding_status,
+						bound_port,
+						bound_ld,
+					});
+				}
+
+				vcs.push(CxlVcsInfo {
+					vcs_id,
+					state,
+					usp_id,
+					num_vppbs,
+					vppbs,
+				});
+			}
+
+			Ok(CxlGetVcsInfoResponse {
+				vcs,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlTunnelRequest - Tunnel Management Command request
+	 * @port_or_ld: ID of egress port (or LD for MLD)
+	 * @message: tunnelled CCI message
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlTunnelRequest {
+		pub port_or_ld: u8,
+		pub message: Vec<u8>,
+	}
+
+	impl CxlTunnelRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.port_or_ld);
+			writer.reserve(1);
+			writer.write_u16(self.message.len() as u16);
+			writer.write_bytes(&self.message);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlTunnelRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let port_or_ld = reader.read_u8()?;
+			reader.skip(1)?;
+			let size = reader.read_u16()?;
+
+			Ok(CxlTunnelRequest {
+				port_or_ld,
+				message: reader.read_bytes(size as usize)?.to_vec(),
+			})
+		}
+	}
+
+	/*
+	 * struct CxlTunnelResponse - Tunnel Management Command responce
+	 * @message: responce CCI message
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlTunnelResponse {
+		pub message: Vec<u8>,
+	}
+
+	impl CxlTunnelResponse {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u16(self.message.len() as u16);
+			writer.reserve(2);
+			writer.write_bytes(&self.message);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlTunnelResponse, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let size = reader.read_u16()?;
+			reader.skip(2)?;
+
+			Ok(CxlTunnelResponse {
+				message: reader.read_bytes(size as usize)?.to_vec(),
+			})
+		}
+	}
+
+	/*
+	 * Unbind vPPB options
+	 */
+	pub const CXL_UNBIND_WAIT: u8			= 0x0;
+	pub const CXL_UNBIND_MANAGED_HOT_REMOVE: u8	= 0x1;
+	pub const CXL_UNBIND_SURPRISE_HOT_REMOVE: u8	= 0x2;
+
+	/*
+	 * Get human-readable name of unbind option
+	 */
+	pub fn unbind_option_name(option: u8) -> &'static str {
+		match option {
+			CXL_UNBIND_WAIT => "wait for port link down",
+			CXL_UNBIND_MANAGED_HOT_REMOVE => "managed hot-remove",
+			CXL_UNBIND_SURPRISE_HOT_REMOVE => "surprise hot-remove",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Get unbind option by its name
+	 */
+	pub fn unbind_option_by_name(name: &str) -> Option<u8> {
+		match name {
+			"wait" => Some(CXL_UNBIND_WAIT),
+			"managed" => Some(CXL_UNBIND_MANAGED_HOT_REMOVE),
+			"surprise" => Some(CXL_UNBIND_SURPRISE_HOT_REMOVE),
+			_ => None,
+		}
+	}
+
+	/*
+	 * LD ID used to bind the whole physical port
+	 */
+	pub const CXL_BIND_PORT_LD_ID: u16 = 0xFFFF;
+
+	/*
+	 * struct CxlBindVppbRequest - Bind vPPB request
+	 * @vcs_id: VCS ID
+	 * @vppb_id: vPPB index inside the VCS
+	 * @port_id: physical port ID
+	 * @ld_id: LD ID (0xFFFF binds the whole port)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlBindVppbRequest {
+		pub vcs_id: u8,
+		pub vppb_id: u8,
+		pub port_id: u8,
+		pub ld_id: u16,
+	}
+
+	impl CxlBindVppbRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.vcs_id);
+			writer.write_u8(self.vppb_id);
+			writer.write_u8(self.port_id);
+			writer.reserve(1);
+			writer.write_u16(self.ld_id);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlBindVppbRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let vcs_id = reader.read_u8()?;
+			let vppb_id = reader.read_u8()?;
+			let port_id = reader.read_u8()?;
+			reader.skip(1)?;
+
+			Ok(CxlBindVppbRequest {
+				vcs_id,
+				vppb_id,
+				port_id,
+				ld_id: reader.read_u16()?,
+			})
+		}
+	}
+
+	/*
+	 * struct CxlUnbindVppbRequest - Unbind vPPB request
+	 * @vcs_id: VCS ID
+	 * @vppb_id: vPPB index inside the VCS
+	 * @option: unbind option (wait, managed or surprise hot-remove)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlUnbindVppbRequest {
+		pub vcs_id: u8,
+		pub vppb_id: u8,
+		pub option: u8,
+	}
+
+	impl CxlUnbindVppbRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.vcs_id);
+			writer.write_u8(self.vppb_id);
+			writer.write_u8(self.option & 0xF);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlUnbindVppbRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlUnbindVppbRequest {
+				vcs_id: reader.read_u8()?,
+				vppb_id: reader.read_u8()?,
+				option: reader.read_u8()? & 0xF,
+			})
+		}
+	}
+
+	/*
+	 * AER error: bits 4:0 are bit position of the error in AER
+	 * Correctable or Uncorrectable Error Status register,
+	 * bit 31 is set for uncorrectable error
+	 */
+	pub const CXL_AER_ERROR_STATUS_BIT_MASK: u32	= 0x1F;
+	pub const CXL_AER_ERROR_UNCORRECTABLE: u32	= 0x80000000;
+
+	/*
+	 * Size of TLP header log of AER event
+	 */
+	pub const CXL_AER_HEADER_SIZE: usize = 32;
+
+	/*
+	 * Describe AER error
+	 */
+	pub fn aer_error_name(aer_error: u32) -> String {
+		let severity = match aer_error & CXL_AER_ERROR_UNCORRECTABLE {
+			0 => "correctable",
+			_ => "uncorrectable",
+		};
+
+		format!("{} error (status bit {})", severity,
+			aer_error & CXL_AER_ERROR_STATUS_BIT_MASK)
+	}
+
+	/*
+	 * Parse AER error, reserved bits have to be clear
+	 */
+	pub fn parse_aer_error(value: &str) -> Result<u32, CxlFmError> {
+		let aer_error = parse_number::<u32>(value)?;
+
+		if aer_error & !(CXL_AER_ERROR_STATUS_BIT_MASK | CXL_AER_ERROR_UNCORRECTABLE) != 0 {
+			return Err(CxlFmError::InvalidInput(format!("invalid AER error type: {}", value)));
+		}
+
+		Ok(aer_error)
+	}
+
+	/*
+	 * Parse TLP header log from hex string (0x prefix is optional),
+	 * short header is padded by zeros
+	 */
+	pub fn parse_aer_header(value: &str) -> Result<[u8; CXL_AER_HEADER_SIZE], CxlFmError> {
+		let hex = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+		let error = || CxlFmError::InvalidInput(format!("invalid AER header: {}", value));
+
+		if hex.len() > CXL_AER_HEADER_SIZE * 2 || !hex.len().is_multiple_of(2) {
+			return Err(error());
+		}
+
+		let mut header = [0; CXL_AER_HEADER_SIZE];
+		for (i, byte) in header.iter_mut().take(hex.len() / 2).enumerate() {
+			*byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| error())?;
+		}
+
+		Ok(header)
+	}
+
+	/*
+	 * struct CxlGenerateAerRequest - Generate AER Event request
+	 * @vcs_id: VCS ID
+	 * @vppb_id: vPPB index inside the VCS
+	 * @aer_error: error type and severity
+	 * @header: TLP header log
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlGenerateAerRequest {
+		pub vcs_id: u8,
+		pub vppb_id: u8,
+		pub aer_error: u32,
+		pub header: [u8; CXL_AER_HEADER_SIZE],
+	}
+
+	impl CxlGenerateAerRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.vcs_id);
+			writer.write_u8(self.vppb_id);
+			writer.reserve(2);
+			writer.write_u32(self.aer_error);
+			writer.write_bytes(&self.header);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlGenerateAerRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			let vcs_id = reader.read_u8()?;
+			let vppb_id = reader.read_u8()?;
+			reader.skip(2)?;
+
+			Ok(CxlGenerateAerRequest {
+				vcs_id,
+				vppb_id,
+				aer_error: reader.read_u32()?,
+				header: reader.read_bytes(CXL_AER_HEADER_SIZE)?.try_into().unwrap(),
+			})
+		}
+	}
+
+	/*
+	 * Physical port control operations
+	 */
+	pub const CXL_PORT_CONTROL_ASSERT_PERST: u8	= 0x0;
+	pub const CXL_PORT_CONTROL_DEASSERT_PERST: u8	= 0x1;
+	pub const CXL_PORT_CONTROL_RESET_PPB: u8	= 0x2;
+
+	/*
+	 * Link state flag of the port with asserted PERST#
+	 */
+	pub const CXL_PORT_LINK_PERST: u16		= 0x2;
+
+	/*
+	 * Get human-readable name of port control operation
+	 */
+	pub fn port_control_name(operation: u8) -> &'static str {
+		match operation {
+			CXL_PORT_CONTROL_ASSERT_PERST => "assert PERST",
+			CXL_PORT_CONTROL_DEASSERT_PERST => "deassert PERST",
+			CXL_PORT_CONTROL_RESET_PPB => "reset PPB",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Get port control operation by its name
+	 */
+	pub fn port_control_by_name(name: &str) -> Option<u8> {
+		match name {
+			"assert-perst" => Some(CXL_PORT_CONTROL_ASSERT_PERST),
+			"deassert-perst" => Some(CXL_PORT_CONTROL_DEASSERT_PERST),
+			"reset-ppb" => Some(CXL_PORT_CONTROL_RESET_PPB),
+			_ => None,
+		}
+	}
+
+	/*
+	 * struct CxlPhysicalPortControlRequest - Physical Port Control request
+	 * @ppb_id: physical PPB ID
+	 * @operation: port opcode (assert/deassert PERST, reset PPB)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlPhysicalPortControlRequest {
+		pub ppb_id: u8,
+		pub operation: u8,
+	}
+
+	impl CxlPhysicalPortControlRequest {
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u8(self.ppb_id);
+			writer.write_u8(self.operation);
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<CxlPhysicalPortControlRequest, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+
+			Ok(CxlPhysicalPortControlRequest {
+				ppb_id: reader.read_u8()?,
+				operation: reader.read_u8()?,
+			})
+		}
+	}
+
+	/*
+	 * Slot operations of emulated switch: device is inserted
+	 * into the slot of downstream port or pulled out of it,
+	 * the link of the slot fails or recovers
+	 */
+	pub const CXL_SLOT_INSERT: u8			= 0x0;
+	pub const CXL_SLOT_REMOVE: u8			= 0x1;
+	pub const CXL_SLOT_LINK_DOWN: u8		= 0x2;
+	pub const CXL_SLOT_LINK_UP: u8			= 0x3;
+
+	/*
+	 * Get human-readable name of slot operation
+	 */
+	pub fn slot_operation_name(operation: u8) -> &'static str {
+		match operation {
+			CXL_SLOT_INSERT => "insert",
+			CXL_SLOT_REMOVE => "remove",
+			CXL_SLOT_LINK_DOWN => "link-down",
+			CXL_SLOT_LINK_UP => "link-up",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Get slot operation by its name
+	 */
+	pub fn slot_operation_by_name(name: &str) -> Option<u8> {
+		match name {
+			"insert" => Some(CXL_SLOT_INSERT),
+			"remove" => Some(CXL_SLOT_REMOVE),
+			"link-down" => Some(CXL_SLOT_LINK_DOWN),
+			"link-up" => Some(CXL_SLOT_LINK_UP),
+			_ => None,
+		}
+	}
+}
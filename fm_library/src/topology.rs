@@ -0,0 +1,541 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL fabric topology model.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod cxl_topology {
+	use crate::cxl_fm_lib::CxlFmError;
+	use crate::cxl_fm_api::CxlPayloadReader;
+	use crate::cxl_fm_api::CxlPayloadWriter;
+
+	/*
+	 * Types of fabric components
+	 */
+	pub const CXL_NODE_HOST: u8		= 0x0;
+	pub const CXL_NODE_SWITCH: u8		= 0x1;
+	pub const CXL_NODE_PHYSICAL_PORT: u8	= 0x2;
+	pub const CXL_NODE_VCS: u8		= 0x3;
+	pub const CXL_NODE_VPPB: u8		= 0x4;
+	pub const CXL_NODE_SLD: u8		= 0x5;
+	pub const CXL_NODE_MLD: u8		= 0x6;
+	pub const CXL_NODE_LD: u8		= 0x7;
+	pub const CXL_NODE_MHD: u8		= 0x8;
+	pub const CXL_NODE_DCD: u8		= 0x9;
+
+	/*
+	 * Get human-readable name of component type
+	 */
+	pub fn node_kind_name(kind: u8) -> &'static str {
+		match kind {
+			CXL_NODE_HOST => "host",
+			CXL_NODE_SWITCH => "switch",
+			CXL_NODE_PHYSICAL_PORT => "port",
+			CXL_NODE_VCS => "vcs",
+			CXL_NODE_VPPB => "vppb",
+			CXL_NODE_SLD => "sld",
+			CXL_NODE_MLD => "mld",
+			CXL_NODE_LD => "ld",
+			CXL_NODE_MHD => "mhd",
+			CXL_NODE_DCD => "dcd",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Capabilities of CXL device listed by its "capabilities"
+	 * attribute. Device kind shows its structure only, so
+	 * multi-headed DCD is MHD with DCD capability.
+	 */
+	pub const CXL_CAPABILITY_MLD: &str	= "mld";
+	pub const CXL_CAPABILITY_MHD: &str	= "mhd";
+	pub const CXL_CAPABILITY_DCD: &str	= "dcd";
+
+	/*
+	 * Types of relationships between components:
+	 * CONTAINS - component is a part of another one
+	 *            (switch -> port, switch -> VCS, VCS -> vPPB, MLD -> LD),
+	 * LINK - physical connection (port -> device, host -> port) or
+	 *        component seen by the host (host -> vPPB of its VCS,
+	 *        host -> LD of its MHD head),
+	 * BINDING - vPPB is bound to physical port or LD.
+	 */
+	pub const CXL_EDGE_CONTAINS: u8		= 0x0;
+	pub const CXL_EDGE_LINK: u8		= 0x1;
+	pub const CXL_EDGE_BINDING: u8		= 0x2;
+
+	/*
+	 * Get human-readable name of relationship type
+	 */
+	pub fn edge_kind_name(kind: u8) -> &'static str {
+		match kind {
+			CXL_EDGE_CONTAINS => "contains",
+			CXL_EDGE_LINK => "link",
+			CXL_EDGE_BINDING => "binding",
+			_ => "unknown",
+		}
+	}
+
+	/*
+	 * Check that component type is a CXL device
+	 */
+	pub fn is_device_kind(kind: u8) -> bool {
+		matches!(kind, CXL_NODE_SLD | CXL_NODE_MLD | CXL_NODE_MHD | CXL_NODE_DCD)
+	}
+
+	fn json_escape(value: &str) -> String {
+		let mut out = String::new();
+
+		for c in value.chars() {
+			match c {
+				'"' => out.push_str("\\\""),
+				'\\' => out.push_str("\\\\"),
+				'\n' => out.push_str("\\n"),
+				c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+				c => out.push(c),
+			}
+		}
+
+		out
+	}
+
+	fn dot_escape(value: &str) -> String {
+		value.replace('\\', "\\\\").replace('"', "\\\"")
+	}
+
+	/*
+	 * struct CxlTopologyNode - fabric component
+	 * @id: unique ID of the component in the topology
+	 * @kind: type of the component
+	 * @name: name of the component
+	 * @attributes: list of key=value properties (capabilities, state)
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlTopologyNode {
+		pub id: u32,
+		pub kind: u8,
+		pub name: String,
+		pub attributes: Vec<(String, String)>,
+	}
+
+	impl CxlTopologyNode {
+		/*
+		 * Get attribute value
+		 */
+		pub fn get(&self, key: &str) -> Option<&str> {
+			self.attributes.iter()
+				.find(|(k, _)| k == key)
+				.map(|(_, v)| v.as_str())
+		}
+
+		/*
+		 * Check that device has the capability
+		 */
+		pub fn has_capability(&self, capability: &str) -> bool {
+			self.get("capabilities")
+				.is_some_and(|list| list.split(',').any(|item| item == capability))
+		}
+	}
+
+	/*
+	 * struct CxlTopologyEdge - relationship between components
+	 * @from: ID of the source component
+	 * @to: ID of the destination component
+	 * @kind: type of the relationship
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlTopologyEdge {
+		pub from: u32,
+		pub to: u32,
+		pub kind: u8,
+	}
+
+	/*
+	 * struct FabricTopology - graph of the whole CXL fabric
+	 * @nodes: fabric components
+	 * @edges: relationships between components
+	 * @next_id: ID of the next added component
+	 */
+	#[derive(Debug, Clone, PartialEq, Default)]
+	pub struct FabricTopology {
+		pub nodes: Vec<CxlTopologyNode>,
+		pub edges: Vec<CxlTopologyEdge>,
+		next_id: u32,
+	}
+
+	impl FabricTopology {
+		pub fn new() -> FabricTopology {
+			FabricTopology {
+				nodes: Vec::new(),
+				edges: Vec::new(),
+				next_id: 0,
+			}
+		}
+
+		/*
+		 * Add component and get its ID
+		 */
+		pub fn add_node(&mut self, kind: u8, name: &str) -> u32 {
+			let id = self.next_id;
+
+			self.next_id += 1;
+			self.nodes.push(CxlTopologyNode {
+				id,
+				kind,
+				name: String::from(name),
+				attributes: Vec::new(),
+			});
+
+			id
+		}
+
+		/*
+		 * Remove component with all its relationships
+		 * and all contained components
+		 */
+		pub fn remove_node(&mut self, id: u32) {
+			for child in self.children(id, CXL_EDGE_CONTAINS) {
+				self.remove_node(child);
+			}
+
+			self.nodes.retain(|node| node.id != id);
+			self.edges.retain(|edge| edge.from != id && edge.to != id);
+		}
+
+		pub fn node(&self, id: u32) -> Option<&CxlTopologyNode> {
+			self.nodes.iter().find(|node| node.id == id)
+		}
+
+		pub fn node_mut(&mut self, id: u32) -> Option<&mut CxlTopologyNode> {
+			self.nodes.iter_mut().find(|node| node.id == id)
+		}
+
+		/*
+		 * Find component by type and name
+		 */
+		pub fn find(&self, kind: u8, name: &str) -> Option<&CxlTopologyNode> {
+			self.nodes.iter().find(|node| node.kind == kind && node.name == name)
+		}
+
+		/*
+		 * Get all components of the type
+		 */
+		pub fn nodes_of_kind(&self, kind: u8) -> Vec<&CxlTopologyNode> {
+			self.nodes.iter().filter(|node| node.kind == kind).collect()
+		}
+
+		/*
+		 * Set attribute of the component
+		 */
+		pub fn set_attr(&mut self, id: u32, key: &str, value: &str) {
+			if let Some(node) = self.node_mut(id) {
+				match node.attributes.iter_mut().find(|(k, _)| k == key) {
+					Some((_, v)) => *v = String::from(value),
+					None => node.attributes.push((String::from(key), String::from(value))),
+				}
+			}
+		}
+
+		/*
+		 * Add relationship between existing components
+		 */
+		pub fn add_edge(&mut self, from: u32, to: u32, kind: u8) -> Result<(), CxlFmError> {
+			if self.node(from).is_none() || self.node(to).is_none() {
+				return Err(CxlFmError::NotFound(format!("component {} or {}", from, to)));
+			}
+
+			let edge = CxlTopologyEdge {
+				from,
+				to,
+				kind,
+			};
+
+			if !self.edges.contains(&edge) {
+				self.edges.push(edge);
+			}
+
+			Ok(())
+		}
+
+		/*
+		 * Remove relationship between components
+		 */
+		pub fn remove_edge(&mut self, from: u32, to: u32, kind: u8) {
+			self.edges.retain(|edge| {
+				edge.from != from || edge.to != to || edge.kind != kind
+			});
+		}
+
+		/*
+		 * Get destination components of relationships of the type
+		 */
+		pub fn children(&self, id: u32, kind: u8) -> Vec<u32> {
+			self.edges.iter()
+				.filter(|edge| edge.from == id && edge.kind == kind)
+				.map(|edge| edge.to)
+				.collect()
+		}
+
+		/*
+		 * Get source component of relationship of the type
+		 */
+		pub fn parent(&self, id: u32, kind: u8) -> Option<u32> {
+			self.edges.iter()
+				.find(|edge| edge.to == id && edge.kind == kind)
+				.map(|edge| edge.from)
+		}
+
+		/*
+		 * Bind vPPB to physical port or LD
+		 * (vPPB can be bound to one target only)
+		 */
+		pub fn bind(&mut self, vppb: u32, target: u32) -> Result<(), CxlFmError> {
+			if !self.children(vppb, CXL_EDGE_BINDING).is_empty() {
+				return Err(CxlFmError::InvalidInput(format!("vPPB {} is already bound",
+									    vppb)));
+			}
+
+			self.add_edge(vppb, target, CXL_EDGE_BINDING)
+		}
+
+		/*
+		 * Unbind vPPB
+		 */
+		pub fn unbind(&mut self, vppb: u32) {
+			self.edges.retain(|edge| edge.from != vppb || edge.kind != CXL_EDGE_BINDING);
+		}
+
+		/*
+		 * Get all (vPPB, target) bindings
+		 */
+		pub fn bindings(&self) -> Vec<(u32, u32)> {
+			self.edges.iter()
+				.filter(|edge| edge.kind == CXL_EDGE_BINDING)
+				.map(|edge| (edge.from, edge.to))
+				.collect()
+		}
+
+		/*
+		 * Get components that are not contained in other ones
+		 */
+		pub fn roots(&self) -> Vec<u32> {
+			self.nodes.iter()
+				.filter(|node| self.parent(node.id, CXL_EDGE_CONTAINS).is_none())
+				.map(|node| node.id)
+				.collect()
+		}
+
+		/*
+		 * Get components shown under the component in the tree:
+		 * contained components and devices linked to ports
+		 */
+		fn tree_children(&self, id: u32) -> Vec<u32> {
+			let mut children = self.children(id, CXL_EDGE_CONTAINS);
+
+			children.extend(self.children(id, CXL_EDGE_LINK)
+				.into_iter()
+				.filter(|child| self.node(*child)
+					.is_some_and(|node| is_device_kind(node.kind))));
+			children
+		}
+
+		fn write_tree(&self, id: u32, depth: usize,
+			      shown: &mut Vec<u32>, out: &mut String) {
+			let node = match self.node(id) {
+				Some(node) => node,
+				None => return,
+			};
+			let attributes: Vec<String> = node.attributes.iter()
+				.map(|(key, value)| format!("{}={}", key, value))
+				.collect();
+
+			out.push_str(&"    ".repeat(depth));
+			out.push_str(&format!("{} {}", node_kind_name(node.kind), node.name));
+
+			if !attributes.is_empty() {
+				out.push_str(&format!(" ({})", attributes.join(", ")));
+			}
+
+			for target in self.children(id, CXL_EDGE_BINDING) {
+				if let Some(target) = self.node(target) {
+					out.push_str(&format!(" -> {}", target.name));
+				}
+			}
+
+			if shown.contains(&id) {
+				out.push_str(" [see above]\n");
+				return;
+			}
+
+			out.push('\n');
+			shown.push(id);
+
+			for child in self.tree_children(id) {
+				self.write_tree(child, depth + 1, shown, out);
+			}
+		}
+
+		/*
+		 * Render topology as indented tree of components
+		 */
+		pub fn to_tree(&self) -> String {
+			let mut out = String::new();
+			let mut shown = Vec::new();
+
+			for root in self.roots() {
+				let linked = self.parent(root, CXL_EDGE_LINK).is_some();
+
+				if self.node(root).is_some_and(|node| is_device_kind(node.kind)) && linked {
+					continue;
+				}
+
+				self.write_tree(root, 0, &mut shown, &mut out);
+			}
+
+			out
+		}
+
+		/*
+		 * Render topology in Graphviz DOT format
+		 */
+		pub fn to_dot(&self) -> String {
+			let mut out = String::from("digraph fabric {\n\trankdir=LR;\n");
+
+			for node in &self.nodes {
+				let shape = match node.kind {
+					CXL_NODE_HOST => "house",
+					CXL_NODE_SWITCH | CXL_NODE_VCS => "box",
+					CXL_NODE_PHYSICAL_PORT | CXL_NODE_VPPB => "ellipse",
+					CXL_NODE_LD => "component",
+					_ => "box3d",
+				};
+
+				out.push_str(&format!("\tn{} [label=\"{}\\n{}\", shape={}];\n",
+						      node.id, dot_escape(&node.name),
+						      node_kind_name(node.kind), shape));
+			}
+
+			for edge in &self.edges {
+				let style = match edge.kind {
+					CXL_EDGE_CONTAINS => "style=solid, arrowhead=none",
+					CXL_EDGE_LINK => "style=bold",
+					_ => "style=dashed, color=blue",
+				};
+
+				out.push_str(&format!("\tn{} -> n{} [label=\"{}\", {}];\n",
+						      edge.from, edge.to,
+						      edge_kind_name(edge.kind), style));
+			}
+
+			out.push_str("}\n");
+			out
+		}
+
+		/*
+		 * Render topology in JSON format
+		 */
+		pub fn to_json(&self) -> String {
+			let nodes: Vec<String> = self.nodes.iter()
+				.map(|node| {
+					let attributes: Vec<String> = node.attributes.iter()
+						.map(|(key, value)| format!("\"{}\": \"{}\"",
+									    json_escape(key),
+									    json_escape(value)))
+						.collect();
+
+					format!("    {{\"id\": {}, \"kind\": \"{}\", \"name\": \"{}\", \"attributes\": {{{}}}}}",
+						node.id, node_kind_name(node.kind),
+						json_escape(&node.name), attributes.join(", "))
+				})
+				.collect();
+			let edges: Vec<String> = self.edges.iter()
+				.map(|edge| format!("    {{\"from\": {}, \"to\": {}, \"kind\": \"{}\"}}",
+						    edge.from, edge.to, edge_kind_name(edge.kind)))
+				.collect();
+
+			format!("{{\n  \"nodes\": [\n{}\n  ],\n  \"edges\": [\n{}\n  ]\n}}\n",
+				nodes.join(",\n"), edges.join(",\n"))
+		}
+
+		pub fn to_bytes(&self) -> Vec<u8> {
+			let mut writer = CxlPayloadWriter::new();
+
+			writer.write_u32(self.next_id);
+			writer.write_u32(self.nodes.len() as u32);
+
+			for node in &self.nodes {
+				writer.write_u32(node.id);
+				writer.write_u8(node.kind);
+				writer.write_string(&node.name);
+				writer.write_u16(node.attributes.len() as u16);
+
+				for (key, value) in &node.attributes {
+					writer.write_string(key);
+					writer.write_string(value);
+				}
+			}
+
+			writer.write_u32(self.edges.len() as u32);
+
+			for edge in &self.edges {
+				writer.write_u32(edge.from);
+				writer.write_u32(edge.to);
+				writer.write_u8(edge.kind);
+			}
+
+			writer.into_bytes()
+		}
+
+		pub fn from_bytes(data: &[u8]) -> Result<FabricTopology, CxlFmError> {
+			let mut reader = CxlPayloadReader::new(data);
+			let mut topology = FabricTopology::new();
+
+			topology.next_id = reader.read_u32()?;
+
+			for _ in 0..reader.read_u32()? {
+				let id = reader.read_u32()?;
+				let kind = reader.read_u8()?;
+				let name = reader.read_string()?;
+
+				let mut attributes = Vec::new();
+				for _ in 0..reader.read_u16()? {
+					attributes.push((reader.read_string()?, reader.read_string()?));
+				}
+
+				topology.nodes.push(CxlTopologyNode {
+					id,
+					kind,
+					name,
+					attributes,
+				});
+			}
+
+			for _ in 0..reader.read_u32()? {
+				let from = reader.read_u32()?;
+				let to = reader.read_u32()?;
+				let kind = reader.read_u8()?;
+
+				topology.add_edge(from, to, kind)
+					.map_err(|_| CxlFmError::Protocol(format!("dangling edge {} -> {}",
+										 from, to)))?;
+			}
+
+			Ok(topology)
+		}
+	}
+}
@@ -0,0 +1,43 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM orchestrator implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub mod cxl_orchestrator_context {
+	use crate::cxl_fm_instance::CxlFmInstances;
+	use crate::cxl_fm_registry::CxlFmRegistry;
+
+	/*
+	 * struct CxlOrchestratorContext - state of orchestrator
+	 * @instances: FM processes started by orchestrator
+	 * @registry: FMs registered in orchestrator
+	 */
+	pub struct CxlOrchestratorContext {
+		pub instances: CxlFmInstances,
+		pub registry: CxlFmRegistry,
+	}
+
+	impl CxlOrchestratorContext {
+		pub fn new(instances: CxlFmInstances) -> CxlOrchestratorContext {
+			CxlOrchestratorContext {
+				instances,
+				registry: CxlFmRegistry::new(),
+			}
+		}
+	}
+}
@@ -0,0 +1,612 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM orchestrator implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub mod cxl_fm_instance {
+	use std::collections::BTreeMap;
+	use std::fs::{self, OpenOptions};
+	use std::io;
+	use std::net::{SocketAddr, TcpStream};
+	use std::path::{Path, PathBuf};
+	use std::process::{Child, Command, ExitStatus, Stdio};
+	use std::sync::{Arc, Mutex, MutexGuard};
+	use std::thread;
+	use std::time::{Duration, Instant};
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_request;
+	use fm_library::cxl_fm_lib::parse_number;
+	use fm_library::cxl_fm_lib::CXL_FM_GET_FM_INFO_COMMAND;
+	use fm_library::cxl_fm_lib::CXL_FM_NAME_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ADDRESS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_STATE_DIR_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_RECONCILE_INTERVAL_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_EVENT_POLL_INTERVAL_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_REMEDIATE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_CONFIG_ARG;
+	use fm_library::cxl_fm_orchestrator::CxlFmInstanceStatus;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_INSTANCE_STOPPED;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_INSTANCE_RUNNING;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_INSTANCE_CRASHED;
+	use crate::cxl_orchestrator_context::CxlOrchestratorContext;
+
+	/*
+	 * Time of graceful shutdown before the process
+	 * is killed (seconds)
+	 */
+	pub const CXL_FM_INSTANCE_STOP_TIMEOUT: u64 = 5;
+
+	/*
+	 * Time to wait for FM API of started instance (seconds)
+	 */
+	pub const CXL_FM_INSTANCE_START_TIMEOUT: u64 = 3;
+
+	/*
+	 * Delay before restart of crashed instance (seconds).
+	 * The delay is doubled after every crash and it is
+	 * reset if the instance worked long enough.
+	 */
+	pub const CXL_FM_INSTANCE_RESTART_DELAY_MIN: u64 = 1;
+	pub const CXL_FM_INSTANCE_RESTART_DELAY_MAX: u64 = 60;
+	pub const CXL_FM_INSTANCE_STABLE_TIME: u64 = 60;
+
+	/*
+	 * Interval of crash detection (seconds)
+	 */
+	pub const CXL_FM_INSTANCE_SUPERVISE_INTERVAL: u64 = 1;
+
+	/*
+	 * Interval of process state checks (milliseconds)
+	 */
+	const CXL_FM_INSTANCE_POLL_INTERVAL: u64 = 100;
+
+	/*
+	 * struct CxlFmInstanceConfig - configuration of FM instance
+	 * @name: instance name
+	 * @address: IP address + port of FM API
+	 * @state_dir: state store directory (instance directory by default)
+	 * @reconcile_interval: interval of reconciliation passes
+	 * @event_poll_interval: interval of event records collection
+	 * @is_remediate: does reconciliation remediate drift?
+	 * @config: configuration file of fm_daemon
+	 */
+	#[derive(Debug, Clone, PartialEq)]
+	pub struct CxlFmInstanceConfig {
+		pub name: String,
+		pub address: SocketAddr,
+		pub state_dir: Option<String>,
+		pub reconcile_interval: Option<u64>,
+		pub event_poll_interval: Option<u64>,
+		pub is_remediate: bool,
+		pub config: Option<String>,
+	}
+
+	impl CxlFmInstanceConfig {
+		pub fn from_request(request: &CxlFmRequest) -> Result<CxlFmInstanceConfig, CxlFmError> {
+			let name = instance_name(request)?;
+			let address = request.get(CXL_FM_ADDRESS_ARG)
+				.ok_or(CxlFmError::InvalidInput(String::from("address is required")))?;
+			let address = address.parse::<SocketAddr>()
+				.map_err(|_| CxlFmError::InvalidInput(format!("invalid address {}", address)))?;
+			let interval = |key: &str| request.get(key)
+				.map(parse_number::<u64>)
+				.transpose();
+
+			Ok(CxlFmInstanceConfig {
+				name: String::from(name),
+				address,
+				state_dir: request.get(CXL_FM_STATE_DIR_ARG).map(String::from),
+				reconcile_interval: interval(CXL_FM_RECONCILE_INTERVAL_ARG)?,
+				event_poll_interval: interval(CXL_FM_EVENT_POLL_INTERVAL_ARG)?,
+				is_remediate: request.get(CXL_FM_REMEDIATE_ARG).is_some_and(|value| value != "0"),
+				config: request.get(CXL_FM_CONFIG_ARG).map(String::from),
+			})
+		}
+
+		/*
+		 * Command line of fm_daemon running in foreground,
+		 * the instance registers in orchestrator by its name
+		 */
+		fn daemon_args(&self, directory: &Path, orchestrator: &str) -> Vec<String> {
+			let state_dir = match &self.state_dir {
+				Some(state_dir) => PathBuf::from(state_dir),
+				None => directory.join(&self.name),
+			};
+			let mut args = vec![
+				String::from("--foreground"),
+				String::from("--ip"), self.address.ip().to_string(),
+				String::from("--port"), self.address.port().to_string(),
+				String::from("--state-dir"), state_dir.to_string_lossy().into_owned(),
+				String::from("--orchestrator"), String::from(orchestrator),
+				String::from("--fm-id"), self.name.clone(),
+			];
+
+			if let Some(interval) = self.reconcile_interval {
+				args.extend([String::from("--reconcile-interval"), interval.to_string()]);
+			}
+
+			if let Some(interval) = self.event_poll_interval {
+				args.extend([String::from("--event-poll-interval"), interval.to_string()]);
+			}
+
+			if self.is_remediate {
+				args.push(String::from("--remediate"));
+			}
+
+			if let Some(config) = &self.config {
+				args.extend([String::from("--config"), config.clone()]);
+			}
+
+			args
+		}
+	}
+
+	/*
+	 * Get instance name of the request. The name is a part
+	 * of file names, so only letters, digits, '-' and '_'
+	 * are accepted.
+	 */
+	pub fn instance_name(request: &CxlFmRequest) -> Result<&str, CxlFmError> {
+		let name = request.get(CXL_FM_NAME_ARG)
+			.ok_or(CxlFmError::InvalidInput(String::from("name is required")))?;
+
+		if name.is_empty() ||
+		   !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+			return Err(CxlFmError::InvalidInput(format!("invalid instance name {}", name)));
+		}
+
+		Ok(name)
+	}
+
+	/*
+	 * struct CxlFmInstance - FM process managed by orchestrator
+	 * @config: instance configuration
+	 * @child: running process
+	 * @state: instance state
+	 * @started: time of the process start
+	 * @restarts: number of restarts after crash
+	 * @restart_delay: current delay before restart (seconds)
+	 * @restart_at: time of the next restart attempt
+	 * @last_exit: exit status of the last process
+	 * @is_busy: start or stop of the process is in progress
+	 */
+	struct CxlFmInstance {
+		config: CxlFmInstanceConfig,
+		child: Option<Child>,
+		state: u8,
+		started: Instant,
+		restarts: u32,
+		restart_delay: u64,
+		restart_at: Instant,
+		last_exit: String,
+		is_busy: bool,
+	}
+
+	impl CxlFmInstance {
+		fn new(config: CxlFmInstanceConfig) -> CxlFmInstance {
+			CxlFmInstance {
+				config,
+				child: None,
+				state: CXL_FM_INSTANCE_STOPPED,
+				started: Instant::now(),
+				restarts: 0,
+				restart_delay: 0,
+				restart_at: Instant::now(),
+				last_exit: String::new(),
+				is_busy: false,
+			}
+		}
+
+		fn status(&self) -> CxlFmInstanceStatus {
+			let is_running = self.state == CXL_FM_INSTANCE_RUNNING;
+
+			CxlFmInstanceStatus {
+				name: self.config.name.clone(),
+				address: self.config.address.to_string(),
+				state: self.state,
+				pid: self.child.as_ref().map(|child| child.id()).unwrap_or(0),
+				restarts: self.restarts,
+				uptime: match is_running {
+					true => self.started.elapsed().as_secs(),
+					false => 0,
+				},
+				restart_delay: match self.state {
+					CXL_FM_INSTANCE_CRASHED => {
+						self.restart_at.saturating_duration_since(Instant::now()).as_secs()
+					},
+					_ => 0,
+				},
+				last_exit: self.last_exit.clone(),
+			}
+		}
+
+		fn is_active(&self) -> bool {
+			self.state != CXL_FM_INSTANCE_STOPPED
+		}
+
+		/*
+		 * Delay restart after crash, the delay grows
+		 * while the instance keeps crashing
+		 */
+		fn schedule_restart(&mut self, is_stable: bool) {
+			self.restart_delay = match is_stable || self.restart_delay == 0 {
+				true => CXL_FM_INSTANCE_RESTART_DELAY_MIN,
+				false => (self.restart_delay * 2).min(CXL_FM_INSTANCE_RESTART_DELAY_MAX),
+			};
+			self.restart_at = Instant::now() + Duration::from_secs(self.restart_delay);
+			self.state = CXL_FM_INSTANCE_CRASHED;
+		}
+	}
+
+	/*
+	 * Stop the process: ask it to terminate and kill it
+	 * if it is still alive after the timeout.
+	 * Returns exit status of the process.
+	 */
+	fn terminate(mut child: Child, timeout: u64) -> String {
+		let deadline = Instant::now() + Duration::from_secs(timeout);
+
+		if unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) } != 0 {
+			let error = io::Error::last_os_error();
+
+			let _ = child.kill();
+
+			return match child.wait() {
+				Ok(status) => format!("{} (SIGTERM failed: {})", status, error),
+				Err(_) => format!("SIGTERM failed: {}", error),
+			};
+		}
+
+		loop {
+			if let Ok(Some(status)) = child.try_wait() {
+				return status.to_string();
+			}
+
+			if Instant::now() >= deadline {
+				break;
+			}
+
+			thread::sleep(Duration::from_millis(CXL_FM_INSTANCE_POLL_INTERVAL));
+		}
+
+		let _ = child.kill();
+		let _ = child.wait();
+		format!("killed after {}s timeout", timeout)
+	}
+
+	/*
+	 * struct CxlFmInstances - FM instances of orchestrator
+	 * @daemon: path of fm_daemon executable
+	 * @directory: directory of instance state and log files
+	 * @orchestrator: IP address + port of orchestrator
+	 * @instances: instances by name
+	 */
+	pub struct CxlFmInstances {
+		daemon: PathBuf,
+		directory: PathBuf,
+		orchestrator: String,
+		instances: Mutex<BTreeMap<String, CxlFmInstance>>,
+	}
+
+	impl CxlFmInstances {
+		pub fn new(daemon: &str, directory: &str, orchestrator: &str) -> CxlFmInstances {
+			CxlFmInstances {
+				daemon: PathBuf::from(daemon),
+				directory: PathBuf::from(directory),
+				orchestrator: String::from(orchestrator),
+				instances: Mutex::new(BTreeMap::new()),
+			}
+		}
+
+		/*
+		 * Start fm_daemon process of the instance,
+		 * its output goes to the instance log file
+		 */
+		fn spawn(&self, config: &CxlFmInstanceConfig) -> Result<Child, CxlFmError> {
+			let io_error = |e: std::io::Error| CxlFmError::Io(e.to_string());
+			let log_path = self.directory.join(format!("{}.log", config.name));
+
+			fs::create_dir_all(&self.directory).map_err(io_error)?;
+
+			let log = OpenOptions::new()
+				.create(true)
+				.append(true)
+				.open(&log_path)
+				.map_err(io_error)?;
+
+			Command::new(&self.daemon)
+				.args(config.daemon_args(&self.directory, &self.orchestrator))
+				.stdin(Stdio::null())
+				.stdout(log.try_clone().map_err(io_error)?)
+				.stderr(log)
+				.spawn()
+				.map_err(|e| CxlFmError::Io(format!("cannot start {}: {}",
+								    self.daemon.display(), e)))
+		}
+
+		/*
+		 * Wait until the started process answers FM API requests.
+		 * The process that is still starting after the timeout
+		 * is considered running. Returns exit status of the process
+		 * that exited at start.
+		 */
+		fn wait_ready(config: &CxlFmInstanceConfig, child: &mut Child) -> Result<(), ExitStatus> {
+			let deadline = Instant::now() + Duration::from_secs(CXL_FM_INSTANCE_START_TIMEOUT);
+			let env = CxlFmOptions {
+				ip_port: config.address.to_string(),
+				is_debug: false,
+				is_dry_run: false,
+				fm: None,
+			};
+			let request = CxlFmRequest::new(CXL_FM_GET_FM_INFO_COMMAND);
+
+			while Instant::now() < deadline {
+				if let Ok(Some(status)) = child.try_wait() {
+					return Err(status);
+				}
+
+				if let Ok(stream) = TcpStream::connect(config.address) {
+					if send_request(&stream, &request, &env).is_ok() {
+						break;
+					}
+				}
+
+				thread::sleep(Duration::from_millis(CXL_FM_INSTANCE_POLL_INTERVAL));
+			}
+
+			Ok(())
+		}
+
+		/*
+		 * Get instance whose start or stop is not in progress
+		 */
+		fn idle<'a>(instances: &'a mut BTreeMap<String, CxlFmInstance>,
+			    name: &str) -> Result<&'a mut CxlFmInstance, CxlFmError> {
+			let instance = instances.get_mut(name)
+				.ok_or(CxlFmError::NotFound(format!("instance {}", name)))?;
+
+			if instance.is_busy {
+				return Err(CxlFmError::InvalidInput(format!("{} is being started or stopped", name)));
+			}
+
+			Ok(instance)
+		}
+
+		/*
+		 * Wait for the started process of busy instance without
+		 * the lock of instances, so supervision and status requests
+		 * are not blocked, and give the process to the instance
+		 */
+		fn finish_start(&self, name: &str, mut child: Child) -> Result<CxlFmInstanceStatus, CxlFmError> {
+			let config = self.instances.lock().unwrap()[name].config.clone();
+			let result = Self::wait_ready(&config, &mut child);
+			let mut instances = self.instances.lock().unwrap();
+			let instance = instances.get_mut(name).unwrap();
+
+			instance.is_busy = false;
+
+			match result {
+				Ok(_) => {
+					instance.child = Some(child);
+					Ok(instance.status())
+				},
+				Err(status) => {
+					instance.state = CXL_FM_INSTANCE_STOPPED;
+					instance.last_exit = status.to_string();
+
+					Err(CxlFmError::Remote(format!("{} exited at start ({}), see {}",
+								       name, status,
+								       self.directory.join(format!("{}.log", name)).display())))
+				},
+			}
+		}
+
+		/*
+		 * Stop process of busy instance without the lock of instances.
+		 * Returns the lock with the stopped instance that is still busy.
+		 */
+		fn stop_process(&self, name: &str, child: Option<Child>,
+				timeout: u64) -> MutexGuard<'_, BTreeMap<String, CxlFmInstance>> {
+			let last_exit = child.map(|child| terminate(child, timeout));
+			let mut instances = self.instances.lock().unwrap();
+			let instance = instances.get_mut(name).unwrap();
+
+			if let Some(last_exit) = last_exit {
+				instance.last_exit = last_exit;
+			}
+
+			instance.state = CXL_FM_INSTANCE_STOPPED;
+			instances
+		}
+
+		/*
+		 * Start new instance or stopped instance
+		 * with new configuration
+		 */
+		pub fn start(&self, config: CxlFmInstanceConfig) -> Result<CxlFmInstanceStatus, CxlFmError> {
+			let mut instances = self.instances.lock().unwrap();
+
+			if let Some(instance) = instances.get(&config.name) {
+				if instance.is_active() || instance.is_busy {
+					return Err(CxlFmError::InvalidInput(format!("{} is already {}",
+										    config.name,
+										    match instance.state {
+											    CXL_FM_INSTANCE_CRASHED => "started and waits for restart",
+											    CXL_FM_INSTANCE_STOPPED => "being stopped",
+											    _ => "running",
+										    })));
+				}
+			}
+
+			if let Some(instance) = instances.values()
+				.find(|instance| (instance.is_active() || instance.is_busy) &&
+						 instance.config.name != config.name &&
+						 instance.config.address == config.address) {
+				return Err(CxlFmError::InvalidInput(format!("{} is used by {}",
+									    config.address,
+									    instance.config.name)));
+			}
+
+			let name = config.name.clone();
+			let child = self.spawn(&config)?;
+			let instance = instances.entry(name.clone())
+				.and_modify(|instance| instance.config = config.clone())
+				.or_insert_with(|| CxlFmInstance::new(config));
+
+			instance.state = CXL_FM_INSTANCE_RUNNING;
+			instance.started = Instant::now();
+			instance.restart_delay = 0;
+			instance.is_busy = true;
+
+			drop(instances);
+			self.finish_start(&name, child)
+		}
+
+		/*
+		 * Stop the instance, restart of crashed
+		 * instance is cancelled
+		 */
+		pub fn stop(&self, name: &str, timeout: u64) -> Result<CxlFmInstanceStatus, CxlFmError> {
+			let mut instances = self.instances.lock().unwrap();
+			let instance = Self::idle(&mut instances, name)?;
+
+			if !instance.is_active() {
+				return Err(CxlFmError::InvalidInput(format!("{} is not running", name)));
+			}
+
+			let child = instance.child.take();
+
+			instance.is_busy = true;
+			drop(instances);
+
+			let mut instances = self.stop_process(name, child, timeout);
+			let instance = instances.get_mut(name).unwrap();
+
+			instance.is_busy = false;
+			Ok(instance.status())
+		}
+
+		/*
+		 * Stop the instance if it is active and start it again
+		 * with the same configuration
+		 */
+		pub fn restart(&self, name: &str, timeout: u64) -> Result<CxlFmInstanceStatus, CxlFmError> {
+			let mut instances = self.instances.lock().unwrap();
+			let instance = Self::idle(&mut instances, name)?;
+			let child = instance.child.take();
+
+			instance.is_busy = true;
+			drop(instances);
+
+			let mut instances = self.stop_process(name, child, timeout);
+			let instance = instances.get_mut(name).unwrap();
+			let child = match self.spawn(&instance.config) {
+				Ok(child) => child,
+				Err(e) => {
+					instance.is_busy = false;
+					return Err(e);
+				}
+			};
+
+			instance.state = CXL_FM_INSTANCE_RUNNING;
+			instance.started = Instant::now();
+			instance.restart_delay = 0;
+
+			drop(instances);
+			self.finish_start(name, child)
+		}
+
+		/*
+		 * Get status of the instance or all instances
+		 */
+		pub fn status(&self, name: Option<&str>) -> Result<Vec<CxlFmInstanceStatus>, CxlFmError> {
+			let instances = self.instances.lock().unwrap();
+
+			match name {
+				Some(name) => instances.get(name)
+					.map(|instance| vec![instance.status()])
+					.ok_or(CxlFmError::NotFound(format!("instance {}", name))),
+				None => Ok(instances.values().map(|instance| instance.status()).collect()),
+			}
+		}
+
+		/*
+		 * Detect crashed processes and restart
+		 * the instances whose delay is over
+		 */
+		pub fn supervise(&self) {
+			let mut instances = self.instances.lock().unwrap();
+
+			for (name, instance) in instances.iter_mut() {
+				if instance.is_busy {
+					continue;
+				}
+
+				match instance.state {
+					CXL_FM_INSTANCE_RUNNING => {
+						let status = match instance.child.as_mut().map(|child| child.try_wait()) {
+							Some(Ok(Some(status))) => status,
+							_ => continue,
+						};
+						let is_stable = instance.started.elapsed() >=
+								Duration::from_secs(CXL_FM_INSTANCE_STABLE_TIME);
+
+						instance.child = None;
+						instance.last_exit = status.to_string();
+						instance.schedule_restart(is_stable);
+
+						println!("{} crashed ({}), restart in {}s",
+							 name, status, instance.restart_delay);
+					},
+					CXL_FM_INSTANCE_CRASHED if Instant::now() >= instance.restart_at => {
+						match self.spawn(&instance.config) {
+							Ok(child) => {
+								println!("{} is restarted, pid {}", name, child.id());
+
+								instance.child = Some(child);
+								instance.state = CXL_FM_INSTANCE_RUNNING;
+								instance.started = Instant::now();
+								instance.restarts += 1;
+							},
+							Err(e) => {
+								instance.last_exit = e.to_string();
+								instance.schedule_restart(false);
+
+								println!("Failed to restart {}: {}, retry in {}s",
+									 name, e, instance.restart_delay);
+							},
+						}
+					},
+					_ => {},
+				}
+			}
+		}
+	}
+
+	/*
+	 * Start periodic supervision of FM instances
+	 */
+	pub fn start_supervision(ctx: Arc<CxlOrchestratorContext>) {
+		thread::spawn(move || loop {
+			thread::sleep(Duration::from_secs(CXL_FM_INSTANCE_SUPERVISE_INTERVAL));
+			ctx.instances.supervise();
+		});
+	}
+}
@@ -0,0 +1,42 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM orchestrator implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub mod cxl_fm_listener {
+	use std::net::UdpSocket;
+	use std::sync::Arc;
+	use std::thread;
+	use fm_library::cxl_fm_announce::receive;
+	use crate::cxl_orchestrator_context::CxlOrchestratorContext;
+
+	/*
+	 * Register FMs that announce themselves into multicast
+	 * group, announcement of registered FM is its heartbeat
+	 */
+	pub fn start_listening(ctx: Arc<CxlOrchestratorContext>, socket: UdpSocket) {
+		thread::spawn(move || loop {
+			let result = receive(&socket)
+				.and_then(|request| ctx.registry.announce(&request));
+
+			if let Err(e) = result {
+				println!("Announcement is ignored: {}", e);
+			}
+		});
+	}
+}
@@ -19,17 +19,39 @@
  * limitations under the License.
  */
 
-extern crate daemonize;
+mod instance;
+mod registry;
+mod context;
+mod router;
+mod listener;
 
-use std::fs::File;
-use daemonize::Daemonize;
 use clap::{Arg, Command};
 use std::{
+	env,
+	fs,
 	io::{prelude::*, BufReader},
-	net::{TcpListener, TcpStream},
+	net::{TcpListener, TcpStream, UdpSocket},
+	sync::Arc,
+	thread,
 };
+pub use crate::instance::cxl_fm_instance;
+pub use crate::instance::cxl_fm_instance::CxlFmInstances;
+pub use crate::instance::cxl_fm_instance::CxlFmInstanceConfig;
+pub use crate::registry::cxl_fm_registry;
+pub use crate::router::cxl_fm_router;
+pub use crate::listener::cxl_fm_listener;
+pub use crate::context::cxl_orchestrator_context;
+pub use crate::context::cxl_orchestrator_context::CxlOrchestratorContext;
 pub use fm_library::cxl_fm_lib::send_responce;
+pub use fm_library::cxl_fm_lib::send_payload;
+pub use fm_library::cxl_fm_lib::send_error;
 pub use fm_library::cxl_fm_lib::CxlFmOptions;
+pub use fm_library::cxl_fm_lib::CxlFmRequest;
+pub use fm_library::cxl_fm_lib::CxlFmError;
+pub use fm_library::cxl_fm_orchestrator::CxlFmInstanceList;
+pub use fm_library::cxl_fm_orchestrator::CxlFmInstanceStatus;
+pub use fm_library::cxl_fm_orchestrator::CxlFmInfoList;
+pub use fm_library::cxl_fm_config::CxlFmProcessConfig;
 
 /*
  * CXL FM orchestrator version
@@ -47,14 +69,13 @@ const CXL_FM_ORCHESTRATOR_IP_ADDRESS_OPTION: &str = "ip";
 const CXL_FM_ORCHESTRATOR_IP_ADDRESS_OPTION_SHORT: char = 'i';
 const CXL_FM_ORCHESTRATOR_PORT_OPTION: &str = "port";
 const CXL_FM_ORCHESTRATOR_PORT_OPTION_SHORT: char = 'p';
-
-const CXL_FM_ORCHESTRATOR_WORKING_DIRECTORY: &str = "/tmp";
-const CXL_FM_ORCHESTRATOR_LOG_FILE_PATH: &str = "/tmp/fm_orchestrator.log";
-const CXL_FM_ORCHESTRATOR_ERROR_MESSAGES_FILE_PATH: &str = "/tmp/fm_orchestrator.err";
-const CXL_FM_ORCHESTRATOR_USER: &str = "nobody";
-const CXL_FM_ORCHESTRATOR_GROUP: &str = "bin";
-const CXL_FM_ORCHESTRATOR_GROUP_ID: u32 = 2;
-const CXL_FM_ORCHESTRATOR_UMASK: u32 = 0o777;
+const CXL_FM_ORCHESTRATOR_FM_DAEMON_OPTION: &str = "fm-daemon";
+const CXL_FM_ORCHESTRATOR_FM_DAEMON_NAME: &str = "fm_daemon";
+const CXL_FM_ORCHESTRATOR_INSTANCE_DIRECTORY_OPTION: &str = "instance-dir";
+const CXL_FM_ORCHESTRATOR_INSTANCE_DIRECTORY_OPTION_DEFAULT: &str = "/tmp/fm_instances";
+const CXL_FM_ORCHESTRATOR_ANNOUNCE_GROUP_OPTION: &str = "announce-group";
+const CXL_FM_ORCHESTRATOR_ANNOUNCE_INTERFACE_OPTION: &str = "announce-interface";
+const CXL_FM_ORCHESTRATOR_CONFIG_OPTION: &str = "config";
 
 /*
  * Command line interface definition
@@ -71,100 +92,248 @@ fn cli() -> Command {
 		.arg(Arg::new(CXL_FM_ORCHESTRATOR_IP_ADDRESS_OPTION)
 			.short(CXL_FM_ORCHESTRATOR_IP_ADDRESS_OPTION_SHORT)
 			.long(CXL_FM_ORCHESTRATOR_IP_ADDRESS_OPTION)
-			.action(clap::ArgAction::Set)
-			.required(true))
+			.action(clap::ArgAction::Set))
 		.arg(Arg::new(CXL_FM_ORCHESTRATOR_PORT_OPTION)
 			.short(CXL_FM_ORCHESTRATOR_PORT_OPTION_SHORT)
 			.long(CXL_FM_ORCHESTRATOR_PORT_OPTION)
 			.action(clap::ArgAction::Set)
-			.required(true))
+			.value_parser(clap::value_parser!(u16)))
+		.arg(Arg::new(CXL_FM_ORCHESTRATOR_CONFIG_OPTION)
+			.long(CXL_FM_ORCHESTRATOR_CONFIG_OPTION)
+			.action(clap::ArgAction::Set))
+		.arg(Arg::new(CXL_FM_ORCHESTRATOR_FM_DAEMON_OPTION)
+			.long(CXL_FM_ORCHESTRATOR_FM_DAEMON_OPTION)
+			.action(clap::ArgAction::Set))
+		.arg(Arg::new(CXL_FM_ORCHESTRATOR_INSTANCE_DIRECTORY_OPTION)
+			.long(CXL_FM_ORCHESTRATOR_INSTANCE_DIRECTORY_OPTION)
+			.action(clap::ArgAction::Set)
+			.default_value(CXL_FM_ORCHESTRATOR_INSTANCE_DIRECTORY_OPTION_DEFAULT))
+		.arg(Arg::new(CXL_FM_ORCHESTRATOR_ANNOUNCE_GROUP_OPTION)
+			.long(CXL_FM_ORCHESTRATOR_ANNOUNCE_GROUP_OPTION)
+			.action(clap::ArgAction::Set))
+		.arg(Arg::new(CXL_FM_ORCHESTRATOR_ANNOUNCE_INTERFACE_OPTION)
+			.long(CXL_FM_ORCHESTRATOR_ANNOUNCE_INTERFACE_OPTION)
+			.action(clap::ArgAction::Set)
+			.default_value(fm_library::cxl_fm_announce::CXL_FM_ANNOUNCE_INTERFACE_DEFAULT))
+}
+
+/*
+ * fm_daemon is expected near the orchestrator executable,
+ * otherwise it is searched in PATH
+ */
+fn default_fm_daemon() -> String {
+	env::current_exe().ok()
+		.and_then(|path| path.parent().map(|dir| dir.join(CXL_FM_ORCHESTRATOR_FM_DAEMON_NAME)))
+		.filter(|path| path.exists())
+		.map(|path| path.to_string_lossy().into_owned())
+		.unwrap_or(String::from(CXL_FM_ORCHESTRATOR_FM_DAEMON_NAME))
+}
+
+/*
+ * Send status of FM instances
+ */
+fn send_status(stream: &TcpStream,
+	       result: Result<Vec<CxlFmInstanceStatus>, CxlFmError>,
+	       env: &CxlFmOptions) {
+	match result {
+		Ok(instances) => {
+			send_payload(stream, &CxlFmInstanceList { instances }.to_bytes(), env);
+		},
+		Err(e) => send_error(stream, &e, env),
+	}
 }
 
 /*
- * Discover available FM instances
+ * Discover FMs registered in orchestrator
  */
-pub fn discover_fm(stream: &TcpStream, env: &CxlFmOptions) {
+pub fn discover_fm(stream: &TcpStream, ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
 	if env.is_debug {
 		println!("{}", fm_library::cxl_fm_lib::CXL_FM_DISCOVER_FM_COMMAND);
 	}
 
-	send_responce(stream, fm_library::cxl_fm_lib::CXL_FM_NO_DATA, env);
+	let list = CxlFmInfoList {
+		fms: ctx.registry.list(),
+	};
+
+	send_payload(stream, &list.to_bytes(), env);
+}
+
+/*
+ * Register FM
+ */
+pub fn register_fm(stream: &TcpStream, request: &CxlFmRequest,
+		   ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
+	if env.is_debug {
+		println!("{}", fm_library::cxl_fm_lib::CXL_FM_REGISTER_FM_COMMAND);
+	}
+
+	match ctx.registry.register(request) {
+		Ok(role) => send_payload(stream, &role.to_bytes(), env),
+		Err(e) => send_error(stream, &e, env),
+	}
+}
+
+/*
+ * Accept heartbeat of FM
+ */
+pub fn heartbeat_fm(stream: &TcpStream, request: &CxlFmRequest,
+		    ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
+	if env.is_debug {
+		println!("{}", fm_library::cxl_fm_lib::CXL_FM_HEARTBEAT_FM_COMMAND);
+	}
+
+	match ctx.registry.heartbeat(request) {
+		Ok(role) => send_payload(stream, &role.to_bytes(), env),
+		Err(e) => send_error(stream, &e, env),
+	}
 }
 
 /*
  * Start FM instance
  */
-pub fn start_fm(stream: &TcpStream, env: &CxlFmOptions) {
+pub fn start_fm(stream: &TcpStream, request: &CxlFmRequest,
+		ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
 	if env.is_debug {
 		println!("{}", fm_library::cxl_fm_lib::CXL_FM_START_FM_COMMAND);
 	}
 
-	send_responce(stream, fm_library::cxl_fm_lib::CXL_FM_NO_DATA, env);
+	let result = CxlFmInstanceConfig::from_request(request)
+		.and_then(|config| ctx.instances.start(config));
+
+	send_status(stream, result.map(|status| vec![status]), env);
 }
 
 /*
  * Restart FM instance
  */
-pub fn restart_fm(stream: &TcpStream, env: &CxlFmOptions) {
+pub fn restart_fm(stream: &TcpStream, request: &CxlFmRequest,
+		  ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
 	if env.is_debug {
 		println!("{}", fm_library::cxl_fm_lib::CXL_FM_RESTART_FM_COMMAND);
 	}
 
-	send_responce(stream, fm_library::cxl_fm_lib::CXL_FM_NO_DATA, env);
+	let result = cxl_fm_instance::instance_name(request)
+		.and_then(|name| {
+			let timeout = request.get_number_or(fm_library::cxl_fm_lib::CXL_FM_TIMEOUT_ARG,
+							    cxl_fm_instance::CXL_FM_INSTANCE_STOP_TIMEOUT)?;
+
+			ctx.instances.restart(name, timeout)
+		});
+
+	send_status(stream, result.map(|status| vec![status]), env);
 }
 
 /*
  * Stop FM instance
  */
-pub fn stop_fm(stream: &TcpStream, env: &CxlFmOptions) {
+pub fn stop_fm(stream: &TcpStream, request: &CxlFmRequest,
+	       ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
 	if env.is_debug {
 		println!("{}", fm_library::cxl_fm_lib::CXL_FM_STOP_FM_COMMAND);
 	}
 
-	send_responce(stream, fm_library::cxl_fm_lib::CXL_FM_NO_DATA, env);
+	let result = cxl_fm_instance::instance_name(request)
+		.and_then(|name| {
+			let timeout = request.get_number_or(fm_library::cxl_fm_lib::CXL_FM_TIMEOUT_ARG,
+							    cxl_fm_instance::CXL_FM_INSTANCE_STOP_TIMEOUT)?;
+
+			let status = ctx.instances.stop(name, timeout)?;
+
+			ctx.registry.unregister(name);
+			Ok(status)
+		});
+
+	send_status(stream, result.map(|status| vec![status]), env);
+}
+
+/*
+ * Get status of FM instances
+ */
+pub fn get_fm_status(stream: &TcpStream, request: &CxlFmRequest,
+		     ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
+	if env.is_debug {
+		println!("{}", fm_library::cxl_fm_lib::CXL_FM_GET_FM_STATUS_COMMAND);
+	}
+
+	let result = match request.get(fm_library::cxl_fm_lib::CXL_FM_NAME_ARG) {
+		Some(_) => cxl_fm_instance::instance_name(request)
+				.and_then(|name| ctx.instances.status(Some(name))),
+		None => ctx.instances.status(None),
+	};
+
+	send_status(stream, result, env);
 }
 
 /*
  * Connection request processing logic
  */
-fn handle_connection(stream: &TcpStream, env: &CxlFmOptions) {
+fn handle_connection(stream: &TcpStream, ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
 	if env.is_debug {
 		println!("Process request...");
 	}
 
 	let buf_reader = BufReader::new(stream);
-	let request_line = buf_reader.lines().next().unwrap().unwrap();
+	let request_line = match buf_reader.lines().next() {
+		Some(Ok(line)) => line,
+		_ => return,
+	};
 
 	if env.is_debug {
 		println!("Request: {:#?}", request_line);
 	}
 
-	match request_line.as_str() {
+	let request = match CxlFmRequest::parse(&request_line) {
+		Ok(request) => request,
+		Err(e) => {
+			send_error(stream, &e, env);
+			return;
+		}
+	};
+
+	match request.command.as_str() {
 		fm_library::cxl_fm_lib::CXL_FM_DISCOVER_FM_COMMAND => {
-			discover_fm(stream, env);
+			discover_fm(stream, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_START_FM_COMMAND => {
-			start_fm(stream, env);
+			start_fm(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_RESTART_FM_COMMAND => {
-			restart_fm(stream, env);
+			restart_fm(stream, &request, ctx, env);
 		},
 		fm_library::cxl_fm_lib::CXL_FM_STOP_FM_COMMAND => {
-			stop_fm(stream, env);
+			stop_fm(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_GET_FM_STATUS_COMMAND => {
+			get_fm_status(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_REGISTER_FM_COMMAND => {
+			register_fm(stream, &request, ctx, env);
+		},
+		fm_library::cxl_fm_lib::CXL_FM_HEARTBEAT_FM_COMMAND => {
+			heartbeat_fm(stream, &request, ctx, env);
 		},
-		_ => send_responce(stream, fm_library::cxl_fm_lib::CXL_FM_UNKNOWN_COMMAND, env),
+		_ => cxl_fm_router::forward(stream, &request, ctx, env),
 	}
 }
 
 /*
  * Main logic of daemon
  */
-fn fm_daemon_logic(env: &CxlFmOptions) {
+fn fm_daemon_logic(env: &CxlFmOptions, ctx: CxlOrchestratorContext,
+		   announcements: Option<UdpSocket>) {
 	if env.is_debug {
 		println!("{} {}: Daemonized!",
 			 CXL_FM_ORCHESTRATOR_NAME, CXL_FM_ORCHESTRATOR_VERSION);
 	}
 
+	let ctx = Arc::new(ctx);
+
+	cxl_fm_instance::start_supervision(Arc::clone(&ctx));
+
+	if let Some(socket) = announcements {
+		cxl_fm_listener::start_listening(Arc::clone(&ctx), socket);
+	}
+
 	loop {
 		let listener = TcpListener::bind(&env.ip_port).unwrap();
 
@@ -173,8 +342,16 @@ fn fm_daemon_logic(env: &CxlFmOptions) {
 				 env.ip_port);
 		}
 
+		/*
+		 * Stopping of instance takes time,
+		 * so every connection has its own thread
+		 */
 		for stream in listener.incoming() {
-			handle_connection(&stream.unwrap(), env);
+			let stream = stream.unwrap();
+			let ctx = Arc::clone(&ctx);
+			let env = env.clone();
+
+			thread::spawn(move || handle_connection(&stream, &ctx, &env));
 		}
 	};
 }
@@ -183,39 +360,87 @@ fn fm_daemon_logic(env: &CxlFmOptions) {
  * Application logic
  */
 fn main() {
-	let stdout = File::create(CXL_FM_ORCHESTRATOR_LOG_FILE_PATH).unwrap();
-	let stderr = File::create(CXL_FM_ORCHESTRATOR_ERROR_MESSAGES_FILE_PATH).unwrap();
-
 	let matches = cli().get_matches();
+	let config = match matches.get_one::<String>(CXL_FM_ORCHESTRATOR_CONFIG_OPTION) {
+		Some(path) => fs::read_to_string(path)
+			.map_err(|e| CxlFmError::Io(e.to_string()))
+			.and_then(|content| CxlFmProcessConfig::parse(&content, CXL_FM_ORCHESTRATOR_NAME))
+			.map_err(|e| format!("cannot read configuration {}: {}", path, e)),
+		None => Ok(CxlFmProcessConfig::new(CXL_FM_ORCHESTRATOR_NAME)),
+	};
+
+	let mut config = match config {
+		Ok(config) => config,
+		Err(e) => {
+			eprintln!("Error, {}", e);
+			return;
+		}
+	};
+
+	/*
+	 * Command line options override the configuration file
+	 */
+	if let Some(ip) = matches.get_one::<String>(CXL_FM_ORCHESTRATOR_IP_ADDRESS_OPTION) {
+		config.listen.ip = Some(ip.clone());
+	}
+
+	if let Some(port) = matches.get_one::<u16>(CXL_FM_ORCHESTRATOR_PORT_OPTION) {
+		config.listen.port = Some(*port);
+	}
 
-	let ip = matches.get_one::<String>(CXL_FM_ORCHESTRATOR_IP_ADDRESS_OPTION).unwrap();
-	let port = matches.get_one::<String>(CXL_FM_ORCHESTRATOR_PORT_OPTION).unwrap();
-	let ip_port = format!("{ip}:{port}");
+	if matches.get_flag(CXL_FM_ORCHESTRATOR_DEBUG_OPTION) {
+		config.log.debug = true;
+	}
+
+	let ip_port = match config.listen.ip_port() {
+		Ok(ip_port) => ip_port,
+		Err(e) => {
+			eprintln!("Error, {}", e);
+			return;
+		}
+	};
+	let fm_daemon = matches.get_one::<String>(CXL_FM_ORCHESTRATOR_FM_DAEMON_OPTION)
+		.cloned()
+		.unwrap_or_else(default_fm_daemon);
+	let instance_directory = matches.get_one::<String>(CXL_FM_ORCHESTRATOR_INSTANCE_DIRECTORY_OPTION).unwrap();
+	let interface = matches.get_one::<String>(CXL_FM_ORCHESTRATOR_ANNOUNCE_INTERFACE_OPTION).unwrap();
+
+	/*
+	 * Socket of announcements is opened before daemonizing,
+	 * so wrong group or interface is reported to the user
+	 */
+	let announcements = matches.get_one::<String>(CXL_FM_ORCHESTRATOR_ANNOUNCE_GROUP_OPTION)
+		.map(|group| {
+			let group = fm_library::cxl_fm_announce::parse_group(group)?;
+			let interface = fm_library::cxl_fm_announce::parse_interface(interface)?;
+
+			fm_library::cxl_fm_announce::listen(&group, &interface)
+		})
+		.transpose();
+
+	let announcements = match announcements {
+		Ok(announcements) => announcements,
+		Err(e) => {
+			eprintln!("Error, cannot listen to announcements: {}", e);
+			return;
+		}
+	};
 
 	let options = CxlFmOptions {
-		ip_port: String::from(ip_port),
-		is_debug: matches.get_flag(CXL_FM_ORCHESTRATOR_DEBUG_OPTION) == true,
+		ip_port,
+		is_debug: config.log.debug,
+		is_dry_run: false,
+		fm: None,
 	};
+	let ctx = CxlOrchestratorContext::new(CxlFmInstances::new(&fm_daemon, instance_directory,
+								   &options.ip_port));
 
 	if options.is_debug {
 		println!("{} {}", CXL_FM_ORCHESTRATOR_NAME, CXL_FM_ORCHESTRATOR_VERSION);
 	}
 
-	let daemonize = Daemonize::new()
-			// Every method except `new` and `start`
-			// is optional, see `Daemonize` documentation
-			// for default behaviour.
-			.working_directory(CXL_FM_ORCHESTRATOR_WORKING_DIRECTORY)
-			.user(CXL_FM_ORCHESTRATOR_USER)
-			.group(CXL_FM_ORCHESTRATOR_GROUP)	// Group name
-			.group(CXL_FM_ORCHESTRATOR_GROUP_ID)	// or group id.
-			.umask(CXL_FM_ORCHESTRATOR_UMASK)	// Set umask, `0o027` by default.
-			.stdout(stdout)				// Redirect stdout to log file.
-			.stderr(stderr)				// Redirect stderr to error messages file.
-			.privileged_action(|| "Executed before drop privileges");
-
-	match daemonize.start() {
-		Ok(_) => fm_daemon_logic(&options),
+	match config.daemonize() {
+		Ok(_) => fm_daemon_logic(&options, ctx, announcements),
 		Err(e) => eprintln!("Error, {}", e),
 	}
 }
@@ -0,0 +1,572 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM orchestrator implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub mod cxl_fm_registry {
+	use std::collections::BTreeMap;
+	use std::collections::BTreeSet;
+	use std::sync::Mutex;
+	use std::time::{Duration, Instant};
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::CXL_FM_ID_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ADDRESS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_VERSION_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_SWITCHES_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICES_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_CCIS_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_UPTIME_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_INTERVAL_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_ROLE_ARG;
+	use fm_library::cxl_fm_orchestrator::CxlFmInfo;
+	use fm_library::cxl_fm_orchestrator::CxlFmRole;
+	use fm_library::cxl_fm_orchestrator::fm_role_by_name;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_HEALTH_HEALTHY;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_HEALTH_LATE;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_HEALTH_LOST;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_ACTIVE;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_STANDBY;
+
+	/*
+	 * FM that missed one heartbeat is late,
+	 * FM that missed this number of heartbeats is lost
+	 */
+	pub const CXL_FM_REGISTRY_MISSED_HEARTBEATS: u32 = 3;
+
+	/*
+	 * struct CxlFmRegistryEntry - registered FM
+	 * @info: FM description reported by FM
+	 * @interval: heartbeat interval of FM
+	 * @heartbeat: time of the last heartbeat
+	 * @claimed: role that FM reports to have
+	 */
+	struct CxlFmRegistryEntry {
+		info: CxlFmInfo,
+		interval: Duration,
+		heartbeat: Instant,
+		claimed: u8,
+	}
+
+	impl CxlFmRegistryEntry {
+		fn health(&self) -> u8 {
+			let age = self.heartbeat.elapsed();
+
+			if age > self.interval * CXL_FM_REGISTRY_MISSED_HEARTBEATS {
+				CXL_FM_HEALTH_LOST
+			} else if age > self.interval * 2 {
+				CXL_FM_HEALTH_LATE
+			} else {
+				CXL_FM_HEALTH_HEALTHY
+			}
+		}
+
+		fn is_alive(&self) -> bool {
+			self.health() != CXL_FM_HEALTH_LOST
+		}
+	}
+
+	/*
+	 * Get FM ID of the request
+	 */
+	fn fm_id(request: &CxlFmRequest) -> Result<&str, CxlFmError> {
+		request.get(CXL_FM_ID_ARG)
+			.filter(|id| !id.is_empty())
+			.ok_or(CxlFmError::InvalidInput(String::from("id is required")))
+	}
+
+	/*
+	 * Get role claimed by the request. FM that doesn't
+	 * report its role doesn't know about standby FMs.
+	 */
+	fn claimed_role(request: &CxlFmRequest) -> Result<u8, CxlFmError> {
+		match request.get(CXL_FM_ROLE_ARG) {
+			Some(name) => fm_role_by_name(name)
+				.ok_or(CxlFmError::InvalidInput(format!("unknown role {}", name))),
+			None => Ok(CXL_FM_ROLE_ACTIVE),
+		}
+	}
+
+	/*
+	 * Get list of component names of the request
+	 */
+	fn names(request: &CxlFmRequest, key: &str) -> Vec<String> {
+		request.get(key)
+			.map(|names| names.split(',')
+				.filter(|name| !name.is_empty())
+				.map(String::from)
+				.collect())
+			.unwrap_or_default()
+	}
+
+	/*
+	 * struct CxlFmRegistryState - registered FMs and elections
+	 * @entries: registered FMs by ID
+	 * @elected: ID of the active FM by switch name
+	 */
+	#[derive(Default)]
+	struct CxlFmRegistryState {
+		entries: BTreeMap<String, CxlFmRegistryEntry>,
+		elected: BTreeMap<String, String>,
+	}
+
+	impl CxlFmRegistryState {
+		/*
+		 * Is FM the alive active FM of the switch?
+		 */
+		fn is_elected(&self, switch: &str, id: &str) -> bool {
+			self.elected.get(switch).is_some_and(|elected| elected == id) &&
+			self.entries.get(id).is_some_and(|entry| {
+				entry.is_alive() && entry.info.switches.iter().any(|other| other == switch)
+			})
+		}
+
+		/*
+		 * Elect active FM for every switch without alive active FM.
+		 * Election is sticky: the active FM keeps its role while it
+		 * sends heartbeats. FM that reports itself active is preferred
+		 * (orchestrator restart), then FM that is active for other
+		 * switches, so one FM manages all switches of a group. Standby
+		 * is not elected until the active FM has had time to register.
+		 */
+		fn elect(&mut self, started: Instant) {
+			let switches: BTreeSet<String> = self.entries.values()
+				.filter(|entry| entry.is_alive())
+				.flat_map(|entry| entry.info.switches.iter().cloned())
+				.collect();
+
+			for switch in switches {
+				let previous = self.elected.get(&switch).cloned();
+
+				if previous.as_ref().is_some_and(|id| self.is_elected(&switch, id)) {
+					continue;
+				}
+
+				let candidate = self.entries.values()
+					.filter(|entry| entry.is_alive() &&
+							entry.info.switches.contains(&switch))
+					.min_by_key(|entry| (entry.claimed != CXL_FM_ROLE_ACTIVE,
+							     !self.elected.values().any(|id| *id == entry.info.id),
+							     entry.info.id.clone()));
+
+				let (id, is_ready) = match candidate {
+					Some(entry) => (entry.info.id.clone(),
+							entry.claimed == CXL_FM_ROLE_ACTIVE ||
+							started.elapsed() > entry.interval * CXL_FM_REGISTRY_MISSED_HEARTBEATS),
+					None => continue,
+				};
+
+				if !is_ready {
+					continue;
+				}
+
+				match previous {
+					Some(previous) if previous != id => {
+						println!("{} is promoted to active FM of {} instead of {}",
+							 id, switch, previous);
+					},
+					_ => println!("{} is elected active FM of {}", id, switch),
+				}
+
+				self.elected.insert(switch, id);
+			}
+		}
+
+		/*
+		 * Get role of FM: FM is active if it is elected for all
+		 * its switches, standby FM replicates state of the active
+		 * FM of its switches
+		 */
+		fn role(&self, id: &str) -> CxlFmRole {
+			let switches = self.entries.get(id)
+				.map(|entry| entry.info.switches.clone())
+				.unwrap_or_default();
+
+			if switches.iter().all(|switch| self.is_elected(switch, id)) {
+				return CxlFmRole {
+					role: CXL_FM_ROLE_ACTIVE,
+					active: String::from(id),
+					address: self.entries.get(id)
+						.map(|entry| entry.info.address.clone())
+						.unwrap_or_default(),
+				};
+			}
+
+			let active = switches.iter()
+				.filter_map(|switch| self.elected.get(switch))
+				.find(|active| *active != id)
+				.and_then(|active| self.entries.get(active))
+				.filter(|entry| entry.is_alive());
+
+			CxlFmRole {
+				role: CXL_FM_ROLE_STANDBY,
+				active: active.map(|entry| entry.info.id.clone()).unwrap_or_default(),
+				address: active.map(|entry| entry.info.address.clone()).unwrap_or_default(),
+			}
+		}
+	}
+
+	/*
+	 * struct CxlFmRegistry - FMs known to orchestrator
+	 * @state: registered FMs and elected active FMs
+	 * @started: start time of the registry
+	 */
+	pub struct CxlFmRegistry {
+		state: Mutex<CxlFmRegistryState>,
+		started: Instant,
+	}
+
+	impl Default for CxlFmRegistry {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	impl CxlFmRegistry {
+		pub fn new() -> CxlFmRegistry {
+			CxlFmRegistry {
+				state: Mutex::new(CxlFmRegistryState::default()),
+				started: Instant::now(),
+			}
+		}
+
+		/*
+		 * Register FM or update registration of restarted FM.
+		 * Returns role of FM.
+		 */
+		pub fn register(&self, request: &CxlFmRequest) -> Result<CxlFmRole, CxlFmError> {
+			let id = fm_id(request)?;
+			let address = request.get(CXL_FM_ADDRESS_ARG)
+				.ok_or(CxlFmError::InvalidInput(String::from("address is required")))?;
+			let interval = request.get_number::<u64>(CXL_FM_INTERVAL_ARG)?;
+			let claimed = claimed_role(request)?;
+
+			if interval == 0 {
+				return Err(CxlFmError::InvalidInput(String::from("heartbeat interval is zero")));
+			}
+
+			let mut state = self.state.lock().unwrap();
+
+			if let Some((other, _)) = state.entries.iter()
+				.find(|(other, entry)| *other != id &&
+						       entry.info.address == address &&
+						       entry.is_alive()) {
+				return Err(CxlFmError::InvalidInput(format!("{} is registered by {}",
+									    address, other)));
+			}
+
+			let is_new = !state.entries.contains_key(id);
+
+			state.entries.insert(String::from(id), CxlFmRegistryEntry {
+				info: CxlFmInfo {
+					id: String::from(id),
+					address: String::from(address),
+					version: String::from(request.get(CXL_FM_VERSION_ARG).unwrap_or_default()),
+					switches: names(request, CXL_FM_SWITCHES_ARG),
+					devices: names(request, CXL_FM_DEVICES_ARG),
+					ccis: names(request, CXL_FM_CCIS_ARG),
+					health: CXL_FM_HEALTH_HEALTHY,
+					role: claimed,
+					uptime: request.get_number_or::<u64>(CXL_FM_UPTIME_ARG, 0)?,
+					heartbeat_age: 0,
+				},
+				interval: Duration::from_secs(interval),
+				heartbeat: Instant::now(),
+				claimed,
+			});
+
+			println!("{} is {}registered: {}", id,
+				 match is_new {
+					 true => "",
+					 false => "re-",
+				 }, address);
+
+			state.elect(self.started);
+			Ok(state.role(id))
+		}
+
+		/*
+		 * Accept heartbeat of registered FM. Unknown FM
+		 * (e.g. after orchestrator restart) has to register.
+		 * Returns role of FM.
+		 */
+		pub fn heartbeat(&self, request: &CxlFmRequest) -> Result<CxlFmRole, CxlFmError> {
+			let id = fm_id(request)?;
+			let claimed = claimed_role(request)?;
+			let mut state = self.state.lock().unwrap();
+			let entry = state.entries.get_mut(id)
+				.ok_or(CxlFmError::NotFound(format!("FM {} is not registered", id)))?;
+
+			if !entry.is_alive() {
+				println!("{} is back", id);
+			}
+
+			entry.info.uptime = request.get_number_or::<u64>(CXL_FM_UPTIME_ARG, entry.info.uptime)?;
+
+			if request.get(CXL_FM_SWITCHES_ARG).is_some() {
+				entry.info.switches = names(request, CXL_FM_SWITCHES_ARG);
+			}
+
+			if request.get(CXL_FM_DEVICES_ARG).is_some() {
+				entry.info.devices = names(request, CXL_FM_DEVICES_ARG);
+			}
+
+			if request.get(CXL_FM_CCIS_ARG).is_some() {
+				entry.info.ccis = names(request, CXL_FM_CCIS_ARG);
+			}
+
+			entry.heartbeat = Instant::now();
+			entry.claimed = claimed;
+
+			state.elect(self.started);
+			Ok(state.role(id))
+		}
+
+		/*
+		 * Register FM by its announcement. Announcement
+		 * of FM registered with the same address is
+		 * its heartbeat. Announcement doesn't move alive
+		 * FM to another address.
+		 */
+		pub fn announce(&self, request: &CxlFmRequest) -> Result<(), CxlFmError> {
+			let id = fm_id(request)?;
+			let registered = self.state.lock().unwrap()
+				.entries
+				.get(id)
+				.filter(|entry| entry.is_alive())
+				.map(|entry| entry.info.address.clone());
+
+			match registered {
+				Some(address) if request.get(CXL_FM_ADDRESS_ARG) == Some(address.as_str()) => {
+					self.heartbeat(request).map(|_| ())
+				},
+				Some(address) => Err(CxlFmError::InvalidInput(format!("{} is registered with {}",
+										    id, address))),
+				None => self.register(request).map(|_| ()),
+			}
+		}
+
+		/*
+		 * Remove FM that is stopped on purpose
+		 */
+		pub fn unregister(&self, id: &str) {
+			if self.state.lock().unwrap().entries.remove(id).is_some() {
+				println!("{} is unregistered", id);
+			}
+		}
+
+		/*
+		 * Get registered FMs
+		 */
+		pub fn list(&self) -> Vec<CxlFmInfo> {
+			let mut state = self.state.lock().unwrap();
+
+			state.elect(self.started);
+			state.entries.values()
+				.map(|entry| {
+					let age = entry.heartbeat.elapsed().as_secs();
+
+					CxlFmInfo {
+						health: entry.health(),
+						role: state.role(&entry.info.id).role,
+						uptime: entry.info.uptime + age,
+						heartbeat_age: age,
+						..entry.info.clone()
+					}
+				})
+				.collect()
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use fm_library::cxl_fm_lib::CXL_FM_REGISTER_FM_COMMAND;
+		use fm_library::cxl_fm_lib::CXL_FM_HEARTBEAT_FM_COMMAND;
+		use fm_library::cxl_fm_lib::CXL_FM_ANNOUNCE_FM_COMMAND;
+		use fm_library::cxl_fm_orchestrator::fm_role_name;
+
+		fn registration(id: &str, port: u16, role: u8) -> CxlFmRequest {
+			CxlFmRequest::new(CXL_FM_REGISTER_FM_COMMAND)
+				.arg(CXL_FM_ID_ARG, id)
+				.arg(CXL_FM_ADDRESS_ARG, &format!("127.0.0.1:{}", port))
+				.arg(CXL_FM_INTERVAL_ARG, "1")
+				.arg(CXL_FM_SWITCHES_ARG, "sw0")
+				.arg(CXL_FM_ROLE_ARG, fm_role_name(role))
+		}
+
+		fn heartbeat(id: &str, role: u8) -> CxlFmRequest {
+			CxlFmRequest::new(CXL_FM_HEARTBEAT_FM_COMMAND)
+				.arg(CXL_FM_ID_ARG, id)
+				.arg(CXL_FM_ROLE_ARG, fm_role_name(role))
+		}
+
+		/*
+		 * Registry that was started long enough ago
+		 * to elect standby FMs
+		 */
+		fn started_registry() -> CxlFmRegistry {
+			CxlFmRegistry {
+				state: Mutex::new(CxlFmRegistryState::default()),
+				started: Instant::now() - Duration::from_secs(10),
+			}
+		}
+
+		/*
+		 * Make FM miss the number of heartbeats
+		 */
+		fn miss_heartbeats(registry: &CxlFmRegistry, id: &str, missed: u32) {
+			let mut state = registry.state.lock().unwrap();
+			let entry = state.entries.get_mut(id).unwrap();
+
+			entry.heartbeat = Instant::now() - entry.interval * missed - Duration::from_millis(100);
+		}
+
+		fn health(registry: &CxlFmRegistry, id: &str) -> u8 {
+			registry.list().iter()
+				.find(|fm| fm.id == id)
+				.map(|fm| fm.health)
+				.unwrap()
+		}
+
+		#[test]
+		fn fm_claiming_active_role_is_elected() {
+			let registry = CxlFmRegistry::new();
+			let role = registry.register(&registration("fm0", 7400, CXL_FM_ROLE_ACTIVE)).unwrap();
+
+			assert_eq!(role.role, CXL_FM_ROLE_ACTIVE);
+			assert_eq!(role.active, "fm0");
+
+			let role = registry.register(&registration("fm1", 7401, CXL_FM_ROLE_STANDBY)).unwrap();
+
+			assert_eq!(role.role, CXL_FM_ROLE_STANDBY);
+			assert_eq!(role.active, "fm0");
+			assert_eq!(role.address, "127.0.0.1:7400");
+		}
+
+		#[test]
+		fn standby_is_not_elected_after_start() {
+			let registry = CxlFmRegistry::new();
+			let role = registry.register(&registration("fm0", 7400, CXL_FM_ROLE_STANDBY)).unwrap();
+
+			assert_eq!(role.role, CXL_FM_ROLE_STANDBY);
+			assert!(role.active.is_empty());
+
+			let registry = started_registry();
+			let role = registry.register(&registration("fm0", 7400, CXL_FM_ROLE_STANDBY)).unwrap();
+
+			assert_eq!(role.role, CXL_FM_ROLE_ACTIVE);
+		}
+
+		#[test]
+		fn election_is_sticky() {
+			let registry = started_registry();
+
+			registry.register(&registration("fm1", 7401, CXL_FM_ROLE_STANDBY)).unwrap();
+
+			let role = registry.register(&registration("fm0", 7400, CXL_FM_ROLE_ACTIVE)).unwrap();
+
+			assert_eq!(role.role, CXL_FM_ROLE_STANDBY);
+			assert_eq!(role.active, "fm1");
+			assert_eq!(registry.heartbeat(&heartbeat("fm1", CXL_FM_ROLE_ACTIVE)).unwrap().role,
+				   CXL_FM_ROLE_ACTIVE);
+		}
+
+		#[test]
+		fn heartbeats_define_health() {
+			let registry = CxlFmRegistry::new();
+
+			registry.register(&registration("fm0", 7400, CXL_FM_ROLE_ACTIVE)).unwrap();
+			assert_eq!(health(&registry, "fm0"), CXL_FM_HEALTH_HEALTHY);
+
+			miss_heartbeats(&registry, "fm0", 2);
+			assert_eq!(health(&registry, "fm0"), CXL_FM_HEALTH_LATE);
+
+			miss_heartbeats(&registry, "fm0", CXL_FM_REGISTRY_MISSED_HEARTBEATS);
+			assert_eq!(health(&registry, "fm0"), CXL_FM_HEALTH_LOST);
+
+			registry.heartbeat(&heartbeat("fm0", CXL_FM_ROLE_ACTIVE)).unwrap();
+			assert_eq!(health(&registry, "fm0"), CXL_FM_HEALTH_HEALTHY);
+		}
+
+		#[test]
+		fn standby_is_promoted_when_active_is_lost() {
+			let registry = started_registry();
+
+			registry.register(&registration("fm0", 7400, CXL_FM_ROLE_ACTIVE)).unwrap();
+			registry.register(&registration("fm1", 7401, CXL_FM_ROLE_STANDBY)).unwrap();
+			miss_heartbeats(&registry, "fm0", CXL_FM_REGISTRY_MISSED_HEARTBEATS);
+
+			let role = registry.heartbeat(&heartbeat("fm1", CXL_FM_ROLE_STANDBY)).unwrap();
+
+			assert_eq!(role.role, CXL_FM_ROLE_ACTIVE);
+
+			/* the lost FM is standby when it is back */
+			let role = registry.heartbeat(&heartbeat("fm0", CXL_FM_ROLE_ACTIVE)).unwrap();
+
+			assert_eq!(role.role, CXL_FM_ROLE_STANDBY);
+			assert_eq!(role.active, "fm1");
+		}
+
+		#[test]
+		fn address_of_alive_fm_is_not_taken() {
+			let registry = CxlFmRegistry::new();
+
+			registry.register(&registration("fm0", 7400, CXL_FM_ROLE_ACTIVE)).unwrap();
+
+			assert!(registry.register(&registration("fm1", 7400, CXL_FM_ROLE_ACTIVE)).is_err());
+
+			miss_heartbeats(&registry, "fm0", CXL_FM_REGISTRY_MISSED_HEARTBEATS);
+
+			assert!(registry.register(&registration("fm1", 7400, CXL_FM_ROLE_ACTIVE)).is_ok());
+		}
+
+		#[test]
+		fn announcement_does_not_move_alive_fm() {
+			let registry = CxlFmRegistry::new();
+			let announcement = |port| CxlFmRequest {
+				command: String::from(CXL_FM_ANNOUNCE_FM_COMMAND),
+				..registration("fm0", port, CXL_FM_ROLE_ACTIVE)
+			};
+
+			registry.announce(&announcement(7400)).unwrap();
+			registry.announce(&announcement(7400)).unwrap();
+
+			assert!(registry.announce(&announcement(7401)).is_err());
+			assert_eq!(registry.list()[0].address, "127.0.0.1:7400");
+
+			miss_heartbeats(&registry, "fm0", CXL_FM_REGISTRY_MISSED_HEARTBEATS);
+
+			registry.announce(&announcement(7401)).unwrap();
+			assert_eq!(registry.list()[0].address, "127.0.0.1:7401");
+		}
+
+		#[test]
+		fn unknown_fm_has_to_register() {
+			let registry = CxlFmRegistry::new();
+
+			assert!(matches!(registry.heartbeat(&heartbeat("fm0", CXL_FM_ROLE_ACTIVE)),
+					 Err(CxlFmError::NotFound(_))));
+
+			registry.register(&registration("fm0", 7400, CXL_FM_ROLE_ACTIVE)).unwrap();
+			registry.unregister("fm0");
+
+			assert!(registry.list().is_empty());
+		}
+	}
+}
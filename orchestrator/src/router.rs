@@ -0,0 +1,341 @@
+/*
+ * CXL FM Infrastructure -- CXl Fabric Manager (FM) Infrastructure.
+ *
+ * CXL FM orchestrator implementation.
+ *
+ * Copyright (c) 2023 Viacheslav Dubeyko <slava@dubeyko.com>,
+ * All rights reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub mod cxl_fm_router {
+	use std::io::{self, Write};
+	use std::net::TcpStream;
+	use fm_library::cxl_fm_lib::CxlFmError;
+	use fm_library::cxl_fm_lib::CxlFmOptions;
+	use fm_library::cxl_fm_lib::CxlFmRequest;
+	use fm_library::cxl_fm_lib::send_error;
+	use fm_library::cxl_fm_lib::CXL_FM_FM_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_SWITCH_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_DEVICE_ARG;
+	use fm_library::cxl_fm_lib::CXL_FM_COMPONENT_ARG;
+	use fm_library::cxl_fm_orchestrator::CxlFmInfo;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_HEALTH_LOST;
+	use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_ACTIVE;
+	use crate::cxl_orchestrator_context::CxlOrchestratorContext;
+
+	/*
+	 * Get names of CCIs of FMs by global CCI index: CCIs of FMs
+	 * are numbered in order of FM IDs, CCI shared by several FMs
+	 * (e.g. active and standby ones) has one index
+	 */
+	fn cci_names(fms: &[CxlFmInfo]) -> Vec<&str> {
+		let mut names: Vec<&str> = Vec::new();
+
+		for cci in fms.iter().flat_map(|fm| fm.ccis.iter()) {
+			if !names.contains(&cci.as_str()) {
+				names.push(cci);
+			}
+		}
+
+		names
+	}
+
+	/*
+	 * Get switch or device the request is addressed to.
+	 * Device defined by CCI index is the CCI with this global
+	 * index, component of events is defined by its top object
+	 * (sw0 for sw0/port3).
+	 */
+	fn request_target<'a>(fms: &'a [CxlFmInfo],
+			      request: &'a CxlFmRequest) -> Option<&'a str> {
+		if let Some(switch) = request.get(CXL_FM_SWITCH_ARG) {
+			return Some(switch);
+		}
+
+		if let Some(device) = request.get(CXL_FM_DEVICE_ARG) {
+			return match device.parse::<usize>() {
+				Ok(index) => Some(cci_names(fms).get(index).copied().unwrap_or(device)),
+				Err(_) => Some(device),
+			};
+		}
+
+		request.get(CXL_FM_COMPONENT_ARG)
+			.and_then(|component| component.split('/').next())
+	}
+
+	fn fm_ids(fms: &[&CxlFmInfo]) -> String {
+		fms.iter()
+			.map(|fm| fm.id.as_str())
+			.collect::<Vec<&str>>()
+			.join(", ")
+	}
+
+	/*
+	 * Select FM that serves the request: FM defined by the request,
+	 * FM that manages switch or device of the request or the only
+	 * registered FM. Lost FMs don't serve requests, standby FMs
+	 * serve only requests that define them explicitly, so CCI
+	 * shared by active and standby FMs is served by the active
+	 * one. Returns
+	 * the request for the selected FM: device defined by global
+	 * CCI index is defined by CCI index of the FM.
+	 */
+	fn select(fms: &[CxlFmInfo],
+		  request: &CxlFmRequest) -> Result<(CxlFmInfo, CxlFmRequest), CxlFmError> {
+		let target = match request.get(CXL_FM_FM_ARG) {
+			Some(_) => None,
+			None => request_target(fms, request),
+		};
+		let candidates: Vec<&CxlFmInfo> = match (request.get(CXL_FM_FM_ARG), target) {
+			(Some(id), _) => fms.iter().filter(|fm| fm.id == id).collect(),
+			(None, Some(component)) => fms.iter()
+				.filter(|fm| fm.manages(component) ||
+					     fm.ccis.iter().any(|cci| cci == component))
+				.collect(),
+			(None, None) => fms.iter().collect(),
+		};
+
+		if candidates.is_empty() {
+			return Err(match (request.get(CXL_FM_FM_ARG), target) {
+				(Some(id), _) => CxlFmError::NotFound(format!("FM {}", id)),
+				(None, Some(component)) if component.parse::<usize>().is_ok() => {
+					CxlFmError::NotFound(format!("CCI {}", component))
+				},
+				(None, Some(component)) => {
+					CxlFmError::NotFound(format!("FM that manages {}", component))
+				},
+				(None, None) => CxlFmError::NotFound(String::from("registered FM")),
+			});
+		}
+
+		let alive: Vec<&CxlFmInfo> = candidates.iter()
+			.filter(|fm| fm.health != CXL_FM_HEALTH_LOST)
+			.copied()
+			.collect();
+
+		if alive.is_empty() {
+			return Err(CxlFmError::Remote(format!("FM {} is lost", fm_ids(&candidates))));
+		}
+
+		let active: Vec<&CxlFmInfo> = match request.get(CXL_FM_FM_ARG) {
+			Some(_) => alive.clone(),
+			None => alive.iter()
+				.filter(|fm| fm.role == CXL_FM_ROLE_ACTIVE)
+				.copied()
+				.collect(),
+		};
+
+		let fm = match active.len() {
+			0 => return Err(CxlFmError::Remote(format!("FM {} is standby, active FM is not elected yet",
+								  fm_ids(&alive)))),
+			1 => active[0].clone(),
+			_ => return Err(CxlFmError::InvalidInput(format!("request can be served by FMs {}, define FM by {} argument",
+									fm_ids(&active), CXL_FM_FM_ARG))),
+		};
+		let mut request = request.clone();
+
+		request.args.retain(|(key, _)| key != CXL_FM_FM_ARG);
+
+		let is_index = request.get(CXL_FM_DEVICE_ARG)
+			.is_some_and(|device| device.parse::<usize>().is_ok());
+		let index = target.and_then(|cci| fm.ccis.iter().position(|name| name == cci));
+
+		if let (true, Some(index)) = (is_index, index) {
+			request.args.iter_mut()
+				.filter(|(key, _)| key == CXL_FM_DEVICE_ARG)
+				.for_each(|(_, value)| *value = index.to_string());
+		}
+
+		Ok((fm, request))
+	}
+
+	/*
+	 * Find FM that serves the request and the request for it
+	 */
+	pub fn route(ctx: &CxlOrchestratorContext,
+		     request: &CxlFmRequest) -> Result<(CxlFmInfo, CxlFmRequest), CxlFmError> {
+		select(&ctx.registry.list(), request)
+	}
+
+	/*
+	 * Forward request to FM that serves it and relay
+	 * the responce. Streamed responce (event subscription)
+	 * is relayed until FM or client closes the connection.
+	 */
+	pub fn forward(mut stream: &TcpStream, request: &CxlFmRequest,
+		       ctx: &CxlOrchestratorContext, env: &CxlFmOptions) {
+		let (fm, request) = match route(ctx, request) {
+			Ok(route) => route,
+			Err(e) => {
+				send_error(stream, &e, env);
+				return;
+			}
+		};
+
+		if env.is_debug {
+			println!("Forward {} to {} ({})", request.command, fm.id, fm.address);
+		}
+
+		let mut fm_stream = match TcpStream::connect(&fm.address) {
+			Ok(fm_stream) => fm_stream,
+			Err(e) => {
+				send_error(stream, &CxlFmError::Io(format!("FM {} at {}: {}",
+									   fm.id, fm.address, e)), env);
+				return;
+			}
+		};
+
+		if let Err(e) = writeln!(fm_stream, "{}", request.to_line()) {
+			send_error(stream, &CxlFmError::Io(format!("FM {} at {}: {}",
+								   fm.id, fm.address, e)), env);
+			return;
+		}
+
+		if let Err(e) = io::copy(&mut fm_stream, &mut stream) {
+			if env.is_debug {
+				println!("Relay from {} is interrupted: {}", fm.id, e);
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use fm_library::cxl_fm_orchestrator::CXL_FM_HEALTH_HEALTHY;
+		use fm_library::cxl_fm_orchestrator::CXL_FM_ROLE_STANDBY;
+
+		fn fm(id: &str, switches: &[&str], ccis: &[&str], role: u8) -> CxlFmInfo {
+			CxlFmInfo {
+				id: String::from(id),
+				address: format!("{}:7400", id),
+				switches: switches.iter().map(|name| String::from(*name)).collect(),
+				ccis: ccis.iter().map(|name| String::from(*name)).collect(),
+				health: CXL_FM_HEALTH_HEALTHY,
+				role,
+				..CxlFmInfo::default()
+			}
+		}
+
+		fn fms() -> Vec<CxlFmInfo> {
+			vec![
+				fm("fm0", &["sw0"], &["sw0", "mhd0"], CXL_FM_ROLE_ACTIVE),
+				fm("fm1", &["sw1"], &["sw1"], CXL_FM_ROLE_ACTIVE),
+				fm("fm2", &["sw0"], &["sw0", "mhd0"], CXL_FM_ROLE_STANDBY),
+			]
+		}
+
+		fn selected(fms: &[CxlFmInfo], request: CxlFmRequest) -> Result<(String, CxlFmRequest), CxlFmError> {
+			select(fms, &request).map(|(fm, request)| (fm.id, request))
+		}
+
+		#[test]
+		fn request_is_routed_by_switch() {
+			let (id, _) = selected(&fms(), CxlFmRequest::new("GET_INFO").arg(CXL_FM_SWITCH_ARG, "sw1")).unwrap();
+
+			assert_eq!(id, "fm1");
+		}
+
+		#[test]
+		fn request_is_routed_by_cci_index() {
+			let fms = fms();
+
+			/* sw0, mhd0 of fm0 (shared with fm2) and sw1 of fm1 */
+			assert_eq!(cci_names(&fms), vec!["sw0", "mhd0", "sw1"]);
+
+			let (id, request) = selected(&fms, CxlFmRequest::new("GET_INFO").arg(CXL_FM_DEVICE_ARG, "1")).unwrap();
+
+			assert_eq!(id, "fm0");
+			assert_eq!(request.get(CXL_FM_DEVICE_ARG), Some("1"));
+
+			let (id, request) = selected(&fms, CxlFmRequest::new("GET_INFO").arg(CXL_FM_DEVICE_ARG, "2")).unwrap();
+
+			assert_eq!(id, "fm1");
+			assert_eq!(request.get(CXL_FM_DEVICE_ARG), Some("0"));
+		}
+
+		#[test]
+		fn shared_cci_is_routed_to_active_fm() {
+			/* standby fm2 is registered first and reports mhd0 before sw0 */
+			let fms = vec![
+				fm("fm2", &["sw0"], &["mhd0", "sw0"], CXL_FM_ROLE_STANDBY),
+				fm("fm1", &["sw1"], &["sw1"], CXL_FM_ROLE_ACTIVE),
+				fm("fm0", &["sw0"], &["sw0", "mhd0"], CXL_FM_ROLE_ACTIVE),
+			];
+
+			assert_eq!(cci_names(&fms), vec!["mhd0", "sw0", "sw1"]);
+
+			let (id, request) = selected(&fms, CxlFmRequest::new("GET_INFO").arg(CXL_FM_DEVICE_ARG, "0")).unwrap();
+
+			assert_eq!(id, "fm0");
+			assert_eq!(request.get(CXL_FM_DEVICE_ARG), Some("1"));
+
+			let (id, request) = selected(&fms, CxlFmRequest::new("GET_INFO").arg(CXL_FM_DEVICE_ARG, "mhd0")).unwrap();
+
+			assert_eq!(id, "fm0");
+			assert_eq!(request.get(CXL_FM_DEVICE_ARG), Some("mhd0"));
+
+			let (id, _) = selected(&fms, CxlFmRequest::new("GET_FM_EVENTS").arg(CXL_FM_COMPONENT_ARG, "mhd0/ld1")).unwrap();
+
+			assert_eq!(id, "fm0");
+		}
+
+		#[test]
+		fn unknown_cci_index_is_not_found() {
+			let result = selected(&fms(), CxlFmRequest::new("GET_INFO").arg(CXL_FM_DEVICE_ARG, "3"));
+
+			assert!(matches!(result, Err(CxlFmError::NotFound(_))));
+		}
+
+		#[test]
+		fn cci_index_of_defined_fm_is_kept() {
+			let request = CxlFmRequest::new("GET_INFO")
+				.arg(CXL_FM_FM_ARG, "fm2")
+				.arg(CXL_FM_DEVICE_ARG, "1");
+			let (id, request) = selected(&fms(), request).unwrap();
+
+			assert_eq!(id, "fm2");
+			assert_eq!(request.get(CXL_FM_DEVICE_ARG), Some("1"));
+			assert_eq!(request.get(CXL_FM_FM_ARG), None);
+		}
+
+		#[test]
+		fn request_without_target_needs_fm() {
+			let fms = fms();
+
+			assert!(matches!(selected(&fms, CxlFmRequest::new("GET_INFO")),
+					 Err(CxlFmError::InvalidInput(_))));
+
+			let (id, _) = selected(&fms[1..2], CxlFmRequest::new("GET_INFO")).unwrap();
+
+			assert_eq!(id, "fm1");
+		}
+
+		#[test]
+		fn lost_and_standby_fms_are_skipped() {
+			let mut fms = fms();
+
+			fms[0].health = CXL_FM_HEALTH_LOST;
+
+			let result = selected(&fms, CxlFmRequest::new("GET_INFO").arg(CXL_FM_SWITCH_ARG, "sw0"));
+
+			assert!(matches!(result, Err(CxlFmError::Remote(_))));
+
+			fms[2].role = CXL_FM_ROLE_ACTIVE;
+
+			let (id, _) = selected(&fms, CxlFmRequest::new("GET_INFO").arg(CXL_FM_SWITCH_ARG, "sw0")).unwrap();
+
+			assert_eq!(id, "fm2");
+		}
+	}
+}